@@ -31,7 +31,7 @@ public class HelloMultilingual {
     public static void main(String[] args) {
         log.info("🌍 Multilingual Edge TTS Demo - Java Implementation");
         log.info("=".repeat(60));
-        log.info("Generating audio for 12 languages with custom sentences...");
+        log.info("Generating audio for 13 languages with custom sentences...");
 
         try {
             ACTIVE_BACKEND = System.getProperty("tts.backend", "edge");
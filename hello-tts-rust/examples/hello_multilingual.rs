@@ -1,12 +1,10 @@
-use hello_tts_rust::prelude::*;
 use clap::Parser;
-use env_logger;
-use serde::{Deserialize, Serialize};
+use hello_tts_rust::config::{resolve_shared_config, LanguageConfig};
+use hello_tts_rust::naming::{FileNamer, NameFields};
+use hello_tts_rust::prelude::*;
+use log::{error, info, LevelFilter};
 use std::fs;
-use std::path::PathBuf;
-use std::time::{SystemTime, UNIX_EPOCH};
-use tokio::time::{sleep, Duration};
-use log::{info, error, LevelFilter};
+use std::path::Path;
 
 #[derive(Parser)]
 #[command(name = "hello-tts-multilingual")]
@@ -17,67 +15,51 @@ struct Cli {
     backend: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-struct LanguageConfig {
-    code: String,
-    name: String,
-    flag: String,
-    text: String,
-    edge_voice: String,
-    google_voice: String,
-}
-
-#[derive(Debug, Deserialize)]
-struct Config {
-    languages: Vec<LanguageConfig>,
-}
-
-/// Load language configuration from JSON file
+/// Load language configuration from the shared language list
 fn load_language_config() -> Result<Vec<LanguageConfig>, TTSError> {
-    let config_path = "../shared/tts_config.json";
-    
-    let config_str = fs::read_to_string(config_path)
-        .map_err(|e| TTSError::Config(format!(
-            "Failed to read config file {}: {}", config_path, e
-        )))?;
-
-    let config: Config = serde_json::from_str(&config_str)
-        .map_err(|e| TTSError::Config(format!(
-            "Invalid JSON in config file: {}", e
-        )))?;
-
-    Ok(config.languages)
+    Ok(resolve_shared_config()?.languages)
 }
 
 /// Generate audio for a single language
 async fn generate_audio_for_language(
-    client: &mut TTSProcessor,
+    client: &TTSProcessor,
     language_config: &LanguageConfig,
     output_dir: &str,
     play_audio: bool,
     backend: &str,
+    namer: &FileNamer,
 ) -> Result<bool, TTSError> {
-    let voice = if backend == "google" {
-        &language_config.google_voice
-    } else {
-        &language_config.edge_voice
+    let text = language_config.text.as_deref();
+    let (Some(text), Some(voice)) = (text, language_config.voice(backend)) else {
+        error!(
+            "❌ No text or {} voice for {}",
+            backend, language_config.name
+        );
+        return Ok(false);
     };
 
-    info!("🗣️  {} ({})", language_config.name, language_config.flag);
-    info!("📝 Text: {}", language_config.text);
+    info!(
+        "🗣️  {} ({})",
+        language_config.name,
+        language_config.flag.as_deref().unwrap_or("")
+    );
+    info!("📝 Text: {}", text);
     info!("🎙️  Voice: {}", voice);
 
-    // Generate timestamp for unique filenames
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    let lang_code = language_config.code.split('-').next().unwrap_or("unknown");
-
-    let output_path = PathBuf::from(output_dir)
-        .join(format!("{}_rust_{}_{}.mp3", lang_code, backend, timestamp));
-
-    match client.synthesize_and_play(&language_config.text, voice, Some(&output_path), play_audio).await {
+    let output_path = namer.path_in(
+        Path::new(output_dir),
+        &NameFields {
+            backend,
+            voice,
+            text,
+            ext: "mp3",
+        },
+    );
+
+    match client
+        .synthesize_and_play(text, voice, Some(&output_path), play_audio)
+        .await
+    {
         Ok(_) => {
             info!("✅ Generated audio saved to {:?}", output_path);
             Ok(true)
@@ -120,16 +102,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     info!("📋 Found {} languages to process", languages.len());
 
+    // Initialize TTS client with backend configuration
+    let config = TTSConfig {
+        backend: backend.clone(),
+        ..Default::default()
+    };
+
     // Create output directory
-    let output_dir = "output";
-    fs::create_dir_all(output_dir)?;
-    let output_path = std::fs::canonicalize(output_dir)?;
+    let output_dir = config.output_directory.clone();
+    fs::create_dir_all(&output_dir)?;
+    let output_path = std::fs::canonicalize(&output_dir)?;
     info!("📁 Output directory: {}", output_path.display());
-
-    // Initialize TTS client with backend configuration
-    let mut config = TTSConfig::default();
-    config.backend = backend.clone();
-    let mut client = TTSProcessor::new(Some(config));
+    let namer = FileNamer::from_config(&config)?;
+    let client = TTSProcessor::try_new(Some(config))?;
     info!("✅ TTS client initialized with {} backend", backend);
 
     // Process each language
@@ -140,7 +125,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     for (i, language_config) in languages.iter().enumerate() {
         info!("📍 Processing language {}/{}", i + 1, languages.len());
 
-        match generate_audio_for_language(&mut client, language_config, output_dir, false, &backend).await {
+        match generate_audio_for_language(
+            &client,
+            language_config,
+            &output_dir,
+            false,
+            &backend,
+            &namer,
+        )
+        .await
+        {
             Ok(success) => {
                 if success {
                     successful_count += 1;
@@ -153,12 +147,6 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 failed_count += 1;
             }
         }
-
-        // Small delay between languages to be polite to the service
-        if i < languages.len() - 1 {
-            info!("⏳ Waiting before next language...");
-            sleep(Duration::from_secs(2)).await;
-        }
     }
 
     // Summary
@@ -172,7 +160,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("📁 Output files saved in: {}", output_path.display());
 
     if successful_count > 0 {
-        info!("🎉 Successfully generated audio files for {} languages!", successful_count);
+        info!(
+            "🎉 Successfully generated audio files for {} languages!",
+            successful_count
+        );
         info!("Generated MP3 files are in the output directory.");
     }
 
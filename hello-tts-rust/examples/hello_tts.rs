@@ -1,176 +1,2377 @@
-use clap::Parser;
-use env_logger;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use hello_tts_rust::alignment;
+use hello_tts_rust::analysis::LoudnessPreset;
+use hello_tts_rust::anki;
+use hello_tts_rust::assets;
+use hello_tts_rust::audio_player::{open_sink, StdoutSink};
+use hello_tts_rust::audiobook::{self, AudiobookLayout};
+use hello_tts_rust::batch::{self, BatchItem, ItemStatus};
+use hello_tts_rust::bench;
+use hello_tts_rust::catalog::{CatalogFormat, GenderPreference};
+use hello_tts_rust::checksums::ChecksumManifest;
+use hello_tts_rust::clipboard::{Clipboard, ClipboardChanges};
+use hello_tts_rust::compare;
+use hello_tts_rust::config::{
+    load_layered_with_profile, resolve_shared_config, secrets, LanguageConfig, ENV_CONFIG_PATH,
+};
+use hello_tts_rust::dialogue;
+use hello_tts_rust::document;
+use hello_tts_rust::flashcards;
+use hello_tts_rust::localization;
+use hello_tts_rust::logging::{self, RunLog};
+use hello_tts_rust::naming::NameFields;
+use hello_tts_rust::narration;
+use hello_tts_rust::output_target::OutputTarget;
+use hello_tts_rust::pdf;
+use hello_tts_rust::podcast;
 use hello_tts_rust::prelude::*;
+use hello_tts_rust::readability;
+use hello_tts_rust::run_report::{ItemOutcome, ReportItem, RunReport};
+use hello_tts_rust::sources;
+use hello_tts_rust::sweep;
+use hello_tts_rust::sync::{self, SyncTarget};
+use hello_tts_rust::usage;
+use hello_tts_rust::watch::FileWatcher;
 use log::{error, info, warn, LevelFilter};
 use std::collections::HashMap;
+use std::ffi::OsString;
 use std::fs;
-use std::path::Path;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
 #[derive(Parser)]
 #[command(name = "hello-tts-rust")]
 #[command(about = "A Rust implementation supporting both Edge TTS and Google TTS")]
 #[command(version = "0.1.0")]
+#[command(after_help = "Without a command, the arguments are those of `speak`: \
+`hello-tts-rust --text Hello` is short for `hello-tts-rust speak --text Hello`.")]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    /// TTS backend to use (edge, google, or mock for offline runs), overriding config and environment
+    #[arg(short, long, global = true)]
+    backend: Option<String>,
+
+    /// Audio format: mp3 as the backend sends it, wav, headerless pcm_s16le, or
+    /// opus (with the opus feature)
+    /// [default: configured output_format]
+    #[arg(long = "format", global = true, value_name = "FORMAT")]
+    audio_format: Option<String>,
+
+    /// Resample wav, pcm_s16le or opus audio to this rate, e.g. 16000
+    #[arg(long, global = true, value_name = "HZ")]
+    sample_rate: Option<u32>,
+
+    /// Mix wav, pcm_s16le or opus audio down to this many channels
+    #[arg(long, global = true)]
+    channels: Option<u16>,
+
+    /// Normalize wav, pcm_s16le or opus audio to podcast (-16 LUFS), broadcast
+    /// (-23 LUFS) or telephony loudness
+    #[arg(long = "loudness", global = true, value_name = "PRESET")]
+    loudness_preset: Option<LoudnessPreset>,
+
+    /// List available voices
+    #[arg(short = 'l', long)]
+    list_voices: bool,
+
+    /// Filter voices by language
+    #[arg(short = 'L', long)]
+    language: Option<String>,
+
+    /// Run basic demo
+    #[arg(long)]
+    demo: bool,
+
+    /// Named profile from the config file to use
+    #[arg(short, long, global = true)]
+    profile: Option<String>,
+
+    /// Log level
+    #[arg(long, default_value = "info", global = true)]
+    log_level: String,
+
+    /// Format of results printed to stdout (logs always go to stderr)
+    #[arg(long, value_enum, default_value = "text", global = true)]
+    output_format: OutputFormat,
+
+    /// Pin file name timestamps to SOURCE_DATE_EPOCH (or 1970) and name files by content, so
+    /// reruns produce identical output
+    #[arg(long, global = true)]
+    deterministic: bool,
+
+    /// Write a JSON report of speak, batch and demo runs here and exit with 0, 1 when the run
+    /// failed or 3 when only some items failed
+    #[arg(long, global = true, value_name = "FILE")]
+    report: Option<PathBuf>,
+}
+
+/// How command results are printed on stdout
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Human-readable log lines only
+    Text,
+    /// A single JSON document
+    Json,
+    /// One JSON object per line
+    Ndjson,
+}
+
+impl OutputFormat {
+    /// Print a list of items as a JSON array or as one object per line
+    fn print_list<T: serde::Serialize>(self, items: &[T]) -> serde_json::Result<()> {
+        match self {
+            OutputFormat::Text => {}
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(items)?),
+            OutputFormat::Ndjson => {
+                for item in items {
+                    println!("{}", serde_json::to_string(item)?);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Print a single item
+    fn print_item<T: serde::Serialize>(self, item: &T) -> serde_json::Result<()> {
+        match self {
+            OutputFormat::Text => {}
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(item)?),
+            OutputFormat::Ndjson => println!("{}", serde_json::to_string(item)?),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Convert text to speech
+    Speak(SpeakArgs),
+    /// List available voices
+    Voices {
+        /// Filter voices by language
+        #[arg(short = 'L', long)]
+        language: Option<String>,
+
+        /// Write the voices of all backends to a .json or .csv file
+        #[arg(long)]
+        export: Option<PathBuf>,
+
+        /// Print the voice chosen for this locale or language (e.g. de-DE) instead of listing
+        #[arg(long, value_name = "LOCALE", conflicts_with = "export")]
+        default_for: Option<String>,
+
+        /// Gender of the --default-for voice: female, male, neutral or any
+        #[arg(long, default_value = "any", requires = "default_for")]
+        gender: GenderPreference,
+    },
+    /// Speak a sample sentence in every voice matching a language
+    Audition {
+        /// Language or locale prefix to match (e.g. en, en-GB)
+        #[arg(short = 'L', long)]
+        language: String,
+
+        /// Sample text to speak
+        #[arg(short, long, default_value = "Hello! This is how my voice sounds.")]
+        text: String,
+
+        /// Save every sample to this directory
+        #[arg(short, long)]
+        output_dir: Option<String>,
+
+        /// Don't play the samples (only useful with --output-dir)
+        #[arg(long)]
+        noplay: bool,
+
+        /// Audition at most this many voices
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Write samples of one text in several voices and an HTML page to compare them
+    Compare {
+        /// Text every voice speaks
+        #[arg(short, long, default_value = "Hello! This is how my voice sounds.")]
+        text: String,
+
+        /// Voice to include (repeatable)
+        #[arg(short, long = "voice")]
+        voices: Vec<String>,
+
+        /// Include every voice of this language or locale (e.g. en, en-GB)
+        #[arg(short = 'L', long)]
+        language: Option<String>,
+
+        /// Include at most this many voices of the language
+        #[arg(long)]
+        limit: Option<usize>,
+
+        /// Directory for the samples and index.html [default: named after the text]
+        #[arg(short, long)]
+        output_dir: Option<PathBuf>,
+    },
+    /// Write samples of one text at a grid of rates and pitches and an HTML page to compare them
+    Sweep {
+        /// Text to speak
+        #[arg(short, long, default_value = "Hello! This is how my voice sounds.")]
+        text: String,
+
+        /// Voice to use [default: configured default voice]
+        #[arg(short, long)]
+        voice: Option<String>,
+
+        /// Rates: values and FROM..TO:STEP ranges, comma separated
+        #[arg(long, default_value = "-20%..+20%:10%")]
+        rates: String,
+
+        /// Pitches, e.g. -10Hz..+10Hz:5Hz [default: configured pitch]
+        #[arg(long)]
+        pitches: Option<String>,
+
+        /// Directory for the samples and index.html [default: named after the text]
+        #[arg(short, long)]
+        output_dir: Option<PathBuf>,
+    },
+    /// Write samples of one text in each speaking style of a voice and an HTML page to compare them
+    Styles {
+        /// Text to speak
+        #[arg(short, long, default_value = "Hello! This is how my voice sounds.")]
+        text: String,
+
+        /// Voice to use [default: configured default voice]
+        #[arg(short, long)]
+        voice: Option<String>,
+
+        /// Style to include (repeatable) [default: every style the voice lists]
+        #[arg(short, long = "style")]
+        styles: Vec<String>,
+
+        /// Directory for the samples and index.html [default: named after the voice]
+        #[arg(short, long)]
+        output_dir: Option<PathBuf>,
+    },
+    /// Speak the entries of an RSS or Atom feed as episodes of a podcast feed
+    Podcast {
+        /// Feed URL or file
+        #[arg(short, long)]
+        feed: String,
+
+        /// Voice to use [default: configured default voice]
+        #[arg(short, long)]
+        voice: Option<String>,
+
+        /// Directory for the episodes and feed.xml [default: named after the feed]
+        #[arg(short, long)]
+        output_dir: Option<PathBuf>,
+
+        /// URL the output directory is published at, for enclosure URLs
+        #[arg(long)]
+        base_url: Option<String>,
+
+        /// Speak at most this many of the newest entries
+        #[arg(short, long)]
+        limit: Option<usize>,
+    },
+    /// Run basic demo
+    Demo {
+        /// Demo language from the language list, e.g. en, zh, ja, ar or he
+        #[arg(short = 'L', long, default_value = "en")]
+        language: String,
+
+        /// Run every language of the list
+        #[arg(long)]
+        all: bool,
+
+        /// Language list with the demo texts and voices [default: the shared list]
+        #[arg(long)]
+        languages: Option<PathBuf>,
+    },
+    /// Print a shell completion script
+    Completions {
+        /// Shell to generate completions for
+        shell: Shell,
+    },
+    /// Render a dialogue script (`Speaker: line` or JSON) with one voice per speaker
+    Dialogue {
+        /// Script file, or `-` for stdin
+        script: PathBuf,
+
+        /// Speaker voice, e.g. `--voice Alice=en-US-AriaNeural` (repeatable)
+        #[arg(short, long = "voice", value_name = "SPEAKER=VOICE")]
+        voices: Vec<String>,
+
+        /// Silence between lines, in milliseconds
+        #[arg(long, default_value = "400")]
+        gap_ms: u64,
+
+        /// Output file; a bare file name is saved in the output directory
+        #[arg(short, long, default_value = "dialogue.mp3")]
+        output: PathBuf,
+
+        /// Don't play the rendered dialogue
+        #[arg(long)]
+        noplay: bool,
+    },
+    /// Narrate an SRT subtitle file into one audio track that follows its timing
+    Narrate {
+        /// SubRip subtitle file
+        subtitles: PathBuf,
+
+        /// Voice to use [default: configured default voice]
+        #[arg(short, long)]
+        voice: Option<String>,
+
+        /// Fastest a clip may be played to fit its cue, e.g. 1.5 times
+        #[arg(long, default_value = "1.5")]
+        max_speed: f64,
+
+        /// Output file; a bare file name is saved in the output directory
+        /// [default: <subtitles>.<ext>]
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Don't play the narration
+        #[arg(long)]
+        noplay: bool,
+    },
+    /// Render (source, translation) pairs from CSV/TSV into one file with pauses for practice
+    Flashcards(FlashcardArgs),
+    /// Add `[sound:...]` audio to a CSV/TSV deck for Anki import
+    Anki {
+        /// CSV or TSV deck
+        deck: PathBuf,
+
+        /// Voice to use [default: configured default voice]
+        #[arg(short, long)]
+        voice: Option<String>,
+
+        /// Column to speak, counting from 0
+        #[arg(long, default_value = "0")]
+        column: usize,
+
+        /// The first row holds column names
+        #[arg(long)]
+        header: bool,
+
+        /// Directory for the audio files [default: anki in the output directory]
+        #[arg(long)]
+        media_dir: Option<PathBuf>,
+
+        /// Deck with the audio column [default: <deck>_anki.<ext>]
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Render a localization sheet to `<locale>/<key>` audio files and a manifest
+    /// for Unity or Godot
+    Assets {
+        /// CSV or TSV sheet with a header row
+        sheet: PathBuf,
+
+        /// Voice for rows without one [default: configured default voice]
+        #[arg(short, long)]
+        voice: Option<String>,
+
+        /// Engine whose manifest layout to write (unity or godot)
+        #[arg(long, default_value = "unity")]
+        engine: Engine,
+
+        /// Project path the output directory is imported under, e.g. res://audio/voice
+        #[arg(long)]
+        root: Option<String>,
+
+        /// Column with the text to speak
+        #[arg(long, default_value = "text", value_name = "NAME")]
+        text_column: String,
+
+        /// Column with each row's voice
+        #[arg(long, value_name = "NAME")]
+        voice_column: Option<String>,
+
+        /// Column with the string keys
+        #[arg(long, default_value = "key", value_name = "NAME")]
+        id_column: String,
+
+        /// Directory for the assets [default: configured output directory]
+        #[arg(short, long)]
+        output_dir: Option<PathBuf>,
+
+        /// Skip rows already rendered by an earlier run
+        #[arg(long)]
+        resume: bool,
+    },
+    /// Render a Markdown, JSON, text or EPUB document to one audio file per chapter with a
+    /// playlist
+    Render {
+        /// Document file; in Markdown `#` headings start chapters and `##` headings sections
+        file: PathBuf,
+
+        /// Voice to use [default: configured default voice]
+        #[arg(short, long)]
+        voice: Option<String>,
+
+        /// Directory for chapter files [default: named after the document in the output directory]
+        #[arg(short, long)]
+        output_dir: Option<PathBuf>,
+
+        /// Skip chapters already rendered by an earlier, interrupted run
+        #[arg(long)]
+        resume: bool,
+    },
+    /// Render an EPUB (or any document `render` reads) as an audiobook with chapter metadata
+    Audiobook {
+        /// Book file
+        file: PathBuf,
+
+        /// Voice to use [default: configured default voice]
+        #[arg(short, long)]
+        voice: Option<String>,
+
+        /// Directory for chapter files [default: named after the book in the output directory]
+        #[arg(short, long)]
+        output_dir: Option<PathBuf>,
+
+        /// m4b: chapter files with metadata.txt and files.txt for ffmpeg; mp3: ID3-tagged chapters
+        #[arg(long, default_value_t = AudiobookLayout::M4b)]
+        layout: AudiobookLayout,
+
+        /// Skip chapters already rendered by an earlier, interrupted run
+        #[arg(long)]
+        resume: bool,
+
+        /// Push the chapters to sftp://host/dir, rsync:DEST or command:CMD afterwards
+        /// [default: configured sync_target]
+        #[arg(long, value_name = "TARGET")]
+        sync: Option<String>,
+    },
+    /// Synthesize every entry of a manifest (JSON, NDJSON or one text per line) to its own file
+    Batch {
+        /// Manifest file, or `-` for stdin
+        manifest: PathBuf,
+
+        /// Voice for entries without their own [default: configured default voice]
+        #[arg(short, long)]
+        voice: Option<String>,
+
+        /// Directory for the audio files and job state [default: output directory]
+        #[arg(short, long)]
+        output_dir: Option<PathBuf>,
+
+        /// Skip entries finished by an earlier run of the same manifest
+        #[arg(long)]
+        resume: bool,
+
+        /// Also upload each file to this directory or s3://, gs:// or az:// prefix
+        #[arg(long, value_name = "TARGET")]
+        upload: Option<String>,
+
+        /// Push the output directory to sftp://host/dir, rsync:DEST or command:CMD afterwards
+        /// [default: configured sync_target]
+        #[arg(long, value_name = "TARGET")]
+        sync: Option<String>,
+
+        /// Only report progress recorded in the job state, without synthesizing
+        #[arg(long)]
+        status: bool,
+    },
+    /// Check a batch's audio against a saved checksums.json and report drift
+    VerifyManifest {
+        /// Checksum manifest of an earlier run
+        manifest: PathBuf,
+
+        /// Directory of the rendering to check [default: the manifest's directory]
+        #[arg(short, long)]
+        dir: Option<PathBuf>,
+    },
+    /// Re-synthesize text and Markdown files to sibling audio files whenever they change
+    Watch {
+        /// File or directory to watch (directories are watched recursively)
+        path: PathBuf,
+
+        /// Voice to use [default: configured default voice]
+        #[arg(short, long)]
+        voice: Option<String>,
+
+        /// Wait until a file is quiet for this long before synthesizing it
+        #[arg(long, default_value = "500", value_name = "MS")]
+        debounce_ms: u64,
+
+        /// Play each file after synthesizing it
+        #[arg(long)]
+        play: bool,
+    },
+    /// Delete old audio from the output directory to meet the configured limits
+    Clean {
+        /// Keep at most this many megabytes of audio [default: output_max_size_mb]
+        #[arg(long)]
+        max_size_mb: Option<u64>,
+
+        /// Delete audio older than this many days [default: output_max_age_days]
+        #[arg(long)]
+        max_age_days: Option<u64>,
+    },
+    /// Check the backends, their tools, audio output, output directory and config
+    Doctor,
+    /// Measure synthesis latency per backend and voice
+    Bench {
+        /// Text to synthesize on every run
+        #[arg(short, long, default_value = "Hello! How fast can you say this?")]
+        text: String,
+
+        /// Backend and voice to measure as BACKEND:VOICE (repeatable)
+        /// [default: configured backend and default voice]
+        #[arg(long = "target", value_name = "BACKEND:VOICE")]
+        targets: Vec<BenchTarget>,
+
+        /// Measured runs per target
+        #[arg(short = 'n', long, default_value = "5")]
+        runs: usize,
+
+        /// Unmeasured runs per target before measuring
+        #[arg(long, default_value = "1")]
+        warmup: usize,
+    },
+    /// Inspect and create configuration files
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Run an HTTP gateway exposing /synthesize, /jobs, /voices, /health and /metrics
+    #[cfg(feature = "server")]
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        addr: std::net::SocketAddr,
+    },
+    /// List, search and replay earlier syntheses
+    #[cfg(feature = "history")]
+    History {
+        #[command(subcommand)]
+        action: HistoryAction,
+    },
+    /// Play earlier audio of a text from the history without contacting any backend
+    #[cfg(feature = "history")]
+    Replay {
+        /// Text that was synthesized
+        #[arg(short, long)]
+        text: String,
+
+        /// Voice it was synthesized with [default: configured default voice]
+        #[arg(short, long)]
+        voice: Option<String>,
+    },
+    /// Synthesize a list of phrases ahead of time so `replay` can play them offline
+    #[cfg(feature = "history")]
+    Precache {
+        /// Phrases as a batch manifest (JSON, NDJSON or one text per line)
+        #[arg(short, long)]
+        manifest: PathBuf,
+
+        /// Voice for phrases without their own [default: configured default voice]
+        #[arg(short, long)]
+        voice: Option<String>,
+
+        /// Directory for the audio [default: cache/ in the output directory]
+        #[arg(long)]
+        output_dir: Option<PathBuf>,
+    },
+    /// Stay in the background and speak the selection or clipboard on a global hotkey
+    #[cfg(feature = "hotkey")]
+    Daemon {
+        /// Voice to use [default: configured default voice]
+        #[arg(short, long)]
+        voice: Option<String>,
+
+        /// Hotkey such as CmdOrCtrl+Alt+S [default: configured hotkey]
+        #[arg(long)]
+        hotkey: Option<String>,
+    },
+    /// Poll an IMAP mailbox and speak each new email to an audio file until Ctrl-C
+    #[cfg(feature = "mail")]
+    Mail {
+        /// IMAP server, reached over TLS
+        #[arg(long)]
+        server: String,
+
+        #[arg(long, default_value_t = hello_tts_rust::mail::IMAPS_PORT)]
+        port: u16,
+
+        /// Login name
+        #[arg(long)]
+        user: String,
+
+        /// Password, or an env:VAR or keyring:service/user reference
+        #[arg(long, default_value = "env:HELLO_TTS_IMAP_PASSWORD")]
+        password: String,
+
+        #[arg(long, default_value = "INBOX")]
+        mailbox: String,
+
+        /// Voice to use [default: configured default voice]
+        #[arg(short, long)]
+        voice: Option<String>,
+
+        /// Directory for the audio [default: mail/ in the output directory]
+        #[arg(short, long)]
+        output_dir: Option<PathBuf>,
+
+        /// Seconds between checks of the mailbox
+        #[arg(long, default_value = "60")]
+        interval: u64,
+
+        /// Play each email after saving it
+        #[arg(long)]
+        play: bool,
+
+        /// Check the mailbox once and exit
+        #[arg(long)]
+        once: bool,
+    },
+}
+
+#[cfg(feature = "history")]
+#[derive(Subcommand)]
+enum HistoryAction {
+    /// Show the newest entries
+    List {
+        /// Number of entries to show
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+    },
+    /// Show entries whose text or voice contains a string
+    Search {
+        query: String,
+
+        /// Number of entries to show
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+    },
+    /// Play an entry from its saved file, or synthesize it again when the file is gone
+    Replay {
+        /// Entry id from `history list`
+        id: i64,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Show which config file would be loaded and where files are searched
+    Path,
+    /// Print the effective configuration, including environment overrides
+    Show {
+        /// Config file to read instead of the default locations
+        #[arg(long)]
+        path: Option<String>,
+    },
+    /// Write a new config file from a preset
+    Init {
+        /// Preset to start from (default, fast, slow, high_quality, batch_processing, whisper, excited)
+        #[arg(long, default_value = "default")]
+        preset: String,
+
+        /// Where to write the config file (.json, .toml or .yaml)
+        #[arg(long, default_value = "./tts_config.json")]
+        path: String,
+
+        /// Overwrite an existing file
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Args)]
+struct FlashcardArgs {
+    /// CSV or TSV file with a source and a translation column
+    file: PathBuf,
+
+    /// Language code of the source column, e.g. en-us (voice from --languages)
+    #[arg(long)]
+    from: Option<String>,
+
+    /// Language code of the translation column, e.g. fr (voice from --languages)
+    #[arg(long)]
+    to: Option<String>,
+
+    /// Language list mapping codes to voices [default: the shared list]
+    #[arg(long)]
+    languages: Option<PathBuf>,
+
+    /// Voice for the source column, overriding --from
+    #[arg(long)]
+    source_voice: Option<String>,
+
+    /// Voice for the translation column, overriding --to
+    #[arg(long)]
+    translation_voice: Option<String>,
+
+    /// Silence before the translation, in milliseconds
+    #[arg(long, default_value = "2000")]
+    pause_ms: u64,
+
+    /// Silence after each card, in milliseconds
+    #[arg(long, default_value = "3000")]
+    gap_ms: u64,
+
+    /// Output file; a bare file name is saved in the output directory
+    #[arg(short, long, default_value = "flashcards.mp3")]
+    output: PathBuf,
+
+    /// Don't play the rendered deck
+    #[arg(long)]
+    noplay: bool,
+}
+
+#[derive(Args)]
+struct SpeakArgs {
+    /// Text to speak, or `-` to read it from stdin
+    input: Option<String>,
+
     /// Text to convert to speech
-    #[arg(short, long, default_value = "Hello World! This is a demonstration of TTS in Rust.")]
+    #[arg(
+        short,
+        long,
+        default_value = "Hello World! This is a demonstration of TTS in Rust."
+    )]
     text: String,
 
-    /// Voice to use for synthesis
-    #[arg(short, long, default_value = "en-US-AriaNeural")]
-    voice: String,
+    /// Read the text to speak from a file (`-` for stdin)
+    #[arg(short, long)]
+    file: Option<PathBuf>,
+
+    /// Fetch a web page and speak its article, without navigation, ads and comments
+    #[arg(short, long, conflicts_with = "file")]
+    url: Option<String>,
+
+    /// Voice to use for synthesis [default: configured default voice]; repeat it to render the
+    /// text once per voice, to files suffixed with the voice name
+    #[arg(short, long = "voice")]
+    voices: Vec<String>,
+
+    /// Output directory [default: configured output directory]
+    #[arg(short, long)]
+    output_dir: Option<String>,
+
+    /// Don't play audio after synthesis (default is to play)
+    #[arg(long)]
+    noplay: bool,
+
+    /// File to save the audio to instead of a generated name in the output
+    /// directory, or `-` to write it to stdout without playing it
+    #[arg(long, value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    /// Input text format (plain, markdown or html), detected from --file when omitted
+    #[arg(long)]
+    input_format: Option<InputFormat>,
+
+    /// Drop lines of a PDF --file matching this regex, such as running headers, footers and page
+    /// numbers (PDF files require the `pdf` feature)
+    #[arg(long, value_name = "REGEX")]
+    skip_lines: Option<regex::Regex>,
+
+    /// Check the voice, text and output path without synthesizing anything
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Write viseme timings (for lip-sync) to this JSON file
+    #[arg(long, value_name = "PATH")]
+    visemes: Option<PathBuf>,
+
+    /// Write SubRip subtitles to this file; timings are estimated from the
+    /// audio for backends that report none
+    #[arg(long, value_name = "PATH")]
+    subtitles: Option<PathBuf>,
+
+    /// Render every row of a localization CSV/TSV sheet with a header row to
+    /// audio files and a summary.csv in the output directory
+    #[arg(long, value_name = "PATH")]
+    csv: Option<PathBuf>,
+
+    /// Column of --csv with the text to speak
+    #[arg(long, default_value = "text", value_name = "NAME")]
+    text_column: String,
+
+    /// Column of --csv with each row's voice; rows without one use --voice
+    #[arg(long, value_name = "NAME")]
+    voice_column: Option<String>,
+
+    /// Column of --csv with the key naming each row's file
+    #[arg(long, value_name = "NAME")]
+    id_column: Option<String>,
+
+    /// Speak the text on the system clipboard (requires the `clipboard` feature)
+    #[arg(long)]
+    clipboard: bool,
+
+    /// Keep running and speak every new text copied to the clipboard; with
+    /// --clipboard, the current clipboard text is spoken first
+    #[arg(long)]
+    watch_clipboard: bool,
+
+    /// Wait until copied text is unchanged for this long before speaking it
+    #[arg(long, default_value = "500", value_name = "MS")]
+    debounce_ms: u64,
+}
+
+impl SpeakArgs {
+    /// The first --voice
+    fn voice(&self) -> Option<&str> {
+        self.voices.first().map(String::as_str)
+    }
+
+    /// Resolve the text to speak from stdin, a file, the positional argument or --text
+    fn read_text(&self) -> Result<(String, InputFormat), TTSError> {
+        let from_stdin =
+            self.input.as_deref() == Some("-") || self.file.as_deref() == Some(Path::new("-"));
+
+        let (text, detected) = if from_stdin {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)?;
+            (text, InputFormat::Plain)
+        } else if let Some(path) = self.file.as_deref().filter(|p| is_pdf(p)) {
+            (
+                pdf::read_text(path, self.skip_lines.as_ref())?,
+                InputFormat::Plain,
+            )
+        } else if let Some(path) = &self.file {
+            (fs::read_to_string(path)?, InputFormat::from_path(path))
+        } else if let Some(input) = &self.input {
+            (input.clone(), InputFormat::Plain)
+        } else {
+            (self.text.clone(), InputFormat::Plain)
+        };
+
+        Ok((
+            text.trim().to_string(),
+            self.input_format.unwrap_or(detected),
+        ))
+    }
+}
+
+fn is_pdf(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("pdf"))
+}
+
+async fn speak(
+    args: SpeakArgs,
+    base_config: &TTSConfig,
+    output_format: OutputFormat,
+    run_report: &mut RunReport,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let single_voice_only = args.watch_clipboard
+        || args.csv.is_some()
+        || args.dry_run
+        || args.visemes.is_some()
+        || args.subtitles.is_some()
+        || args.output.as_deref() == Some(Path::new("-"));
+    if args.voices.len() > 1 && single_voice_only {
+        return Err(
+            "--voice can only be repeated without --csv, --watch-clipboard, --dry-run, \
+                    --visemes, --subtitles and --output -"
+                .into(),
+        );
+    }
+    if args.watch_clipboard {
+        return watch_clipboard(args, base_config, output_format, run_report).await;
+    }
+    if let Some(sheet) = &args.csv {
+        return run_sheet(sheet, &args, base_config, output_format, run_report).await;
+    }
+    let (text, input_format) = if args.clipboard {
+        let text = Clipboard::new()?
+            .text()?
+            .ok_or("The clipboard holds no text")?;
+        (
+            text.trim().to_string(),
+            args.input_format.unwrap_or_default(),
+        )
+    } else if let Some(url) = &args.url {
+        let article = readability::fetch(url).await?;
+        if let Some(title) = &article.title {
+            info!("📰 {}", title);
+        }
+        (article.speakable_text(), InputFormat::Plain)
+    } else {
+        args.read_text()?
+    };
+    let job = speak_job(&args, text, input_format, base_config);
+    if args.voices.len() > 1 {
+        return speak_voices(&args.voices, job, output_format, run_report).await;
+    }
+    run_report.push(handle_speak(job, output_format).await?);
+    Ok(())
+}
+
+/// Render the text of `job` once per voice as a batch, naming each file
+/// after the --output file or the text, suffixed with the voice
+async fn speak_voices(
+    voices: &[String],
+    job: SpeakJob,
+    output_format: OutputFormat,
+    run_report: &mut RunReport,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (output_dir, base) = match &job.output {
+        Some(path) => (
+            path.parent()
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .map_or_else(|| PathBuf::from("."), Path::to_path_buf),
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        ),
+        None => (PathBuf::from(&job.output_dir), document::slug(&job.text)),
+    };
+    let base = if base.is_empty() {
+        "speech".to_string()
+    } else {
+        base
+    };
+    let manifest = BatchManifest {
+        items: voices
+            .iter()
+            .map(|voice| BatchItem {
+                text: job.text.clone(),
+                voice: Some(voice.clone()),
+                output: None,
+                id: Some(format!("{}_{}", base, voice)),
+            })
+            .collect(),
+    };
+    let options = BatchOptions {
+        output_dir: Some(output_dir),
+        resume: false,
+        upload: None,
+    };
+
+    info!("🎤 Speaking the text in {} voices", voices.len());
+    let client = TTSProcessor::try_new(Some(job.config))?.with_cancellation(cancellation());
+    let report = batch::synthesize(&client, &manifest, &job.voice, &options).await?;
+    for item in &report.items {
+        run_report.push(ReportItem::from_batch_item(item, &report.output_dir));
+        let path = report.output_dir.join(&item.file);
+        if let ItemStatus::Failed { error } = &item.status {
+            error!("❌ {}: {}", item.voice, error);
+            continue;
+        }
+        info!("✅ {}: {:?}", item.voice, path);
+        if job.play {
+            open_sink(&client.config().audio_output)?.play(fs::read(&path)?)?;
+        }
+    }
+    if output_format == OutputFormat::Ndjson {
+        output_format.print_list(&report.items)?;
+    } else {
+        output_format.print_item(&report)?;
+    }
+    if report.failed > 0 {
+        return Err(format!("{} of {} voices failed", report.failed, voices.len()).into());
+    }
+    Ok(())
+}
+
+/// Render a localization sheet, failing if any row has no audio
+async fn run_sheet(
+    sheet: &Path,
+    args: &SpeakArgs,
+    base_config: &TTSConfig,
+    output_format: OutputFormat,
+    run_report: &mut RunReport,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let client =
+        TTSProcessor::try_new(Some(base_config.clone()))?.with_cancellation(cancellation());
+    let voice = args.voice().unwrap_or(&base_config.default_voice);
+    let options = SheetOptions {
+        text_column: args.text_column.clone(),
+        voice_column: args.voice_column.clone(),
+        id_column: args.id_column.clone(),
+        output_dir: args.output_dir.as_ref().map(PathBuf::from),
+        resume: false,
+    };
+    let report =
+        localization::render_sheet(&client, &fs::read_to_string(sheet)?, voice, &options).await?;
+    info!(
+        "✅ {} of {} rows rendered to {:?}",
+        report.rows.len() - report.failed(),
+        report.rows.len(),
+        report.batch.output_dir
+    );
+    info!("📋 Row summary in {:?}", report.summary);
+    for item in &report.batch.items {
+        run_report.push(ReportItem::from_batch_item(item, &report.batch.output_dir));
+    }
+    if output_format == OutputFormat::Ndjson {
+        output_format.print_list(&report.rows)?;
+    } else {
+        output_format.print_item(&report)?;
+    }
+    if report.failed() > 0 {
+        return Err(format!("{} rows have no audio", report.failed()).into());
+    }
+    Ok(())
+}
+
+fn speak_job(
+    args: &SpeakArgs,
+    text: String,
+    input_format: InputFormat,
+    base_config: &TTSConfig,
+) -> SpeakJob {
+    SpeakJob {
+        text,
+        voice: args
+            .voice()
+            .unwrap_or(&base_config.default_voice)
+            .to_string(),
+        output_dir: args
+            .output_dir
+            .clone()
+            .unwrap_or_else(|| base_config.output_directory.clone()),
+        play: !args.noplay,
+        dry_run: args.dry_run,
+        visemes_path: args.visemes.clone(),
+        subtitles_path: args.subtitles.clone(),
+        output: args.output.clone(),
+        config: TTSConfig {
+            input_format,
+            visemes: args.visemes.is_some(),
+            word_timings: args.subtitles.is_some(),
+            estimate_timings: base_config.estimate_timings || args.subtitles.is_some(),
+            ..base_config.clone()
+        },
+    }
+}
+
+/// Speak every new clipboard text until Ctrl-C
+async fn watch_clipboard(
+    args: SpeakArgs,
+    base_config: &TTSConfig,
+    output_format: OutputFormat,
+    run_report: &mut RunReport,
+) -> Result<(), Box<dyn std::error::Error>> {
+    const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(200);
+
+    let mut clipboard = Clipboard::new()?;
+    let current = clipboard.text()?;
+    let debounce = std::time::Duration::from_millis(args.debounce_ms);
+    let mut changes = ClipboardChanges::new(debounce, current.clone());
+    let input_format = args.input_format.unwrap_or_default();
+    let mut pending = current.filter(|_| args.clipboard);
+
+    info!("📋 Watching the clipboard, press Ctrl-C to stop");
+    loop {
+        if let Some(text) = pending.take() {
+            let job = speak_job(&args, text.trim().to_string(), input_format, base_config);
+            match handle_speak(job, output_format).await {
+                Ok(item) => run_report.push(item),
+                Err(e) => error!("❌ {}", e),
+            }
+        }
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            _ = tokio::time::sleep(POLL_INTERVAL) => {}
+        }
+        pending = changes.observe(clipboard.text()?, std::time::Instant::now());
+    }
+    Ok(())
+}
+
+/// A single text-to-speech request issued by the CLI
+struct SpeakJob {
+    text: String,
+    voice: String,
+    output_dir: String,
+    play: bool,
+    dry_run: bool,
+    /// Where to write viseme timings, if requested
+    visemes_path: Option<PathBuf>,
+    /// Where to write subtitles, if requested
+    subtitles_path: Option<PathBuf>,
+    /// Explicit output file, `-` for stdout
+    output: Option<PathBuf>,
+    config: TTSConfig,
+}
+
+/// Create output directory if it doesn't exist
+fn create_output_directory(directory: &str) -> std::io::Result<()> {
+    fs::create_dir_all(directory)
+}
+
+/// Print a progress line on stderr while long texts are synthesized in chunks
+fn show_chunk_progress(event: &TTSEvent) {
+    if let TTSEvent::ChunkReceived { index, total, .. } = event {
+        if *total > 1 {
+            eprint!("\r⏳ Synthesized chunk {}/{}", index + 1, total);
+            if index + 1 == *total {
+                eprintln!();
+            }
+        }
+    }
+}
+
+/// Run a speak job, returning its report item
+async fn handle_speak(
+    job: SpeakJob,
+    output_format: OutputFormat,
+) -> Result<ReportItem, Box<dyn std::error::Error>> {
+    let SpeakJob {
+        text,
+        voice,
+        output_dir,
+        play,
+        dry_run,
+        visemes_path,
+        subtitles_path,
+        output,
+        config,
+    } = job;
+    let backend = config.backend.clone();
+    // Audio goes to stdout, so results must not; logs already go to stderr
+    let to_stdout = output.as_deref() == Some(Path::new("-"));
+    let output_format = if to_stdout {
+        OutputFormat::Text
+    } else {
+        output_format
+    };
+
+    info!("🎤 Converting text to speech...");
+    info!("Backend: {}", backend);
+    if text.chars().count() > 200 {
+        info!("Text: {} characters", text.chars().count());
+    } else {
+        info!("Text: {}", text);
+    }
+    info!("Voice: {}", voice);
+
+    let mut client = TTSProcessor::try_new(Some(config))?.with_cancellation(cancellation());
+    if visemes_path.is_some() && !client.supports_visemes(&voice)? {
+        return Err(format!(
+            "The backend for {} does not report visemes, so --visemes cannot be written",
+            voice
+        )
+        .into());
+    }
+    if output_format == OutputFormat::Text {
+        client.subscribe(show_chunk_progress);
+    }
+    if to_stdout {
+        client.set_audio_sink(StdoutSink);
+    }
+
+    let fields = NameFields {
+        backend: &backend,
+        voice: &voice,
+        text: &text,
+        ext: client.config().file_extension(),
+    };
+    let output_path = match output {
+        Some(path) if !to_stdout => path,
+        _ => client
+            .file_namer()?
+            .path_in(Path::new(&output_dir), &fields),
+    };
+    let id = match output_path.file_name() {
+        Some(name) if !to_stdout => name.to_string_lossy().into_owned(),
+        _ => "-".to_string(),
+    };
+
+    if dry_run {
+        let options = ValidationOptions {
+            output_path: Some(output_path).filter(|_| !to_stdout),
+            ..ValidationOptions::default()
+        };
+        let report = client.validate(&text, &voice, &options).await;
+        output_format.print_item(&report)?;
+        if report.is_ok() {
+            info!("✅ Dry run passed, nothing was synthesized");
+            return Ok(ReportItem::new(id, voice, ItemOutcome::Skipped));
+        }
+        for problem in &report.problems {
+            error!("❌ {}", problem);
+        }
+        std::process::exit(1);
+    }
+
+    let saved_to = if to_stdout {
+        None
+    } else {
+        create_output_directory(&output_dir)?;
+        Some(output_path.as_path())
+    };
+
+    let item = match client
+        .synthesize_and_play(&text, &voice, saved_to, play || to_stdout)
+        .await
+    {
+        Ok(result) => {
+            let mut item = ReportItem::from_result(id, &result);
+            if to_stdout {
+                info!(
+                    "✅ Synthesis complete. {} bytes written to stdout",
+                    result.bytes
+                );
+            } else {
+                info!("✅ Synthesis complete. Audio saved to {:?}", output_path);
+            }
+            if let Some(stats) = &result.stats {
+                info!(
+                    "⏱️  {:.1} s, peak {}, loudness {}",
+                    stats.duration_ms as f64 / 1000.0,
+                    stats
+                        .peak_dbfs
+                        .map_or("-inf dBFS".to_string(), |db| format!("{:.1} dBFS", db)),
+                    stats
+                        .loudness_lufs
+                        .map_or("n/a".to_string(), |lufs| format!("{:.1} LUFS", lufs))
+                );
+            }
+            if let Some(path) = &visemes_path {
+                if result.visemes.is_empty() {
+                    let warning = format!("The {} backend reported no visemes", backend);
+                    warn!("{}", warning);
+                    item.warnings.push(warning);
+                }
+                fs::write(path, serde_json::to_string_pretty(&result.visemes)?)?;
+                info!("👄 Visemes written to {:?}", path);
+            }
+            if let Some(path) = &subtitles_path {
+                if result.boundaries.is_empty() {
+                    let warning = format!("The {} backend reported no word timings", backend);
+                    warn!("{}", warning);
+                    item.warnings.push(warning);
+                }
+                fs::write(path, alignment::to_srt(&result.boundaries))?;
+                info!("💬 Subtitles written to {:?}", path);
+            }
+            output_format.print_item(&result)?;
+            item
+        }
+        // Fail the pipeline rather than hand the next command empty input
+        Err(e) if to_stdout => return Err(e.into()),
+        Err(e) => {
+            error!("❌ Synthesis failed: {}", e);
+            output_format.print_item(&serde_json::json!({
+                "backend": backend,
+                "voice": voice,
+                "error": e.to_string(),
+            }))?;
+            ReportItem::failed(id, voice, e)
+        }
+    };
+
+    Ok(item)
+}
+
+/// Write the merged voice catalog of all backends
+async fn export_voices(
+    client: &TTSProcessor,
+    path: &Path,
+    language: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let format = CatalogFormat::from_path(path)?;
+    let mut catalog = client.voice_catalog().await?;
+    if let Some(language) = language {
+        catalog.retain_language(language);
+    }
+    fs::write(path, catalog.to_format(format)?)?;
+    info!("📇 Wrote {} voices to {:?}", catalog.voices.len(), path);
+    Ok(())
+}
+
+/// Print the catalog's default voice for `locale` and `gender`
+async fn print_default_voice(
+    client: &TTSProcessor,
+    locale: &str,
+    gender: GenderPreference,
+    output_format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let catalog = client.voice_catalog().await?;
+    let voice = catalog
+        .default_for(locale, gender)
+        .ok_or_else(|| format!("No {:?} voice for {}", gender, locale))?;
+    info!(
+        "🎙️  {}:{} ({})",
+        voice.backend, voice.voice.name, voice.voice.gender
+    );
+    output_format.print_item(voice)?;
+    Ok(())
+}
+
+async fn display_voices_by_language(
+    client: &TTSProcessor,
+    filter_language: Option<String>,
+    output_format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match client.list_voices().await {
+        Ok(voices) if output_format != OutputFormat::Text => {
+            let voices: Vec<&Voice> = voices
+                .iter()
+                .filter(|v| {
+                    filter_language
+                        .as_deref()
+                        .is_none_or(|lang| v.matches_language(lang))
+                })
+                .collect();
+            output_format.print_list(&voices)?;
+        }
+        Ok(voices) => {
+            let mut voices_by_language: HashMap<String, Vec<&Voice>> = HashMap::new();
+            for voice in &voices {
+                voices_by_language
+                    .entry(voice.locale.clone())
+                    .or_default()
+                    .push(voice);
+            }
+
+            if let Some(lang) = filter_language {
+                if let Some(lang_voices) = voices_by_language.get(&lang) {
+                    info!(
+                        "{} Voices ({} voices):",
+                        lang.to_uppercase(),
+                        lang_voices.len()
+                    );
+                    for voice in lang_voices.iter().take(5) {
+                        info!(
+                            "  {} - {} ({})",
+                            voice.name, voice.display_name, voice.gender
+                        );
+                    }
+                    if lang_voices.len() > 5 {
+                        info!("  ... and {} more voices", lang_voices.len() - 5);
+                    }
+                } else {
+                    warn!("No voices found for language: {}", lang);
+                }
+            } else {
+                info!("Available voices by language:");
+                for (lang, lang_voices) in &voices_by_language {
+                    info!("{} ({} voices):", lang.to_uppercase(), lang_voices.len());
+                    for voice in lang_voices.iter().take(5) {
+                        info!(
+                            "  {} - {} ({})",
+                            voice.name, voice.display_name, voice.gender
+                        );
+                    }
+                    if lang_voices.len() > 5 {
+                        info!("  ... and {} more voices", lang_voices.len() - 5);
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            error!("Error displaying voices: {}", e);
+        }
+    }
+    Ok(())
+}
+
+async fn run_audition(
+    client: &TTSProcessor,
+    language: &str,
+    text: &str,
+    output_dir: Option<String>,
+    play: bool,
+    limit: Option<usize>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let voices: Vec<Voice> = client
+        .list_voices()
+        .await?
+        .into_iter()
+        .filter(|v| v.matches_language(language))
+        .take(limit.unwrap_or(usize::MAX))
+        .collect();
+
+    if voices.is_empty() {
+        warn!("No voices found for language: {}", language);
+        return Ok(());
+    }
+
+    if let Some(dir) = &output_dir {
+        create_output_directory(dir)?;
+    }
+
+    info!("🎧 Auditioning {} voices for {}", voices.len(), language);
+    for (i, voice) in voices.iter().enumerate() {
+        info!(
+            "🎙️  {}/{}: {} - {} ({})",
+            i + 1,
+            voices.len(),
+            voice.name,
+            voice.display_name,
+            voice.gender
+        );
+        let output_path = output_dir
+            .as_ref()
+            .map(|dir| Path::new(dir).join(format!("audition_{}.mp3", voice.name)));
+
+        match client
+            .synthesize_and_play(text, &voice.name, output_path.as_deref(), play)
+            .await
+        {
+            Ok(_) => {
+                if let Some(path) = &output_path {
+                    info!("✅ Saved to {:?}", path);
+                }
+            }
+            Err(e) => error!("❌ {} failed: {}", voice.name, e),
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_config(
+    action: ConfigAction,
+    profile: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        ConfigAction::Path => {
+            let from_env = std::env::var(ENV_CONFIG_PATH).ok().map(PathBuf::from);
+            match from_env.or_else(ConfigManager::find_config_path) {
+                Some(path) => println!("{}", path.display()),
+                None => info!("No config file found, using built-in defaults"),
+            }
+            info!("Search locations:");
+            for path in ConfigManager::default_config_paths() {
+                info!("  {}", path.display());
+            }
+        }
+        ConfigAction::Show { path } => {
+            let mut config = match path {
+                Some(path) => {
+                    let mut config = ConfigManager::load_config(Some(&path))?;
+                    if let Some(name) = profile {
+                        config = config.profile(name)?;
+                    }
+                    config.apply_env_overrides()?;
+                    config
+                }
+                None => load_layered_with_profile(profile)?,
+            };
+            for section in config.backends.values_mut() {
+                section.api_key = section.api_key.as_deref().map(secrets::redact);
+            }
+            println!("{}", serde_json::to_string_pretty(&config)?);
+        }
+        ConfigAction::Init {
+            preset,
+            path,
+            force,
+        } => {
+            if Path::new(&path).exists() && !force {
+                error!("❌ {} already exists (use --force to overwrite)", path);
+                std::process::exit(1);
+            }
+            ConfigManager::create_default_config(&path, &preset)?;
+            info!("✅ Wrote {} preset to {}", preset, path);
+        }
+    }
+    Ok(())
+}
+
+/// Compare named voices and/or all voices of a language
+async fn run_compare(
+    client: &TTSProcessor,
+    text: &str,
+    names: &[String],
+    language: Option<&str>,
+    limit: Option<usize>,
+    options: CompareOptions,
+    output_format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let available = client.list_voices().await?;
+    let mut voices: Vec<Voice> = names
+        .iter()
+        .map(|name| {
+            available
+                .iter()
+                .find(|v| &v.name == name)
+                .cloned()
+                .ok_or_else(|| format!("Unknown voice: {}", name))
+        })
+        .collect::<Result<_, _>>()?;
+    if let Some(language) = language {
+        voices.extend(
+            available
+                .into_iter()
+                .filter(|v| v.matches_language(language) && !names.contains(&v.name))
+                .take(limit.unwrap_or(usize::MAX)),
+        );
+    }
+    if voices.is_empty() {
+        return Err("No voices to compare; pass --voice or --language".into());
+    }
+
+    info!("🎚️  Comparing {} voices", voices.len());
+    let comparison = compare::render(client, text, &voices, &options).await?;
+    for sample in comparison.samples.iter().filter(|s| s.error.is_some()) {
+        warn!(
+            "❌ {}: {}",
+            sample.voice.name,
+            sample.error.as_deref().unwrap_or_default()
+        );
+    }
+    info!("✅ Open {:?} to listen", comparison.index);
+    output_format.print_item(&comparison)?;
+    Ok(())
+}
+
+/// Speak one text at every rate and pitch of a sweep
+async fn run_sweep(
+    client: &TTSProcessor,
+    text: &str,
+    voice: &str,
+    options: &SweepOptions,
+    output_format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    info!(
+        "🎚️  Sweeping {} rates and {} pitches of {}",
+        options.rates.len(),
+        options.pitches.len().max(1),
+        voice
+    );
+    let sweep = sweep::prosody(client, text, voice, options).await?;
+    for sample in sweep.samples.iter().filter(|s| s.error.is_some()) {
+        warn!(
+            "❌ rate {}, pitch {}: {}",
+            sample.rate,
+            sample.pitch,
+            sample.error.as_deref().unwrap_or_default()
+        );
+    }
+    info!("✅ Open {:?} to listen", sweep.index);
+    output_format.print_item(&sweep)?;
+    Ok(())
+}
 
-    /// TTS backend to use (edge or google)
-    #[arg(short, long, default_value = "edge")]
-    backend: String,
+/// Speak one text in each speaking style of a voice
+async fn run_styles(
+    client: &TTSProcessor,
+    text: &str,
+    voice: &str,
+    options: &SweepOptions,
+    output_format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let sweep = sweep::styles(client, text, voice, options).await?;
+    for sample in sweep.samples.iter().filter(|s| s.error.is_some()) {
+        warn!(
+            "❌ {}: {}",
+            sample.style,
+            sample.error.as_deref().unwrap_or_default()
+        );
+    }
+    info!(
+        "✅ Open {:?} to listen to {} styles",
+        sweep.index,
+        sweep.samples.len()
+    );
+    output_format.print_item(&sweep)?;
+    Ok(())
+}
 
-    /// Output directory
-    #[arg(short, long, default_value = "output")]
-    output_dir: String,
+/// Turn the entries of a feed into podcast episodes
+async fn run_podcast(
+    client: &TTSProcessor,
+    source: &str,
+    voice: &str,
+    options: &PodcastOptions,
+    output_format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let feed = Feed::parse(&podcast::fetch(source).await?)?;
+    info!("📰 {} with {} entries", feed.title, feed.entries.len());
+    let podcast = podcast::render(client, &feed, voice, options).await?;
+    if podcast.failed > 0 {
+        warn!(
+            "❌ {} entries failed and will be retried on the next run",
+            podcast.failed
+        );
+    }
+    info!(
+        "✅ {} new episodes, {} in {:?}",
+        podcast.new_episodes,
+        podcast.episodes.len(),
+        podcast.feed
+    );
+    output_format.print_item(&podcast)?;
+    Ok(())
+}
 
-    /// Don't play audio after synthesis (default is to play)
-    #[arg(long)]
-    noplay: bool,
+/// Render a dialogue script into a single audio file
+async fn run_dialogue(
+    client: &TTSProcessor,
+    script: &Path,
+    voices: &[String],
+    gap_ms: u64,
+    output: &Path,
+    play: bool,
+    output_format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let input = if script == Path::new("-") {
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input)?;
+        input
+    } else {
+        fs::read_to_string(script)?
+    };
+    let dialogue = Dialogue::parse(&input)?;
 
-    /// List available voices
-    #[arg(short = 'l', long)]
-    list_voices: bool,
+    let mut options = DialogueOptions {
+        gap: std::time::Duration::from_millis(gap_ms),
+        ..DialogueOptions::default()
+    };
+    for mapping in voices {
+        let (speaker, voice) = mapping
+            .split_once('=')
+            .ok_or_else(|| format!("Expected SPEAKER=VOICE, got {}", mapping))?;
+        options
+            .voices
+            .insert(speaker.trim().to_string(), voice.trim().to_string());
+    }
 
-    /// Filter voices by language
-    #[arg(short = 'L', long)]
-    language: Option<String>,
+    info!(
+        "🎭 Rendering {} lines for {}",
+        dialogue.lines.len(),
+        dialogue.speakers().join(", ")
+    );
+    let rendered = dialogue::render(client, &dialogue, &options).await?;
+    let output = client.output_manager().resolve(output);
+    client
+        .save_audio(&rendered.audio, &output.to_string_lossy())
+        .await?;
+    info!("✅ Dialogue saved to {:?}", output);
+    output_format.print_list(&rendered.lines)?;
 
-    /// Run basic demo
-    #[arg(long)]
-    demo: bool,
+    if play {
+        open_sink(&client.config().audio_output)?.play(rendered.audio)?;
+    }
+    Ok(())
+}
 
-    /// Log level
-    #[arg(long, default_value = "info")]
-    log_level: String,
+/// Narrate subtitle cues into one track aligned to their timing
+async fn run_narrate(
+    client: &TTSProcessor,
+    subtitles: &Path,
+    options: &NarrationOptions,
+    output: Option<PathBuf>,
+    play: bool,
+    output_format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if options.max_speed.is_nan() || options.max_speed < 1.0 {
+        return Err("--max-speed must be at least 1".into());
+    }
+    let cues = narration::parse_srt(&fs::read_to_string(subtitles)?)?;
+    info!("🎬 Narrating {} cues with {}", cues.len(), options.voice);
+    let rendered = narration::render(client, &cues, options).await?;
+    for cue in rendered.cues.iter().filter(|cue| cue.truncated) {
+        warn!(
+            "Cue {} is cut short: {} ms of speech at {}x does not fit before the next cue",
+            cue.index, cue.speech_ms, cue.speed
+        );
+    }
+
+    let output = output.unwrap_or_else(|| {
+        let stem = subtitles.file_stem().unwrap_or_default();
+        Path::new(stem).with_extension(narration::encoding(client.config()).extension())
+    });
+    let output = client.output_manager().resolve(&output);
+    client
+        .save_audio(&rendered.audio, &output.to_string_lossy())
+        .await?;
+    info!("✅ Narration saved to {:?}", output);
+    output_format.print_list(&rendered.cues)?;
+
+    if play {
+        open_sink(&client.config().audio_output)?.play(rendered.audio)?;
+    }
+    Ok(())
 }
 
-/// Create output directory if it doesn't exist
-fn create_output_directory(directory: &str) -> std::io::Result<()> {
-    fs::create_dir_all(directory)
+/// Render a flashcard deck into a single audio file
+async fn run_flashcards(
+    client: &TTSProcessor,
+    args: FlashcardArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let cards = hello_tts_rust::flashcards::parse(&fs::read_to_string(&args.file)?)?;
+    let backend = client.backend_name();
+    let language_voice = |code: &Option<String>| -> Result<Option<String>, TTSError> {
+        match code {
+            Some(code) => {
+                let languages = load_languages(args.languages.as_deref())?;
+                let voice = languages.voice_for(code, backend).ok_or_else(|| {
+                    TTSError::Config(format!("No {} voice for language {}", backend, code))
+                })?;
+                Ok(Some(voice.to_string()))
+            }
+            None => Ok(None),
+        }
+    };
+    let source_voice = match args.source_voice {
+        Some(voice) => voice,
+        None => language_voice(&args.from)?.ok_or("Pass --from or --source-voice")?,
+    };
+    let translation_voice = match args.translation_voice {
+        Some(voice) => voice,
+        None => language_voice(&args.to)?.ok_or("Pass --to or --translation-voice")?,
+    };
+
+    let mut options = FlashcardOptions::new(&source_voice, &translation_voice);
+    options.answer_pause = std::time::Duration::from_millis(args.pause_ms);
+    options.card_gap = std::time::Duration::from_millis(args.gap_ms);
+    info!(
+        "🃏 Rendering {} cards: {} → {}",
+        cards.len(),
+        source_voice,
+        translation_voice
+    );
+    let rendered = flashcards::render(client, &cards, &options).await?;
+    let output = client.output_manager().resolve(&args.output);
+    client
+        .save_audio(&rendered.audio, &output.to_string_lossy())
+        .await?;
+    info!("✅ Flashcards saved to {:?}", output);
+
+    if !args.noplay {
+        open_sink(&client.config().audio_output)?.play(rendered.audio)?;
+    }
+    Ok(())
 }
 
-async fn handle_speak(
-    text: String,
-    voice: String,
-    backend: String,
-    output_dir: String,
-    play: bool,
+/// Export audio and an augmented deck for Anki
+async fn run_anki(
+    client: &TTSProcessor,
+    deck: &Path,
+    voice: &str,
+    output: Option<PathBuf>,
+    options: AnkiOptions,
+    output_format: OutputFormat,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    info!("🎤 Converting text to speech...");
-    info!("Backend: {}", backend);
-    info!("Text: {}", text);
-    info!("Voice: {}", voice);
+    let export = anki::export(client, &fs::read_to_string(deck)?, voice, &options).await?;
+    let output = output.unwrap_or_else(|| {
+        let stem = deck.file_stem().unwrap_or_default().to_string_lossy();
+        let ext = deck.extension().unwrap_or_default().to_string_lossy();
+        deck.with_file_name(format!("{}_anki.{}", stem, ext))
+    });
+    fs::write(&output, &export.csv)?;
+    info!(
+        "✅ {} new and {} reused sounds in {:?}",
+        export.report.synthesized + export.report.deduplicated,
+        export.report.completed,
+        export.media_dir
+    );
+    info!("📇 Copy them to collection.media, then import {:?}", output);
+    output_format.print_item(&export.report)?;
+    if export.report.failed > 0 {
+        return Err(format!("{} terms failed", export.report.failed).into());
+    }
+    Ok(())
+}
+
+/// Render a localization sheet as game-engine assets
+async fn run_assets(
+    client: &TTSProcessor,
+    sheet: &Path,
+    voice: &str,
+    options: AssetOptions,
+    output_format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let export = assets::export(client, &fs::read_to_string(sheet)?, voice, &options).await?;
+    let failed = export.report.failed();
+    info!(
+        "✅ {} assets in {:?}, {} rows without audio",
+        export.manifest.entries.len(),
+        export.report.batch.output_dir,
+        failed
+    );
+    info!("🎮 Import {:?} with the audio", export.manifest_path);
+    output_format.print_item(&export.manifest)?;
+    if failed > 0 {
+        let summary = &export.report.summary;
+        return Err(format!("{} rows have no audio, see {:?}", failed, summary).into());
+    }
+    Ok(())
+}
 
-    let mut config = TTSConfig::default();
-    config.backend = backend.clone();
-    let client = TTSProcessor::new(Some(config));
+/// Render a document chapter by chapter
+async fn run_render(
+    client: &TTSProcessor,
+    file: &Path,
+    voice: &str,
+    options: DocumentOptions,
+    output_format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let document = sources::open(file)?.to_document()?;
+    info!(
+        "📚 Rendering {} chapters with {}",
+        document.chapters.len(),
+        voice
+    );
+    let rendered = document::render(client, &document, voice, &options).await?;
+    if rendered.resumed > 0 {
+        info!("⏭️  Reused {} finished chapters", rendered.resumed);
+    }
+    info!("✅ Playlist written to {:?}", rendered.playlist);
+    output_format.print_item(&rendered)?;
+    Ok(())
+}
 
-    create_output_directory(&output_dir)?;
+/// Render a book as an audiobook in the chosen layout
+async fn run_audiobook(
+    client: &TTSProcessor,
+    file: &Path,
+    voice: &str,
+    options: &AudiobookOptions,
+    output_format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let document = sources::open(file)?.to_document()?;
+    info!(
+        "📚 Rendering {} chapters with {}",
+        document.chapters.len(),
+        voice
+    );
+    let book = audiobook::render(client, &document, voice, options).await?;
+    if book.document.resumed > 0 {
+        info!("⏭️  Reused {} finished chapters", book.document.resumed);
+    }
+    let minutes = book.duration_ms as f64 / 60_000.0;
+    info!(
+        "✅ {:.1} minutes of chapters in {:?}",
+        minutes, book.document.output_dir
+    );
+    if book.ffmetadata.is_some() {
+        info!(
+            "🎧 Build the M4B there with: ffmpeg -f concat -safe 0 -i files.txt \
+             -i metadata.txt -map_metadata 1 -c:a aac book.m4b"
+        );
+    }
+    sync_output(client, &book.document.output_dir).await?;
+    output_format.print_item(&book)?;
+    Ok(())
+}
 
-    let lang = voice.split('-').next().unwrap_or("unknown");
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    let output_path = Path::new(&output_dir).join(format!(
-        "{}_rust_{}_{}.mp3",
-        lang,
-        backend,
-        timestamp
-    ));
+/// Push `dir` to `TTSConfig.sync_target`, when set
+async fn sync_output(client: &TTSProcessor, dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(target) = &client.config().sync_target {
+        sync::push(&target.parse()?, dir).await?;
+        info!("📤 Synced {:?} to {}", dir, target);
+    }
+    Ok(())
+}
 
-    match client.synthesize_and_play(&text, &voice, Some(&output_path), play).await {
-        Ok(_) => {
-            info!("✅ Synthesis complete. Audio saved to {:?}", output_path);
-        }
-        Err(e) => {
-            error!("❌ Synthesis failed: {}", e);
+/// Synthesize a batch manifest, or report its progress with `status`
+#[allow(clippy::too_many_arguments)]
+async fn run_batch(
+    client: &TTSProcessor,
+    manifest: &Path,
+    voice: &str,
+    options: BatchOptions,
+    status: bool,
+    output_format: OutputFormat,
+    run_log: Option<RunLog>,
+    run_report: &mut RunReport,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let manifest = if manifest == Path::new("-") {
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input)?;
+        BatchManifest::parse(&input)?
+    } else {
+        BatchManifest::parse(&fs::read_to_string(manifest)?)?
+    };
+    let price = usage::price_per_million_chars(client.config(), client.backend_name());
+    let report = if status {
+        let dir = options
+            .output_dir
+            .unwrap_or_else(|| client.output_manager().dir().to_path_buf());
+        let mut report =
+            BatchReport::from_checkpoint(&dir, &manifest, voice, client.config().file_extension())?;
+        report.estimate_cost(price);
+        report
+    } else {
+        info!(
+            "📦 Synthesizing {} items with {}",
+            manifest.items.len(),
+            voice
+        );
+        if let Some(price) = price {
+            let chars: usize = manifest.items.iter().map(|i| i.text.chars().count()).sum();
+            info!(
+                "💰 At most {} characters, about ${:.2}",
+                chars,
+                chars as f64 * price / 1_000_000.0
+            );
         }
+        batch::synthesize(client, &manifest, voice, &options).await?
+    };
+    info!(
+        "✅ {} synthesized, {} already done, {} failed, {} pending in {:?}",
+        report.synthesized, report.completed, report.failed, report.pending, report.output_dir
+    );
+    if report.audio_minutes > 0.0 {
+        info!("⏱️  {:.1} minutes of audio", report.audio_minutes);
+    }
+    if report.deduplicated > 0 {
+        info!(
+            "♻️  {} duplicates reused existing audio, saving {} characters",
+            report.deduplicated, report.deduplicated_chars
+        );
+    }
+    if let Some(cost) = report.estimated_cost.filter(|_| report.characters > 0) {
+        info!(
+            "💰 {} characters synthesized, about ${:.2}",
+            report.characters, cost
+        );
     }
+    if let Some(cost) = report.pending_cost.filter(|_| report.pending > 0) {
+        info!(
+            "💰 {} characters pending, about ${:.2}",
+            report.pending_characters, cost
+        );
+    }
+    for item in &report.items {
+        run_report.push(ReportItem::from_batch_item(item, &report.output_dir));
+    }
+    if !status {
+        sync_output(client, &report.output_dir).await?;
+    }
+    if let Some(run_log) = run_log {
+        info!(
+            "📝 Report saved to {}",
+            run_log.save_report(&report)?.display()
+        );
+    }
+    // One result per item, so tools feeding NDJSON in can read results line by line
+    if output_format == OutputFormat::Ndjson {
+        output_format.print_list(&report.items)?;
+    } else {
+        output_format.print_item(&report)?;
+    }
+    if report.failed > 0 {
+        return Err(format!("{} batch items failed", report.failed).into());
+    }
+    Ok(())
+}
 
+/// Compare a rendering with a checksum manifest, failing on any drift
+fn verify_manifest(
+    manifest: &Path,
+    dir: Option<PathBuf>,
+    output_format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let baseline = ChecksumManifest::load(manifest)?;
+    let dir = dir
+        .or_else(|| manifest.parent().map(Path::to_path_buf))
+        .unwrap_or_default();
+    let drift = baseline.verify(&dir)?;
+    if output_format == OutputFormat::Text {
+        for problem in &drift {
+            warn!("⚠️  {}", problem);
+        }
+    } else {
+        output_format.print_list(&drift)?;
+    }
+    if !drift.is_empty() {
+        return Err(format!("{} differences from {}", drift.len(), manifest.display()).into());
+    }
+    info!(
+        "✅ {} files match {}",
+        baseline.files.len(),
+        manifest.display()
+    );
     Ok(())
 }
 
-async fn display_voices_by_language(
-    client: &mut TTSProcessor,
-    filter_language: Option<String>,
+#[cfg(feature = "history")]
+async fn handle_history(
+    action: HistoryAction,
+    config: &TTSConfig,
+    output_format: OutputFormat,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    match client.list_voices().await {
-        Ok(voices) => {
-            let mut voices_by_language: HashMap<String, Vec<&Voice>> = HashMap::new();
-            for voice in &voices {
-                voices_by_language
-                                        .entry(voice.locale.clone())
-                    .or_insert_with(Vec::new)
-                    .push(voice);
+    use hello_tts_rust::history::History;
+
+    let history = History::from_config(config)?;
+    let entries = match action {
+        HistoryAction::List { limit } => history.list(limit)?,
+        HistoryAction::Search { query, limit } => history.search(&query, limit)?,
+        HistoryAction::Replay { id } => {
+            let entry = history
+                .get(id)?
+                .ok_or_else(|| format!("No history entry {}", id))?;
+            match entry.output_path.as_ref().filter(|path| path.exists()) {
+                Some(path) => {
+                    info!("🔊 Playing {:?}", path);
+                    open_sink(&config.audio_output)?.play(fs::read(path)?)?;
+                }
+                None => {
+                    info!("🎤 Synthesizing entry {} again with {}", id, entry.backend);
+                    let processor = TTSProcessor::try_new(Some(TTSConfig {
+                        backend: entry.backend.clone(),
+                        ..config.clone()
+                    }))?;
+                    processor
+                        .synthesize_and_play(&entry.text, &entry.voice, None, true)
+                        .await?;
+                }
             }
+            return Ok(());
+        }
+    };
+    if output_format == OutputFormat::Text {
+        for entry in &entries {
+            info!(
+                "#{} {} [{} {}] {}",
+                entry.id, entry.created_at, entry.backend, entry.voice, entry.text
+            );
+        }
+    } else {
+        output_format.print_list(&entries)?;
+    }
+    Ok(())
+}
 
-            if let Some(lang) = filter_language {
-                if let Some(lang_voices) = voices_by_language.get(&lang) {
-                    info!(
-                        "{} Voices ({} voices):",
-                        lang.to_uppercase(),
-                        lang_voices.len()
-                    );
-                    for voice in lang_voices.iter().take(5) {
-                        info!("  {} - {} ({})", voice.name, voice.display_name, voice.gender);
-                    }
-                    if lang_voices.len() > 5 {
-                        info!("  ... and {} more voices", lang_voices.len() - 5);
+/// Play the newest saved audio of `text` in `voice`, or fail with a cache miss
+#[cfg(feature = "history")]
+fn replay_cached(
+    text: &str,
+    voice: &str,
+    config: &TTSConfig,
+    output_format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let history = hello_tts_rust::history::History::from_config(config)?;
+    let Some(entry) = history.find_cached(text, voice)? else {
+        return Err(format!("Cache miss: no saved audio of this text in {}", voice).into());
+    };
+    output_format.print_item(&entry)?;
+    if let Some(path) = &entry.output_path {
+        info!("🔊 Cache hit, playing {:?} from entry {}", path, entry.id);
+        open_sink(&config.audio_output)?.play(fs::read(path)?)?;
+    }
+    Ok(())
+}
+
+/// Synthesize the phrases of `manifest` that have no saved audio yet
+///
+/// Files are named after the hash of their text and voice, so reordering or
+/// extending the list and running again only synthesizes new phrases.
+#[cfg(feature = "history")]
+async fn precache(
+    client: &TTSProcessor,
+    manifest: &Path,
+    voice: &str,
+    output_dir: PathBuf,
+    output_format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let history = hello_tts_rust::history::History::from_config(client.config())?;
+    let manifest = BatchManifest::parse(&fs::read_to_string(manifest)?)?;
+    let total = manifest.items.len();
+    let ext = client.config().file_extension();
+    let mut seen = std::collections::HashSet::new();
+    let mut missing = Vec::new();
+    for mut item in manifest.items {
+        let hash = item.hash(voice);
+        let cached = history
+            .find_cached(&item.text, item.voice(voice))?
+            .is_some();
+        if cached || !seen.insert(hash.clone()) {
+            continue;
+        }
+        item.output
+            .get_or_insert_with(|| format!("{}.{}", hash, ext));
+        missing.push(item);
+    }
+    info!("📦 {} of {} phrases need audio", missing.len(), total);
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let manifest = BatchManifest { items: missing };
+    let options = BatchOptions {
+        output_dir: Some(output_dir),
+        resume: true,
+        upload: None,
+    };
+    let report = batch::synthesize(client, &manifest, voice, &options).await?;
+    // Files of an interrupted earlier run are skipped by the batch, but may
+    // not be in the history if it was deleted since
+    for item in &report.items {
+        if matches!(item.status, ItemStatus::Completed { .. }) {
+            history.record(&hello_tts_rust::history::NewEntry {
+                text: &manifest.items[item.index].text,
+                voice: &item.voice,
+                backend: client.backend_name(),
+                duration_ms: item.duration_ms,
+                elapsed_ms: 0,
+                output_path: Some(&report.output_dir.join(&item.file)),
+            })?;
+        }
+    }
+    info!(
+        "✅ {} cached, {} failed, {} pending in {:?}",
+        report.synthesized + report.completed,
+        report.failed,
+        report.pending,
+        report.output_dir
+    );
+    output_format.print_item(&report)?;
+    if report.failed > 0 {
+        return Err(format!("{} phrases failed", report.failed).into());
+    }
+    Ok(())
+}
+
+/// Re-synthesize changed files until Ctrl-C
+async fn run_watch(
+    client: &TTSProcessor,
+    path: &Path,
+    voice: &str,
+    debounce: std::time::Duration,
+    play: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut watcher = FileWatcher::new(path, debounce)?;
+    if let Err(e) = client.warm_up().await {
+        warn!(
+            "⚠️  Warm-up failed, the first change may be spoken slowly: {}",
+            e
+        );
+    }
+    info!("👀 Watching {:?}, press Ctrl-C to stop", path);
+    loop {
+        let changed = tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            changed = watcher.changed() => match changed {
+                Some(changed) => changed,
+                None => break,
+            },
+        };
+        for file in changed {
+            info!("✏️  {:?} changed", file);
+            match client.synthesize_file(&file, voice).await {
+                Ok(output) => {
+                    info!("✅ Saved to {:?}", output);
+                    if play {
+                        let audio = fs::read(&output)?;
+                        open_sink(&client.config().audio_output)?.play(audio)?;
                     }
-                } else {
-                    warn!("No voices found for language: {}", lang);
                 }
-            } else {
-                info!("Available voices by language:");
-                for (lang, lang_voices) in &voices_by_language {
-                    info!("{} ({} voices):", lang.to_uppercase(), lang_voices.len());
-                    for voice in lang_voices.iter().take(5) {
-                        info!("  {} - {} ({})", voice.name, voice.display_name, voice.gender);
-                    }
-                    if lang_voices.len() > 5 {
-                        info!("  ... and {} more voices", lang_voices.len() - 5);
+                Err(e) => error!("❌ {:?}: {}", file, e),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Speak new emails on every check of the mailbox until Ctrl-C
+#[cfg(feature = "mail")]
+async fn run_mail(
+    client: &TTSProcessor,
+    options: hello_tts_rust::mail::MailOptions,
+    voice: &str,
+    interval: std::time::Duration,
+    play: bool,
+    once: bool,
+    output_format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mailbox = format!("{} on {}", options.mailbox, options.server);
+    let mut watcher = hello_tts_rust::mail::MailWatcher::new(options);
+    info!(
+        "📬 Checking {} every {} s, press Ctrl-C to stop",
+        mailbox,
+        interval.as_secs()
+    );
+    loop {
+        match watcher.poll(client, voice).await {
+            Ok(spoken) => {
+                for email in &spoken {
+                    let subject = email.subject.as_deref().unwrap_or("Email");
+                    info!("✅ {} saved to {:?}", subject, email.file);
+                    output_format.print_item(email)?;
+                    if play {
+                        open_sink(&client.config().audio_output)?.play(fs::read(&email.file)?)?;
                     }
                 }
             }
+            Err(e) if once => return Err(e.into()),
+            Err(e) => error!("❌ Checking {} failed: {}", mailbox, e),
         }
-        Err(e) => {
-            error!("Error displaying voices: {}", e);
+        if once {
+            break;
+        }
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            _ = tokio::time::sleep(interval) => {}
         }
     }
     Ok(())
 }
 
-async fn run_demo(language: &str) -> Result<(), Box<dyn std::error::Error>> {
-    info!("Running demo for language: {}", language);
-    let text = match language {
-        "en" => "Hello, this is a demo of the text-to-speech system.",
-        "zh" => "你好，这是一个文本转语音系统的演示。",
-        "ja" => "こんにちは、これはテキスト読み上げシステムのデモです。",
-        _ => {
-            error!("Unsupported language for demo: {}", language);
-            return Ok(());
-        }
-    };
-    let voice = match language {
-        "en" => "en-US-AriaNeural",
-        "zh" => "zh-CN-XiaoxiaoNeural",
-        "ja" => "ja-JP-NanamiNeural",
-        _ => "en-US-AriaNeural",
+/// Apply the output directory limits and log what was removed
+/// Print the diagnostics table; exits with status 1 if the backend is unusable
+async fn run_doctor(
+    config: &TTSConfig,
+    output_format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    info!(
+        "🩺 Checking the {} backend and its environment...",
+        config.backend
+    );
+    let report = hello_tts_rust::doctor::diagnose(config).await;
+    if output_format == OutputFormat::Text {
+        print!("{}", report.to_table());
+    }
+    output_format.print_item(&report)?;
+    if !report.is_usable() {
+        error!("❌ The {} backend is not usable", report.backend);
+        std::process::exit(1);
+    }
+    info!("✅ The {} backend is ready", report.backend);
+    Ok(())
+}
+
+/// Benchmark every target and print latency percentiles
+async fn run_bench(
+    client: &TTSProcessor,
+    text: &str,
+    targets: &[BenchTarget],
+    options: &BenchOptions,
+    output_format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    info!(
+        "⏱️  Benchmarking {} targets, {} runs each",
+        targets.len(),
+        options.runs
+    );
+    if let Some(rpm) = client.config().requests_per_minute.filter(|&rpm| rpm > 0) {
+        warn!(
+            "Waits for the rate limit ({} requests per minute) count as latency; \
+             set HELLO_TTS_REQUESTS_PER_MINUTE=0 to measure the backends alone",
+            rpm
+        );
+    }
+    let report = bench::run(client, text, targets, options).await?;
+    if output_format == OutputFormat::Text {
+        print!("{}", report.to_table());
+    }
+    output_format.print_item(&report)?;
+    Ok(())
+}
+
+fn clean_output(config: &TTSConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let manager = OutputManager::from_config(config);
+    let report = manager.cleanup()?;
+    if report.removed.is_empty() {
+        info!("🧹 Nothing to clean in {}", manager.dir().display());
+    } else {
+        info!(
+            "🧹 Removed {} files ({} bytes) from {}",
+            report.removed.len(),
+            report.freed_bytes,
+            manager.dir().display()
+        );
+    }
+    Ok(())
+}
+
+/// `path`, or the shared language list wherever the CLI runs from
+fn load_languages(path: Option<&Path>) -> Result<TTSConfigFile, TTSError> {
+    match path {
+        Some(path) => TTSConfigFile::load(path),
+        None => resolve_shared_config(),
+    }
+}
+
+async fn run_demo(
+    languages: &TTSConfigFile,
+    language: Option<&str>,
+    base_config: &TTSConfig,
+    run_report: &mut RunReport,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let selected: Vec<&LanguageConfig> = match language {
+        Some(code) => vec![languages
+            .language(code)
+            .ok_or_else(|| format!("Unsupported language for demo: {}", code))?],
+        None => languages.languages.iter().collect(),
     };
+    let backend = base_config.backend.as_str();
 
-    handle_speak(text.to_string(), voice.to_string(), "edge".to_string(), "output".to_string(), true).await?;
+    for lang in selected {
+        let (Some(text), Some(voice)) = (lang.text.as_deref(), lang.voice(backend)) else {
+            let warning = format!(
+                "No {} demo text or voice for {}, skipping",
+                backend, lang.code
+            );
+            warn!("{}", warning);
+            run_report.warn(warning);
+            continue;
+        };
+        info!(
+            "Running demo for {} {} ({})",
+            lang.flag.as_deref().unwrap_or(""),
+            lang.name,
+            lang.code
+        );
+        let job = SpeakJob {
+            text: text.to_string(),
+            voice: voice.to_string(),
+            output_dir: base_config.output_directory.clone(),
+            play: true,
+            dry_run: false,
+            visemes_path: None,
+            subtitles_path: None,
+            output: None,
+            config: base_config.clone(),
+        };
+        let item = handle_speak(job, OutputFormat::Text).await?;
+        run_report.push(ReportItem {
+            id: lang.code.clone(),
+            ..item
+        });
+    }
     Ok(())
 }
 
+/// Token cancelled by the first Ctrl-C, shared by every processor the CLI creates
+fn cancellation() -> CancellationToken {
+    static TOKEN: std::sync::OnceLock<CancellationToken> = std::sync::OnceLock::new();
+    TOKEN.get_or_init(CancellationToken::new).clone()
+}
+
+/// Cancel running synthesis on Ctrl-C, giving backends a moment to kill their
+/// subprocesses and remove temp files before the process exits
+async fn cancel_on_ctrl_c() {
+    if tokio::signal::ctrl_c().await.is_err() {
+        return;
+    }
+    warn!("Cancelling, press Ctrl-C again to quit immediately");
+    cancellation().cancel();
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = tokio::time::sleep(std::time::Duration::from_secs(2)) => {}
+    }
+    std::process::exit(130);
+}
+
+/// Insert `speak` into arguments that name no command, so the top-level form
+/// `hello_tts --text Hi` stays an alias of `hello_tts speak --text Hi`
+fn speak_by_default(mut args: Vec<OsString>) -> Vec<OsString> {
+    let alias = match Cli::command().try_get_matches_from(&args) {
+        Ok(matches) => {
+            matches.subcommand_name().is_none()
+                && !matches.get_flag("list_voices")
+                && !matches.get_flag("demo")
+        }
+        Err(e) => matches!(
+            e.kind(),
+            clap::error::ErrorKind::UnknownArgument | clap::error::ErrorKind::InvalidSubcommand
+        ),
+    };
+    if alias {
+        args.insert(1.min(args.len()), OsString::from("speak"));
+    }
+    args
+}
+
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
+async fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let matches = Cli::command().get_matches_from(speak_by_default(std::env::args_os().collect()));
+    let cli = Cli::from_arg_matches(&matches)?;
 
     let log_level = match cli.log_level.to_lowercase().as_str() {
         "error" => LevelFilter::Error,
@@ -181,21 +2382,443 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         _ => LevelFilter::Info,
     };
 
-    env_logger::Builder::new().filter_level(log_level).init();
+    let mut config = load_layered_with_profile(cli.profile.as_deref())?;
+    if let Some(backend) = &cli.backend {
+        config.backend = backend.clone();
+    }
+    if let Some(format) = &cli.audio_format {
+        config.output_format = format.clone();
+    }
+    config.sample_rate = cli.sample_rate.or(config.sample_rate);
+    config.channels = cli.channels.or(config.channels);
+    config.loudness_preset = cli.loudness_preset.or(config.loudness_preset);
+    config.deterministic |= cli.deterministic;
+    if let Some(Commands::Batch {
+        sync: Some(target), ..
+    })
+    | Some(Commands::Audiobook {
+        sync: Some(target), ..
+    }) = &cli.command
+    {
+        config.sync_target = Some(target.clone());
+    }
+    if let Some(target) = &config.sync_target {
+        target.parse::<SyncTarget>()?;
+    }
+    config.validate()?;
 
-    let mut config = TTSConfig::default();
-    config.backend = cli.backend.clone();
-    let mut client = TTSProcessor::new(Some(config));
+    let mut logger = env_logger::Builder::new();
+    logger.filter_level(log_level);
+    // Tee to a per-run log file when `log_file` is configured
+    let run_log = logging::start_run(&config, matches.subcommand_name().unwrap_or("speak"))?;
+    if run_log.is_some() {
+        logger.target(env_logger::Target::Pipe(Box::new(logging::LogWriter)));
+    }
+    logger.init();
+    if let Some(run_log) = &run_log {
+        info!("📝 Logging to {}", run_log.path().display());
+    }
+    if config.cleanup_on_startup {
+        if let Err(e) = clean_output(&config) {
+            warn!("Output directory cleanup failed: {}", e);
+        }
+    }
+    tokio::spawn(cancel_on_ctrl_c());
+    let client = TTSProcessor::try_new(Some(config.clone()))?.with_cancellation(cancellation());
+    let command = matches
+        .subcommand_name()
+        .unwrap_or(if cli.demo { "demo" } else { "voices" });
+    let mut run_report = RunReport::new(command, &config.backend);
+    let report_path = cli.report.clone();
+    if report_path.is_some() && !matches!(command, "speak" | "batch" | "demo") {
+        warn!("--report is only written by speak, batch and demo");
+    }
+    let mut exit_code = ExitCode::SUCCESS;
 
-    if cli.list_voices {
-        display_voices_by_language(&mut client, cli.language).await?;
-    } else if cli.demo {
-        let lang = cli.language.unwrap_or_else(|| "en".to_string());
-        run_demo(&lang).await?;
-    } else {
-        // text now has a default value, so we can always use it
-        handle_speak(cli.text, cli.voice, cli.backend, cli.output_dir, !cli.noplay).await?;
+    match cli.command {
+        Some(Commands::Speak(args)) => {
+            let result = speak(args, &config, cli.output_format, &mut run_report).await;
+            exit_code = finish_report(result, &mut run_report, report_path.as_deref())?
+        }
+        Some(Commands::Voices {
+            default_for: Some(locale),
+            gender,
+            ..
+        }) => print_default_voice(&client, &locale, gender, cli.output_format).await?,
+        Some(Commands::Voices {
+            language,
+            export: Some(path),
+            ..
+        }) => export_voices(&client, &path, language.as_deref()).await?,
+        Some(Commands::Voices { language, .. }) => {
+            display_voices_by_language(&client, language, cli.output_format).await?
+        }
+        Some(Commands::Audition {
+            language,
+            text,
+            output_dir,
+            noplay,
+            limit,
+        }) => run_audition(&client, &language, &text, output_dir, !noplay, limit).await?,
+        Some(Commands::Compare {
+            text,
+            voices,
+            language,
+            limit,
+            output_dir,
+        }) => {
+            let options = CompareOptions { output_dir };
+            run_compare(
+                &client,
+                &text,
+                &voices,
+                language.as_deref(),
+                limit,
+                options,
+                cli.output_format,
+            )
+            .await?
+        }
+        Some(Commands::Sweep {
+            text,
+            voice,
+            rates,
+            pitches,
+            output_dir,
+        }) => {
+            let voice = voice.unwrap_or_else(|| client.config().default_voice.clone());
+            let pitches = pitches.as_deref().map(sweep::parse_values).transpose()?;
+            let options = SweepOptions {
+                rates: sweep::parse_values(&rates)?,
+                pitches: pitches.unwrap_or_default(),
+                output_dir,
+                ..SweepOptions::default()
+            };
+            run_sweep(&client, &text, &voice, &options, cli.output_format).await?
+        }
+        Some(Commands::Styles {
+            text,
+            voice,
+            styles,
+            output_dir,
+        }) => {
+            let voice = voice.unwrap_or_else(|| client.config().default_voice.clone());
+            let options = SweepOptions {
+                styles,
+                output_dir,
+                ..SweepOptions::default()
+            };
+            run_styles(&client, &text, &voice, &options, cli.output_format).await?
+        }
+        Some(Commands::Podcast {
+            feed,
+            voice,
+            output_dir,
+            base_url,
+            limit,
+        }) => {
+            let voice = voice.unwrap_or_else(|| client.config().default_voice.clone());
+            let options = PodcastOptions {
+                output_dir,
+                base_url,
+                max_episodes: limit,
+            };
+            run_podcast(&client, &feed, &voice, &options, cli.output_format).await?
+        }
+        Some(Commands::Demo {
+            language,
+            all,
+            languages,
+        }) => {
+            let languages = load_languages(languages.as_deref())?;
+            let language = (!all).then_some(language.as_str());
+            let result = run_demo(&languages, language, &config, &mut run_report).await;
+            exit_code = finish_report(result, &mut run_report, report_path.as_deref())?
+        }
+        Some(Commands::Completions { shell }) => clap_complete::generate(
+            shell,
+            &mut Cli::command(),
+            "hello-tts-rust",
+            &mut io::stdout(),
+        ),
+        Some(Commands::Dialogue {
+            script,
+            voices,
+            gap_ms,
+            output,
+            noplay,
+        }) => {
+            run_dialogue(
+                &client,
+                &script,
+                &voices,
+                gap_ms,
+                &output,
+                !noplay,
+                cli.output_format,
+            )
+            .await?
+        }
+        Some(Commands::Narrate {
+            subtitles,
+            voice,
+            max_speed,
+            output,
+            noplay,
+        }) => {
+            let voice = voice.unwrap_or_else(|| client.config().default_voice.clone());
+            let options = NarrationOptions {
+                max_speed,
+                ..NarrationOptions::new(&voice)
+            };
+            run_narrate(
+                &client,
+                &subtitles,
+                &options,
+                output,
+                !noplay,
+                cli.output_format,
+            )
+            .await?
+        }
+        Some(Commands::Anki {
+            deck,
+            voice,
+            column,
+            header,
+            media_dir,
+            output,
+        }) => {
+            let voice = voice.unwrap_or_else(|| config.default_voice.clone());
+            let options = AnkiOptions {
+                column,
+                header,
+                media_dir,
+            };
+            run_anki(&client, &deck, &voice, output, options, cli.output_format).await?
+        }
+        Some(Commands::Assets {
+            sheet,
+            voice,
+            engine,
+            root,
+            text_column,
+            voice_column,
+            id_column,
+            output_dir,
+            resume,
+        }) => {
+            let voice = voice.unwrap_or_else(|| config.default_voice.clone());
+            let options = AssetOptions {
+                sheet: SheetOptions {
+                    text_column,
+                    voice_column,
+                    id_column: Some(id_column),
+                    output_dir,
+                    resume,
+                },
+                engine,
+                root,
+            };
+            run_assets(&client, &sheet, &voice, options, cli.output_format).await?
+        }
+        Some(Commands::Flashcards(args)) => run_flashcards(&client, args).await?,
+        Some(Commands::Render {
+            file,
+            voice,
+            output_dir,
+            resume,
+        }) => {
+            let voice = voice.unwrap_or_else(|| config.default_voice.clone());
+            let options = DocumentOptions { output_dir, resume };
+            run_render(&client, &file, &voice, options, cli.output_format).await?
+        }
+        Some(Commands::Audiobook {
+            file,
+            voice,
+            output_dir,
+            layout,
+            resume,
+            ..
+        }) => {
+            let voice = voice.unwrap_or_else(|| config.default_voice.clone());
+            let options = AudiobookOptions {
+                output_dir,
+                resume,
+                layout,
+            };
+            run_audiobook(&client, &file, &voice, &options, cli.output_format).await?
+        }
+        Some(Commands::Batch {
+            manifest,
+            voice,
+            output_dir,
+            resume,
+            upload,
+            status,
+            ..
+        }) => {
+            let voice = voice.unwrap_or_else(|| config.default_voice.clone());
+            let upload = upload.as_deref().map(OutputTarget::parse).transpose()?;
+            let options = BatchOptions {
+                output_dir,
+                resume,
+                upload,
+            };
+            let output_format = cli.output_format;
+            let result = run_batch(
+                &client,
+                &manifest,
+                &voice,
+                options,
+                status,
+                output_format,
+                run_log,
+                &mut run_report,
+            )
+            .await;
+            exit_code = finish_report(result, &mut run_report, report_path.as_deref())?
+        }
+        Some(Commands::VerifyManifest { manifest, dir }) => {
+            verify_manifest(&manifest, dir, cli.output_format)?
+        }
+        Some(Commands::Watch {
+            path,
+            voice,
+            debounce_ms,
+            play,
+        }) => {
+            let voice = voice.unwrap_or_else(|| config.default_voice.clone());
+            let debounce = std::time::Duration::from_millis(debounce_ms);
+            run_watch(&client, &path, &voice, debounce, play).await?
+        }
+        Some(Commands::Clean {
+            max_size_mb,
+            max_age_days,
+        }) => clean_output(&TTSConfig {
+            output_max_size_mb: max_size_mb.or(config.output_max_size_mb),
+            output_max_age_days: max_age_days.or(config.output_max_age_days),
+            ..config.clone()
+        })?,
+        Some(Commands::Doctor) => run_doctor(&config, cli.output_format).await?,
+        Some(Commands::Bench {
+            text,
+            mut targets,
+            runs,
+            warmup,
+        }) => {
+            if targets.is_empty() {
+                targets.push(BenchTarget {
+                    backend: config.backend.clone(),
+                    voice: config.default_voice.clone(),
+                });
+            }
+            let options = BenchOptions { runs, warmup };
+            run_bench(&client, &text, &targets, &options, cli.output_format).await?
+        }
+        Some(Commands::Config { action }) => handle_config(action, cli.profile.as_deref())?,
+        #[cfg(feature = "server")]
+        Some(Commands::Serve { addr }) => hello_tts_rust::server::serve(client, addr).await?,
+        #[cfg(feature = "history")]
+        Some(Commands::History { action }) => {
+            handle_history(action, &config, cli.output_format).await?
+        }
+        #[cfg(feature = "history")]
+        Some(Commands::Replay { text, voice }) => {
+            let voice = voice.unwrap_or_else(|| config.default_voice.clone());
+            replay_cached(&text, &voice, &config, cli.output_format)?
+        }
+        #[cfg(feature = "history")]
+        Some(Commands::Precache {
+            manifest,
+            voice,
+            output_dir,
+        }) => {
+            let voice = voice.unwrap_or_else(|| config.default_voice.clone());
+            let output_dir =
+                output_dir.unwrap_or_else(|| client.output_manager().dir().join("cache"));
+            precache(&client, &manifest, &voice, output_dir, cli.output_format).await?
+        }
+        #[cfg(feature = "hotkey")]
+        Some(Commands::Daemon { voice, hotkey }) => {
+            let voice = voice.unwrap_or_else(|| config.default_voice.clone());
+            let config = TTSConfig {
+                hotkey: hotkey.unwrap_or_else(|| config.hotkey.clone()),
+                ..config.clone()
+            };
+            let processor = TTSProcessor::try_new(Some(config))?.with_cancellation(cancellation());
+            let daemon = hello_tts_rust::daemon::SpeakDaemon::new(processor, &voice)?;
+            info!("⌨️  Press the hotkey to speak the selection, again to stop; Ctrl-C quits");
+            daemon
+                .run(async {
+                    let _ = tokio::signal::ctrl_c().await;
+                })
+                .await?
+        }
+        #[cfg(feature = "mail")]
+        Some(Commands::Mail {
+            server,
+            port,
+            user,
+            password,
+            mailbox,
+            voice,
+            output_dir,
+            interval,
+            play,
+            once,
+        }) => {
+            let voice = voice.unwrap_or_else(|| config.default_voice.clone());
+            let options = hello_tts_rust::mail::MailOptions {
+                server,
+                port,
+                username: user,
+                password: secrets::resolve(&password)?,
+                mailbox,
+                output_dir: output_dir
+                    .unwrap_or_else(|| client.output_manager().dir().join("mail")),
+            };
+            let interval = std::time::Duration::from_secs(interval.max(1));
+            run_mail(
+                &client,
+                options,
+                &voice,
+                interval,
+                play,
+                once,
+                cli.output_format,
+            )
+            .await?
+        }
+        None if cli.demo => {
+            let languages = resolve_shared_config()?;
+            let lang = cli.language.unwrap_or_else(|| "en".to_string());
+            let result = run_demo(&languages, Some(&lang), &config, &mut run_report).await;
+            exit_code = finish_report(result, &mut run_report, report_path.as_deref())?
+        }
+        None => display_voices_by_language(&client, cli.language, cli.output_format).await?,
     }
 
-    Ok(())
+    Ok(exit_code)
+}
+
+/// Finish the run's report, save it to `path` if given, and return its exit code, which is not
+/// 0 when any item failed; without a path an error ending the run is returned as is
+fn finish_report(
+    result: Result<(), Box<dyn std::error::Error>>,
+    run_report: &mut RunReport,
+    path: Option<&Path>,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let Some(path) = path else {
+        result?;
+        run_report.finish(None);
+        return Ok(ExitCode::from(run_report.exit_code as u8));
+    };
+    if let Err(e) = &result {
+        error!("❌ {}", e);
+    }
+    run_report.finish(result.err().map(|e| e.to_string()));
+    run_report.save(path)?;
+    info!("📋 Run report saved to {}", path.display());
+    Ok(ExitCode::from(run_report.exit_code as u8))
 }
@@ -0,0 +1,202 @@
+//! Estimated word timings and subtitles.
+//!
+//! Edge reports when each word is spoken; Google and gTTS report nothing. With
+//! `TTSConfig.estimate_timings` set, [`TTSProcessor`](crate::TTSProcessor)
+//! fills the gap with [`estimate_boundaries`], which spreads the measured
+//! duration of each chunk over its words in proportion to their length, with
+//! extra time for the pauses after commas and sentence ends. The estimates
+//! drift within a sentence but are good enough for subtitles, which
+//! [`to_srt`] produces the same way from reported and estimated timings.
+
+use crate::models::WordBoundary;
+use crate::validation;
+use std::fmt::Write;
+
+/// Longest subtitle cue, in characters
+pub const MAX_CUE_CHARS: usize = 42;
+
+/// Marks ending a sentence, after which a cue ends
+const SENTENCE_ENDS: [char; 9] = ['.', '!', '?', '…', '。', '！', '？', '؟', '۔'];
+
+/// Pause after a clause, in units of one spoken character
+const CLAUSE_PAUSE: usize = 3;
+/// Pause after a sentence, in units of one spoken character
+const SENTENCE_PAUSE: usize = 6;
+
+/// Spread `duration_ms` over the words of `text`
+///
+/// SSML tags are skipped. Chinese and Japanese characters, which are written
+/// without spaces, are timed one by one.
+pub fn estimate_boundaries(text: &str, duration_ms: u64) -> Vec<WordBoundary> {
+    let words = words(text);
+    let weights: Vec<(usize, usize)> = words
+        .iter()
+        .map(|word| {
+            let spoken = word.chars().filter(|c| c.is_alphanumeric()).count().max(1);
+            let pause = if word.ends_with(SENTENCE_ENDS) {
+                SENTENCE_PAUSE
+            } else if word.ends_with([',', ';', ':', '、', '，', '；', '،', '؛']) {
+                CLAUSE_PAUSE
+            } else {
+                0
+            };
+            (spoken, pause)
+        })
+        .collect();
+    let total: usize = weights.iter().map(|(spoken, pause)| spoken + pause).sum();
+    if total == 0 {
+        return Vec::new();
+    }
+
+    let ms = |units: usize| (units as u64 * duration_ms) / total as u64;
+    let mut elapsed = 0;
+    words
+        .into_iter()
+        .zip(weights)
+        .map(|(text, (spoken, pause))| {
+            let boundary = WordBoundary {
+                offset_ms: ms(elapsed),
+                duration_ms: ms(elapsed + spoken) - ms(elapsed),
+                text,
+            };
+            elapsed += spoken + pause;
+            boundary
+        })
+        .collect()
+}
+
+/// SubRip subtitles, grouping words into cues of up to [`MAX_CUE_CHARS`]
+/// characters that end at sentence ends
+pub fn to_srt(boundaries: &[WordBoundary]) -> String {
+    let mut cues: Vec<(u64, u64, String)> = Vec::new();
+    let mut open = false;
+    for boundary in boundaries {
+        let end = boundary.offset_ms + boundary.duration_ms;
+        match cues.last_mut() {
+            Some((_, cue_end, text))
+                if open && text.chars().count() + boundary.text.chars().count() < MAX_CUE_CHARS =>
+            {
+                let last = text.chars().last().is_some_and(is_cjk);
+                if !(last && boundary.text.starts_with(is_cjk)) {
+                    text.push(' ');
+                }
+                text.push_str(&boundary.text);
+                *cue_end = end;
+            }
+            _ => cues.push((boundary.offset_ms, end, boundary.text.clone())),
+        }
+        open = !boundary.text.ends_with(SENTENCE_ENDS);
+    }
+
+    let mut srt = String::new();
+    for (index, (start, end, text)) in cues.iter().enumerate() {
+        let _ = writeln!(
+            srt,
+            "{}\n{} --> {}\n{}\n",
+            index + 1,
+            timestamp(*start),
+            timestamp(*end),
+            text
+        );
+    }
+    srt
+}
+
+/// `HH:MM:SS,mmm`
+fn timestamp(ms: u64) -> String {
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+fn words(text: &str) -> Vec<String> {
+    let text = if validation::looks_like_ssml(text) {
+        strip_tags(text)
+    } else {
+        text.to_string()
+    };
+    let mut words: Vec<String> = Vec::new();
+    for token in text.split_whitespace() {
+        let mut word = String::new();
+        for c in token.chars() {
+            // Every Han or kana character starts a word, and so does the
+            // first letter after one; punctuation stays with the word before
+            let after_cjk = word.chars().last().is_some_and(is_cjk);
+            if c.is_alphanumeric() && (is_cjk(c) || after_cjk) && !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            word.push(c);
+        }
+        if !word.is_empty() {
+            words.push(word);
+        }
+    }
+    words
+}
+
+fn strip_tags(text: &str) -> String {
+    let mut plain = String::new();
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                plain.push(' ');
+            }
+            _ if !in_tag => plain.push(c),
+            _ => {}
+        }
+    }
+    plain
+}
+
+/// Han, kana and CJK punctuation
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3000}'..='\u{30FF}' | '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}'
+        | '\u{F900}'..='\u{FAFF}' | '\u{FF00}'..='\u{FF60}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_spreads_duration_with_pauses() {
+        let boundaries = estimate_boundaries("<speak>Hi, you there.</speak>", 1300);
+        let words: Vec<(&str, u64, u64)> = boundaries
+            .iter()
+            .map(|b| (b.text.as_str(), b.offset_ms, b.duration_ms))
+            .collect();
+        // 2 + 3 (pause) + 3 + 5 + 6 (pause) = 19 units of about 68 ms
+        assert_eq!(
+            words,
+            [("Hi,", 0, 136), ("you", 342, 205), ("there.", 547, 342)]
+        );
+        assert!(estimate_boundaries("", 1000).is_empty());
+
+        let cjk: Vec<String> = estimate_boundaries("你好。OK", 1000)
+            .into_iter()
+            .map(|b| b.text)
+            .collect();
+        assert_eq!(cjk, ["你", "好。", "OK"]);
+    }
+
+    #[test]
+    fn test_srt_cues_end_at_sentences() {
+        // 5 + 5 + 6 (pause) + 7 + 6 (pause) = 29 units of about 69 ms
+        let boundaries = estimate_boundaries("Hello world. Goodbye.", 2000);
+        assert_eq!(
+            to_srt(&boundaries),
+            "1\n00:00:00,000 --> 00:00:00,689\nHello world.\n\n\
+             2\n00:00:01,103 --> 00:00:01,586\nGoodbye.\n\n"
+        );
+        let arabic = to_srt(&estimate_boundaries("كيف حالك؟ بخير، شكرا.", 2000));
+        assert!(arabic.contains("\nكيف حالك؟\n\n2\n"));
+    }
+}
@@ -0,0 +1,301 @@
+//! Duration, peak level and loudness of synthesized audio.
+//!
+//! Loudness is the integrated loudness of ITU-R BS.1770 (K-weighting,
+//! 400 ms blocks with 75 % overlap, absolute gate at -70 LUFS and relative
+//! gate at -10 LU), as used by EBU R 128 and the streaming platforms.
+//! [`normalize_loudness`] brings audio to the loudness of a
+//! [`LoudnessPreset`], so voices and backends that differ widely in level
+//! sound alike.
+
+use crate::audio_player::AudioError;
+use crate::convert::Pcm;
+use serde::{Deserialize, Serialize};
+#[cfg(not(target_arch = "wasm32"))]
+use tracing::debug;
+
+/// Measurements of decoded audio
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AudioStats {
+    pub duration_ms: u64,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Sample peak in dBFS; `None` for digital silence
+    pub peak_dbfs: Option<f64>,
+    /// Integrated loudness in LUFS; `None` when the audio is too short or
+    /// too quiet to pass the gates
+    pub loudness_lufs: Option<f64>,
+}
+
+impl AudioStats {
+    /// Measure interleaved 16-bit samples
+    pub fn from_samples(samples: &[i16], channels: u16, sample_rate: u32) -> Self {
+        let channels = channels.max(1);
+        let frames = samples.len() / channels as usize;
+        let peak = samples
+            .iter()
+            .map(|s| (*s as f64 / 32768.0).abs())
+            .fold(0.0, f64::max);
+        Self {
+            duration_ms: frames as u64 * 1000 / u64::from(sample_rate.max(1)),
+            sample_rate,
+            channels,
+            peak_dbfs: (peak > 0.0).then(|| 20.0 * peak.log10()),
+            loudness_lufs: integrated_loudness(samples, channels as usize, sample_rate),
+        }
+    }
+
+    /// Duration in minutes
+    pub fn minutes(&self) -> f64 {
+        self.duration_ms as f64 / 60_000.0
+    }
+}
+
+/// Loudness targets for `TTSConfig.loudness_preset`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoudnessPreset {
+    /// -16 LUFS, the level of most podcast platforms
+    Podcast,
+    /// -23 LUFS, EBU R 128
+    Broadcast,
+    /// -18 LUFS with extra headroom for narrowband codecs
+    Telephony,
+}
+
+impl LoudnessPreset {
+    /// Integrated loudness to reach, in LUFS
+    pub fn target_lufs(self) -> f64 {
+        match self {
+            Self::Podcast => -16.0,
+            Self::Broadcast => -23.0,
+            Self::Telephony => -18.0,
+        }
+    }
+
+    /// Highest sample peak allowed, in dBFS
+    pub fn peak_ceiling_dbfs(self) -> f64 {
+        match self {
+            Self::Podcast | Self::Broadcast => -1.0,
+            Self::Telephony => -3.0,
+        }
+    }
+}
+
+impl std::fmt::Display for LoudnessPreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Podcast => "podcast",
+            Self::Broadcast => "broadcast",
+            Self::Telephony => "telephony",
+        };
+        f.write_str(name)
+    }
+}
+
+impl std::str::FromStr for LoudnessPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "podcast" => Ok(Self::Podcast),
+            "broadcast" | "ebu-r128" | "r128" => Ok(Self::Broadcast),
+            "telephony" => Ok(Self::Telephony),
+            other => Err(format!("Unknown loudness preset: {}", other)),
+        }
+    }
+}
+
+/// Scale `pcm` to the loudness of `preset`
+///
+/// The gain is lowered where it would push the sample peak above the
+/// preset's ceiling, so quiet audio with loud peaks ends up below the target
+/// rather than clipped. Audio too short or quiet to measure is unchanged.
+pub fn normalize_loudness(mut pcm: Pcm, preset: LoudnessPreset) -> Pcm {
+    let stats = AudioStats::from_samples(&pcm.samples, pcm.channels, pcm.sample_rate);
+    let (Some(loudness), Some(peak)) = (stats.loudness_lufs, stats.peak_dbfs) else {
+        return pcm;
+    };
+    let gain_db = (preset.target_lufs() - loudness).min(preset.peak_ceiling_dbfs() - peak);
+    let gain = 10f64.powf(gain_db / 20.0);
+    for sample in &mut pcm.samples {
+        *sample = (f64::from(*sample) * gain).round().clamp(-32768.0, 32767.0) as i16;
+    }
+    pcm
+}
+
+/// Decode MP3, WAV, OGG or FLAC audio and measure it
+pub fn analyze(audio: &[u8]) -> Result<AudioStats, AudioError> {
+    let pcm = Pcm::decode(audio)?;
+    Ok(AudioStats::from_samples(
+        &pcm.samples,
+        pcm.channels,
+        pcm.sample_rate,
+    ))
+}
+
+/// Second-order IIR filter in direct form I
+#[derive(Debug, Clone, Copy, Default)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
+
+/// The two K-weighting stages of BS.1770 for `sample_rate`
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let fs = f64::from(sample_rate);
+
+    // High shelf modelling the acoustic effect of the head
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (std::f64::consts::PI * f0 / fs).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        ..Biquad::default()
+    };
+
+    // High pass (RLB weighting)
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (std::f64::consts::PI * f0 / fs).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        ..Biquad::default()
+    };
+    [shelf, high_pass]
+}
+
+fn integrated_loudness(samples: &[i16], channels: usize, sample_rate: u32) -> Option<f64> {
+    let frames = samples.len() / channels;
+    let block = (sample_rate as usize * 400) / 1000;
+    let step = block / 4;
+    if block == 0 || frames < block {
+        return None;
+    }
+
+    // K-weighted energy of every frame, summed over channels
+    let mut filters = vec![k_weighting(sample_rate); channels];
+    let energy: Vec<f64> = samples
+        .chunks_exact(channels)
+        .map(|frame| {
+            frame
+                .iter()
+                .zip(filters.iter_mut())
+                .map(|(sample, [shelf, high_pass])| {
+                    let y = high_pass.process(shelf.process(*sample as f64 / 32768.0));
+                    y * y
+                })
+                .sum::<f64>()
+        })
+        .collect();
+
+    let mut prefix = Vec::with_capacity(energy.len() + 1);
+    prefix.push(0.0);
+    for e in &energy {
+        prefix.push(prefix.last().unwrap() + e);
+    }
+    let blocks: Vec<f64> = (0..=frames - block)
+        .step_by(step)
+        .map(|start| (prefix[start + block] - prefix[start]) / block as f64)
+        .collect();
+
+    let loudness = |z: f64| -0.691 + 10.0 * z.log10();
+    let gated_mean = |threshold: f64| {
+        let gated: Vec<f64> = blocks
+            .iter()
+            .copied()
+            .filter(|z| *z > 0.0 && loudness(*z) > threshold)
+            .collect();
+        (!gated.is_empty()).then(|| gated.iter().sum::<f64>() / gated.len() as f64)
+    };
+    let absolute = gated_mean(-70.0)?;
+    gated_mean(loudness(absolute) - 10.0).map(loudness)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Duration and levels of `audio`, or `None` if it cannot be decoded
+pub(crate) fn measure(audio: &[u8]) -> Option<AudioStats> {
+    analyze(audio)
+        .map_err(|e| debug!("Audio analysis skipped: {}", e))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frequency: f64, amplitude: f64, seconds: f64, sample_rate: u32) -> Vec<i16> {
+        (0..(seconds * sample_rate as f64) as usize)
+            .map(|n| {
+                let t = n as f64 / sample_rate as f64;
+                (amplitude * 32767.0 * (2.0 * std::f64::consts::PI * frequency * t).sin()) as i16
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sine_levels() {
+        let stats = AudioStats::from_samples(&sine(997.0, 0.5, 2.0, 48_000), 1, 48_000);
+        assert_eq!(stats.duration_ms, 2000);
+        assert!((stats.peak_dbfs.unwrap() + 6.02).abs() < 0.05);
+        // A full-scale 1 kHz sine reads -3.01 LUFS, so half scale is about -9
+        assert!((stats.loudness_lufs.unwrap() + 9.0).abs() < 0.2);
+
+        let silence = AudioStats::from_samples(&[0; 48_000], 1, 48_000);
+        assert_eq!((silence.peak_dbfs, silence.loudness_lufs), (None, None));
+    }
+
+    #[test]
+    fn test_normalize_loudness() {
+        let pcm = Pcm {
+            samples: sine(997.0, 0.05, 2.0, 48_000),
+            channels: 1,
+            sample_rate: 48_000,
+        };
+        let podcast = normalize_loudness(pcm.clone(), LoudnessPreset::Podcast);
+        let stats = AudioStats::from_samples(&podcast.samples, 1, 48_000);
+        assert!((stats.loudness_lufs.unwrap() + 16.0).abs() < 0.1);
+
+        let telephony = normalize_loudness(podcast, LoudnessPreset::Telephony);
+        let stats = AudioStats::from_samples(&telephony.samples, 1, 48_000);
+        assert!((stats.loudness_lufs.unwrap() + 18.0).abs() < 0.1);
+
+        // A click near full scale keeps quiet speech below the target
+        let mut clicked = pcm;
+        clicked.samples[1000] = 30_000;
+        let clicked = normalize_loudness(clicked, LoudnessPreset::Podcast);
+        let stats = AudioStats::from_samples(&clicked.samples, 1, 48_000);
+        assert!((stats.peak_dbfs.unwrap() + 1.0).abs() < 0.01);
+        assert!(stats.loudness_lufs.unwrap() < -25.0);
+        assert_eq!("EBU-R128".parse(), Ok(LoudnessPreset::Broadcast));
+    }
+
+    #[test]
+    fn test_analyze_mp3() {
+        let audio = crate::dialogue::mp3_silence(std::time::Duration::from_secs(1));
+        let stats = analyze(&audio).unwrap();
+        assert_eq!((stats.sample_rate, stats.channels), (24_000, 1));
+        assert!((900..=1100).contains(&stats.duration_ms));
+        assert!(analyze(b"not audio").is_err());
+    }
+}
@@ -0,0 +1,268 @@
+//! Audio for Anki decks.
+//!
+//! [`export`] speaks one column of a CSV or TSV deck, saves each term as
+//! `hello-tts-<hash>.mp3` and returns the deck with an extra column of
+//! `[sound:...]` tags. Copy the audio into Anki's `collection.media` folder
+//! and import the new file. File names depend only on voice and text, so
+//! re-exporting a grown deck reuses the audio already produced.
+
+use crate::batch::{self, BatchManifest, BatchOptions, ItemStatus};
+use crate::batch::{BatchItem, BatchReport};
+use crate::flashcards::{detect_delimiter, split_row};
+use crate::models::TTSError;
+use crate::tts_client::TTSProcessor;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// How a deck is exported
+#[derive(Debug, Clone, Default)]
+pub struct AnkiOptions {
+    /// Column to speak, counting from 0
+    pub column: usize,
+    /// The first row holds column names and gets an `Audio` column instead of a tag
+    pub header: bool,
+    /// Directory for the audio files; defaults to `anki` in
+    /// `TTSConfig.output_directory`
+    pub media_dir: Option<PathBuf>,
+}
+
+/// Result of [`export`]
+#[derive(Debug, Clone, Serialize)]
+pub struct AnkiExport {
+    /// The deck with a `[sound:...]` column appended, ready for import
+    pub csv: String,
+    pub media_dir: PathBuf,
+    pub report: BatchReport,
+}
+
+/// A CSV or TSV deck, keeping comment lines such as `#separator:tab` as they are
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnkiDeck {
+    delimiter: char,
+    header: Option<Vec<String>>,
+    lines: Vec<DeckLine>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum DeckLine {
+    Verbatim(String),
+    Row(Vec<String>),
+}
+
+impl AnkiDeck {
+    pub fn parse(input: &str, header: bool) -> Self {
+        let first_row = input
+            .lines()
+            .find(|line| !line.trim().is_empty() && !line.starts_with('#'))
+            .unwrap_or_default();
+        let delimiter = detect_delimiter(first_row);
+        let mut deck = AnkiDeck {
+            delimiter,
+            header: None,
+            lines: Vec::new(),
+        };
+        for line in input.lines() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                deck.lines.push(DeckLine::Verbatim(line.to_string()));
+            } else if header && deck.header.is_none() {
+                deck.header = Some(split_row(line, delimiter));
+                deck.lines.push(DeckLine::Row(Vec::new()));
+            } else {
+                deck.lines.push(DeckLine::Row(split_row(line, delimiter)));
+            }
+        }
+        deck
+    }
+
+    /// Text of `column` for every data row; `None` where the cell is empty
+    pub fn terms(&self, column: usize) -> Vec<Option<&str>> {
+        self.rows()
+            .map(|row| {
+                row.get(column)
+                    .map(|cell| cell.trim())
+                    .filter(|cell| !cell.is_empty())
+            })
+            .collect()
+    }
+
+    /// The deck with `sounds[i]` appended to data row `i` as a `[sound:...]` tag
+    pub fn to_csv(&self, sounds: &[Option<String>]) -> String {
+        let mut sounds = sounds.iter();
+        let mut header = self.header.as_ref();
+        let mut out = String::new();
+        for line in &self.lines {
+            match line {
+                DeckLine::Verbatim(text) => out.push_str(text),
+                DeckLine::Row(_) if header.is_some() => {
+                    let mut fields = header.take().unwrap().clone();
+                    fields.push("Audio".to_string());
+                    out.push_str(&self.join(&fields));
+                }
+                DeckLine::Row(row) => {
+                    let mut fields = row.clone();
+                    let tag = match sounds.next() {
+                        Some(Some(file)) => format!("[sound:{}]", file),
+                        _ => String::new(),
+                    };
+                    fields.push(tag);
+                    out.push_str(&self.join(&fields));
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    fn rows(&self) -> impl Iterator<Item = &Vec<String>> {
+        let skip = usize::from(self.header.is_some());
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                DeckLine::Row(row) => Some(row),
+                DeckLine::Verbatim(_) => None,
+            })
+            .skip(skip)
+    }
+
+    fn join(&self, fields: &[String]) -> String {
+        let delimiter = self.delimiter.to_string();
+        fields
+            .iter()
+            .map(|field| {
+                if field.contains(self.delimiter) || field.contains('"') {
+                    format!("\"{}\"", field.replace('"', "\"\""))
+                } else {
+                    field.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(&delimiter)
+    }
+}
+
+/// Stable media file name for `text` spoken by `voice`, e.g. `hello-tts-1f3a….mp3`
+pub fn media_file_name(text: &str, voice: &str, ext: &str) -> String {
+    let item = BatchItem {
+        text: text.to_string(),
+        ..BatchItem::default()
+    };
+    format!("hello-tts-{}.{}", item.hash(voice), ext)
+}
+
+/// Speak one column of a CSV/TSV deck and tag every row with its audio for Anki
+///
+/// Terms are synthesized with [`batch::synthesize`]
+/// into files named by a hash of voice and text, so audio already in the
+/// media directory is reused and repeated terms are synthesized once.
+pub async fn export(
+    processor: &TTSProcessor,
+    input: &str,
+    voice: &str,
+    options: &AnkiOptions,
+) -> Result<AnkiExport, TTSError> {
+    let deck = AnkiDeck::parse(input, options.header);
+    let ext = processor.config().file_extension();
+    let rows: Vec<(usize, &str)> = deck
+        .terms(options.column)
+        .into_iter()
+        .enumerate()
+        .filter_map(|(row, term)| term.map(|term| (row, term)))
+        .collect();
+    if rows.is_empty() {
+        return Err(TTSError::Config(format!(
+            "No terms in column {}",
+            options.column
+        )));
+    }
+    let manifest = BatchManifest {
+        items: rows
+            .iter()
+            .map(|(_, term)| BatchItem {
+                text: term.to_string(),
+                output: Some(media_file_name(term, voice, ext)),
+                ..BatchItem::default()
+            })
+            .collect(),
+    };
+
+    let media_dir = options
+        .media_dir
+        .clone()
+        .unwrap_or_else(|| processor.output_manager().dir().join("anki"));
+    let batch_options = BatchOptions {
+        output_dir: Some(media_dir.clone()),
+        resume: true,
+        upload: None,
+    };
+    let report = batch::synthesize(processor, &manifest, voice, &batch_options).await?;
+
+    let mut sounds = vec![None; deck.terms(options.column).len()];
+    for ((row, _), item) in rows.iter().zip(&report.items) {
+        if !matches!(item.status, ItemStatus::Failed { .. } | ItemStatus::Pending) {
+            sounds[*row] = Some(item.file.clone());
+        }
+    }
+    Ok(AnkiExport {
+        csv: deck.to_csv(&sounds),
+        media_dir,
+        report,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::mock::MockBackend;
+    use crate::config::TTSConfig;
+
+    #[test]
+    fn test_deck_round_trip() {
+        let input = "#separator:comma\nTerm,Meaning\nchat,\"cat, the animal\"\n,empty\n";
+        let deck = AnkiDeck::parse(input, true);
+        assert_eq!(deck.terms(0), [Some("chat"), None]);
+        let csv = deck.to_csv(&[Some("a.mp3".to_string()), None]);
+        assert_eq!(
+            csv,
+            "#separator:comma\nTerm,Meaning,Audio\nchat,\"cat, the animal\",[sound:a.mp3]\n,empty,\n"
+        );
+    }
+
+    #[test]
+    fn test_media_file_names_are_stable() {
+        let name = media_file_name("chat", "fr-FR-DeniseNeural", "mp3");
+        assert!(name.starts_with("hello-tts-") && name.ends_with(".mp3"));
+        assert_eq!(name, media_file_name("chat", "fr-FR-DeniseNeural", "mp3"));
+        assert_ne!(name, media_file_name("chat", "fr-FR-HenriNeural", "mp3"));
+    }
+
+    #[tokio::test]
+    async fn test_export_anki_reuses_media() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-anki-{}", uuid::Uuid::new_v4()));
+        let options = AnkiOptions {
+            column: 0,
+            header: false,
+            media_dir: Some(dir.clone()),
+        };
+        let backend = MockBackend::new();
+        let processor = TTSProcessor::with_backend(TTSConfig::default(), Box::new(backend.clone()));
+
+        let deck = export(&processor, "chat\tcat\n", "v", &options)
+            .await
+            .unwrap();
+        let file = media_file_name("chat", "v", "mp3");
+        assert_eq!(deck.csv, format!("chat\tcat\t[sound:{}]\n", file));
+        assert!(dir.join(&file).exists());
+
+        let deck = export(
+            &processor,
+            "chat\tcat\nchien\tdog\nchat\tcat\n",
+            "v",
+            &options,
+        )
+        .await
+        .unwrap();
+        assert_eq!(backend.calls().len(), 2);
+        assert_eq!((deck.report.completed, deck.report.synthesized), (2, 1));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
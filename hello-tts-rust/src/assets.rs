@@ -0,0 +1,296 @@
+//! Voice-over assets for game engines.
+//!
+//! [`export`] renders a localization sheet like
+//! [`localization::render_sheet`], but lays
+//! the files out as `<locale>/<key>.<ext>` and writes [`ASSET_MANIFEST`]
+//! mapping every string key to its file, voice and duration. Unity's
+//! `JsonUtility` cannot read dictionaries, so its manifest lists the entries in
+//! an array; Godot's maps each string key to a dictionary keyed by locale,
+//! ready for `JSON.parse_string` and `TranslationServer.get_locale()`.
+
+use crate::localization;
+use crate::localization::{SheetOptions, SheetReport};
+use crate::models::{TTSError, Voice};
+use crate::tts_client::TTSProcessor;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tokio::fs;
+
+/// Name of the manifest written to the output directory
+pub const ASSET_MANIFEST: &str = "voice_manifest.json";
+
+/// Engine whose import conventions the manifest follows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Engine {
+    #[default]
+    Unity,
+    Godot,
+}
+
+impl std::str::FromStr for Engine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "unity" => Ok(Engine::Unity),
+            "godot" => Ok(Engine::Godot),
+            other => Err(format!("Unknown engine: {}", other)),
+        }
+    }
+}
+
+/// How assets are exported
+#[derive(Debug, Clone, Default)]
+pub struct AssetOptions {
+    /// Columns of the sheet; `id_column` is required and holds the string keys
+    pub sheet: SheetOptions,
+    pub engine: Engine,
+    /// Project path the output directory is imported under, e.g.
+    /// `Assets/Audio/Voice` or `res://audio/voice`; without it manifest paths
+    /// are relative to the manifest
+    pub root: Option<String>,
+}
+
+/// One string key's audio
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AssetEntry {
+    pub key: String,
+    pub path: String,
+    pub voice: String,
+    pub locale: String,
+    pub text: String,
+    /// Length of the audio, 0 if it could not be measured
+    pub duration_ms: u64,
+}
+
+/// Keys and their audio files, in sheet order
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AssetManifest {
+    pub engine: Engine,
+    pub entries: Vec<AssetEntry>,
+}
+
+impl AssetManifest {
+    pub fn to_json(&self) -> Result<String, TTSError> {
+        let entries = match self.engine {
+            Engine::Unity => serde_json::to_value(&self.entries)?,
+            Engine::Godot => {
+                let mut entries = Map::new();
+                for entry in &self.entries {
+                    let mut value = serde_json::to_value(entry)?;
+                    if let Value::Object(fields) = &mut value {
+                        fields.remove("key");
+                        fields.remove("locale");
+                    }
+                    if let Value::Object(locales) = entries
+                        .entry(entry.key.clone())
+                        .or_insert_with(|| Value::Object(Map::new()))
+                    {
+                        locales.insert(entry.locale.clone(), value);
+                    }
+                }
+                Value::Object(entries)
+            }
+        };
+        let manifest = json!({ "entries": entries });
+        Ok(serde_json::to_string_pretty(&manifest)?)
+    }
+}
+
+/// Result of [`export`]
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetExport {
+    pub manifest_path: PathBuf,
+    pub manifest: AssetManifest,
+    pub report: SheetReport,
+}
+
+/// Locale of `voice` from the backend's voice list, else the start of a
+/// `en-US-AriaNeural` style name, else the voice name itself
+pub fn locale_of(voice: &str, voices: &[Voice]) -> String {
+    if let Some(known) = voices.iter().find(|v| v.name.eq_ignore_ascii_case(voice)) {
+        return known.locale.clone();
+    }
+    let parts: Vec<&str> = voice.splitn(3, '-').collect();
+    match parts.as_slice() {
+        [language, region, _] if language.len() <= 3 && region.len() <= 4 => {
+            format!("{}-{}", language, region)
+        }
+        _ => voice.to_string(),
+    }
+}
+
+/// `<locale>/<key>.<ext>`, with characters other than letters, digits, `-`
+/// and `_` in the key replaced by `_`
+pub fn asset_file(locale: &str, key: &str, ext: &str) -> String {
+    format!("{}/{}", locale, crate::compare::sample_file_name(key, ext))
+}
+
+/// Path of `file` in the engine project, given the import root
+pub fn project_path(root: Option<&str>, file: &str) -> String {
+    match root {
+        Some(root) => format!("{}/{}", root.trim_end_matches('/'), file),
+        None => file.to_string(),
+    }
+}
+
+/// Render a localization sheet as game-engine assets with a key manifest
+///
+/// Rows are rendered like [`localization::render_sheet`] into
+/// `<locale>/<key>.<ext>`, and the rendered ones are listed in
+/// [`ASSET_MANIFEST`] in the output directory.
+pub async fn export(
+    processor: &TTSProcessor,
+    input: &str,
+    voice: &str,
+    options: &AssetOptions,
+) -> Result<AssetExport, TTSError> {
+    if options.sheet.id_column.is_none() {
+        return Err(TTSError::Config(
+            "Assets need an id column with the string keys".to_string(),
+        ));
+    }
+    let voices = processor.list_voices().await?;
+    let ext = processor.config().file_extension();
+    let mut rows = localization::parse_sheet(input, &options.sheet)?;
+    for row in &mut rows {
+        if let Some(key) = &row.item.id {
+            let locale = locale_of(row.item.voice(voice), &voices);
+            row.item.output = Some(asset_file(&locale, key, ext));
+        }
+    }
+    let report = localization::render_rows(processor, &rows, voice, &options.sheet).await?;
+
+    let durations: HashMap<&str, Option<u64>> = report
+        .batch
+        .items
+        .iter()
+        .map(|item| (item.file.as_str(), item.duration_ms))
+        .collect();
+    let entries = rows
+        .iter()
+        .zip(&report.rows)
+        .filter(|(_, result)| result.status == "ok")
+        .map(|(row, result)| AssetEntry {
+            key: result.id.clone().unwrap_or_default(),
+            path: project_path(options.root.as_deref(), &result.file),
+            voice: result.voice.clone(),
+            locale: locale_of(&result.voice, &voices),
+            text: row.item.text.clone(),
+            duration_ms: durations
+                .get(result.file.as_str())
+                .copied()
+                .flatten()
+                .unwrap_or(0),
+        })
+        .collect();
+    let manifest = AssetManifest {
+        engine: options.engine,
+        entries,
+    };
+    let manifest_path = report.batch.output_dir.join(ASSET_MANIFEST);
+    fs::write(&manifest_path, manifest.to_json()?).await?;
+    Ok(AssetExport {
+        manifest_path,
+        manifest,
+        report,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::mock::MockBackend;
+    use crate::config::TTSConfig;
+
+    #[test]
+    fn test_locale_and_paths() {
+        assert_eq!(locale_of("en-US-AriaNeural", &[]), "en-US");
+        assert_eq!(locale_of("Joanna", &[]), "Joanna");
+        let voices = [Voice::new(
+            "Joanna".to_string(),
+            "Joanna".to_string(),
+            "en-US".to_string(),
+            "Female".to_string(),
+        )];
+        assert_eq!(locale_of("joanna", &voices), "en-US");
+        assert_eq!(
+            asset_file("de-DE", "menu.start", "ogg"),
+            "de-DE/menu_start.ogg"
+        );
+        assert_eq!(
+            project_path(Some("res://audio/"), "de-DE/menu_start.ogg"),
+            "res://audio/de-DE/menu_start.ogg"
+        );
+    }
+
+    #[test]
+    fn test_manifest_layout_per_engine() {
+        let mut manifest = AssetManifest {
+            engine: Engine::Unity,
+            entries: vec![AssetEntry {
+                key: "menu.start".to_string(),
+                path: "en-US/menu_start.mp3".to_string(),
+                voice: "en-US-AriaNeural".to_string(),
+                locale: "en-US".to_string(),
+                text: "Start".to_string(),
+                duration_ms: 0,
+            }],
+        };
+        let unity: Value = serde_json::from_str(&manifest.to_json().unwrap()).unwrap();
+        assert_eq!(unity["entries"][0]["key"], "menu.start");
+
+        manifest.engine = Engine::Godot;
+        let godot: Value = serde_json::from_str(&manifest.to_json().unwrap()).unwrap();
+        let entry = &godot["entries"]["menu.start"]["en-US"];
+        assert_eq!(entry["path"], "en-US/menu_start.mp3");
+        assert!(entry.get("key").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_export_assets_by_locale_and_key() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-assets-{}", uuid::Uuid::new_v4()));
+        let processor =
+            TTSProcessor::with_backend(TTSConfig::default(), Box::new(MockBackend::new()));
+        let sheet = "key,text,voice\nmenu.start,Start,\n\
+                     menu.start,Starten,de-DE-KatjaNeural\n,Quit,\n";
+        let options = AssetOptions {
+            sheet: SheetOptions {
+                voice_column: Some("voice".to_string()),
+                id_column: Some("key".to_string()),
+                output_dir: Some(dir.clone()),
+                ..SheetOptions::default()
+            },
+            engine: crate::Engine::Godot,
+            root: Some("res://voice".to_string()),
+        };
+        let export = export(&processor, sheet, "en-US-AriaNeural", &options)
+            .await
+            .unwrap();
+        let paths: Vec<&str> = export
+            .manifest
+            .entries
+            .iter()
+            .map(|e| e.path.as_str())
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "res://voice/en-US/menu_start.mp3",
+                "res://voice/de-DE/menu_start.mp3"
+            ]
+        );
+        assert!(dir.join("en-US/menu_start.mp3").exists());
+        assert_eq!(
+            export.report.rows[2].error.as_deref(),
+            Some("No id"),
+            "rows without a key cannot be looked up"
+        );
+        let written = std::fs::read_to_string(&export.manifest_path).unwrap();
+        assert!(written.contains("\"menu.start\": {"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
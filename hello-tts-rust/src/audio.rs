@@ -0,0 +1,229 @@
+//! Gapless joining of synthesized audio.
+//!
+//! Every MP3 a backend returns starts with the encoder's delay and ends with
+//! padding up to a whole frame, so MP3s joined byte by byte click or leave
+//! short gaps at each seam, and parts from different backends may not even
+//! share a sample rate. [`concat`] decodes each part instead, converts it to
+//! the rate and channels of the first one, adds silence or crossfades the
+//! seams, and returns one [`Pcm`] recording to be encoded once.
+//! [`time_stretch`] changes the speed of decoded speech without changing its
+//! pitch, to fit a clip into a subtitle cue.
+
+use crate::audio_player::AudioError;
+use crate::convert::Pcm;
+use std::f64::consts::PI;
+use std::time::Duration;
+
+/// Rate of a recording with no audio parts, the rate of the backends' MP3
+pub(crate) const DEFAULT_SAMPLE_RATE: u32 = 24_000;
+/// Length of the windows [`time_stretch`] overlap-adds
+const STRETCH_WINDOW_MS: usize = 30;
+
+/// A piece of a joined recording
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    /// Encoded audio such as a backend's MP3
+    Audio(Vec<u8>),
+    Silence(Duration),
+}
+
+/// Decode `segments` and join them into one recording
+///
+/// Audio parts that directly follow each other overlap by `crossfade`, but
+/// by no more than half of either part.
+pub fn concat(segments: &[Segment], crossfade: Duration) -> Result<Pcm, AudioError> {
+    let mut decoded = Vec::with_capacity(segments.len());
+    for segment in segments {
+        decoded.push(match segment {
+            Segment::Audio(audio) => Some(Pcm::decode(audio)?),
+            Segment::Silence(_) => None,
+        });
+    }
+    let (channels, sample_rate) = decoded
+        .iter()
+        .flatten()
+        .next()
+        .map_or((1, DEFAULT_SAMPLE_RATE), |pcm| {
+            (pcm.channels.max(1), pcm.sample_rate)
+        });
+    let width = channels as usize;
+    let mut joined = Pcm {
+        samples: Vec::new(),
+        channels,
+        sample_rate,
+    };
+    // Frames of the previous part, when it was audio
+    let mut previous = None;
+    for (segment, pcm) in segments.iter().zip(decoded) {
+        match (segment, pcm) {
+            (_, Some(pcm)) => {
+                let pcm = pcm.with_channels(channels).resample(sample_rate);
+                let frames = pcm.samples.len() / width;
+                let overlap = previous.map_or(0, |previous: usize| {
+                    frames_in(crossfade, sample_rate)
+                        .min(previous / 2)
+                        .min(frames / 2)
+                });
+                crossfade_into(&mut joined.samples, &pcm.samples, overlap * width, width);
+                previous = Some(frames);
+            }
+            (Segment::Silence(duration), _) if !duration.is_zero() => {
+                let len = joined.samples.len() + frames_in(*duration, sample_rate) * width;
+                joined.samples.resize(len, 0);
+                previous = None;
+            }
+            _ => {}
+        }
+    }
+    Ok(joined)
+}
+
+/// Play `pcm` `speed` times as fast, keeping its pitch
+///
+/// Uses WSOLA: windows are overlap-added at half their length, each taken
+/// from within a quarter window of its nominal position where it best
+/// continues the previous one, so voiced speech keeps its periods intact.
+pub fn time_stretch(pcm: Pcm, speed: f64) -> Pcm {
+    if !speed.is_finite() || speed <= 0.0 || (speed - 1.0).abs() < 1e-3 {
+        return pcm;
+    }
+    let width = pcm.channels.max(1) as usize;
+    let frames = pcm.samples.len() / width;
+    let window_len = (pcm.sample_rate as usize * STRETCH_WINDOW_MS / 1000).max(4) & !1;
+    let hop = window_len / 2;
+    let tolerance = window_len / 4;
+    let out_frames = (frames as f64 / speed).round() as usize;
+    // Periodic Hann windows at half their length sum to one
+    let window: Vec<f64> = (0..window_len)
+        .map(|n| 0.5 - 0.5 * (2.0 * PI * n as f64 / window_len as f64).cos())
+        .collect();
+    let sample = |frame: usize, channel: usize| {
+        if frame < frames {
+            f64::from(pcm.samples[frame * width + channel])
+        } else {
+            0.0
+        }
+    };
+    // Similarity of the first channel's next `hop` frames at two positions
+    let similarity =
+        |a: usize, b: usize| -> f64 { (0..hop).map(|n| sample(a + n, 0) * sample(b + n, 0)).sum() };
+
+    let mut out = vec![0.0; (out_frames + window_len) * width];
+    let mut previous = 0;
+    let mut k = 0;
+    while k * hop < out_frames {
+        let position = if k == 0 {
+            0
+        } else {
+            let nominal = (k as f64 * hop as f64 * speed).round() as usize;
+            let natural = previous + hop;
+            (nominal.saturating_sub(tolerance)..=nominal + tolerance)
+                .map(|p| (similarity(p, natural), p))
+                .max_by(|a, b| a.0.total_cmp(&b.0))
+                .map_or(nominal, |(_, p)| p)
+        };
+        for (n, &gain) in window.iter().enumerate() {
+            // Nothing comes before the first window, so it starts at full gain
+            let gain = if k == 0 && n < hop { 1.0 } else { gain };
+            for c in 0..width {
+                out[(k * hop + n) * width + c] += sample(position + n, c) * gain;
+            }
+        }
+        previous = position;
+        k += 1;
+    }
+    out.truncate(out_frames * width);
+    Pcm {
+        samples: out
+            .into_iter()
+            .map(|s| s.round().clamp(-32768.0, 32767.0) as i16)
+            .collect(),
+        channels: pcm.channels,
+        sample_rate: pcm.sample_rate,
+    }
+}
+
+pub(crate) fn frames_in(duration: Duration, sample_rate: u32) -> usize {
+    (duration.as_secs_f64() * f64::from(sample_rate)).round() as usize
+}
+
+/// Append `next` to `out`, fading the last `overlap` samples of `out` into
+/// the first ones of `next`
+fn crossfade_into(out: &mut Vec<i16>, next: &[i16], overlap: usize, width: usize) {
+    let start = out.len() - overlap;
+    let frames = (overlap / width) as f64;
+    for (i, (old, new)) in out[start..].iter_mut().zip(next).enumerate() {
+        let t = ((i / width) as f64 + 0.5) / frames;
+        let mixed = f64::from(*old) * (1.0 - t) + f64::from(*new) * t;
+        *old = mixed.round() as i16;
+    }
+    out.extend_from_slice(&next[overlap..]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav(value: i16, frames: usize, channels: u16, sample_rate: u32) -> Segment {
+        let pcm = Pcm {
+            samples: vec![value; frames * channels as usize],
+            channels,
+            sample_rate,
+        };
+        Segment::Audio(pcm.to_wav())
+    }
+
+    #[test]
+    fn test_concat_converts_and_adds_silence() {
+        let segments = [
+            wav(1000, 1600, 1, 16_000),
+            Segment::Silence(Duration::from_millis(50)),
+            wav(1000, 800, 2, 8_000),
+        ];
+        let joined = concat(&segments, Duration::from_millis(20)).unwrap();
+        assert_eq!((joined.channels, joined.sample_rate), (1, 16_000));
+        assert_eq!(joined.samples.len(), 1600 + 800 + 1600);
+        assert_eq!(joined.samples[1700], 0);
+        assert!((990..=1010).contains(&joined.samples[3000]));
+    }
+
+    #[test]
+    fn test_concat_crossfades_adjacent_parts() {
+        let segments = [
+            wav(0, 1000, 1, 10_000),
+            Segment::Silence(Duration::ZERO),
+            wav(2000, 1000, 1, 10_000),
+        ];
+        let joined = concat(&segments, Duration::from_millis(10)).unwrap();
+        assert_eq!(joined.samples.len(), 1900);
+        assert_eq!(joined.samples[899], 0);
+        assert_eq!(joined.samples[950], 1010);
+        assert_eq!(joined.samples[1000], 2000);
+        let plain = concat(&segments, Duration::ZERO).unwrap();
+        assert_eq!(plain.samples.len(), 2000);
+    }
+
+    #[test]
+    fn test_time_stretch_keeps_pitch() {
+        // 500 Hz for one second at 16 kHz: 1000 zero crossings
+        let pcm = Pcm {
+            samples: (0..16_000)
+                .map(|n| (8000.0 * (2.0 * PI * 500.0 * n as f64 / 16_000.0).sin()) as i16)
+                .collect(),
+            channels: 1,
+            sample_rate: 16_000,
+        };
+        let crossings = |samples: &[i16]| {
+            samples
+                .windows(2)
+                .filter(|pair| (pair[0] < 0) != (pair[1] < 0))
+                .count()
+        };
+        let fast = time_stretch(pcm.clone(), 1.6);
+        assert_eq!(fast.samples.len(), 10_000);
+        let per_second = crossings(&fast.samples) as f64 * 1.6;
+        assert!((950.0..1050.0).contains(&per_second), "{}", per_second);
+        assert!(fast.samples[2000..8000].iter().any(|&s| s > 7000));
+        assert_eq!(time_stretch(pcm.clone(), 1.0), pcm);
+    }
+}
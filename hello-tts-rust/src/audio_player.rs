@@ -1,6 +1,13 @@
+#[cfg(not(target_arch = "wasm32"))]
 use rodio::{Decoder, OutputStream, Sink};
+#[cfg(not(target_arch = "wasm32"))]
 use std::fs::File;
+#[cfg(not(target_arch = "wasm32"))]
 use std::io::{BufReader, Cursor};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(not(target_arch = "wasm32"))]
+use tracing::{debug, instrument, warn};
 
 /// Custom error type for audio operations
 #[derive(Debug, thiserror::Error)]
@@ -9,18 +16,126 @@ pub enum AudioError {
     Io(#[from] std::io::Error),
     #[error("Audio decode error: {0}")]
     Decode(String),
+    #[error("Audio encode error: {0}")]
+    Encode(String),
     #[error("Audio playback error: {0}")]
     Playback(String),
     #[error("Audio device error: {0}")]
     Device(String),
 }
 
+/// Destination for synthesized audio
+pub trait AudioSink {
+    /// Play `audio_data` and return once it finished
+    fn play(&self, audio_data: Vec<u8>) -> Result<(), AudioError>;
+}
+
+/// Sink that discards all audio, for machines without an audio device
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NullAudioPlayer;
+
+impl AudioSink for NullAudioPlayer {
+    fn play(&self, audio_data: Vec<u8>) -> Result<(), AudioError> {
+        tracing::debug!(
+            bytes = audio_data.len(),
+            "Discarding audio, no output configured"
+        );
+        Ok(())
+    }
+}
+
+/// Sink appending the encoded audio to a file, e.g. to collect a session
+#[derive(Debug, Clone)]
+pub struct FileSink {
+    path: std::path::PathBuf,
+}
+
+impl FileSink {
+    pub fn new(path: impl Into<std::path::PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl AudioSink for FileSink {
+    fn play(&self, audio_data: Vec<u8>) -> Result<(), AudioError> {
+        use std::io::Write;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(&audio_data)?;
+        Ok(())
+    }
+}
+
+/// Sink writing the encoded audio to stdout, for piping into `ffplay -` or `sox`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdoutSink;
+
+impl AudioSink for StdoutSink {
+    fn play(&self, audio_data: Vec<u8>) -> Result<(), AudioError> {
+        use std::io::Write;
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(&audio_data)?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+/// Sink handing the audio to a closure, e.g. to play it in a GUI
+pub struct CallbackSink<F>(pub F);
+
+impl<F> AudioSink for CallbackSink<F>
+where
+    F: Fn(Vec<u8>) -> Result<(), AudioError>,
+{
+    fn play(&self, audio_data: Vec<u8>) -> Result<(), AudioError> {
+        (self.0)(audio_data)
+    }
+}
+
+/// Open the sink selected by `TTSConfig.audio_output`
+///
+/// - `auto`: the default device, or [`NullAudioPlayer`] with a warning when
+///   there is none (CI, servers, containers)
+/// - `default`: the default device, failing without one
+/// - `null`: [`NullAudioPlayer`]
+/// - `stdout` or `-`: [`StdoutSink`]
+/// - `file:<path>`: [`FileSink`] appending to `<path>`
+/// - anything else: the output device with that name
+///
+/// [`TTSProcessor::set_audio_sink`](crate::TTSProcessor::set_audio_sink)
+/// takes precedence, e.g. for a [`CallbackSink`].
+#[cfg(not(target_arch = "wasm32"))]
+pub fn open_sink(audio_output: &str) -> Result<Box<dyn AudioSink>, AudioError> {
+    if let Some(path) = audio_output.strip_prefix("file:") {
+        return Ok(Box::new(FileSink::new(path)));
+    }
+    match audio_output {
+        "auto" => match AudioPlayer::new() {
+            Ok(player) => Ok(Box::new(player)),
+            Err(e) => {
+                warn!("{}; audio is saved but not played", e);
+                Ok(Box::new(NullAudioPlayer))
+            }
+        },
+        "default" => Ok(Box::new(AudioPlayer::new()?)),
+        "null" => Ok(Box::new(NullAudioPlayer)),
+        "stdout" | "-" => Ok(Box::new(StdoutSink)),
+        device => Ok(Box::new(AudioPlayer::with_device(device)?)),
+    }
+}
+
 /// Audio player for cross-platform audio playback
+///
+/// Not available on `wasm32`; use `web::WebAudioPlayer` in the browser.
+#[cfg(not(target_arch = "wasm32"))]
 pub struct AudioPlayer {
     _stream: OutputStream,
     sink: Sink,
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl AudioPlayer {
     /// Create a new AudioPlayer instance
     pub fn new() -> Result<Self, AudioError> {
@@ -33,21 +148,46 @@ impl AudioPlayer {
         Ok(Self { _stream, sink })
     }
 
+    /// Create a player on the output device called `name`
+    pub fn with_device(name: &str) -> Result<Self, AudioError> {
+        use rodio::cpal::traits::{DeviceTrait, HostTrait};
+
+        let device = rodio::cpal::default_host()
+            .output_devices()
+            .map_err(|e| AudioError::Device(format!("Failed to list audio devices: {}", e)))?
+            .find(|d| d.name().is_ok_and(|n| n == name))
+            .ok_or_else(|| AudioError::Device(format!("No audio device named {:?}", name)))?;
+        let (_stream, stream_handle) = OutputStream::try_from_device(&device)
+            .map_err(|e| AudioError::Device(format!("Failed to open {}: {}", name, e)))?;
+
+        let sink = Sink::try_new(&stream_handle)
+            .map_err(|e| AudioError::Device(format!("Failed to create audio sink: {}", e)))?;
+
+        Ok(Self { _stream, sink })
+    }
+
     /// Play audio from a file
+    #[instrument(skip(self))]
     pub fn play_file(&self, filename: &str) -> Result<(), AudioError> {
         let file = File::open(filename)?;
         let source = Decoder::new(BufReader::new(file))
             .map_err(|e| AudioError::Decode(format!("Failed to decode audio file: {}", e)))?;
 
+        let started = Instant::now();
         self.sink.append(source);
 
         // Wait for playback to complete
         self.sink.sleep_until_end();
+        debug!(
+            latency_ms = started.elapsed().as_millis() as u64,
+            "Playback finished"
+        );
 
         Ok(())
     }
 
     /// Play audio from raw audio data
+    #[instrument(skip_all, fields(bytes = audio_data.len()))]
     pub fn play_audio_data(
         &self,
         audio_data: Vec<u8>,
@@ -57,8 +197,23 @@ impl AudioPlayer {
         let source = Decoder::new(cursor)
             .map_err(|e| AudioError::Decode(format!("Failed to decode audio data: {}", e)))?;
 
+        let started = Instant::now();
         self.sink.append(source);
         self.sink.sleep_until_end();
+        debug!(
+            latency_ms = started.elapsed().as_millis() as u64,
+            "Playback finished"
+        );
+        Ok(())
+    }
+
+    /// Start playing audio data without waiting for it to finish
+    ///
+    /// The audio is queued after anything still playing.
+    pub fn queue_audio_data(&self, audio_data: Vec<u8>) -> Result<(), AudioError> {
+        let source = Decoder::new(Cursor::new(audio_data))
+            .map_err(|e| AudioError::Decode(format!("Failed to decode audio data: {}", e)))?;
+        self.sink.append(source);
         Ok(())
     }
 
@@ -93,13 +248,21 @@ impl AudioPlayer {
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+impl AudioSink for AudioPlayer {
+    fn play(&self, audio_data: Vec<u8>) -> Result<(), AudioError> {
+        self.play_audio_data(audio_data, None)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
 impl Default for AudioPlayer {
     fn default() -> Self {
         Self::new().expect("Failed to create default AudioPlayer")
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(target_arch = "wasm32")))]
 mod tests {
     use super::*;
 
@@ -109,6 +272,36 @@ mod tests {
         assert!(result.is_ok(), "AudioPlayer creation should succeed");
     }
 
+    #[test]
+    fn test_null_sinks() {
+        assert!(open_sink("null")
+            .unwrap()
+            .play(b"not audio".to_vec())
+            .is_ok());
+        // Falls back to the null sink where there is no audio device
+        assert!(open_sink("auto").is_ok());
+        assert!(open_sink("no such device").is_err());
+    }
+
+    #[test]
+    fn test_file_and_callback_sinks() {
+        let path =
+            std::env::temp_dir().join(format!("hello-tts-sink-{}.mp3", uuid::Uuid::new_v4()));
+        let sink = open_sink(&format!("file:{}", path.display())).unwrap();
+        sink.play(b"one".to_vec()).unwrap();
+        sink.play(b"two".to_vec()).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"onetwo");
+        std::fs::remove_file(&path).unwrap();
+
+        let played = std::sync::Mutex::new(Vec::new());
+        let sink = CallbackSink(|audio: Vec<u8>| {
+            played.lock().unwrap().push(audio.len());
+            Ok(())
+        });
+        sink.play(vec![0; 4]).unwrap();
+        assert_eq!(*played.lock().unwrap(), [4]);
+    }
+
     #[test]
     fn test_volume_control() {
         if let Ok(player) = AudioPlayer::new() {
@@ -0,0 +1,366 @@
+//! Audiobooks with chapter metadata.
+//!
+//! [`render`] renders a book chapter by chapter like
+//! [`document::render`], with resume, and adds the
+//! metadata audiobook players need. The M4B layout writes an FFmpeg chapter
+//! file and a concat list next to the chapter files, so one command assembles
+//! the book:
+//!
+//! ```text
+//! ffmpeg -f concat -safe 0 -i files.txt -i metadata.txt -map_metadata 1 -c:a aac book.m4b
+//! ```
+//!
+//! The MP3 layout tags each chapter file with ID3 title, album and track
+//! number instead.
+
+use crate::document::{self, Document, DocumentOptions};
+use crate::document::{DocumentMetadata, RenderedDocument};
+use crate::events::TTSEvent;
+use crate::models::TTSError;
+use crate::tts_client::TTSProcessor;
+use std::fmt::Write;
+use std::path::PathBuf;
+use tokio::fs;
+use tracing::{instrument, warn};
+
+/// Name of the FFmpeg chapter metadata file in an M4B audiobook directory
+pub const FFMETADATA_FILE: &str = "metadata.txt";
+/// Name of the FFmpeg concat list in an M4B audiobook directory
+pub const CONCAT_FILE: &str = "files.txt";
+
+/// How the chapters of an audiobook are laid out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudiobookLayout {
+    /// Chapter files with `metadata.txt` and `files.txt` for building an M4B
+    #[default]
+    M4b,
+    /// ID3-tagged MP3 chapter files
+    Mp3,
+}
+
+impl std::fmt::Display for AudiobookLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::M4b => "m4b",
+            Self::Mp3 => "mp3",
+        })
+    }
+}
+
+impl std::str::FromStr for AudiobookLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "m4b" => Ok(Self::M4b),
+            "mp3" => Ok(Self::Mp3),
+            _ => Err(format!("Unknown audiobook layout {:?}; use m4b or mp3", s)),
+        }
+    }
+}
+
+/// Where and how an audiobook is rendered
+#[derive(Debug, Clone, Default)]
+pub struct AudiobookOptions {
+    /// Directory for the chapter files and metadata; defaults to a directory
+    /// named after the book in `TTSConfig.output_directory`
+    pub output_dir: Option<PathBuf>,
+    /// Skip chapters whose audio from a previous run is still up to date
+    pub resume: bool,
+    pub layout: AudiobookLayout,
+}
+
+/// Result of [`render`]
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct RenderedAudiobook {
+    pub layout: AudiobookLayout,
+    #[serde(flatten)]
+    pub document: RenderedDocument,
+    /// `metadata.txt` of the M4B layout
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ffmetadata: Option<PathBuf>,
+    /// Total length of the chapters whose audio could be decoded
+    pub duration_ms: u64,
+}
+
+/// FFmpeg metadata with the book's title and a chapter mark per chapter
+///
+/// Chapters whose length is unknown are given no time, so the marks after
+/// them come early.
+pub fn to_ffmetadata(metadata: &DocumentMetadata) -> String {
+    let mut out = String::from(";FFMETADATA1\n");
+    if let Some(title) = &metadata.title {
+        let _ = writeln!(
+            out,
+            "title={}\nalbum={}",
+            ff_escape(title),
+            ff_escape(title)
+        );
+    }
+    let _ = writeln!(
+        out,
+        "artist={}\ngenre=Audiobook",
+        ff_escape(&metadata.voice)
+    );
+    let mut start = 0;
+    for chapter in &metadata.chapters {
+        let end = start + chapter.duration_ms.unwrap_or_default();
+        let _ = write!(
+            out,
+            "\n[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+            start,
+            end,
+            ff_escape(&chapter.title)
+        );
+        start = end;
+    }
+    out
+}
+
+/// FFmpeg concat list of the chapter files in order
+pub fn to_concat_list(metadata: &DocumentMetadata) -> String {
+    metadata
+        .chapters
+        .iter()
+        .map(|chapter| format!("file '{}'\n", chapter.file.replace('\'', r"'\''")))
+        .collect()
+}
+
+/// Backslash before the characters FFmpeg metadata treats specially
+fn ff_escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// ID3v2.4 tag with title, album, artist and `track/total`, to put in front
+/// of MP3 audio
+pub fn id3_tag(
+    title: &str,
+    album: Option<&str>,
+    artist: &str,
+    track: usize,
+    total: usize,
+) -> Vec<u8> {
+    let track = format!("{}/{}", track, total);
+    let frames = [
+        ("TIT2", Some(title)),
+        ("TALB", album),
+        ("TPE1", Some(artist)),
+        ("TRCK", Some(track.as_str())),
+        ("TCON", Some("Audiobook")),
+    ];
+    let mut body = Vec::new();
+    for (id, value) in frames {
+        let Some(value) = value else { continue };
+        body.extend_from_slice(id.as_bytes());
+        body.extend_from_slice(&synchsafe(value.len() + 1));
+        body.extend_from_slice(&[0, 0, 3]); // no flags, UTF-8
+        body.extend_from_slice(value.as_bytes());
+    }
+    let mut tag = b"ID3\x04\x00\x00".to_vec();
+    tag.extend_from_slice(&synchsafe(body.len()));
+    tag.extend(body);
+    tag
+}
+
+/// Size as four 7-bit bytes, as ID3v2.4 stores sizes
+fn synchsafe(size: usize) -> [u8; 4] {
+    let size = size as u32;
+    [
+        (size >> 21 & 0x7f) as u8,
+        (size >> 14 & 0x7f) as u8,
+        (size >> 7 & 0x7f) as u8,
+        (size & 0x7f) as u8,
+    ]
+}
+
+/// Render `document` as an audiobook with chapter metadata for
+/// `options.layout`
+///
+/// Chapters are rendered and resumed as by
+/// [`document::render`]. The MP3 layout needs the
+/// `mp3` output format; chapter files that already carry an ID3 tag are
+/// left as they are.
+#[instrument(skip_all, fields(backend = processor.backend_name(), voice = %voice))]
+pub async fn render(
+    processor: &TTSProcessor,
+    document: &Document,
+    voice: &str,
+    options: &AudiobookOptions,
+) -> Result<RenderedAudiobook, TTSError> {
+    let result = render_chapters(processor, document, voice, options).await;
+    let summary = match &result {
+        Ok(book) => format!(
+            "{} chapters, {:.1} minutes in {}",
+            book.document.metadata.chapters.len(),
+            book.duration_ms as f64 / 60_000.0,
+            book.document.output_dir.display()
+        ),
+        Err(e) => e.to_string(),
+    };
+    processor.emit(TTSEvent::JobFinished {
+        job: "Audiobook",
+        summary,
+        failed: result.is_err(),
+    });
+    result
+}
+
+async fn render_chapters(
+    processor: &TTSProcessor,
+    document: &Document,
+    voice: &str,
+    options: &AudiobookOptions,
+) -> Result<RenderedAudiobook, TTSError> {
+    if options.layout == AudiobookLayout::Mp3 && processor.config().file_extension() != "mp3" {
+        return Err(TTSError::Config(format!(
+            "The mp3 audiobook layout needs the mp3 output format, not {}",
+            processor.config().output_format
+        )));
+    }
+    let document_options = DocumentOptions {
+        output_dir: options.output_dir.clone(),
+        resume: options.resume,
+    };
+    let mut rendered = document::render(processor, document, voice, &document_options).await?;
+    let output_dir = rendered.output_dir.clone();
+    let metadata = &mut rendered.metadata;
+
+    let mut ffmetadata = None;
+    match options.layout {
+        AudiobookLayout::M4b => {
+            if metadata.chapters.iter().any(|c| c.duration_ms.is_none()) {
+                warn!("Some chapter lengths are unknown, their chapter marks are approximate");
+            }
+            let path = output_dir.join(FFMETADATA_FILE);
+            fs::write(&path, to_ffmetadata(metadata)).await?;
+            let concat_list = to_concat_list(metadata);
+            fs::write(output_dir.join(CONCAT_FILE), concat_list).await?;
+            ffmetadata = Some(path);
+        }
+        AudiobookLayout::Mp3 => {
+            let total = metadata.chapters.len();
+            for chapter in &mut metadata.chapters {
+                let path = output_dir.join(&chapter.file);
+                let audio = fs::read(&path).await?;
+                if audio.starts_with(b"ID3") {
+                    continue;
+                }
+                let mut tagged = id3_tag(
+                    &chapter.title,
+                    metadata.title.as_deref(),
+                    voice,
+                    chapter.index + 1,
+                    total,
+                );
+                tagged.extend(audio);
+                fs::write(&path, &tagged).await?;
+                chapter.bytes = tagged.len();
+            }
+            metadata.save(&output_dir)?;
+        }
+    }
+    let duration_ms = metadata.chapters.iter().filter_map(|c| c.duration_ms).sum();
+    Ok(RenderedAudiobook {
+        layout: options.layout,
+        document: rendered,
+        ffmetadata,
+        duration_ms,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::mock::MockBackend;
+    use crate::config::TTSConfig;
+    use crate::document::ChapterInfo;
+
+    #[test]
+    fn test_ffmetadata_and_concat_list() {
+        let chapter = |index: usize, title: &str, duration_ms: Option<u64>| ChapterInfo {
+            index,
+            title: title.to_string(),
+            file: format!("0{}_{}.mp3", index + 1, title.to_lowercase()),
+            bytes: 3,
+            hash: "0".to_string(),
+            duration_ms,
+        };
+        let metadata = DocumentMetadata {
+            title: Some("Tales; Tides".to_string()),
+            voice: "en-US-GuyNeural".to_string(),
+            chapters: vec![
+                chapter(0, "One", Some(61_500)),
+                chapter(1, "Two's", Some(1_000)),
+            ],
+        };
+        let ffmetadata = to_ffmetadata(&metadata);
+        assert!(ffmetadata.starts_with(";FFMETADATA1\ntitle=Tales\\; Tides\n"));
+        assert!(ffmetadata.contains("START=61500\nEND=62500\ntitle=Two's\n"));
+        assert_eq!(
+            to_concat_list(&metadata),
+            "file '01_one.mp3'\nfile '02_two'\\''s.mp3'\n"
+        );
+        assert_eq!(
+            "MP3".parse::<AudiobookLayout>().unwrap(),
+            AudiobookLayout::Mp3
+        );
+    }
+
+    #[test]
+    fn test_id3_tag() {
+        let tag = id3_tag("One", None, "Guy", 1, 12);
+        assert_eq!(&tag[..10], b"ID3\x04\x00\x00\x00\x00\x00\x3f");
+        assert_eq!(tag.len(), 10 + 0x3f);
+        assert_eq!(&tag[10..14], b"TIT2");
+        assert_eq!(&tag[20..24], b"\x03One");
+        assert!(tag.windows(4).any(|w| w == b"1/12"));
+        assert_eq!(synchsafe(200), [0, 0, 1, 0x48]);
+    }
+
+    #[tokio::test]
+    async fn test_render_audiobook_layouts() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-book-{}", uuid::Uuid::new_v4()));
+        let document = Document::parse("# One\nFirst.\n# Two\nSecond.\n").unwrap();
+        let mut options = AudiobookOptions {
+            output_dir: Some(dir.clone()),
+            resume: true,
+            layout: AudiobookLayout::Mp3,
+        };
+        let backend = MockBackend::new();
+        let processor = TTSProcessor::with_backend(TTSConfig::default(), Box::new(backend.clone()));
+
+        let book = render(&processor, &document, "v", &options).await.unwrap();
+        let audio = std::fs::read(dir.join("02_two.mp3")).unwrap();
+        assert!(audio.starts_with(b"ID3"));
+        assert_eq!(book.document.metadata.chapters[1].bytes, audio.len());
+
+        // A resumed render keeps the tags it wrote and adds the M4B files
+        options.layout = AudiobookLayout::M4b;
+        let book = render(&processor, &document, "v", &options).await.unwrap();
+        assert_eq!((book.document.resumed, backend.calls().len()), (2, 2));
+        assert_eq!(std::fs::read(dir.join("02_two.mp3")).unwrap(), audio);
+        let ffmetadata = std::fs::read_to_string(book.ffmetadata.unwrap()).unwrap();
+        assert!(ffmetadata.contains("title=Two"));
+        assert!(dir.join(CONCAT_FILE).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let config = TTSConfig {
+            output_format: "wav".to_string(),
+            ..TTSConfig::default()
+        };
+        options.layout = AudiobookLayout::Mp3;
+        let processor = TTSProcessor::with_backend(config, Box::new(MockBackend::new()));
+        let err = render(&processor, &document, "v", &options)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TTSError::Config(_)));
+    }
+}
@@ -1,132 +1,365 @@
-use crate::models::{TTSError, Voice};
-use crate::backends::TTSBackend;
+use crate::backends::edge_voices::{EdgeVoiceData, VOICES_URL};
+use crate::backends::temp::TempAudioFile;
+use crate::backends::{classify_failure, probe_result, spawn_failure, stdout_audio, TTSBackend};
+use crate::models::{ErrorKind, SynthesisOptions, SynthesizedAudio, TTSError, Voice, WordBoundary};
+use crate::text::ssml;
 use async_trait::async_trait;
 use reqwest::Client;
-use serde::Deserialize;
+use std::ffi::OsStr;
+use std::path::Path;
 use tokio::fs;
-
-#[derive(Debug, Deserialize)]
-struct EdgeVoiceData {
-    #[serde(rename = "ShortName")]
-    short_name: String,
-    #[serde(rename = "FriendlyName")]
-    friendly_name: String,
-    #[serde(rename = "Locale")]
-    locale: String,
-    #[serde(rename = "Gender")]
-    gender: String,
-}
+use tracing::{debug, instrument};
 
 pub struct EdgeTTS {
     client: Client,
+    proxy: Option<String>,
+    options: SynthesisOptions,
 }
 
 impl EdgeTTS {
     pub fn new() -> Self {
         Self {
             client: Client::new(),
+            proxy: None,
+            options: SynthesisOptions::default(),
         }
     }
-}
 
-#[async_trait]
-impl TTSBackend for EdgeTTS {
-    async fn synthesize_text(&self, text: &str, voice: &str) -> Result<Vec<u8>, TTSError> {
+    /// Create a client that routes voice listing and synthesis through `proxy`
+    ///
+    /// Accepts `http://`, `https://` and `socks5://` URLs.
+    pub fn with_proxy(proxy: Option<&str>) -> Result<Self, TTSError> {
+        let Some(proxy) = proxy else {
+            return Ok(Self::new());
+        };
+        let client = Client::builder()
+            .proxy(
+                reqwest::Proxy::all(proxy)
+                    .map_err(|e| TTSError::Config(format!("Invalid proxy URL {}: {}", proxy, e)))?,
+            )
+            .build()
+            .map_err(|e| TTSError::from_http("edge", e))?;
+        Ok(Self {
+            client,
+            proxy: Some(proxy.to_string()),
+            options: SynthesisOptions::default(),
+        })
+    }
+
+    /// Speak with the rate, pitch and volume of `options`, see
+    /// [`prosody_args`](Self::prosody_args)
+    pub fn with_synthesis_options(mut self, options: SynthesisOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Proxy URL used by this client, if any
+    pub fn proxy(&self) -> Option<&str> {
+        self.proxy.as_deref()
+    }
+
+    /// Environment routing edge-tts through the proxy; unlike a `--proxy`
+    /// argument it keeps proxy credentials out of the process list
+    fn proxy_env(&self) -> Vec<(&str, &str)> {
+        match &self.proxy {
+            Some(proxy) => vec![("HTTPS_PROXY", proxy), ("ALL_PROXY", proxy)],
+            None => Vec::new(),
+        }
+    }
+
+    /// edge-tts `--rate`, `--pitch` and `--volume` arguments for the configured prosody
+    ///
+    /// edge-tts only takes signed relative changes: rate and volume in percent
+    /// and pitch in Hz, such as `+10%` or `-5Hz`. Neutral values are left
+    /// out; anything else, including an absolute `50%`, fails with
+    /// [`TTSError::UnsupportedOption`], and so does a speaking style, which
+    /// the Edge read aloud service does not accept.
+    pub fn prosody_args(&self) -> Result<Vec<String>, TTSError> {
+        if let Some(style) = &self.options.style {
+            return Err(TTSError::UnsupportedOption(format!(
+                "edge-tts cannot speak in a style ({})",
+                style
+            )));
+        }
+        let mut args = Vec::new();
+        for (name, value, unit) in [
+            ("rate", &self.options.rate, "%"),
+            ("pitch", &self.options.pitch, "Hz"),
+            ("volume", &self.options.volume, "%"),
+        ] {
+            if ssml::is_neutral(value) {
+                continue;
+            }
+            let value = value.trim();
+            let amount = value
+                .strip_suffix(unit)
+                .filter(|n| n.starts_with(['+', '-']) && n.parse::<i32>().is_ok())
+                .ok_or_else(|| {
+                    TTSError::UnsupportedOption(format!(
+                        "edge-tts takes {} as a relative change in {}, such as +10{}, got {}",
+                        name, unit, unit, value
+                    ))
+                })?;
+            // `--rate=-10%` keeps the value from being read as an option
+            args.push(format!("--{}={}{}", name, amount, unit));
+        }
+        Ok(args)
+    }
+
+    /// Run the edge-tts command line tool, optionally writing subtitle cues to `subtitles`
+    ///
+    /// The audio is read from the tool's stdout, so nothing is written to disk
+    /// unless subtitles are requested.
+    async fn run_edge_tts(
+        &self,
+        text: &str,
+        voice: &str,
+        subtitles: Option<&Path>,
+    ) -> Result<Vec<u8>, TTSError> {
         use std::process::Stdio;
         use tokio::process::Command;
 
-        let temp_dir = std::env::temp_dir();
-        let temp_file = temp_dir.join(format!(
-            "tts_output_{}.mp3",
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_millis()
-        ));
-
+        let prosody = self.prosody_args()?;
         let mut cmd = Command::new("edge-tts");
-        cmd.args([
-            "--voice",
-            voice,
-            "--text",
-            text,
-            "--write-media",
-            temp_file.to_str().unwrap(),
-        ])
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped());
-
-        let output = cmd.output().await;
-
-        let success = match output {
-            Ok(output) => output.status.success(),
-            Err(_) => false,
-        };
+        cmd.args(["--voice", voice, "--text", text])
+            .args(&prosody)
+            .envs(self.proxy_env())
+            .args(subtitle_args(subtitles))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        let output = match cmd.output().await {
+            Ok(output) if output.status.success() => output,
+            _ => {
+                debug!("edge-tts command failed, falling back to python -m edge_tts");
+                let mut python_cmd = Command::new("python");
+                python_cmd
+                    .args(["-m", "edge_tts", "--voice", voice, "--text", text])
+                    .args(&prosody)
+                    .envs(self.proxy_env())
+                    .args(subtitle_args(subtitles))
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .kill_on_drop(true);
 
-        if !success {
-            let mut python_cmd = Command::new("python");
-            python_cmd
-                .args([
-                    "-m",
-                    "edge_tts",
-                    "--voice",
-                    voice,
-                    "--text",
-                    text,
-                    "--write-media",
-                    temp_file.to_str().unwrap(),
-                ])
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped());
-
-            let python_output = python_cmd
-                .output()
-                .await
-                .map_err(|e| TTSError::Synthesis(format!("Failed to execute edge-tts: {}", e)))?;
-
-            if !python_output.status.success() {
-                let stderr = String::from_utf8_lossy(&python_output.stderr);
-                return Err(TTSError::Synthesis(format!("Edge TTS failed: {}", stderr)));
+                let python_output = python_cmd
+                    .output()
+                    .await
+                    .map_err(|e| spawn_failure("edge", "Failed to execute edge-tts", e))?;
+
+                if !python_output.status.success() {
+                    let stderr = String::from_utf8_lossy(&python_output.stderr);
+                    return Err(TTSError::backend_error(
+                        "edge",
+                        classify_failure(&stderr),
+                        format!("Edge TTS failed: {}", stderr.trim()),
+                    ));
+                }
+                python_output
             }
-        }
+        };
 
-        if temp_file.exists() {
-            let audio_data = fs::read(&temp_file)
-                .await
-                .map_err(|e| TTSError::Synthesis(format!("Failed to read audio file: {}", e)))?;
-            let _ = fs::remove_file(&temp_file).await;
-            Ok(audio_data)
-        } else {
-            Err(TTSError::Synthesis(
-                "Audio file was not generated".to_string(),
-            ))
-        }
+        stdout_audio("edge", output.stdout)
     }
+}
+
+fn subtitle_args(subtitles: Option<&Path>) -> Vec<&OsStr> {
+    match subtitles {
+        Some(path) => vec![OsStr::new("--write-subtitles"), path.as_os_str()],
+        None => Vec::new(),
+    }
+}
+
+/// Parse the WebVTT (edge-tts 6) or SRT (edge-tts 7) cues written by `--write-subtitles`
+///
+/// Older edge-tts releases emit one cue per word, newer ones one per sentence.
+fn parse_subtitles(cues: &str) -> Vec<WordBoundary> {
+    let mut boundaries = Vec::new();
+    let mut lines = cues.lines();
+    while let Some(line) = lines.next() {
+        let Some((start, end)) = line.split_once("-->") else {
+            continue;
+        };
+        let (Some(start), Some(end)) = (parse_timestamp(start), parse_timestamp(end)) else {
+            continue;
+        };
+        let text: Vec<&str> = lines
+            .by_ref()
+            .map(str::trim)
+            .take_while(|l| !l.is_empty())
+            .collect();
+        boundaries.push(WordBoundary {
+            offset_ms: start,
+            duration_ms: end.saturating_sub(start),
+            text: text.join(" "),
+        });
+    }
+    boundaries
+}
 
+/// Parse `HH:MM:SS.mmm` or `HH:MM:SS,mmm` into milliseconds
+fn parse_timestamp(value: &str) -> Option<u64> {
+    let value = value.split_whitespace().next()?.replace(',', ".");
+    let mut parts = value.rsplitn(3, ':');
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let hours: u64 = parts.next().map_or(Ok(0), str::parse).ok()?;
+    Some((hours * 3600 + minutes * 60) * 1000 + (seconds * 1000.0).round() as u64)
+}
+
+impl Default for EdgeTTS {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl TTSBackend for EdgeTTS {
+    fn name(&self) -> &'static str {
+        "edge"
+    }
+
+    #[instrument(skip_all, fields(backend = "edge", voice = %voice, text_len = text.len()))]
+    async fn synthesize_text(&self, text: &str, voice: &str) -> Result<Vec<u8>, TTSError> {
+        self.run_edge_tts(text, voice, None).await
+    }
+
+    #[instrument(skip_all, fields(backend = "edge", voice = %voice, text_len = text.len()))]
+    async fn synthesize_with_boundaries(
+        &self,
+        text: &str,
+        voice: &str,
+    ) -> Result<SynthesizedAudio, TTSError> {
+        let subtitles = TempAudioFile::new("tts_subtitles_", "vtt");
+        let audio = self.run_edge_tts(text, voice, Some(subtitles.path())).await;
+        let cues = fs::read_to_string(subtitles.path())
+            .await
+            .unwrap_or_default();
+        Ok(SynthesizedAudio {
+            audio: audio?,
+            boundaries: parse_subtitles(&cues),
+            // edge-tts does not expose viseme events
+            visemes: Vec::new(),
+        })
+    }
+
+    fn with_prosody(
+        &self,
+        options: &SynthesisOptions,
+    ) -> Option<Box<dyn TTSBackend + Send + Sync>> {
+        Some(Box::new(EdgeTTS {
+            client: self.client.clone(),
+            proxy: self.proxy.clone(),
+            options: options.clone(),
+        }))
+    }
+
+    #[instrument(skip_all, fields(backend = "edge"))]
     async fn list_voices(&self) -> Result<Vec<Voice>, TTSError> {
-        let url = "https://speech.platform.bing.com/consumer/speech/synthesize/readaloud/voices/list?trustedclienttoken=6A5AA1D4EAFF4E9FB37E23D68491D6F4";
         let response = self
             .client
-            .get(url)
+            .get(VOICES_URL)
             .send()
             .await
-            .map_err(TTSError::Network)?;
-        
-        let voices: Vec<EdgeVoiceData> = response.json().await.map_err(TTSError::Network)?;
-        
-        Ok(voices
-            .into_iter()
-            .map(|v| Voice {
-                name: v.short_name,
-                display_name: v.friendly_name,
-                locale: v.locale,
-                gender: v.gender,
-                description: None,
-            })
-            .collect())
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| TTSError::from_http("edge", e))?;
+
+        let voices: Vec<EdgeVoiceData> = response
+            .json()
+            .await
+            .map_err(|e| TTSError::from_http("edge", e))?;
+
+        Ok(voices.into_iter().map(Voice::from).collect())
     }
 
     async fn save_audio(&self, audio_data: &[u8], filename: &str) -> Result<(), TTSError> {
         fs::write(filename, audio_data).await.map_err(TTSError::Io)
     }
+
+    /// Start edge-tts once, so its interpreter and modules are loaded from
+    /// the OS cache on the first real request, and fail early if it is missing
+    ///
+    /// Every synthesis still starts a new edge-tts process, which opens its
+    /// own WebSocket; there is no session to keep connected in between.
+    async fn warm_up(&self) -> Result<(), TTSError> {
+        use tokio::process::Command;
+
+        let edge_tts = Command::new("edge-tts")
+            .arg("--version")
+            .kill_on_drop(true)
+            .output()
+            .await;
+        let Err(error) = probe_result("edge", "edge-tts does not run", edge_tts) else {
+            return Ok(());
+        };
+        let python = Command::new("python")
+            .args(["-m", "edge_tts", "--version"])
+            .kill_on_drop(true)
+            .output()
+            .await;
+        match probe_result("edge", "python -m edge_tts does not run", python) {
+            // Without the module, the edge-tts error says more about what is wrong
+            Err(e) if e.kind() == ErrorKind::NotInstalled => Err(error),
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_subtitles() {
+        let vtt = "WEBVTT\n\n00:00:00.100 --> 00:00:00.500\nHello\n\n00:00:00.500 --> 00:00:01.250\nworld\n";
+        assert_eq!(
+            parse_subtitles(vtt),
+            vec![
+                WordBoundary {
+                    offset_ms: 100,
+                    duration_ms: 400,
+                    text: "Hello".to_string()
+                },
+                WordBoundary {
+                    offset_ms: 500,
+                    duration_ms: 750,
+                    text: "world".to_string()
+                },
+            ]
+        );
+
+        let srt = "1\n00:01:02,000 --> 00:01:03,500\nHello world.\n";
+        let boundaries = parse_subtitles(srt);
+        assert_eq!(boundaries[0].offset_ms, 62_000);
+        assert_eq!(boundaries[0].duration_ms, 1_500);
+        assert_eq!(boundaries[0].text, "Hello world.");
+    }
+
+    #[test]
+    fn test_prosody_args() {
+        let edge = |rate: &str, pitch: &str, volume: &str| {
+            EdgeTTS::new().with_synthesis_options(SynthesisOptions {
+                rate: rate.to_string(),
+                pitch: pitch.to_string(),
+                volume: volume.to_string(),
+                ..SynthesisOptions::default()
+            })
+        };
+        assert!(edge("0%", "+0Hz", "100%")
+            .prosody_args()
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            edge("-10%", "+5Hz", "+20%").prosody_args().unwrap(),
+            ["--rate=-10%", "--pitch=+5Hz", "--volume=+20%"]
+        );
+        let err = edge("fast", "0%", "").prosody_args().unwrap_err();
+        assert!(matches!(err, TTSError::UnsupportedOption(_)));
+        assert!(edge("0%", "+5%", "").prosody_args().is_err());
+        assert!(edge("0%", "", "50%").prosody_args().is_err());
+        let mut cheerful = edge("0%", "", "");
+        cheerful.options.style = Some("cheerful".to_string());
+        assert!(cheerful.prosody_args().is_err());
+    }
 }
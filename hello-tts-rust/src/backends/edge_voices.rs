@@ -0,0 +1,125 @@
+//! The Edge voice list, shared by the edge-tts backend and the browser client.
+//!
+//! [`VOICES_URL`] returns every voice as JSON. [`EdgeVoiceData`] reads one
+//! entry, including the Azure variants of its fields, and converts into a
+//! [`Voice`].
+
+use crate::models::Voice;
+use serde::Deserialize;
+
+/// Public Edge voice list endpoint
+pub(crate) const VOICES_URL: &str = "https://speech.platform.bing.com/consumer/speech/synthesize/readaloud/voices/list?trustedclienttoken=6A5AA1D4EAFF4E9FB37E23D68491D6F4";
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct EdgeVoiceData {
+    #[serde(rename = "ShortName")]
+    short_name: String,
+    #[serde(rename = "FriendlyName")]
+    friendly_name: String,
+    #[serde(rename = "Locale")]
+    locale: String,
+    #[serde(rename = "Gender")]
+    gender: String,
+    #[serde(rename = "Status", default)]
+    status: Option<String>,
+    #[serde(rename = "SampleRateHertz", default, deserialize_with = "sample_rate")]
+    sample_rate_hertz: Option<u32>,
+    #[serde(rename = "SuggestedCodec", default)]
+    suggested_codec: Option<String>,
+    #[serde(rename = "StyleList", default)]
+    style_list: Vec<String>,
+    #[serde(rename = "VoiceTag", default)]
+    voice_tag: EdgeVoiceTag,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct EdgeVoiceTag {
+    #[serde(rename = "ContentCategories", default)]
+    content_categories: Vec<String>,
+    #[serde(rename = "VoicePersonalities", default)]
+    voice_personalities: Vec<String>,
+}
+
+/// `SampleRateHertz` is a string in Azure's voice list and a number elsewhere
+fn sample_rate<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(
+        match Option::<serde_json::Value>::deserialize(deserializer)? {
+            Some(serde_json::Value::Number(n)) => n.as_u64().and_then(|n| u32::try_from(n).ok()),
+            Some(serde_json::Value::String(s)) => s.parse().ok(),
+            _ => None,
+        },
+    )
+}
+
+/// Sample rate encoded in a codec name such as `audio-24khz-48kbitrate-mono-mp3`
+fn codec_sample_rate(codec: &str) -> Option<u32> {
+    codec
+        .split('-')
+        .find_map(|part| part.strip_suffix("khz")?.parse::<u32>().ok())
+        .map(|khz| khz * 1000)
+}
+
+impl From<EdgeVoiceData> for Voice {
+    fn from(v: EdgeVoiceData) -> Self {
+        let sample_rate_hz = v
+            .sample_rate_hertz
+            .or_else(|| v.suggested_codec.as_deref().and_then(codec_sample_rate));
+        Voice {
+            name: v.short_name,
+            display_name: v.friendly_name,
+            locale: v.locale,
+            gender: v.gender,
+            description: None,
+            sample_rate_hz,
+            styles: v.style_list,
+            personalities: v.voice_tag.voice_personalities,
+            content_categories: v.voice_tag.content_categories,
+            status: v.status,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_voice_metadata() {
+        let json = r#"[{
+            "Name": "Microsoft Server Speech Text to Speech Voice (en-US, AriaNeural)",
+            "ShortName": "en-US-AriaNeural",
+            "Gender": "Female",
+            "Locale": "en-US",
+            "SuggestedCodec": "audio-24khz-48kbitrate-mono-mp3",
+            "FriendlyName": "Microsoft Aria Online (Natural) - English (United States)",
+            "Status": "GA",
+            "VoiceTag": {
+                "ContentCategories": ["News", "Novel"],
+                "VoicePersonalities": ["Positive", "Confident"]
+            }
+        }, {
+            "ShortName": "en-US-JennyNeural",
+            "Gender": "Female",
+            "Locale": "en-US",
+            "FriendlyName": "Jenny",
+            "SampleRateHertz": "48000",
+            "StyleList": ["cheerful", "sad"]
+        }]"#;
+        let voices: Vec<Voice> = serde_json::from_str::<Vec<EdgeVoiceData>>(json)
+            .unwrap()
+            .into_iter()
+            .map(Voice::from)
+            .collect();
+
+        assert_eq!(voices[0].sample_rate_hz, Some(24_000));
+        assert_eq!(voices[0].status.as_deref(), Some("GA"));
+        assert_eq!(voices[0].personalities, ["Positive", "Confident"]);
+        assert_eq!(voices[0].content_categories, ["News", "Novel"]);
+        assert_eq!(voices[1].sample_rate_hz, Some(48_000));
+        assert_eq!(voices[1].styles, ["cheerful", "sad"]);
+        assert!(voices[1].status.is_none());
+    }
+}
@@ -1,121 +1,167 @@
-use crate::models::{TTSError, Voice};
-use crate::config::TTSConfigFile;
-use crate::backends::TTSBackend;
+use crate::backends::{
+    classify_failure, gtts_voices, probe_result, spawn_failure, stdout_audio, TTSBackend,
+};
+use crate::models::{SynthesisOptions, TTSError, Voice};
+use crate::text::ssml;
 use async_trait::async_trait;
-use log::{debug, error, info};
 use tokio::fs;
+use tracing::instrument;
 
-pub struct GoogleTTS;
+#[derive(Default)]
+pub struct GoogleTTS {
+    options: SynthesisOptions,
+}
 
 impl GoogleTTS {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Apply `options.accent` to voices that do not name an accent themselves,
+    /// and `options.rate` as far as gTTS can, see [`slow_mode`](Self::slow_mode)
+    pub fn with_options(options: SynthesisOptions) -> Self {
+        Self { options }
+    }
+
+    /// Whether to pass `--slow` for the configured prosody
+    ///
+    /// gTTS only speaks at normal or slow speed, so a rate of
+    /// [`SLOW_RATE_PERCENT`] or less (or `slow`/`x-slow`) selects slow mode
+    /// and a neutral rate normal speed. Other rates, any pitch or volume
+    /// change and a speaking style fail with [`TTSError::UnsupportedOption`].
+    pub fn slow_mode(&self) -> Result<bool, TTSError> {
+        let unsupported = |name: &str, value: &str| {
+            TTSError::UnsupportedOption(format!(
+                "Google TTS cannot change {} ({}); it only has normal and slow speed \
+                 (rate {}% or less)",
+                name, value, SLOW_RATE_PERCENT
+            ))
+        };
+        for (name, value) in [
+            ("pitch", &self.options.pitch),
+            ("volume", &self.options.volume),
+        ] {
+            if !ssml::is_neutral(value) {
+                return Err(unsupported(name, value));
+            }
+        }
+        if let Some(style) = &self.options.style {
+            return Err(unsupported("style", style));
+        }
+        let rate = self.options.rate.trim();
+        if ssml::is_neutral(rate) || rate == "medium" {
+            return Ok(false);
+        }
+        if matches!(rate, "slow" | "x-slow") {
+            return Ok(true);
+        }
+        match rate.strip_suffix('%').and_then(|r| r.parse::<i32>().ok()) {
+            Some(percent) if percent <= SLOW_RATE_PERCENT => Ok(true),
+            _ => Err(unsupported("rate", rate)),
+        }
     }
 }
 
+/// Rates at or below this percentage use gTTS's slow mode
+pub const SLOW_RATE_PERCENT: i32 = -20;
+
 #[async_trait]
 impl TTSBackend for GoogleTTS {
+    fn name(&self) -> &'static str {
+        "google"
+    }
+
+    #[instrument(skip_all, fields(backend = "google", voice = %voice, text_len = text.len()))]
     async fn synthesize_text(&self, text: &str, voice: &str) -> Result<Vec<u8>, TTSError> {
         use std::process::Stdio;
         use tokio::process::Command;
 
-        let temp_dir = std::env::temp_dir();
-        let temp_file = temp_dir.join(format!(
-            "gtts_output_{}.mp3",
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_millis()
-        ));
-
-        let vn = voice.replace('_', "-");
-        let mut lang_code = "en".to_string();
-        if vn.contains('-') {
-            let parts: Vec<&str> = vn.split('-').collect();
-            if parts.len() >= 2 && parts[1].len() == 2 {
-                lang_code = format!("{}-{}", parts[0].to_lowercase(), parts[1].to_lowercase());
-            } else {
-                lang_code = parts[0].to_lowercase();
-            }
-        } else if !vn.is_empty() {
-            lang_code = vn.to_lowercase();
-        }
-
+        let slow = self.slow_mode()?;
+        let (lang_code, tld) = gtts_voices::resolve(voice);
+        let tld = tld.map(str::to_string).or_else(|| {
+            let accent = self.options.accent.as_deref()?;
+            gtts_voices::accent_tld(accent)
+        });
         let mut cmd = Command::new("gtts-cli");
-        cmd.args([
-            text,
-            "--lang",
-            &lang_code,
-            "--output",
-            temp_file.to_str().unwrap(),
-        ])
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped());
+        // Without --output, gtts-cli writes the audio to stdout
+        cmd.args([text, "--lang", &lang_code]);
+        if let Some(tld) = tld {
+            cmd.args(["--tld", &tld]);
+        }
+        if slow {
+            cmd.arg("--slow");
+        }
+        cmd.stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
 
         let output = cmd
             .output()
             .await
-            .map_err(|e| TTSError::Synthesis(format!("Failed to execute gtts-cli: {}", e)))?;
+            .map_err(|e| spawn_failure("google", "Failed to execute gtts-cli", e))?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(TTSError::Synthesis(format!(
-                "Google TTS failed: {}",
-                stderr
-            )));
+            return Err(TTSError::backend_error(
+                "google",
+                classify_failure(&stderr),
+                format!("Google TTS failed: {}", stderr.trim()),
+            ));
         }
 
-        if temp_file.exists() {
-            let audio_data = fs::read(&temp_file)
-                .await
-                .map_err(|e| TTSError::Synthesis(format!("Failed to read audio file: {}", e)))?;
-            let _ = fs::remove_file(&temp_file).await;
-            Ok(audio_data)
-        } else {
-            Err(TTSError::Synthesis(
-                "Audio file was not generated".to_string(),
-            ))
-        }
+        stdout_audio("google", output.stdout)
     }
 
+    fn with_prosody(
+        &self,
+        options: &SynthesisOptions,
+    ) -> Option<Box<dyn TTSBackend + Send + Sync>> {
+        Some(Box::new(GoogleTTS::with_options(options.clone())))
+    }
+
+    #[instrument(skip_all, fields(backend = "google"))]
     async fn list_voices(&self) -> Result<Vec<Voice>, TTSError> {
-        use std::env;
-        use std::path::Path;
-
-        let config_path = "../../shared/tts_config.json";
-        let cwd = env::current_dir().unwrap_or_else(|_| Path::new("").to_path_buf());
-        info!("Loading voices from config file: {}", config_path);
-        debug!("Current working directory: {}", cwd.display());
-        let abs_path = if Path::new(config_path).is_absolute() {
-            Path::new(config_path).to_path_buf()
-        } else {
-            cwd.join(config_path)
-        };
-        debug!("Resolved config file path: {}", abs_path.display());
-        let content = tokio::fs::read_to_string(&abs_path).await.map_err(|e| {
-            error!("Failed to read config file: {}", abs_path.display());
-            TTSError::Config(format!("Failed to read config file {}: {}", abs_path.display(), e))
-        })?;
-        let config: TTSConfigFile = serde_json::from_str(&content)
-            .map_err(|e| TTSError::Config(format!("Invalid JSON in config file: {}", e)))?;
-
-        let voices = config
-            .languages
-            .iter()
-            .filter_map(|lang| {
-                lang.google_voice.as_ref().map(|voice_name| Voice {
-                    name: voice_name.clone(),
-                    display_name: lang.name.clone(),
-                    locale: lang.code.clone(),
-                    gender: "Unknown".to_string(),
-                    description: None,
-                })
-            })
-            .collect();
-        Ok(voices)
+        Ok(gtts_voices::voices())
+    }
+
+    /// The voice list is built in, so probe the gtts-cli tool instead
+    async fn health_check(&self) -> Result<(), TTSError> {
+        let output = tokio::process::Command::new("gtts-cli")
+            .arg("--version")
+            .kill_on_drop(true)
+            .output()
+            .await;
+        probe_result("google", "gtts-cli does not run", output)
     }
 
     async fn save_audio(&self, audio_data: &[u8], filename: &str) -> Result<(), TTSError> {
         fs::write(filename, audio_data).await.map_err(TTSError::Io)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ErrorKind;
+
+    fn google(rate: &str, pitch: &str) -> GoogleTTS {
+        GoogleTTS::with_options(SynthesisOptions {
+            rate: rate.to_string(),
+            pitch: pitch.to_string(),
+            volume: "100%".to_string(),
+            ..SynthesisOptions::default()
+        })
+    }
+
+    #[test]
+    fn test_slow_mode() {
+        assert!(!google("0%", "0%").slow_mode().unwrap());
+        assert!(google("-20%", "0%").slow_mode().unwrap());
+        assert!(google("x-slow", "").slow_mode().unwrap());
+        let err = google("+20%", "0%").slow_mode().unwrap_err();
+        assert!(matches!(err, TTSError::UnsupportedOption(_)));
+        assert_eq!(err.kind(), ErrorKind::InvalidRequest);
+        assert!(google("-10%", "0%").slow_mode().is_err());
+        assert!(google("0%", "+5%").slow_mode().is_err());
+    }
+}
@@ -0,0 +1,234 @@
+//! Languages and regional accents of gTTS, built into the crate.
+//!
+//! gTTS has no voices, only a language code and the Google Translate domain
+//! (`tld`) to send it to, which gives some languages a regional accent. The
+//! tables below list both as voices: a language by its gTTS code (`en`,
+//! `zh-CN`) and an accent by its locale (`en-AU` is `en` on `com.au`), so
+//! `voices --backend google` works without a language list file. They follow
+//! `gtts-cli --all` and the localized accents of the gTTS documentation.
+
+use crate::models::Voice;
+
+/// gTTS language codes and names
+pub const LANGUAGES: &[(&str, &str)] = &[
+    ("af", "Afrikaans"),
+    ("am", "Amharic"),
+    ("ar", "Arabic"),
+    ("bg", "Bulgarian"),
+    ("bn", "Bengali"),
+    ("bs", "Bosnian"),
+    ("ca", "Catalan"),
+    ("cs", "Czech"),
+    ("cy", "Welsh"),
+    ("da", "Danish"),
+    ("de", "German"),
+    ("el", "Greek"),
+    ("en", "English"),
+    ("es", "Spanish"),
+    ("et", "Estonian"),
+    ("eu", "Basque"),
+    ("fi", "Finnish"),
+    ("fr", "French"),
+    ("gl", "Galician"),
+    ("gu", "Gujarati"),
+    ("ha", "Hausa"),
+    ("hi", "Hindi"),
+    ("hr", "Croatian"),
+    ("hu", "Hungarian"),
+    ("id", "Indonesian"),
+    ("is", "Icelandic"),
+    ("it", "Italian"),
+    ("iw", "Hebrew"),
+    ("ja", "Japanese"),
+    ("jw", "Javanese"),
+    ("km", "Khmer"),
+    ("kn", "Kannada"),
+    ("ko", "Korean"),
+    ("la", "Latin"),
+    ("lt", "Lithuanian"),
+    ("lv", "Latvian"),
+    ("ml", "Malayalam"),
+    ("mr", "Marathi"),
+    ("ms", "Malay"),
+    ("my", "Myanmar (Burmese)"),
+    ("ne", "Nepali"),
+    ("nl", "Dutch"),
+    ("no", "Norwegian"),
+    ("pa", "Punjabi (Gurmukhi)"),
+    ("pl", "Polish"),
+    ("pt", "Portuguese"),
+    ("ro", "Romanian"),
+    ("ru", "Russian"),
+    ("si", "Sinhala"),
+    ("sk", "Slovak"),
+    ("sq", "Albanian"),
+    ("sr", "Serbian"),
+    ("su", "Sundanese"),
+    ("sv", "Swedish"),
+    ("sw", "Swahili"),
+    ("ta", "Tamil"),
+    ("te", "Telugu"),
+    ("th", "Thai"),
+    ("tl", "Filipino"),
+    ("tr", "Turkish"),
+    ("uk", "Ukrainian"),
+    ("ur", "Urdu"),
+    ("vi", "Vietnamese"),
+    ("yue", "Cantonese"),
+    ("zh", "Chinese (Mandarin)"),
+    ("zh-CN", "Chinese (Simplified)"),
+    ("zh-TW", "Chinese (Mandarin/Taiwan)"),
+];
+
+/// A language spoken with the accent of a Google Translate domain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Accent {
+    /// Voice name, e.g. `en-AU`
+    pub locale: &'static str,
+    /// gTTS language code
+    pub lang: &'static str,
+    /// Google Translate top-level domain, e.g. `com.au`
+    pub tld: &'static str,
+    pub name: &'static str,
+}
+
+const fn accent(
+    locale: &'static str,
+    lang: &'static str,
+    tld: &'static str,
+    name: &'static str,
+) -> Accent {
+    Accent {
+        locale,
+        lang,
+        tld,
+        name,
+    }
+}
+
+/// Regional accents gTTS documents
+pub const ACCENTS: &[Accent] = &[
+    accent("en-AU", "en", "com.au", "English (Australia)"),
+    accent("en-GB", "en", "co.uk", "English (United Kingdom)"),
+    accent("en-US", "en", "us", "English (United States)"),
+    accent("en-CA", "en", "ca", "English (Canada)"),
+    accent("en-IN", "en", "co.in", "English (India)"),
+    accent("en-IE", "en", "ie", "English (Ireland)"),
+    accent("en-ZA", "en", "co.za", "English (South Africa)"),
+    accent("en-NG", "en", "com.ng", "English (Nigeria)"),
+    accent("fr-CA", "fr", "ca", "French (Canada)"),
+    accent("fr-FR", "fr", "fr", "French (France)"),
+    accent("pt-BR", "pt", "com.br", "Portuguese (Brazil)"),
+    accent("pt-PT", "pt", "pt", "Portuguese (Portugal)"),
+    accent("es-MX", "es", "com.mx", "Spanish (Mexico)"),
+    accent("es-ES", "es", "es", "Spanish (Spain)"),
+    accent("es-US", "es", "us", "Spanish (United States)"),
+];
+
+/// Locale of a gTTS language code; gTTS keeps the old codes of Hebrew and Javanese
+fn locale_of(code: &str) -> &str {
+    match code {
+        "iw" => "he",
+        "jw" => "jv",
+        _ => code,
+    }
+}
+
+/// Every language and accent as a voice
+pub fn voices() -> Vec<Voice> {
+    let languages = LANGUAGES.iter().map(|(code, name)| {
+        Voice::new(
+            code.to_string(),
+            name.to_string(),
+            locale_of(code).to_string(),
+            "Unknown".to_string(),
+        )
+    });
+    let accents = ACCENTS.iter().map(|accent| Voice {
+        description: Some(format!(
+            "gTTS {} on translate.google.{}",
+            accent.lang, accent.tld
+        )),
+        ..Voice::new(
+            accent.locale.to_string(),
+            accent.name.to_string(),
+            accent.locale.to_string(),
+            "Unknown".to_string(),
+        )
+    });
+    languages
+        .chain(accents)
+        .map(|voice| Voice {
+            // gTTS always returns 24 kHz MP3
+            sample_rate_hz: Some(24_000),
+            ..voice
+        })
+        .collect()
+}
+
+/// gTTS language code and, for accents, domain of a voice name
+///
+/// Names match case-insensitively with `_` or `-`; other locales such as
+/// `de-DE` fall back to their language part.
+pub fn resolve(voice: &str) -> (String, Option<&'static str>) {
+    let voice = voice.replace('_', "-");
+    if let Some(accent) = ACCENTS
+        .iter()
+        .find(|a| a.locale.eq_ignore_ascii_case(&voice))
+    {
+        return (accent.lang.to_string(), Some(accent.tld));
+    }
+    if let Some((code, _)) = LANGUAGES
+        .iter()
+        .find(|(code, _)| code.eq_ignore_ascii_case(&voice))
+    {
+        return (code.to_string(), None);
+    }
+    match voice.split('-').next() {
+        Some(lang) if !lang.is_empty() => (lang.to_lowercase(), None),
+        _ => ("en".to_string(), None),
+    }
+}
+
+/// Google Translate domain of an accent locale (`en-GB`) or a domain itself (`co.uk`)
+pub fn accent_tld(accent: &str) -> Option<String> {
+    let accent = accent.trim().replace('_', "-");
+    if let Some(found) = ACCENTS
+        .iter()
+        .find(|a| a.locale.eq_ignore_ascii_case(&accent))
+    {
+        return Some(found.tld.to_string());
+    }
+    let is_domain = !accent.is_empty()
+        && accent
+            .split('.')
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphabetic()));
+    is_domain.then(|| accent.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(resolve("en-AU"), ("en".to_string(), Some("com.au")));
+        assert_eq!(resolve("pt_br"), ("pt".to_string(), Some("com.br")));
+        assert_eq!(resolve("zh-cn"), ("zh-CN".to_string(), None));
+        assert_eq!(resolve("de-DE"), ("de".to_string(), None));
+        assert_eq!(resolve(""), ("en".to_string(), None));
+        assert_eq!(accent_tld("en_gb").as_deref(), Some("co.uk"));
+        assert_eq!(accent_tld("com.AU").as_deref(), Some("com.au"));
+        assert_eq!(accent_tld("en-XX"), None);
+    }
+
+    #[test]
+    fn test_voices_are_unique() {
+        let voices = voices();
+        let names: HashSet<String> = voices.iter().map(|v| v.name.to_lowercase()).collect();
+        assert_eq!(names.len(), voices.len());
+        let hebrew = voices.iter().find(|v| v.name == "iw").unwrap();
+        assert!(hebrew.matches_language("he"));
+    }
+}
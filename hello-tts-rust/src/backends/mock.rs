@@ -0,0 +1,384 @@
+//! Backends for testing without network access.
+//!
+//! [`MockBackend`] answers from a script of responses and can inject failures
+//! and latency. [`FixtureBackend`] records what a real backend returns into a
+//! fixture directory and replays it later, and [`check_golden`] compares audio
+//! against a stored golden file.
+
+use crate::backends::TTSBackend;
+use crate::models::{ErrorKind, SynthesisOptions, SynthesizedAudio, TTSError, Viseme, Voice};
+use crate::naming::fnv1a;
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::fs;
+
+/// Set to re-record golden files instead of comparing against them
+pub const ENV_UPDATE_GOLDEN: &str = "HELLO_TTS_UPDATE_GOLDEN";
+
+/// A scripted reply of [`MockBackend`]
+#[derive(Debug, Clone)]
+pub enum MockResponse {
+    Audio(Vec<u8>),
+    Error(ErrorKind, String),
+}
+
+/// A synthesis request received by [`MockBackend`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockCall {
+    pub text: String,
+    pub voice: String,
+    /// Options of a copy made with `with_prosody`
+    pub options: Option<SynthesisOptions>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    script: VecDeque<MockResponse>,
+    calls: Vec<MockCall>,
+    voice_list_calls: usize,
+    /// Reason `health_check` fails with, if set
+    down: Option<String>,
+}
+
+/// Backend answering from a script of responses
+///
+/// Once the script is used up, the UTF-8 bytes of the text are returned as
+/// "audio", which makes outputs deterministic. Clones share their script and
+/// call log, so a test can keep a handle after giving one to a processor.
+#[derive(Debug, Clone, Default)]
+pub struct MockBackend {
+    name: Option<&'static str>,
+    voices: Vec<Voice>,
+    latency: Duration,
+    max_text_chars: Option<usize>,
+    visemes: Vec<Viseme>,
+    options: Option<SynthesisOptions>,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report `name` instead of `mock`, e.g. to stand in for a real backend
+    pub fn with_name(mut self, name: &'static str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Voices returned by `list_voices`
+    pub fn with_voices(mut self, voices: Vec<Voice>) -> Self {
+        self.voices = voices;
+        self
+    }
+
+    /// Delay every request by `latency`
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Report `limit` as the longest text accepted in one request
+    pub fn with_max_text_chars(mut self, limit: usize) -> Self {
+        self.max_text_chars = Some(limit);
+        self
+    }
+
+    /// Visemes reported with every response that asks for timings
+    pub fn with_visemes(mut self, visemes: Vec<Viseme>) -> Self {
+        self.visemes = visemes;
+        self
+    }
+
+    /// Queue audio to return for the next request
+    pub fn then_audio(self, audio: impl Into<Vec<u8>>) -> Self {
+        self.push(MockResponse::Audio(audio.into()));
+        self
+    }
+
+    /// Queue a failure of `kind` for the next request
+    pub fn then_error(self, kind: ErrorKind, message: &str) -> Self {
+        self.push(MockResponse::Error(kind, message.to_string()));
+        self
+    }
+
+    /// Queue a response for the next request
+    pub fn push(&self, response: MockResponse) {
+        self.state.lock().unwrap().script.push_back(response);
+    }
+
+    /// Make `health_check` fail with `reason`, or pass again with `None`
+    pub fn set_down(&self, reason: Option<&str>) {
+        self.state.lock().unwrap().down = reason.map(str::to_string);
+    }
+
+    /// Synthesis requests received so far
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.state.lock().unwrap().calls.clone()
+    }
+
+    /// Number of times the voice list was requested
+    pub fn voice_list_calls(&self) -> usize {
+        self.state.lock().unwrap().voice_list_calls
+    }
+}
+
+#[async_trait]
+impl TTSBackend for MockBackend {
+    fn name(&self) -> &'static str {
+        self.name.unwrap_or("mock")
+    }
+
+    async fn synthesize_text(&self, text: &str, voice: &str) -> Result<Vec<u8>, TTSError> {
+        if !self.latency.is_zero() {
+            tokio::time::sleep(self.latency).await;
+        }
+        let response = {
+            let mut state = self.state.lock().unwrap();
+            state.calls.push(MockCall {
+                text: text.to_string(),
+                voice: voice.to_string(),
+                options: self.options.clone(),
+            });
+            state.script.pop_front()
+        };
+        match response {
+            Some(MockResponse::Audio(audio)) => Ok(audio),
+            Some(MockResponse::Error(kind, message)) => {
+                Err(TTSError::backend_error(self.name(), kind, message))
+            }
+            None => Ok(text.as_bytes().to_vec()),
+        }
+    }
+
+    async fn synthesize_with_boundaries(
+        &self,
+        text: &str,
+        voice: &str,
+    ) -> Result<SynthesizedAudio, TTSError> {
+        Ok(SynthesizedAudio {
+            audio: self.synthesize_text(text, voice).await?,
+            boundaries: Vec::new(),
+            visemes: self.visemes.clone(),
+        })
+    }
+
+    fn supports_visemes(&self) -> bool {
+        true
+    }
+
+    fn with_prosody(
+        &self,
+        options: &SynthesisOptions,
+    ) -> Option<Box<dyn TTSBackend + Send + Sync>> {
+        Some(Box::new(Self {
+            options: Some(options.clone()),
+            ..self.clone()
+        }))
+    }
+
+    async fn list_voices(&self) -> Result<Vec<Voice>, TTSError> {
+        self.state.lock().unwrap().voice_list_calls += 1;
+        Ok(self.voices.clone())
+    }
+
+    fn max_text_chars(&self) -> Option<usize> {
+        self.max_text_chars
+    }
+
+    async fn health_check(&self) -> Result<(), TTSError> {
+        match &self.state.lock().unwrap().down {
+            Some(reason) => Err(TTSError::backend_error(
+                self.name(),
+                ErrorKind::Unavailable,
+                reason.clone(),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    async fn save_audio(&self, audio_data: &[u8], filename: &str) -> Result<(), TTSError> {
+        fs::write(filename, audio_data).await.map_err(TTSError::Io)
+    }
+}
+
+/// Whether [`FixtureBackend`] talks to the real backend or only to fixtures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixtureMode {
+    /// Forward to the wrapped backend and save its responses
+    Record,
+    /// Answer from saved fixtures only; a missing fixture is an error
+    Replay,
+}
+
+/// Records a real backend's responses into a directory and replays them
+///
+/// Audio is stored as `<voice>-<hash of text>.mp3` and the voice list as
+/// `voices.json`, so fixtures can be committed next to the tests using them.
+pub struct FixtureBackend {
+    inner: Option<Box<dyn TTSBackend + Send + Sync>>,
+    dir: PathBuf,
+}
+
+impl FixtureBackend {
+    /// Record responses of `inner` into `dir`
+    pub fn record(inner: Box<dyn TTSBackend + Send + Sync>, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            inner: Some(inner),
+            dir: dir.into(),
+        }
+    }
+
+    /// Replay responses previously recorded into `dir`
+    pub fn replay(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            inner: None,
+            dir: dir.into(),
+        }
+    }
+
+    pub fn mode(&self) -> FixtureMode {
+        if self.inner.is_some() {
+            FixtureMode::Record
+        } else {
+            FixtureMode::Replay
+        }
+    }
+
+    /// Path of the fixture holding the audio for `text` spoken by `voice`
+    pub fn fixture_path(&self, text: &str, voice: &str) -> PathBuf {
+        let voice: String = voice
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.dir
+            .join(format!("{}-{:016x}.mp3", voice, fnv1a(text.as_bytes())))
+    }
+
+    fn missing(path: &Path) -> TTSError {
+        TTSError::backend_error(
+            "fixture",
+            ErrorKind::InvalidRequest,
+            format!("No recorded fixture at {}", path.display()),
+        )
+    }
+}
+
+#[async_trait]
+impl TTSBackend for FixtureBackend {
+    fn name(&self) -> &'static str {
+        match &self.inner {
+            Some(inner) => inner.name(),
+            None => "fixture",
+        }
+    }
+
+    async fn synthesize_text(&self, text: &str, voice: &str) -> Result<Vec<u8>, TTSError> {
+        let path = self.fixture_path(text, voice);
+        match &self.inner {
+            Some(inner) => {
+                let audio = inner.synthesize_text(text, voice).await?;
+                fs::create_dir_all(&self.dir).await?;
+                fs::write(&path, &audio).await?;
+                Ok(audio)
+            }
+            None => fs::read(&path).await.map_err(|_| Self::missing(&path)),
+        }
+    }
+
+    async fn list_voices(&self) -> Result<Vec<Voice>, TTSError> {
+        let path = self.dir.join("voices.json");
+        match &self.inner {
+            Some(inner) => {
+                let voices = inner.list_voices().await?;
+                fs::create_dir_all(&self.dir).await?;
+                fs::write(&path, serde_json::to_vec_pretty(&voices)?).await?;
+                Ok(voices)
+            }
+            None => {
+                let content = fs::read(&path).await.map_err(|_| Self::missing(&path))?;
+                Ok(serde_json::from_slice(&content)?)
+            }
+        }
+    }
+
+    async fn save_audio(&self, audio_data: &[u8], filename: &str) -> Result<(), TTSError> {
+        fs::write(filename, audio_data).await.map_err(TTSError::Io)
+    }
+}
+
+/// Compare `actual` with the golden file at `path`
+///
+/// The golden file is written when it does not exist yet or when
+/// `HELLO_TTS_UPDATE_GOLDEN` is set.
+pub fn check_golden(path: &Path, actual: &[u8]) -> Result<(), String> {
+    if std::env::var_os(ENV_UPDATE_GOLDEN).is_some() || !path.exists() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        return std::fs::write(path, actual).map_err(|e| e.to_string());
+    }
+    let expected = std::fs::read(path).map_err(|e| e.to_string())?;
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} differs from golden file ({} bytes expected, {} bytes produced); set {} to update it",
+            path.display(),
+            expected.len(),
+            actual.len(),
+            ENV_UPDATE_GOLDEN
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scripted_responses() {
+        let mock = MockBackend::new()
+            .then_error(ErrorKind::RateLimited, "slow down")
+            .then_audio(b"audio".to_vec());
+        let err = mock.synthesize_text("a", "v").await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::RateLimited);
+        assert_eq!(mock.synthesize_text("b", "v").await.unwrap(), b"audio");
+        assert_eq!(mock.synthesize_text("c", "v").await.unwrap(), b"c");
+        assert_eq!(mock.calls().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-fixtures-{}", uuid::Uuid::new_v4()));
+        let recorder = FixtureBackend::record(
+            Box::new(MockBackend::new().then_audio(b"recorded".to_vec())),
+            &dir,
+        );
+        assert_eq!(
+            recorder.synthesize_text("Hi", "en-US-A").await.unwrap(),
+            b"recorded"
+        );
+        recorder.list_voices().await.unwrap();
+
+        let replay = FixtureBackend::replay(&dir);
+        assert_eq!(replay.mode(), FixtureMode::Replay);
+        assert_eq!(
+            replay.synthesize_text("Hi", "en-US-A").await.unwrap(),
+            b"recorded"
+        );
+        assert!(replay.list_voices().await.unwrap().is_empty());
+        assert!(replay.synthesize_text("Other", "en-US-A").await.is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
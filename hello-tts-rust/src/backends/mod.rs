@@ -1,12 +1,190 @@
-use crate::models::{TTSError, Voice};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::models::ErrorKind;
+use crate::models::{SynthesisOptions, SynthesizedAudio, TTSError, Voice};
 use async_trait::async_trait;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io;
+#[cfg(not(target_arch = "wasm32"))]
+use std::process::Output;
+
+/// Names of the backends [`TTSProcessor`](crate::TTSProcessor) can create
+pub const BUILTIN_BACKENDS: &[&str] = &["edge", "google", "mock"];
 
 #[async_trait]
 pub trait TTSBackend {
+    /// Short identifier of the backend (e.g. "edge", "google")
+    fn name(&self) -> &'static str;
     async fn synthesize_text(&self, text: &str, voice: &str) -> Result<Vec<u8>, TTSError>;
+    /// Synthesize text and report word boundary and viseme timings where the backend supports them
+    async fn synthesize_with_boundaries(
+        &self,
+        text: &str,
+        voice: &str,
+    ) -> Result<SynthesizedAudio, TTSError> {
+        Ok(SynthesizedAudio {
+            audio: self.synthesize_text(text, voice).await?,
+            boundaries: Vec::new(),
+            visemes: Vec::new(),
+        })
+    }
+    /// Whether [`synthesize_with_boundaries`](Self::synthesize_with_boundaries)
+    /// reports viseme timings
+    fn supports_visemes(&self) -> bool {
+        false
+    }
     async fn list_voices(&self) -> Result<Vec<Voice>, TTSError>;
+    /// A copy of this backend that applies the prosody of `options`, for
+    /// voices with `TTSConfig.voice_overrides` and prosody sweeps
+    ///
+    /// `None` when the backend has no prosody settings.
+    fn with_prosody(
+        &self,
+        _options: &SynthesisOptions,
+    ) -> Option<Box<dyn TTSBackend + Send + Sync>> {
+        None
+    }
+    /// Longest text, in characters, accepted in a single request
+    ///
+    /// `None` when the backend has no known limit or splits long text itself.
+    fn max_text_chars(&self) -> Option<usize> {
+        None
+    }
     async fn save_audio(&self, audio_data: &[u8], filename: &str) -> Result<(), TTSError>;
+    /// Prepare for a fast first request, e.g. by starting tools or opening connections
+    async fn warm_up(&self) -> Result<(), TTSError> {
+        Ok(())
+    }
+    /// Check that the backend can currently synthesize
+    ///
+    /// The default fetches the voice list, which for remote backends probes
+    /// the service without synthesizing anything.
+    async fn health_check(&self) -> Result<(), TTSError> {
+        self.list_voices().await.map(|_| ())
+    }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 pub mod edge;
+#[cfg(any(feature = "wasm", not(target_arch = "wasm32")))]
+pub(crate) mod edge_voices;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod google;
+pub mod gtts_voices;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod mock;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod temp;
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Error for a synthesis tool that could not be started; a missing program
+/// is [`ErrorKind::NotInstalled`] so it is not retried
+pub(crate) fn spawn_failure(backend: &'static str, message: &str, error: io::Error) -> TTSError {
+    let kind = if error.kind() == io::ErrorKind::NotFound {
+        ErrorKind::NotInstalled
+    } else {
+        ErrorKind::Unavailable
+    };
+    TTSError::backend_error_with_source(backend, kind, message, error)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Outcome of a tool's `--version` probe: a missing program or Python module
+/// is [`ErrorKind::NotInstalled`], any other failure [`ErrorKind::Unavailable`]
+pub(crate) fn probe_result(
+    backend: &'static str,
+    message: &str,
+    output: io::Result<Output>,
+) -> Result<(), TTSError> {
+    match output {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let kind = match classify_failure(&stderr) {
+                ErrorKind::NotInstalled => ErrorKind::NotInstalled,
+                _ => ErrorKind::Unavailable,
+            };
+            Err(TTSError::backend_error(
+                backend,
+                kind,
+                format!("{}: {}", message, stderr.trim()),
+            ))
+        }
+        Err(e) => Err(spawn_failure(backend, message, e)),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Audio a synthesis tool wrote to stdout; empty output means it produced none
+pub(crate) fn stdout_audio(backend: &'static str, stdout: Vec<u8>) -> Result<Vec<u8>, TTSError> {
+    if stdout.is_empty() {
+        return Err(TTSError::backend_error(
+            backend,
+            ErrorKind::Synthesis,
+            "No audio was generated",
+        ));
+    }
+    Ok(stdout)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Classify the stderr of a failed synthesis tool run
+pub(crate) fn classify_failure(stderr: &str) -> ErrorKind {
+    let stderr = stderr.to_lowercase();
+    if stderr.contains("429")
+        || stderr.contains("too many requests")
+        || stderr.contains("rate limit")
+    {
+        ErrorKind::RateLimited
+    } else if stderr.contains("timed out") || stderr.contains("timeout") {
+        ErrorKind::Timeout
+    } else if stderr.contains("no module named") || stderr.contains("command not found") {
+        ErrorKind::NotInstalled
+    } else if stderr.contains("401") || stderr.contains("403") || stderr.contains("unauthorized") {
+        ErrorKind::Authentication
+    } else if stderr.contains("invalid voice") || stderr.contains("language not supported") {
+        ErrorKind::VoiceNotFound
+    } else if stderr.contains("connection")
+        || stderr.contains("name resolution")
+        || stderr.contains("503")
+    {
+        ErrorKind::Network
+    } else {
+        ErrorKind::Synthesis
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_failure() {
+        assert_eq!(
+            classify_failure("aiohttp.ClientResponseError: 429, message='Too Many Requests'"),
+            ErrorKind::RateLimited
+        );
+        assert_eq!(
+            classify_failure("/usr/bin/python: No module named edge_tts"),
+            ErrorKind::NotInstalled
+        );
+        let missing = spawn_failure("google", "gtts-cli", io::ErrorKind::NotFound.into());
+        assert_eq!(missing.kind(), ErrorKind::NotInstalled);
+        let refused = probe_result(
+            "edge",
+            "edge-tts",
+            Err(io::ErrorKind::PermissionDenied.into()),
+        );
+        assert_eq!(refused.unwrap_err().kind(), ErrorKind::Unavailable);
+        assert_eq!(classify_failure("something odd"), ErrorKind::Synthesis);
+    }
+
+    #[test]
+    fn test_stdout_audio() {
+        assert_eq!(stdout_audio("edge", b"ID3".to_vec()).unwrap(), b"ID3");
+        let err = stdout_audio("google", Vec::new()).unwrap_err();
+        assert_eq!(
+            (err.kind(), err.backend()),
+            (ErrorKind::Synthesis, Some("google"))
+        );
+    }
+}
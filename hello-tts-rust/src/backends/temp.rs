@@ -0,0 +1,133 @@
+//! Temporary files written by the subprocess backends.
+//!
+//! Audio is read from the tools' stdout, but edge-tts can only write subtitle
+//! cues (used for word boundaries) to a file. [`TempAudioFile`] deletes such
+//! a file when dropped, so early returns, errors and cancelled futures cannot
+//! leak it. Files left behind by crashed or killed processes, including the
+//! `tts_output_*`/`gtts_output_*` audio files of earlier releases, are removed
+//! by [`sweep_stale`], which [`TTSProcessor::new`](crate::TTSProcessor::new)
+//! runs once per process.
+
+use crate::models::{ErrorKind, TTSError};
+use std::path::{Path, PathBuf};
+use std::sync::Once;
+use std::time::{Duration, SystemTime};
+use tracing::{debug, info};
+
+/// Name prefixes of the temp files created by the backends
+pub const TEMP_PREFIXES: [&str; 3] = ["tts_output_", "gtts_output_", "tts_subtitles_"];
+
+/// A uniquely named file in the system temp directory, deleted on drop
+#[derive(Debug)]
+pub struct TempAudioFile {
+    path: PathBuf,
+}
+
+impl TempAudioFile {
+    /// Reserve a path like `<temp dir>/tts_output_<uuid>.mp3`; the file itself
+    /// is created by whoever writes to it
+    pub fn new(prefix: &str, ext: &str) -> Self {
+        let name = format!("{}{}.{}", prefix, uuid::Uuid::new_v4(), ext);
+        Self {
+            path: std::env::temp_dir().join(name),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Read the audio written by `backend`'s tool
+    pub async fn read(&self, backend: &'static str) -> Result<Vec<u8>, TTSError> {
+        match tokio::fs::read(&self.path).await {
+            Ok(audio) => Ok(audio),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(TTSError::backend_error(
+                backend,
+                ErrorKind::Synthesis,
+                "Audio file was not generated",
+            )),
+            Err(e) => Err(TTSError::backend_error_with_source(
+                backend,
+                ErrorKind::Io,
+                "Failed to read audio file",
+                e,
+            )),
+        }
+    }
+}
+
+impl Drop for TempAudioFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Delete backend temp files in `dir` that were last modified more than
+/// `max_age` ago; returns how many were removed
+pub fn sweep_stale(dir: &Path, max_age: Duration) -> std::io::Result<usize> {
+    let now = SystemTime::now();
+    let mut removed = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if !TEMP_PREFIXES.iter().any(|prefix| name.starts_with(prefix)) {
+            continue;
+        }
+        let Ok(modified) = entry.metadata().and_then(|m| m.modified()) else {
+            continue;
+        };
+        if now.duration_since(modified).unwrap_or_default() > max_age
+            && std::fs::remove_file(entry.path()).is_ok()
+        {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Run [`sweep_stale`] on the system temp directory the first time it is
+/// called in this process; a zero `max_age` disables the sweep
+pub(crate) fn sweep_once(max_age: Duration) {
+    static SWEEP: Once = Once::new();
+    if max_age.is_zero() {
+        return;
+    }
+    SWEEP.call_once(|| match sweep_stale(&std::env::temp_dir(), max_age) {
+        Ok(0) => {}
+        Ok(removed) => info!("Removed {} stale temp files", removed),
+        Err(e) => debug!("Temp file sweep failed: {}", e),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_temp_file_is_removed_on_drop() {
+        let file = TempAudioFile::new("tts_output_", "mp3");
+        let path = file.path().to_path_buf();
+        let err = file.read("edge").await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Synthesis);
+
+        std::fs::write(&path, b"audio").unwrap();
+        assert_eq!(file.read("edge").await.unwrap(), b"audio");
+        drop(file);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_sweep_only_removes_old_backend_files() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-sweep-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["tts_output_1.mp3", "gtts_output_2.mp3", "notes.txt"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        assert_eq!(sweep_stale(&dir, Duration::from_secs(3600)).unwrap(), 0);
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(sweep_stale(&dir, Duration::from_millis(10)).unwrap(), 2);
+        assert!(dir.join("notes.txt").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
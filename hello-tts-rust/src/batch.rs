@@ -0,0 +1,873 @@
+//! Batch synthesis of many texts from a manifest.
+//!
+//! A manifest is JSON (`{"items": [{"text": "...", "voice": "...", "output":
+//! "..."}]}` or a bare array), NDJSON with one item object per line, or plain
+//! text with one item per line. Every
+//! finished item is recorded with its hash in `job-state.json` in the output
+//! directory, so a resumed run only synthesizes what is missing or changed.
+//! Items with the same text and voice are synthesized once and hard-linked
+//! (or copied) to the other outputs.
+
+use crate::analysis;
+use crate::checksums::ChecksumManifest;
+use crate::convert;
+use crate::document::slug;
+use crate::events::TTSEvent;
+use crate::hooks;
+use crate::models::TTSError;
+use crate::naming::fnv1a;
+use crate::output_target::OutputTarget;
+use crate::tts_client::TTSProcessor;
+use crate::usage;
+use futures_util::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tokio::fs;
+use tracing::{info, instrument, warn};
+
+/// Name of the job-state file in a batch output directory
+pub const JOB_STATE_FILE: &str = "job-state.json";
+
+/// Texts to synthesize
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BatchManifest {
+    pub items: Vec<BatchItem>,
+}
+
+/// One entry of a manifest
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BatchItem {
+    pub text: String,
+    /// Voice for this item, overriding the batch voice
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voice: Option<String>,
+    /// Output file relative to the output directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    /// Caller's identifier, echoed in the report and used to name the output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+}
+
+/// Where and how a batch is synthesized
+#[derive(Debug, Clone, Default)]
+pub struct BatchOptions {
+    /// Directory for the audio files and job state; defaults to
+    /// `TTSConfig.output_directory`
+    pub output_dir: Option<PathBuf>,
+    /// Skip items recorded as finished in an existing job-state file
+    pub resume: bool,
+    /// Also upload every finished file to this directory or prefix; an item
+    /// whose upload fails is failed
+    pub upload: Option<OutputTarget>,
+}
+
+/// Finished items of a batch, as stored in `job-state.json`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct JobState {
+    pub completed: Vec<CompletedItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompletedItem {
+    /// Audio file, relative to the output directory
+    pub file: String,
+    /// Hash of voice and text, used to detect changed items on resume
+    pub hash: String,
+    pub bytes: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+/// State of one manifest item
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ItemStatus {
+    /// Synthesized by this run
+    Synthesized {
+        bytes: usize,
+    },
+    /// Finished by an earlier run
+    Completed {
+        bytes: usize,
+    },
+    /// Linked to the audio of an identical item
+    Deduplicated {
+        bytes: usize,
+        source: String,
+    },
+    Failed {
+        error: String,
+    },
+    Pending,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ItemReport {
+    pub index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub file: String,
+    pub voice: String,
+    pub hash: String,
+    /// Length of the text in characters
+    pub chars: usize,
+    /// Length of the audio, when it could be decoded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(flatten)]
+    pub status: ItemStatus,
+}
+
+/// Outcome of a batch, or its progress according to a checkpoint
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BatchReport {
+    pub output_dir: PathBuf,
+    pub synthesized: usize,
+    pub completed: usize,
+    /// Items that reused the audio of an identical item
+    pub deduplicated: usize,
+    /// Characters not sent to the backend thanks to deduplication
+    pub deduplicated_chars: usize,
+    pub failed: usize,
+    pub pending: usize,
+    /// Total length of the decodable audio of all finished items
+    pub audio_minutes: f64,
+    /// Characters synthesized by this run
+    pub characters: usize,
+    /// Characters of the pending items, an upper bound for what is left to synthesize
+    pub pending_characters: usize,
+    /// Estimated cost of `characters`, for paid backends
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_cost: Option<f64>,
+    /// Estimated cost of `pending_characters`, for paid backends
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pending_cost: Option<f64>,
+    pub items: Vec<ItemReport>,
+}
+
+impl BatchManifest {
+    /// Parse a JSON or NDJSON manifest, or plain text with one item per
+    /// non-empty line
+    pub fn parse(input: &str) -> Result<Self, TTSError> {
+        let trimmed = input.trim_start();
+        let manifest = if trimmed.starts_with('{') {
+            // `{"items": [...]}`, or else one item object per line
+            match serde_json::from_str::<serde_json::Value>(input) {
+                Ok(value) if value.get("items").is_some() => serde_json::from_value(value)?,
+                _ => Self::parse_ndjson(input)?,
+            }
+        } else if trimmed.starts_with('[') {
+            BatchManifest {
+                items: serde_json::from_str(input)?,
+            }
+        } else {
+            BatchManifest {
+                items: input
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(|line| BatchItem {
+                        text: line.to_string(),
+                        ..BatchItem::default()
+                    })
+                    .collect(),
+            }
+        };
+        if manifest.items.is_empty() {
+            return Err(TTSError::Config("Batch manifest has no items".to_string()));
+        }
+        Ok(manifest)
+    }
+
+    /// One JSON item per non-empty line
+    fn parse_ndjson(input: &str) -> Result<Self, TTSError> {
+        let items = input
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(number, line)| {
+                serde_json::from_str(line).map_err(|e| {
+                    TTSError::Config(format!("Invalid manifest line {}: {}", number + 1, e))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { items })
+    }
+}
+
+impl BatchItem {
+    /// Voice of this item, falling back to `default`
+    pub fn voice<'a>(&'a self, default: &'a str) -> &'a str {
+        self.voice.as_deref().unwrap_or(default)
+    }
+
+    /// Stable hash of this item's text spoken by its voice
+    pub fn hash(&self, default_voice: &str) -> String {
+        format!(
+            "{:016x}",
+            fnv1a(format!("{}\0{}", self.voice(default_voice), self.text).as_bytes())
+        )
+    }
+
+    /// `output`, a name made from `id`, or a name like
+    /// `0007_hello-world.mp3` for item `index`
+    pub fn file_name(&self, index: usize, ext: &str) -> String {
+        if let Some(output) = &self.output {
+            return output.clone();
+        }
+        if let Some(id) = &self.id {
+            return crate::compare::sample_file_name(id, ext);
+        }
+        let slug = slug(&self.text);
+        if slug.is_empty() {
+            format!("{:04}.{}", index + 1, ext)
+        } else {
+            format!("{:04}_{}.{}", index + 1, slug, ext)
+        }
+    }
+}
+
+impl JobState {
+    /// Read `job-state.json` from an output directory, if present
+    pub fn load(dir: &Path) -> Result<Option<Self>, TTSError> {
+        match std::fs::read_to_string(dir.join(JOB_STATE_FILE)) {
+            Ok(content) => Ok(Some(serde_json::from_str(&content)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<(), TTSError> {
+        std::fs::write(
+            dir.join(JOB_STATE_FILE),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        Ok(())
+    }
+
+    /// Finished entry for `file` with `hash` whose audio still exists in `dir`
+    pub fn find(&self, dir: &Path, file: &str, hash: &str) -> Option<&CompletedItem> {
+        self.completed
+            .iter()
+            .find(|item| item.file == file && item.hash == hash && dir.join(file).exists())
+    }
+
+    /// Record a finished item, replacing an older entry for the same file
+    pub fn record(&mut self, item: CompletedItem) {
+        self.completed.retain(|c| c.file != item.file);
+        self.completed.push(item);
+    }
+}
+
+/// Hard-link `src` to `dst`, copying when linking is not possible
+pub(crate) fn link_or_copy(src: &Path, dst: &Path) -> std::io::Result<()> {
+    if let Some(parent) = dst.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    match std::fs::remove_file(dst) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    if std::fs::hard_link(src, dst).is_err() {
+        std::fs::copy(src, dst)?;
+    }
+    Ok(())
+}
+
+impl BatchReport {
+    /// Progress of `manifest` according to the job state in `dir`, without
+    /// synthesizing anything
+    pub fn from_checkpoint(
+        dir: &Path,
+        manifest: &BatchManifest,
+        voice: &str,
+        ext: &str,
+    ) -> Result<Self, TTSError> {
+        let state = JobState::load(dir)?.unwrap_or_default();
+        Ok(Self::from_state(dir, manifest, voice, ext, &state))
+    }
+
+    pub(crate) fn from_state(
+        dir: &Path,
+        manifest: &BatchManifest,
+        voice: &str,
+        ext: &str,
+        state: &JobState,
+    ) -> Self {
+        let items = manifest
+            .items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let file = item.file_name(index, ext);
+                let hash = item.hash(voice);
+                let done = state.find(dir, &file, &hash);
+                let status = match done {
+                    Some(done) => ItemStatus::Completed { bytes: done.bytes },
+                    None => ItemStatus::Pending,
+                };
+                ItemReport {
+                    index,
+                    id: item.id.clone(),
+                    file,
+                    voice: item.voice(voice).to_string(),
+                    hash,
+                    chars: item.text.chars().count(),
+                    duration_ms: done.and_then(|done| done.duration_ms),
+                    status,
+                }
+            })
+            .collect();
+        let mut report = Self {
+            output_dir: dir.to_path_buf(),
+            synthesized: 0,
+            completed: 0,
+            deduplicated: 0,
+            deduplicated_chars: 0,
+            failed: 0,
+            pending: 0,
+            audio_minutes: 0.0,
+            characters: 0,
+            pending_characters: 0,
+            estimated_cost: None,
+            pending_cost: None,
+            items,
+        };
+        report.tally();
+        report
+    }
+
+    /// Recount the items by status
+    pub(crate) fn tally(&mut self) {
+        let count = |f: fn(&ItemStatus) -> bool| self.items.iter().filter(|i| f(&i.status)).count();
+        self.synthesized = count(|s| matches!(s, ItemStatus::Synthesized { .. }));
+        self.completed = count(|s| matches!(s, ItemStatus::Completed { .. }));
+        self.deduplicated = count(|s| matches!(s, ItemStatus::Deduplicated { .. }));
+        self.failed = count(|s| matches!(s, ItemStatus::Failed { .. }));
+        self.pending = count(|s| matches!(s, ItemStatus::Pending));
+        let total_ms: u64 = self.items.iter().filter_map(|i| i.duration_ms).sum();
+        self.audio_minutes = total_ms as f64 / 60_000.0;
+        let chars = |f: fn(&ItemStatus) -> bool| {
+            self.items
+                .iter()
+                .filter(|i| f(&i.status))
+                .map(|i| i.chars)
+                .sum()
+        };
+        self.characters = chars(|s| matches!(s, ItemStatus::Synthesized { .. }));
+        self.pending_characters = chars(|s| matches!(s, ItemStatus::Pending));
+    }
+
+    /// Price the synthesized and pending characters, see
+    /// [`usage::price_per_million_chars`]
+    pub fn estimate_cost(&mut self, price_per_million_chars: Option<f64>) {
+        let cost =
+            |chars: usize| price_per_million_chars.map(|price| chars as f64 * price / 1_000_000.0);
+        self.estimated_cost = cost(self.characters);
+        self.pending_cost = cost(self.pending_characters);
+    }
+
+    /// Every item has audio
+    pub fn is_complete(&self) -> bool {
+        self.failed == 0 && self.pending == 0
+    }
+}
+
+/// Synthesize every item of a manifest to its own file
+///
+/// Items are synthesized concurrently and a failed item does not stop the
+/// batch. Items with the same text and voice are synthesized once and
+/// linked to the first one's file. Each finished item is recorded in `job-state.json`; with
+/// `options.resume`, items recorded there with the same hash are skipped.
+#[instrument(
+    skip_all,
+    fields(backend = processor.backend_name(), voice = %voice, items = manifest.items.len())
+)]
+pub async fn synthesize(
+    processor: &TTSProcessor,
+    manifest: &BatchManifest,
+    voice: &str,
+    options: &BatchOptions,
+) -> Result<BatchReport, TTSError> {
+    let result = synthesize_items(processor, manifest, voice, options).await;
+    let batch_hooks = &processor.config().hooks.on_batch_complete;
+    if let (Ok(report), false) = (&result, batch_hooks.is_empty()) {
+        let vars = vec![
+            ("output_dir", report.output_dir.display().to_string()),
+            ("voice", voice.to_string()),
+            ("duration", format!("{:.3}", report.audio_minutes * 60.0)),
+            ("synthesized", report.synthesized.to_string()),
+            ("completed", report.completed.to_string()),
+            ("failed", report.failed.to_string()),
+            ("pending", report.pending.to_string()),
+        ];
+        hooks::run("on_batch_complete", batch_hooks, &vars).await;
+    }
+    let (summary, failed) = match &result {
+        Ok(report) => (
+            format!(
+                "{} synthesized, {} already done, {} failed, {} pending in {}",
+                report.synthesized + report.deduplicated,
+                report.completed,
+                report.failed,
+                report.pending,
+                report.output_dir.display()
+            ),
+            report.failed + report.pending > 0,
+        ),
+        Err(e) => (e.to_string(), true),
+    };
+    processor.emit(TTSEvent::JobFinished {
+        job: "Batch",
+        summary,
+        failed,
+    });
+    result
+}
+
+async fn synthesize_items(
+    processor: &TTSProcessor,
+    manifest: &BatchManifest,
+    voice: &str,
+    options: &BatchOptions,
+) -> Result<BatchReport, TTSError> {
+    let output_dir = options
+        .output_dir
+        .clone()
+        .unwrap_or_else(|| processor.output_manager().dir().to_path_buf());
+    fs::create_dir_all(&output_dir).await?;
+    let mut state = if options.resume {
+        JobState::load(&output_dir)?.unwrap_or_default()
+    } else {
+        JobState::default()
+    };
+    let ext = processor.config().file_extension();
+    let mut report = BatchReport::from_state(&output_dir, manifest, voice, ext, &state);
+    if report.completed > 0 {
+        info!("Skipping {} finished items", report.completed);
+    }
+
+    // Identical items are synthesized once; the others get a link to the
+    // first one's file, which may also come from an earlier run
+    let mut sources: HashMap<String, usize> = report
+        .items
+        .iter()
+        .filter(|item| matches!(item.status, ItemStatus::Completed { .. }))
+        .map(|item| (item.hash.clone(), item.index))
+        .collect();
+    let mut pending = Vec::new();
+    let mut duplicates = Vec::new();
+    for item in report
+        .items
+        .iter()
+        .filter(|item| item.status == ItemStatus::Pending)
+    {
+        match sources.get(&item.hash) {
+            Some(&source) => duplicates.push((item.index, source)),
+            None => {
+                sources.insert(item.hash.clone(), item.index);
+                pending.push(item.index);
+            }
+        }
+    }
+
+    let mut results = stream::iter(pending)
+        .map(|index| {
+            let item = &manifest.items[index];
+            async move {
+                let started = Instant::now();
+                let result = processor
+                    .synthesize_text(&item.text, item.voice(voice))
+                    .await;
+                (index, result, started.elapsed())
+            }
+        })
+        .buffered(processor.config().max_concurrent.max(1));
+    while let Some((index, result, _elapsed)) = results.next().await {
+        let entry = &mut report.items[index];
+        entry.status = match result {
+            Err(TTSError::Cancelled) => {
+                warn!("Batch cancelled, {} is left pending", entry.file);
+                break;
+            }
+            Err(e @ TTSError::QuotaExceeded(_)) => {
+                warn!(
+                    "Batch stopped, {} and later items are left pending: {}",
+                    entry.file, e
+                );
+                break;
+            }
+            Ok(audio) => {
+                let path = output_dir.join(&entry.file);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).await?;
+                }
+                fs::write(&path, &audio).await?;
+                if let Err(e) = upload(processor, options, &entry.file, &audio).await {
+                    warn!("Batch item {} failed: {}", index + 1, e);
+                    entry.status = ItemStatus::Failed {
+                        error: e.to_string(),
+                    };
+                    continue;
+                }
+                entry.duration_ms =
+                    analysis::measure(&processor.decodable(&audio)).map(|stats| stats.duration_ms);
+                #[cfg(feature = "history")]
+                processor.record_history(&crate::history::NewEntry {
+                    text: &manifest.items[index].text,
+                    voice: &entry.voice,
+                    backend: processor.backend_name(),
+                    duration_ms: entry.duration_ms,
+                    elapsed_ms: _elapsed.as_millis() as u64,
+                    output_path: Some(&path),
+                });
+                state.record(CompletedItem {
+                    file: entry.file.clone(),
+                    hash: entry.hash.clone(),
+                    bytes: audio.len(),
+                    duration_ms: entry.duration_ms,
+                });
+                state.save(&output_dir)?;
+                processor
+                    .synthesis_hooks(
+                        Some(&path),
+                        &entry.voice,
+                        processor.backend_name(),
+                        entry.duration_ms,
+                        audio.len(),
+                    )
+                    .await;
+                ItemStatus::Synthesized { bytes: audio.len() }
+            }
+            Err(e) => {
+                warn!("Batch item {} failed: {}", index + 1, e);
+                ItemStatus::Failed {
+                    error: e.to_string(),
+                }
+            }
+        };
+    }
+    drop(results);
+
+    for (index, source) in duplicates {
+        let source_file = report.items[source].file.clone();
+        let duration_ms = report.items[source].duration_ms;
+        let status = match report.items[source].status {
+            ItemStatus::Synthesized { bytes } | ItemStatus::Completed { bytes } => {
+                let entry = &mut report.items[index];
+                if entry.file != source_file {
+                    link_or_copy(
+                        &output_dir.join(&source_file),
+                        &output_dir.join(&entry.file),
+                    )?;
+                }
+                if options.upload.is_some() {
+                    let audio = fs::read(output_dir.join(&entry.file)).await?;
+                    if let Err(e) = upload(processor, options, &entry.file, &audio).await {
+                        entry.status = ItemStatus::Failed {
+                            error: e.to_string(),
+                        };
+                        continue;
+                    }
+                }
+                entry.duration_ms = duration_ms;
+                state.record(CompletedItem {
+                    file: entry.file.clone(),
+                    hash: entry.hash.clone(),
+                    bytes,
+                    duration_ms,
+                });
+                report.deduplicated_chars += manifest.items[index].text.chars().count();
+                ItemStatus::Deduplicated {
+                    bytes,
+                    source: source_file,
+                }
+            }
+            ItemStatus::Pending => ItemStatus::Pending,
+            _ => ItemStatus::Failed {
+                error: format!("Duplicate of failed item {}", source + 1),
+            },
+        };
+        report.items[index].status = status;
+    }
+    state.save(&output_dir)?;
+    report.tally();
+    report.estimate_cost(usage::price_per_million_chars(
+        processor.config(),
+        processor.backend_name(),
+    ));
+    ChecksumManifest::from_report(
+        &report,
+        manifest,
+        processor.backend_name(),
+        &processor.config().output_format,
+        processor.config().synthesis_options(),
+    )?
+    .save(&output_dir)?;
+    Ok(report)
+}
+
+/// Upload a finished batch file to `options.upload`, when set
+async fn upload(
+    processor: &TTSProcessor,
+    options: &BatchOptions,
+    file: &str,
+    audio: &[u8],
+) -> Result<(), TTSError> {
+    let Some(target) = &options.upload else {
+        return Ok(());
+    };
+    let content_type = convert::content_type(processor.config().file_extension());
+    target
+        .join(file)
+        .write(audio, content_type.unwrap_or("application/octet-stream"))
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::mock::MockBackend;
+    use crate::config::TTSConfig;
+    use crate::models::ErrorKind;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio_util::sync::CancellationToken;
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest = BatchManifest::parse("# UI strings\nHello\n\n  Goodbye  \n").unwrap();
+        assert_eq!(manifest.items.len(), 2);
+        assert_eq!(manifest.items[1].text, "Goodbye");
+        assert_eq!(manifest.items[1].file_name(1, "mp3"), "0002_goodbye.mp3");
+
+        let json = r#"[{"text": "Hi", "voice": "fr-FR-DeniseNeural", "output": "hi.mp3"}]"#;
+        let manifest = BatchManifest::parse(json).unwrap();
+        assert_eq!(manifest.items[0].voice("en"), "fr-FR-DeniseNeural");
+        assert_eq!(manifest.items[0].file_name(0, "mp3"), "hi.mp3");
+        assert_ne!(
+            manifest.items[0].hash("en"),
+            BatchItem::default().hash("en")
+        );
+
+        assert!(BatchManifest::parse(r#"{"items": []}"#).is_err());
+
+        let ndjson = "{\"text\": \"Hi\", \"id\": \"greeting\"}\n\n{\"text\": \"Bye\"}\n";
+        let manifest = BatchManifest::parse(ndjson).unwrap();
+        assert_eq!(manifest.items.len(), 2);
+        assert_eq!(
+            BatchManifest::parse("{\"text\": \"Hi\"}")
+                .unwrap()
+                .items
+                .len(),
+            1
+        );
+        assert_eq!(manifest.items[0].file_name(0, "mp3"), "greeting.mp3");
+        assert_eq!(manifest.items[1].file_name(1, "mp3"), "0002_bye.mp3");
+        let err = BatchManifest::parse("{\"text\": \"Hi\"}\n{oops}\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn test_report_from_checkpoint() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-batch-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let manifest = BatchManifest::parse("One\nTwo\n").unwrap();
+        let report = BatchReport::from_checkpoint(&dir, &manifest, "v", "mp3").unwrap();
+        assert_eq!(report.pending, 2);
+
+        let file = manifest.items[0].file_name(0, "mp3");
+        std::fs::write(dir.join(&file), b"abc").unwrap();
+        let mut state = JobState::default();
+        state.record(CompletedItem {
+            file: file.clone(),
+            hash: manifest.items[0].hash("v"),
+            bytes: 3,
+            duration_ms: Some(90_000),
+        });
+        state.save(&dir).unwrap();
+
+        let mut report = BatchReport::from_checkpoint(&dir, &manifest, "v", "mp3").unwrap();
+        assert_eq!(report.items[0].status, ItemStatus::Completed { bytes: 3 });
+        assert_eq!((report.completed, report.pending), (1, 1));
+        assert_eq!(report.audio_minutes, 1.5);
+        assert!(!report.is_complete());
+        report.estimate_cost(Some(500_000.0));
+        assert_eq!(
+            (report.pending_characters, report.pending_cost),
+            (3, Some(1.5))
+        );
+        assert_eq!(report.estimated_cost, Some(0.0));
+
+        // A different voice changes the hash, so the item is pending again
+        let report = BatchReport::from_checkpoint(&dir, &manifest, "w", "mp3").unwrap();
+        assert_eq!(report.pending, 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_batch_resumes_from_checkpoint() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-batch-{}", uuid::Uuid::new_v4()));
+        let manifest = BatchManifest::parse("One\nTwo\nThree\n").unwrap();
+        let options = BatchOptions {
+            output_dir: Some(dir.clone()),
+            resume: true,
+            upload: None,
+        };
+
+        let backend = MockBackend::new()
+            .then_audio(b"1".to_vec())
+            .then_error(ErrorKind::InvalidRequest, "rejected");
+        let processor = TTSProcessor::with_backend(TTSConfig::default(), Box::new(backend.clone()));
+        let report = synthesize(&processor, &manifest, "v", &options)
+            .await
+            .unwrap();
+        assert_eq!((report.synthesized, report.failed), (2, 1));
+        assert!(!report.is_complete());
+
+        let checkpoint = BatchReport::from_checkpoint(&dir, &manifest, "v", "mp3").unwrap();
+        assert_eq!((checkpoint.completed, checkpoint.pending), (2, 1));
+
+        let report = synthesize(&processor, &manifest, "v", &options)
+            .await
+            .unwrap();
+        assert_eq!((report.completed, report.synthesized), (2, 1));
+        assert!(report.is_complete());
+        assert_eq!(backend.calls().len(), 4);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_batch_runs_hooks() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-hooks-{}", uuid::Uuid::new_v4()));
+        let log = dir.join("hooks.log");
+        let mut config = TTSConfig::default();
+        config.hooks.on_synthesis_complete = vec![format!(
+            "echo item {{voice}} {{bytes}} >> '{}'",
+            log.display()
+        )];
+        config.hooks.on_batch_complete = vec![format!(
+            "echo batch {{synthesized}} {{failed}} >> '{}'",
+            log.display()
+        )];
+        let options = BatchOptions {
+            output_dir: Some(dir.clone()),
+            resume: false,
+            upload: None,
+        };
+        let backend = MockBackend::new()
+            .then_audio(b"12".to_vec())
+            .then_error(ErrorKind::InvalidRequest, "rejected");
+        let processor = TTSProcessor::with_backend(config, Box::new(backend));
+        let manifest = BatchManifest::parse("One\nTwo\n").unwrap();
+        synthesize(&processor, &manifest, "v", &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&log).unwrap(),
+            "item v 2\nbatch 1 1\n"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_synthesis_and_batch() {
+        let backend = MockBackend::new().with_latency(Duration::from_secs(30));
+        let token = CancellationToken::new();
+        let processor = TTSProcessor::with_backend(TTSConfig::default(), Box::new(backend))
+            .with_cancellation(token.clone());
+        let cancel = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            token.cancel();
+        });
+        let started = Instant::now();
+        let err = processor.synthesize_text("Hello", "v").await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Cancelled);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        cancel.await.unwrap();
+
+        // The token stays cancelled, so a batch leaves every item pending
+        let dir = std::env::temp_dir().join(format!("hello-tts-cancel-{}", uuid::Uuid::new_v4()));
+        let manifest = BatchManifest::parse("One\nTwo\nOne\n").unwrap();
+        let options = BatchOptions {
+            output_dir: Some(dir.clone()),
+            resume: false,
+            upload: None,
+        };
+        let report = synthesize(&processor, &manifest, "v", &options)
+            .await
+            .unwrap();
+        assert_eq!((report.pending, report.failed), (3, 0));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_quota_stops_batch() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-quota-{}", uuid::Uuid::new_v4()));
+        let config = TTSConfig {
+            max_characters_per_run: Some(7),
+            max_concurrent: 1,
+            ..TTSConfig::default()
+        };
+        let mut processor = TTSProcessor::with_backend(config, Box::new(MockBackend::new()));
+        let finished = Arc::new(Mutex::new(Vec::new()));
+        let sink = finished.clone();
+        processor.subscribe(move |e: &TTSEvent| {
+            if let TTSEvent::JobFinished { failed, .. } = e {
+                sink.lock().unwrap().push(*failed);
+            }
+        });
+        let manifest = BatchManifest::parse("One\nTwo\nThree\n").unwrap();
+        let options = BatchOptions {
+            output_dir: Some(dir.clone()),
+            resume: false,
+            upload: None,
+        };
+        let report = synthesize(&processor, &manifest, "v", &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            (report.synthesized, report.pending, report.failed),
+            (2, 1, 0)
+        );
+        assert_eq!(*finished.lock().unwrap(), [true]);
+
+        let err = processor.synthesize_text("Four", "v").await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::QuotaExceeded);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_batch_deduplicates_identical_items() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-dedup-{}", uuid::Uuid::new_v4()));
+        let manifest = BatchManifest::parse("OK\nCancel\nOK\nOK\n").unwrap();
+        let options = BatchOptions {
+            output_dir: Some(dir.clone()),
+            resume: false,
+            upload: Some(OutputTarget::Local(dir.join("uploaded"))),
+        };
+
+        let backend = MockBackend::new();
+        let processor = TTSProcessor::with_backend(TTSConfig::default(), Box::new(backend.clone()));
+        let report = synthesize(&processor, &manifest, "v", &options)
+            .await
+            .unwrap();
+        assert_eq!(backend.calls().len(), 2);
+        assert_eq!((report.synthesized, report.deduplicated), (2, 2));
+        assert_eq!(report.deduplicated_chars, 4);
+        assert_eq!(
+            report.items[3].status,
+            ItemStatus::Deduplicated {
+                bytes: 2,
+                source: "0001_ok.mp3".to_string()
+            }
+        );
+        assert_eq!(std::fs::read(dir.join("0004_ok.mp3")).unwrap(), b"OK");
+        assert_eq!(std::fs::read_dir(dir.join("uploaded")).unwrap().count(), 4);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,271 @@
+//! Latency benchmarks for choosing a backend and voice for interactive use.
+//!
+//! [`run`] synthesizes the same text several times per [`BenchTarget`] and
+//! records the time to the first audio chunk and to the complete audio.
+//! Backends return a chunk's audio in one piece, so time to first byte is the
+//! latency of the first chunk; it is below the total only for texts longer
+//! than `TTSConfig.max_chunk_chars`.
+
+use crate::events::TTSEvent;
+use crate::models::TTSError;
+use crate::tts_client::TTSProcessor;
+use serde::Serialize;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use std::time::Instant;
+use tracing::{info, instrument, warn};
+
+/// A backend and one of its voices
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BenchTarget {
+    pub backend: String,
+    pub voice: String,
+}
+
+impl std::str::FromStr for BenchTarget {
+    type Err = String;
+
+    /// Parse `backend:voice`, e.g. `edge:en-US-AriaNeural`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some((backend, voice)) if !backend.is_empty() && !voice.is_empty() => Ok(Self {
+                backend: backend.to_string(),
+                voice: voice.to_string(),
+            }),
+            _ => Err(format!("expected BACKEND:VOICE, got {:?}", s)),
+        }
+    }
+}
+
+/// How a benchmark is run
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    /// Measured runs per target
+    pub runs: usize,
+    /// Unmeasured runs per target first, to create backends and warm connections
+    pub warmup: usize,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self { runs: 5, warmup: 1 }
+    }
+}
+
+/// Percentiles of a set of latencies, in milliseconds
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct LatencyStats {
+    pub min_ms: f64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl LatencyStats {
+    /// Nearest-rank percentiles of `samples`, or `None` when there are none
+    pub fn from_samples(samples: &[Duration]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut ms: Vec<f64> = samples.iter().map(|d| d.as_secs_f64() * 1000.0).collect();
+        ms.sort_by(f64::total_cmp);
+        let percentile = |p: f64| ms[((p * ms.len() as f64).ceil() as usize).max(1) - 1];
+        Some(Self {
+            min_ms: ms[0],
+            mean_ms: ms.iter().sum::<f64>() / ms.len() as f64,
+            p50_ms: percentile(0.5),
+            p90_ms: percentile(0.9),
+            p99_ms: percentile(0.99),
+            max_ms: ms[ms.len() - 1],
+        })
+    }
+}
+
+/// Measurements for one target
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchResult {
+    #[serde(flatten)]
+    pub target: BenchTarget,
+    pub runs: usize,
+    pub failures: usize,
+    /// Time until the first chunk's audio arrived
+    pub first_byte: Option<LatencyStats>,
+    /// Time until all audio arrived
+    pub total: Option<LatencyStats>,
+    /// Error of the last failed run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// Result of [`run`]
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchReport {
+    pub text_chars: usize,
+    pub results: Vec<BenchResult>,
+}
+
+impl BenchReport {
+    /// Aligned table with one target per row, fastest median first
+    pub fn to_table(&self) -> String {
+        let mut results: Vec<&BenchResult> = self.results.iter().collect();
+        results.sort_by(|a, b| {
+            let median = |r: &BenchResult| r.total.map_or(f64::INFINITY, |s| s.p50_ms);
+            median(a).total_cmp(&median(b))
+        });
+        let names: Vec<String> = results
+            .iter()
+            .map(|r| format!("{}:{}", r.target.backend, r.target.voice))
+            .collect();
+        let width = names.iter().map(String::len).fold(6, usize::max);
+        let mut table = format!(
+            "{:width$}  {:>4}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}\n",
+            "Target", "Fail", "TTFB p50", "p50", "p90", "p99", "max"
+        );
+        for (name, result) in names.iter().zip(results) {
+            let ms = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{:.0} ms", v));
+            let total = result.total;
+            let _ = writeln!(
+                table,
+                "{:width$}  {:>4}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}",
+                name,
+                result.failures,
+                ms(result.first_byte.map(|s| s.p50_ms)),
+                ms(total.map(|s| s.p50_ms)),
+                ms(total.map(|s| s.p90_ms)),
+                ms(total.map(|s| s.p99_ms)),
+                ms(total.map(|s| s.max_ms)),
+            );
+        }
+        table
+    }
+}
+
+/// Measure the latency of synthesizing `text` with each target
+///
+/// Targets are run one after another so they do not slow each other
+/// down; waiting for the rate limiter counts as latency. Failed runs are
+/// counted and left out of the statistics; an unknown backend or a
+/// cancelled processor ends the benchmark early.
+#[instrument(skip_all, fields(targets = targets.len(), runs = options.runs))]
+pub async fn run(
+    processor: &TTSProcessor,
+    text: &str,
+    targets: &[BenchTarget],
+    options: &BenchOptions,
+) -> Result<BenchReport, TTSError> {
+    let format = processor.config().input_format;
+    let mut results = Vec::new();
+    for target in targets {
+        if target.backend != processor.backend_name() {
+            processor.backend_named(&target.backend)?;
+        }
+        info!("Benchmarking {}:{}", target.backend, target.voice);
+        let mut first_byte = Vec::new();
+        let mut total = Vec::new();
+        let mut failures = 0;
+        let mut last_error = None;
+        for run in 0..options.warmup + options.runs {
+            let first_chunk = Arc::new(Mutex::new(None));
+            let mut processor = processor.clone();
+            let received = first_chunk.clone();
+            processor.subscribe(move |event: &TTSEvent| {
+                if let TTSEvent::ChunkReceived { .. } = event {
+                    received.lock().unwrap().get_or_insert_with(Instant::now);
+                }
+            });
+            let started = Instant::now();
+            let result = processor
+                .synthesize_formatted_with_backend(text, &target.voice, format, &target.backend)
+                .await;
+            let elapsed = started.elapsed();
+            if run < options.warmup {
+                continue;
+            }
+            match result {
+                Ok(_) => {
+                    let first = first_chunk.lock().unwrap().unwrap_or(started + elapsed);
+                    first_byte.push(first - started);
+                    total.push(elapsed);
+                }
+                Err(TTSError::Cancelled) => return Err(TTSError::Cancelled),
+                Err(e) => {
+                    warn!("{}:{} failed: {}", target.backend, target.voice, e);
+                    failures += 1;
+                    last_error = Some(e.to_string());
+                }
+            }
+        }
+        results.push(BenchResult {
+            target: target.clone(),
+            runs: options.runs,
+            failures,
+            first_byte: LatencyStats::from_samples(&first_byte),
+            total: LatencyStats::from_samples(&total),
+            last_error,
+        });
+    }
+    Ok(BenchReport {
+        text_chars: text.chars().count(),
+        results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::mock::MockBackend;
+    use crate::config::TTSConfig;
+
+    #[test]
+    fn test_percentiles() {
+        let samples: Vec<Duration> = (1..=10).map(|i| Duration::from_millis(i * 10)).collect();
+        let stats = LatencyStats::from_samples(&samples).unwrap();
+        assert_eq!((stats.min_ms, stats.max_ms), (10.0, 100.0));
+        assert_eq!(
+            (stats.p50_ms, stats.p90_ms, stats.p99_ms),
+            (50.0, 90.0, 100.0)
+        );
+        assert_eq!(stats.mean_ms, 55.0);
+        assert!(LatencyStats::from_samples(&[]).is_none());
+    }
+
+    #[test]
+    fn test_parse_target() {
+        let target: BenchTarget = "edge:en-US-AriaNeural".parse().unwrap();
+        assert_eq!(
+            (target.backend.as_str(), target.voice.as_str()),
+            ("edge", "en-US-AriaNeural")
+        );
+        assert!("edge".parse::<BenchTarget>().is_err());
+        assert!(":en".parse::<BenchTarget>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bench_measures_first_chunk_and_total() {
+        let backend = MockBackend::new().with_latency(Duration::from_millis(20));
+        let config = TTSConfig {
+            max_chunk_chars: 25,
+            max_concurrent: 1,
+            ..TTSConfig::default()
+        };
+        let processor = TTSProcessor::with_backend(config, Box::new(backend.clone()));
+        let targets = ["mock:v".parse().unwrap()];
+        let options = BenchOptions { runs: 2, warmup: 1 };
+        let text = "First sentence here. Second sentence here.";
+        let report = run(&processor, text, &targets, &options).await.unwrap();
+        assert_eq!(backend.calls().len(), 6);
+
+        let result = &report.results[0];
+        assert_eq!((result.runs, result.failures), (2, 0));
+        let (first_byte, total) = (result.first_byte.unwrap(), result.total.unwrap());
+        assert!(first_byte.p50_ms >= 20.0 && first_byte.p50_ms < total.p50_ms);
+        assert!(report.to_table().contains("mock:v"));
+
+        let unknown = ["nope:v".parse().unwrap()];
+        let err = run(&processor, text, &unknown, &options).await.unwrap_err();
+        assert!(matches!(err, TTSError::Config(_)));
+    }
+}
@@ -0,0 +1,336 @@
+//! Voice catalog of all backends for other tools to consume.
+//!
+//! [`TTSProcessor::voice_catalog`](crate::TTSProcessor::voice_catalog) merges
+//! the voice lists of every built-in backend into one [`VoiceCatalog`] with a
+//! `backend` field per voice. Locales are normalized to `ll-RR` casing (gTTS
+//! reports `en-us`, Edge `en-US`) and genders to `Female`, `Male`, `Neutral`
+//! or `Unknown`, so the JSON and CSV exports are the same canonical data
+//! whichever client reads them.
+//!
+//! [`VoiceCatalog::default_for`] picks a voice for a locale and gender in a
+//! fixed order, so programs get the same voice from run to run.
+
+use crate::models::{TTSError, Voice};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Format of a catalog export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CatalogFormat {
+    Json,
+    Csv,
+}
+
+impl CatalogFormat {
+    /// Format of `path` by its extension
+    pub fn from_path(path: &Path) -> Result<Self, TTSError> {
+        match path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase())
+            .as_deref()
+        {
+            Some("json") => Ok(CatalogFormat::Json),
+            Some("csv") => Ok(CatalogFormat::Csv),
+            _ => Err(TTSError::Config(format!(
+                "Voice catalog must be a .json or .csv file: {}",
+                path.display()
+            ))),
+        }
+    }
+}
+
+/// Gender a program asks [`VoiceCatalog::default_for`] for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GenderPreference {
+    Female,
+    Male,
+    Neutral,
+    #[default]
+    Any,
+}
+
+impl GenderPreference {
+    /// Whether a voice with the normalized `gender` is acceptable
+    pub fn accepts(self, gender: &str) -> bool {
+        match self {
+            Self::Female => gender == "Female",
+            Self::Male => gender == "Male",
+            Self::Neutral => gender == "Neutral",
+            Self::Any => true,
+        }
+    }
+}
+
+impl std::str::FromStr for GenderPreference {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "female" | "f" => Ok(Self::Female),
+            "male" | "m" => Ok(Self::Male),
+            "neutral" => Ok(Self::Neutral),
+            "any" | "" => Ok(Self::Any),
+            other => Err(format!("Unknown gender preference: {}", other)),
+        }
+    }
+}
+
+/// A voice and the backend offering it
+#[derive(Debug, Clone, Serialize)]
+pub struct CatalogVoice {
+    pub backend: String,
+    #[serde(flatten)]
+    pub voice: Voice,
+}
+
+/// Voices of all backends, sorted by backend, locale and name
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VoiceCatalog {
+    pub voices: Vec<CatalogVoice>,
+}
+
+impl VoiceCatalog {
+    /// Add the voices of `backend`, normalized
+    pub fn add(&mut self, backend: &str, voices: Vec<Voice>) {
+        self.voices
+            .extend(voices.into_iter().map(|voice| CatalogVoice {
+                backend: backend.to_string(),
+                voice: normalize(voice),
+            }));
+        self.voices.sort_by(|a, b| {
+            let key = |v: &CatalogVoice| {
+                (
+                    v.backend.clone(),
+                    v.voice.locale.clone(),
+                    v.voice.name.clone(),
+                )
+            };
+            key(a).cmp(&key(b))
+        });
+    }
+
+    /// Default voice for `locale` (`de-DE`, or a language such as `de`) and
+    /// `gender`, or `None` if no voice matches
+    ///
+    /// Of the matching voices, the first by these rules is chosen:
+    ///
+    /// 1. voices not marked deprecated
+    /// 2. neural voices (`Neural` or `Wavenet` in the name)
+    /// 3. generally available voices (status `GA` or none) before previews
+    /// 4. backends in name order, then voice names in name order
+    pub fn default_for(&self, locale: &str, gender: GenderPreference) -> Option<&CatalogVoice> {
+        let locale = normalize_locale(locale);
+        self.voices
+            .iter()
+            .filter(|v| {
+                let matches_locale = if locale.contains('-') {
+                    v.voice.locale == locale
+                } else {
+                    v.voice.language_code() == locale
+                };
+                matches_locale && gender.accepts(&v.voice.gender)
+            })
+            .min_by_key(|v| {
+                let status = v.voice.status.as_deref().unwrap_or("GA");
+                let name = v.voice.name.to_lowercase();
+                (
+                    status.eq_ignore_ascii_case("deprecated"),
+                    !(name.contains("neural") || name.contains("wavenet")),
+                    !status.eq_ignore_ascii_case("ga"),
+                    &v.backend,
+                    &v.voice.name,
+                )
+            })
+    }
+
+    /// Keep the voices whose locale starts with `language`
+    pub fn retain_language(&mut self, language: &str) {
+        self.voices.retain(|v| v.voice.matches_language(language));
+    }
+
+    pub fn to_json(&self) -> Result<String, TTSError> {
+        Ok(serde_json::to_string_pretty(&self.voices)?)
+    }
+
+    /// One row per voice; list fields are joined with `;`
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from(
+            "backend,name,display_name,locale,language,gender,sample_rate_hz,styles,personalities,content_categories,status\n",
+        );
+        for entry in &self.voices {
+            let voice = &entry.voice;
+            let fields = [
+                entry.backend.clone(),
+                voice.name.clone(),
+                voice.display_name.clone(),
+                voice.locale.clone(),
+                voice.language_code().to_string(),
+                voice.gender.clone(),
+                voice
+                    .sample_rate_hz
+                    .map(|hz| hz.to_string())
+                    .unwrap_or_default(),
+                voice.styles.join(";"),
+                voice.personalities.join(";"),
+                voice.content_categories.join(";"),
+                voice.status.clone().unwrap_or_default(),
+            ];
+            let fields: Vec<String> = fields.iter().map(|f| quote(f)).collect();
+            out.push_str(&fields.join(","));
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn to_format(&self, format: CatalogFormat) -> Result<String, TTSError> {
+        match format {
+            CatalogFormat::Json => self.to_json(),
+            CatalogFormat::Csv => Ok(self.to_csv()),
+        }
+    }
+}
+
+/// `en_us` or `EN-us` as `en-US`; script subtags such as `Hans` keep title case
+pub fn normalize_locale(locale: &str) -> String {
+    locale
+        .split(['-', '_'])
+        .enumerate()
+        .map(|(index, part)| match (index, part.len()) {
+            (0, _) => part.to_lowercase(),
+            (_, 4) => {
+                let mut chars = part.chars();
+                chars.next().map_or(String::new(), |first| {
+                    first
+                        .to_uppercase()
+                        .chain(chars.flat_map(char::to_lowercase))
+                        .collect()
+                })
+            }
+            (_, 2) | (_, 3) => part.to_uppercase(),
+            _ => part.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn normalize_gender(gender: &str) -> String {
+    match gender.to_lowercase().as_str() {
+        "female" | "f" => "Female",
+        "male" | "m" => "Male",
+        "neutral" => "Neutral",
+        _ => "Unknown",
+    }
+    .to_string()
+}
+
+fn normalize(voice: Voice) -> Voice {
+    Voice {
+        locale: normalize_locale(&voice.locale),
+        gender: normalize_gender(&voice.gender),
+        ..voice
+    }
+}
+
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalization() {
+        assert_eq!(normalize_locale("en-us"), "en-US");
+        assert_eq!(normalize_locale("zh_hans_cn"), "zh-Hans-CN");
+        assert_eq!(normalize_locale("iw"), "iw");
+        assert_eq!(normalize_gender("FEMALE"), "Female");
+        assert_eq!(normalize_gender(""), "Unknown");
+        assert!(CatalogFormat::from_path(Path::new("voices.txt")).is_err());
+    }
+
+    #[test]
+    fn test_default_for_locale_and_gender() {
+        let voice = |name: &str, gender: &str, status: Option<&str>| Voice {
+            status: status.map(str::to_string),
+            ..Voice::new(
+                name.to_string(),
+                name.to_string(),
+                "de-DE".to_string(),
+                gender.to_string(),
+            )
+        };
+        let mut catalog = VoiceCatalog::default();
+        catalog.add(
+            "edge",
+            vec![
+                voice("de-DE-AmalaNeural", "Female", Some("Deprecated")),
+                voice("de-DE-KatjaNeural", "Female", Some("GA")),
+                voice("de-DE-ConradNeural", "Male", Some("GA")),
+                voice("de-DE-SeraphinaNeural", "Female", Some("Preview")),
+            ],
+        );
+        catalog.add("google", vec![voice("de", "Unknown", None)]);
+
+        let name = |locale, gender| {
+            catalog
+                .default_for(locale, gender)
+                .map(|v| v.voice.name.as_str())
+        };
+        assert_eq!(
+            name("de-de", GenderPreference::Female),
+            Some("de-DE-KatjaNeural")
+        );
+        assert_eq!(
+            name("de", GenderPreference::Male),
+            Some("de-DE-ConradNeural")
+        );
+        assert_eq!(
+            name("de-DE", GenderPreference::Any),
+            Some("de-DE-ConradNeural")
+        );
+        assert_eq!(name("de-AT", GenderPreference::Any), None);
+        assert_eq!(name("de-DE", GenderPreference::Neutral), None);
+        assert_eq!("F".parse(), Ok(GenderPreference::Female));
+    }
+
+    #[test]
+    fn test_csv_and_json() {
+        let mut catalog = VoiceCatalog::default();
+        let mut aria = Voice::new(
+            "en-US-AriaNeural".to_string(),
+            "Microsoft Aria, English".to_string(),
+            "en-US".to_string(),
+            "Female".to_string(),
+        );
+        aria.styles = vec!["cheerful".to_string(), "sad".to_string()];
+        catalog.add("edge", vec![aria]);
+        catalog.add(
+            "google",
+            vec![Voice::new(
+                "en".to_string(),
+                "English (US)".to_string(),
+                "en-us".to_string(),
+                "Unknown".to_string(),
+            )],
+        );
+
+        let csv = catalog.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[1],
+            "edge,en-US-AriaNeural,\"Microsoft Aria, English\",en-US,en,Female,,cheerful;sad,,,"
+        );
+        assert_eq!(lines[2], "google,en,English (US),en-US,en,Unknown,,,,,");
+
+        let json: serde_json::Value = serde_json::from_str(&catalog.to_json().unwrap()).unwrap();
+        assert_eq!(json[1]["backend"], "google");
+        assert_eq!(json[1]["locale"], "en-US");
+    }
+}
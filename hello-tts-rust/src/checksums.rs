@@ -0,0 +1,287 @@
+//! Checksum manifests for reproducible batch outputs.
+//!
+//! [`batch::synthesize`](crate::batch::synthesize) writes [`CHECKSUM_MANIFEST`]
+//! to the output directory: the backend, prosody and output format of the run,
+//! and for every finished file the SHA-256 of its audio and of its text, and
+//! its voice. Keeping that file and checking a later rendering of the same
+//! batch against it with [`ChecksumManifest::verify`] (the CLI's
+//! `verify-manifest`) reports every file whose audio, input or settings
+//! drifted.
+
+use crate::batch::{BatchManifest, BatchReport, ItemStatus};
+use crate::models::{SynthesisOptions, TTSError};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// Name of the checksum manifest written to a batch output directory
+pub const CHECKSUM_MANIFEST: &str = "checksums.json";
+
+/// Settings of a run and the checksums of the files it produced
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChecksumManifest {
+    pub backend: String,
+    pub output_format: String,
+    pub options: SynthesisOptions,
+    pub files: Vec<FileChecksum>,
+}
+
+/// Checksums of one audio file and its input
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileChecksum {
+    /// Audio file, relative to the output directory
+    pub file: String,
+    /// SHA-256 of the audio
+    pub sha256: String,
+    /// SHA-256 of the text
+    pub text_sha256: String,
+    pub voice: String,
+    pub bytes: u64,
+}
+
+/// A difference between a checksum manifest and a later rendering
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Drift {
+    /// The run used a different backend, format or prosody setting
+    Setting {
+        name: String,
+        expected: String,
+        actual: String,
+    },
+    /// The audio file no longer exists
+    Missing { file: String },
+    /// The file was rendered from a different text or voice
+    Input { file: String, field: String },
+    /// The audio differs
+    Audio {
+        file: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+impl std::fmt::Display for Drift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Drift::Setting {
+                name,
+                expected,
+                actual,
+            } => write!(f, "{} changed from {:?} to {:?}", name, expected, actual),
+            Drift::Missing { file } => write!(f, "{} is missing", file),
+            Drift::Input { file, field } => {
+                write!(f, "{} was rendered from another {}", file, field)
+            }
+            Drift::Audio { file, .. } => write!(f, "{} has different audio", file),
+        }
+    }
+}
+
+/// Lowercase hex SHA-256 of `data`
+pub fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+impl ChecksumManifest {
+    /// Checksums of the finished items of `report`, read from its output directory
+    pub fn from_report(
+        report: &BatchReport,
+        manifest: &BatchManifest,
+        backend: &str,
+        output_format: &str,
+        options: SynthesisOptions,
+    ) -> Result<Self, TTSError> {
+        let mut files = Vec::new();
+        for item in &report.items {
+            let finished = matches!(
+                item.status,
+                ItemStatus::Synthesized { .. }
+                    | ItemStatus::Completed { .. }
+                    | ItemStatus::Deduplicated { .. }
+            );
+            if !finished {
+                continue;
+            }
+            let audio = std::fs::read(report.output_dir.join(&item.file))?;
+            files.push(FileChecksum {
+                file: item.file.clone(),
+                sha256: sha256_hex(&audio),
+                text_sha256: sha256_hex(manifest.items[item.index].text.as_bytes()),
+                voice: item.voice.clone(),
+                bytes: audio.len() as u64,
+            });
+        }
+        Ok(Self {
+            backend: backend.to_string(),
+            output_format: output_format.to_string(),
+            options,
+            files,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, TTSError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| TTSError::Config(format!("Failed to read {}: {}", path.display(), e)))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Write the manifest as [`CHECKSUM_MANIFEST`] in `dir`
+    pub fn save(&self, dir: &Path) -> Result<(), TTSError> {
+        std::fs::write(
+            dir.join(CHECKSUM_MANIFEST),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        Ok(())
+    }
+
+    fn settings(&self) -> [String; 10] {
+        [
+            self.backend.clone(),
+            self.output_format.clone(),
+            self.options.rate.clone(),
+            self.options.pitch.clone(),
+            self.options.volume.clone(),
+            self.options.accent.clone().unwrap_or_default(),
+            self.options
+                .sample_rate
+                .map(|r| r.to_string())
+                .unwrap_or_default(),
+            self.options
+                .channels
+                .map(|c| c.to_string())
+                .unwrap_or_default(),
+            self.options
+                .loudness_preset
+                .map(|p| p.to_string())
+                .unwrap_or_default(),
+            self.options.style.clone().unwrap_or_default(),
+        ]
+    }
+
+    /// Compare with the audio files in `dir`
+    ///
+    /// When `dir` has its own checksum manifest from a later run, changed
+    /// settings, texts and voices are reported as well.
+    pub fn verify(&self, dir: &Path) -> Result<Vec<Drift>, TTSError> {
+        let current_path = dir.join(CHECKSUM_MANIFEST);
+        let current = match current_path.exists() {
+            true => Some(Self::load(&current_path)?),
+            false => None,
+        };
+        let mut drift = Vec::new();
+        if let Some(current) = &current {
+            let names = [
+                "backend",
+                "output_format",
+                "rate",
+                "pitch",
+                "volume",
+                "accent",
+                "sample_rate",
+                "channels",
+                "loudness_preset",
+                "style",
+            ];
+            for ((name, expected), actual) in names
+                .into_iter()
+                .zip(self.settings())
+                .zip(current.settings())
+            {
+                if expected != actual {
+                    drift.push(Drift::Setting {
+                        name: name.to_string(),
+                        expected,
+                        actual,
+                    });
+                }
+            }
+        }
+
+        for expected in &self.files {
+            let path = dir.join(&expected.file);
+            let Ok(audio) = std::fs::read(&path) else {
+                drift.push(Drift::Missing {
+                    file: expected.file.clone(),
+                });
+                continue;
+            };
+            let rendered = current
+                .as_ref()
+                .and_then(|c| c.files.iter().find(|f| f.file == expected.file));
+            if let Some(rendered) = rendered {
+                for (field, changed) in [
+                    ("text", rendered.text_sha256 != expected.text_sha256),
+                    ("voice", rendered.voice != expected.voice),
+                ] {
+                    if changed {
+                        drift.push(Drift::Input {
+                            file: expected.file.clone(),
+                            field: field.to_string(),
+                        });
+                    }
+                }
+            }
+            let actual = sha256_hex(&audio);
+            if actual != expected.sha256 {
+                drift.push(Drift::Audio {
+                    file: expected.file.clone(),
+                    expected: expected.sha256.clone(),
+                    actual,
+                });
+            }
+        }
+        Ok(drift)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_reports_drift() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-sums-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.mp3"), b"aaa").unwrap();
+        std::fs::write(dir.join("b.mp3"), b"bbb").unwrap();
+        let file = |name: &str, audio: &[u8]| FileChecksum {
+            file: name.to_string(),
+            sha256: sha256_hex(audio),
+            text_sha256: sha256_hex(b"hello"),
+            voice: "v".to_string(),
+            bytes: audio.len() as u64,
+        };
+        let baseline = ChecksumManifest {
+            backend: "mock".to_string(),
+            output_format: "mp3".to_string(),
+            options: SynthesisOptions::default(),
+            files: vec![
+                file("a.mp3", b"aaa"),
+                file("b.mp3", b"bbb"),
+                file("c.mp3", b"c"),
+            ],
+        };
+        assert_eq!(
+            baseline.verify(&dir).unwrap(),
+            [Drift::Missing {
+                file: "c.mp3".to_string()
+            }]
+        );
+
+        std::fs::write(dir.join("b.mp3"), b"BBB").unwrap();
+        let mut rerun = baseline.clone();
+        rerun.options.rate = "+10%".to_string();
+        rerun.files[0].voice = "w".to_string();
+        rerun.save(&dir).unwrap();
+        let drift = baseline.verify(&dir).unwrap();
+        assert_eq!(drift.len(), 4);
+        assert_eq!(drift[0].to_string(), "rate changed from \"\" to \"+10%\"");
+        assert_eq!(
+            drift[1].to_string(),
+            "a.mp3 was rendered from another voice"
+        );
+        assert_eq!(drift[2].to_string(), "b.mp3 has different audio");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
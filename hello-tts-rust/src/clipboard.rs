@@ -0,0 +1,132 @@
+//! System clipboard access for `speak --clipboard` and `--watch-clipboard`.
+//!
+//! Reading the clipboard requires the `clipboard` feature. [`ClipboardChanges`]
+//! decides when a watched clipboard holds new text worth speaking: the text
+//! must differ from the last one spoken and stay unchanged for the debounce
+//! interval, so rapid successive copies only speak the final one.
+
+use crate::models::TTSError;
+use std::time::{Duration, Instant};
+
+/// Handle to the system clipboard
+pub struct Clipboard {
+    #[cfg(feature = "clipboard")]
+    inner: arboard::Clipboard,
+}
+
+impl Clipboard {
+    #[cfg(feature = "clipboard")]
+    pub fn new() -> Result<Self, TTSError> {
+        let inner = arboard::Clipboard::new()
+            .map_err(|e| TTSError::Config(format!("Cannot access the clipboard: {}", e)))?;
+        Ok(Self { inner })
+    }
+
+    #[cfg(not(feature = "clipboard"))]
+    pub fn new() -> Result<Self, TTSError> {
+        Err(TTSError::Config(
+            "Cannot access the clipboard: built without the `clipboard` feature".to_string(),
+        ))
+    }
+
+    /// Current clipboard text, or `None` when it holds no text
+    #[cfg(feature = "clipboard")]
+    pub fn text(&mut self) -> Result<Option<String>, TTSError> {
+        match self.inner.get_text() {
+            Ok(text) if text.trim().is_empty() => Ok(None),
+            Ok(text) => Ok(Some(text)),
+            Err(arboard::Error::ContentNotAvailable) => Ok(None),
+            Err(e) => Err(TTSError::Config(format!(
+                "Cannot read the clipboard: {}",
+                e
+            ))),
+        }
+    }
+
+    #[cfg(not(feature = "clipboard"))]
+    pub fn text(&mut self) -> Result<Option<String>, TTSError> {
+        Ok(None)
+    }
+
+    /// Currently selected text where the platform has a primary selection
+    /// (X11 and Wayland), falling back to the clipboard text
+    pub fn selection(&mut self) -> Result<Option<String>, TTSError> {
+        #[cfg(all(feature = "clipboard", target_os = "linux"))]
+        {
+            use arboard::{GetExtLinux, LinuxClipboardKind};
+            if let Ok(text) = self
+                .inner
+                .get()
+                .clipboard(LinuxClipboardKind::Primary)
+                .text()
+            {
+                if !text.trim().is_empty() {
+                    return Ok(Some(text));
+                }
+            }
+        }
+        self.text()
+    }
+}
+
+/// Debounced detection of new clipboard text
+#[derive(Debug, Clone)]
+pub struct ClipboardChanges {
+    debounce: Duration,
+    last: Option<String>,
+    candidate: Option<(String, Instant)>,
+}
+
+impl ClipboardChanges {
+    /// Start watching; `current` is treated as already spoken
+    pub fn new(debounce: Duration, current: Option<String>) -> Self {
+        Self {
+            debounce,
+            last: current,
+            candidate: None,
+        }
+    }
+
+    /// Feed the clipboard text seen at `now`; returns text that should be spoken
+    pub fn observe(&mut self, text: Option<String>, now: Instant) -> Option<String> {
+        let Some(text) = text.filter(|t| Some(t) != self.last.as_ref()) else {
+            self.candidate = None;
+            return None;
+        };
+        match &self.candidate {
+            Some((candidate, since)) if *candidate == text => {
+                if now.duration_since(*since) < self.debounce {
+                    return None;
+                }
+                self.candidate = None;
+                self.last = Some(text.clone());
+                Some(text)
+            }
+            _ => {
+                self.candidate = Some((text, now));
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_are_debounced() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let text = |s: &str| Some(s.to_string());
+        let mut changes = ClipboardChanges::new(Duration::from_millis(300), text("old"));
+
+        assert_eq!(changes.observe(text("old"), at(0)), None);
+        assert_eq!(changes.observe(text("draft"), at(100)), None);
+        assert_eq!(changes.observe(text("final"), at(200)), None);
+        assert_eq!(changes.observe(text("final"), at(400)), None);
+        assert_eq!(changes.observe(text("final"), at(500)), text("final"));
+        assert_eq!(changes.observe(text("final"), at(900)), None);
+        assert_eq!(changes.observe(None, at(1000)), None);
+    }
+}
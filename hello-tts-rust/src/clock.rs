@@ -0,0 +1,80 @@
+//! Source of the current time for output filenames.
+//!
+//! Code that names output after the time asks a [`Clock`] instead of the
+//! system, so tests and reproducible builds can pin it with a [`FixedClock`]
+//! through [`TTSProcessor::with_clock`](crate::TTSProcessor::with_clock) or
+//! [`FileNamer::with_clock`](crate::naming::FileNamer::with_clock).
+//!
+//! `TTSConfig.deterministic` pins the clock to `SOURCE_DATE_EPOCH`, or the
+//! Unix epoch when that is unset, and makes generated names depend on the
+//! content only. The history and daily quotas keep the real time, so a
+//! pinned clock never stops the daily character count from rolling over.
+
+use crate::config::TTSConfig;
+use chrono::{DateTime, Local, TimeZone};
+use std::fmt;
+use std::sync::Arc;
+
+/// Environment variable with the seconds since the epoch a reproducible
+/// build is stamped with
+pub const SOURCE_DATE_EPOCH: &str = "SOURCE_DATE_EPOCH";
+
+/// Tells the current time
+pub trait Clock: fmt::Debug + Send + Sync {
+    fn now(&self) -> DateTime<Local>;
+}
+
+/// The system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+}
+
+/// A clock that always tells the same time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock(pub DateTime<Local>);
+
+impl FixedClock {
+    /// Fixed at `secs` seconds since the Unix epoch
+    pub fn at_timestamp(secs: i64) -> Self {
+        Self(Local.timestamp_opt(secs, 0).single().unwrap_or_default())
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Local> {
+        self.0
+    }
+}
+
+/// A fixed clock for deterministic configurations, the system clock otherwise
+pub fn from_config(config: &TTSConfig) -> Arc<dyn Clock> {
+    if !config.deterministic {
+        return Arc::new(SystemClock);
+    }
+    let epoch = std::env::var(SOURCE_DATE_EPOCH)
+        .ok()
+        .and_then(|secs| secs.trim().parse().ok())
+        .unwrap_or(0);
+    Arc::new(FixedClock::at_timestamp(epoch))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deterministic_config_fixes_the_clock() {
+        let config = TTSConfig {
+            deterministic: true,
+            ..TTSConfig::default()
+        };
+        let clock = from_config(&config);
+        assert_eq!(clock.now(), clock.now());
+        assert_eq!(FixedClock::at_timestamp(86_400).now().timestamp(), 86_400);
+    }
+}
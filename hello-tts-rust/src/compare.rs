@@ -0,0 +1,236 @@
+//! Voice comparisons: the same text spoken by several voices.
+//!
+//! [`render`] writes one sample per voice and an `index.html` page with an
+//! audio player for each sample, so voices can be listened to side by side.
+
+use crate::document;
+use crate::models::TTSError;
+use crate::models::Voice;
+use crate::tts_client::TTSProcessor;
+use futures_util::stream::{self, StreamExt};
+use serde::Serialize;
+use std::fmt::Write;
+use std::path::PathBuf;
+use tokio::fs;
+use tracing::{instrument, warn};
+
+/// Name of the HTML page in a comparison directory
+pub const INDEX_FILE: &str = "index.html";
+
+/// Where a comparison is written
+#[derive(Debug, Clone, Default)]
+pub struct CompareOptions {
+    /// Directory for the samples and index page; defaults to a directory
+    /// named after the text in `TTSConfig.output_directory`
+    pub output_dir: Option<PathBuf>,
+}
+
+/// One voice of a comparison
+#[derive(Debug, Clone, Serialize)]
+pub struct VoiceSample {
+    pub voice: Voice,
+    /// Sample file, relative to the comparison directory
+    pub file: String,
+    pub bytes: usize,
+    /// Why the sample could not be synthesized
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of [`render`]
+#[derive(Debug, Clone, Serialize)]
+pub struct Comparison {
+    pub text: String,
+    pub output_dir: PathBuf,
+    pub index: PathBuf,
+    pub samples: Vec<VoiceSample>,
+}
+
+/// Sample file name for `voice`, e.g. `en-US-AriaNeural.mp3`
+pub fn sample_file_name(voice: &str, ext: &str) -> String {
+    let name: String = voice
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}.{}", name, ext)
+}
+
+impl Comparison {
+    /// HTML page with a row and an audio player per voice
+    pub fn to_html(&self) -> String {
+        let mut out = String::from(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>Voice comparison</title>\n<style>\n\
+             body { font-family: sans-serif; margin: 2em; }\n\
+             td, th { padding: 0.4em 1em; text-align: left; }\n\
+             </style>\n</head>\n<body>\n<h1>Voice comparison</h1>\n",
+        );
+        let _ = writeln!(out, "<blockquote>{}</blockquote>", escape(&self.text));
+        out.push_str(
+            "<table>\n<tr><th>Voice</th><th>Name</th><th>Gender</th>\
+             <th>Locale</th><th>Sample</th></tr>\n",
+        );
+        for sample in &self.samples {
+            let voice = &sample.voice;
+            let player = match &sample.error {
+                Some(error) => format!("<em>{}</em>", escape(error)),
+                None => format!(
+                    "<audio controls preload=\"none\" src=\"{}\"></audio>",
+                    escape(&sample.file)
+                ),
+            };
+            let _ = writeln!(
+                out,
+                "<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape(&voice.name),
+                escape(&voice.display_name),
+                escape(&voice.gender),
+                escape(&voice.locale),
+                player
+            );
+        }
+        out.push_str("</table>\n</body>\n</html>\n");
+        out
+    }
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Speak `text` with every voice in `voices` and write an HTML page to compare them
+///
+/// A voice that fails is listed with its error instead of a sample.
+#[instrument(skip_all, fields(backend = processor.backend_name(), voices = voices.len()))]
+pub async fn render(
+    processor: &TTSProcessor,
+    text: &str,
+    voices: &[Voice],
+    options: &CompareOptions,
+) -> Result<Comparison, TTSError> {
+    let output_dir = options.output_dir.clone().unwrap_or_else(|| {
+        let name = format!("compare_{}", document::slug(text));
+        processor
+            .output_manager()
+            .dir()
+            .join(name.trim_end_matches('_'))
+    });
+    fs::create_dir_all(&output_dir).await?;
+
+    let results: Vec<Result<Vec<u8>, TTSError>> = stream::iter(voices)
+        .map(|voice| processor.synthesize_text(text, &voice.name))
+        .buffered(processor.config().max_concurrent.max(1))
+        .collect()
+        .await;
+    let mut samples = Vec::new();
+    for (voice, result) in voices.iter().zip(results) {
+        let file = sample_file_name(&voice.name, processor.config().file_extension());
+        let (bytes, error) = match result {
+            Ok(audio) => {
+                fs::write(output_dir.join(&file), &audio).await?;
+                (audio.len(), None)
+            }
+            Err(e) => {
+                warn!("Voice {} failed: {}", voice.name, e);
+                (0, Some(e.to_string()))
+            }
+        };
+        samples.push(VoiceSample {
+            voice: voice.clone(),
+            file,
+            bytes,
+            error,
+        });
+    }
+
+    let index = output_dir.join(INDEX_FILE);
+    let comparison = Comparison {
+        text: text.to_string(),
+        output_dir,
+        index,
+        samples,
+    };
+    fs::write(&comparison.index, comparison.to_html()).await?;
+    Ok(comparison)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::mock::MockBackend;
+    use crate::config::TTSConfig;
+    use crate::models::ErrorKind;
+
+    #[test]
+    fn test_index_page() {
+        let voice = Voice::new(
+            "en-US-AriaNeural".to_string(),
+            "Aria".to_string(),
+            "en-US".to_string(),
+            "Female".to_string(),
+        );
+        let comparison = Comparison {
+            text: "Fish & <chips>".to_string(),
+            output_dir: PathBuf::from("out"),
+            index: PathBuf::from("out/index.html"),
+            samples: vec![
+                VoiceSample {
+                    file: sample_file_name(&voice.name, "mp3"),
+                    voice: voice.clone(),
+                    bytes: 10,
+                    error: None,
+                },
+                VoiceSample {
+                    file: sample_file_name("x/y", "mp3"),
+                    voice,
+                    bytes: 0,
+                    error: Some("Voice not found".to_string()),
+                },
+            ],
+        };
+        let html = comparison.to_html();
+        assert!(html.contains("<blockquote>Fish &amp; &lt;chips&gt;</blockquote>"));
+        assert!(html.contains("src=\"en-US-AriaNeural.mp3\""));
+        assert!(html.contains("<em>Voice not found</em>"));
+        assert_eq!(comparison.samples[1].file, "x_y.mp3");
+    }
+
+    #[tokio::test]
+    async fn test_compare_voices() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-compare-{}", uuid::Uuid::new_v4()));
+        let backend = MockBackend::new()
+            .then_audio(b"a".to_vec())
+            .then_error(ErrorKind::VoiceNotFound, "no such voice");
+        let processor = TTSProcessor::with_backend(TTSConfig::default(), Box::new(backend));
+        let voices: Vec<Voice> = ["en-US-AriaNeural", "en-US-Missing"]
+            .iter()
+            .map(|name| {
+                Voice::new(
+                    name.to_string(),
+                    name.to_string(),
+                    "en-US".into(),
+                    "Female".into(),
+                )
+            })
+            .collect();
+        let options = CompareOptions {
+            output_dir: Some(dir.clone()),
+        };
+        let comparison = render(&processor, "Hi", &voices, &options).await.unwrap();
+        assert_eq!(comparison.samples[0].bytes, 1);
+        assert!(comparison.samples[1].error.is_some());
+        assert!(dir.join(&comparison.samples[0].file).exists());
+        let html = std::fs::read_to_string(dir.join("index.html")).unwrap();
+        assert!(html.contains(&comparison.samples[0].file));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
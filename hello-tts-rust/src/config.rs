@@ -1,5 +1,17 @@
-use crate::models::TTSError;
+pub mod secrets;
+
+use crate::analysis::LoudnessPreset;
+use crate::backends::gtts_voices;
+use crate::convert::{self, Encoding};
+use crate::hooks::HooksConfig;
+use crate::models::{SynthesisOptions, TTSError};
+use crate::text::{ssml, InputFormat, NormalizationConfig};
+use crate::voice_ref::VoiceRef;
+use crate::voice_remap::VoiceResolution;
+use log::{debug, info};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 /// Configuration for TTS
@@ -16,8 +28,292 @@ pub struct TTSConfig {
     pub rate: String,
     pub pitch: String,
     pub volume: String,
+    /// Regional accent, e.g. `en-GB` or the gTTS domain `co.uk`; voices that
+    /// name an accent themselves keep theirs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accent: Option<String>,
+    /// Speaking style such as `cheerful`, for voices that list it in
+    /// `Voice.styles`; applied by backends that take SSML
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<String>,
+    /// Sample rate in Hz to convert the audio to, e.g. 16000 or 8000
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<u32>,
+    /// Channel count to convert the audio to; 1 mixes down to mono
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channels: Option<u16>,
+    /// Bitrate of `opus` output: a preset (`telephony`, `voice`, `high`) or kbit/s
+    #[serde(default = "default_opus_bitrate")]
+    pub opus_bitrate: String,
+    /// Loudness to normalize `wav`, `pcm_s16le` and `opus` output to:
+    /// `podcast` (-16 LUFS), `broadcast` (-23 LUFS, EBU R 128) or `telephony`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loudness_preset: Option<LoudnessPreset>,
+    /// Milliseconds by which joined chunks and lines overlap in `wav`,
+    /// `pcm_s16le` and `opus` output
+    #[serde(default)]
+    pub crossfade_ms: u64,
     pub batch_size: usize,
     pub max_concurrent: usize,
+    #[serde(default)]
+    pub text_normalization: NormalizationConfig,
+    #[serde(default)]
+    pub input_format: InputFormat,
+    /// Turn headings and list items into pauses when stripping markup
+    #[serde(default = "default_markup_prosody")]
+    pub markup_prosody: bool,
+    /// Longest text sent to the backend in one request; longer input is chunked
+    #[serde(default = "default_max_chunk_chars")]
+    pub max_chunk_chars: usize,
+    /// Upper bound on backend requests per minute; unset or `0` means unlimited
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_per_minute: Option<u32>,
+    /// Requests that may be sent back to back before throttling applies
+    #[serde(default = "default_rate_limit_burst")]
+    pub rate_limit_burst: u32,
+    /// Ask backends for viseme timings and include them in synthesis results
+    #[serde(default)]
+    pub visemes: bool,
+    /// Ask backends for word timings and include them in synthesis results
+    #[serde(default)]
+    pub word_timings: bool,
+    /// Estimate word timings from the audio duration when a backend reports
+    /// none, see [`crate::alignment`]
+    #[serde(default)]
+    pub estimate_timings: bool,
+    /// Pass SSML tags in the input to the backend instead of stripping them,
+    /// see [`crate::text::ssml`]
+    #[serde(default)]
+    pub allow_raw_ssml: bool,
+    /// Voice per language code, e.g. `fr = "google:fr"`; a request for the
+    /// bare code uses it, see [`crate::voice_ref`]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub language_voices: HashMap<String, String>,
+    /// Default rate, pitch and volume per voice name, e.g. for a voice that
+    /// is always too fast; the global settings win where they are not neutral
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub voice_overrides: HashMap<String, VoiceOverride>,
+    /// Backends tried in order when the configured one is down, see
+    /// [`crate::health`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_backends: Vec<String>,
+    /// Consecutive failed requests after which a backend's circuit opens;
+    /// `0` disables the circuit breaker
+    #[serde(default = "default_circuit_failure_threshold")]
+    pub circuit_failure_threshold: u32,
+    /// How long an open circuit makes requests skip its backend
+    #[serde(default = "default_backend_cooldown_secs")]
+    pub backend_cooldown_secs: u64,
+    /// Template for generated output filenames, see [`crate::naming`]
+    #[serde(default = "default_filename_template")]
+    pub filename_template: String,
+    /// Derive time placeholders from the content hash so filenames are reproducible
+    #[serde(default)]
+    pub stable_filenames: bool,
+    /// Pin the clock and name outputs by content only, see [`crate::clock`]
+    #[serde(default)]
+    pub deterministic: bool,
+    /// Fail on retired or missing voices instead of substituting them; takes
+    /// precedence over `voice_resolution`, see [`crate::voice_remap`]
+    #[serde(default)]
+    pub strict_voices: bool,
+    /// Fail on voices the backend does not list, or substitute similar ones,
+    /// see [`crate::voice_remap`]
+    #[serde(default)]
+    pub voice_resolution: VoiceResolution,
+    /// Delete the oldest audio files once the output directory exceeds this size
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_max_size_mb: Option<u64>,
+    /// Delete audio files older than this many days from the output directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_max_age_days: Option<u64>,
+    /// Apply the output directory limits when the CLI or server starts
+    #[serde(default)]
+    pub cleanup_on_startup: bool,
+    /// Where audio is played: `auto`, `default`, `null`, `stdout`,
+    /// `file:<path>` or a device name, see [`open_sink`](crate::audio_player::open_sink)
+    #[serde(default = "default_audio_output")]
+    pub audio_output: String,
+    /// Backend temp files older than this many hours are deleted when the
+    /// first processor is created; `0` disables the sweep
+    #[serde(default = "default_temp_max_age_hours")]
+    pub temp_max_age_hours: u64,
+    /// Refuse synthesis once a processor and its clones have sent this many characters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_characters_per_run: Option<u64>,
+    /// Refuse synthesis once this many characters were sent today, counted
+    /// across runs in `quota_file`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_characters_per_day: Option<u64>,
+    /// Daily character counter; defaults to `.hello-tts-quota.json` in the
+    /// output directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota_file: Option<String>,
+    /// Log file of the CLI; each run writes its own timestamped copy beside
+    /// it, see [`crate::logging`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_file: Option<String>,
+    /// Run logs kept beside `log_file`; older ones are deleted, `0` keeps all
+    #[serde(default = "default_log_max_files")]
+    pub log_max_files: usize,
+    /// SQLite database of the `history` feature; defaults to
+    /// `.hello-tts-history.sqlite` in the output directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history_file: Option<String>,
+    /// SQLite database of the server's job queue; defaults to
+    /// `.hello-tts-jobs.sqlite` in the output directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jobs_file: Option<String>,
+    /// Where the CLI pushes the output of `batch` and `audiobook` runs, see
+    /// [`crate::sync`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sync_target: Option<String>,
+    /// Commands and URLs run when work completes, see [`crate::hooks`]
+    #[serde(default, skip_serializing_if = "HooksConfig::is_empty")]
+    pub hooks: HooksConfig,
+    /// Global hotkey of the `daemon` subcommand, e.g. `CmdOrCtrl+Alt+S`
+    #[serde(default = "default_hotkey")]
+    pub hotkey: String,
+    /// Show a desktop notification when a batch or audiobook job ends, see
+    /// [`crate::notifications`]
+    #[serde(default)]
+    pub notify_on_completion: bool,
+    /// HTTP or SOCKS5 proxy URL for backends that talk to remote services
+    ///
+    /// A `proxy` in the backend's own section takes precedence.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// Backend-specific settings keyed by backend name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub backends: HashMap<String, BackendConfig>,
+    /// Named variations of this configuration, selected with [`TTSConfig::profile`]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub profiles: HashMap<String, ConfigProfile>,
+}
+
+/// Settings and credentials for a single backend
+///
+/// Only the fields relevant to a backend need to be set, e.g. `api_key` and
+/// `region` for Azure, `model` for OpenAI, `proxy` for Edge or `model_path`
+/// for Piper. Unknown keys are kept in `extra`. `api_key` and `proxy` may be
+/// written as `env:VAR` or `keyring:service/user` references, see [`secrets`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackendConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_path: Option<PathBuf>,
+    /// USD per million characters, overriding [`crate::usage::DEFAULT_PRICES`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price_per_million_chars: Option<f64>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl BackendConfig {
+    /// API key with `env:`/`keyring:` references resolved
+    pub fn resolved_api_key(&self) -> Result<Option<String>, TTSError> {
+        secrets::resolve_opt(self.api_key.as_deref())
+    }
+
+    /// Proxy URL with `env:`/`keyring:` references resolved
+    pub fn resolved_proxy(&self) -> Result<Option<String>, TTSError> {
+        secrets::resolve_opt(self.proxy.as_deref())
+    }
+}
+
+/// Prosody adjustments for one voice, in the SSML values of `TTSConfig.rate`,
+/// `pitch` and `volume`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VoiceOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pitch: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume: Option<String>,
+}
+
+/// Settings a named profile may override; unset fields keep the base value
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfigProfile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_voice: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_directory: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pitch: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_play: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_format: Option<InputFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_normalization: Option<NormalizationConfig>,
+}
+
+fn default_markup_prosody() -> bool {
+    true
+}
+
+fn default_max_chunk_chars() -> usize {
+    3000
+}
+
+fn default_rate_limit_burst() -> u32 {
+    1
+}
+
+fn default_circuit_failure_threshold() -> u32 {
+    3
+}
+
+fn default_backend_cooldown_secs() -> u64 {
+    60
+}
+
+fn default_filename_template() -> String {
+    crate::naming::DEFAULT_TEMPLATE.to_string()
+}
+
+fn default_audio_output() -> String {
+    "auto".to_string()
+}
+
+fn default_temp_max_age_hours() -> u64 {
+    24
+}
+
+fn default_log_max_files() -> usize {
+    20
+}
+
+fn default_opus_bitrate() -> String {
+    "voice".to_string()
+}
+
+fn default_hotkey() -> String {
+    "CmdOrCtrl+Alt+S".to_string()
 }
 
 impl Default for TTSConfig {
@@ -34,8 +330,54 @@ impl Default for TTSConfig {
             rate: "0%".to_string(),
             pitch: "0%".to_string(),
             volume: "100%".to_string(),
+            accent: None,
+            style: None,
+            sample_rate: None,
+            channels: None,
+            opus_bitrate: default_opus_bitrate(),
+            loudness_preset: None,
+            crossfade_ms: 0,
             batch_size: 5,
             max_concurrent: 3,
+            text_normalization: NormalizationConfig::default(),
+            input_format: InputFormat::Plain,
+            markup_prosody: true,
+            max_chunk_chars: default_max_chunk_chars(),
+            requests_per_minute: None,
+            rate_limit_burst: default_rate_limit_burst(),
+            visemes: false,
+            word_timings: false,
+            estimate_timings: false,
+            allow_raw_ssml: false,
+            language_voices: HashMap::new(),
+            voice_overrides: HashMap::new(),
+            fallback_backends: Vec::new(),
+            circuit_failure_threshold: default_circuit_failure_threshold(),
+            backend_cooldown_secs: default_backend_cooldown_secs(),
+            filename_template: default_filename_template(),
+            stable_filenames: false,
+            deterministic: false,
+            strict_voices: false,
+            voice_resolution: VoiceResolution::default(),
+            output_max_size_mb: None,
+            output_max_age_days: None,
+            cleanup_on_startup: false,
+            audio_output: default_audio_output(),
+            temp_max_age_hours: default_temp_max_age_hours(),
+            max_characters_per_run: None,
+            max_characters_per_day: None,
+            quota_file: None,
+            log_file: None,
+            log_max_files: default_log_max_files(),
+            history_file: None,
+            jobs_file: None,
+            sync_target: None,
+            hooks: HooksConfig::default(),
+            hotkey: default_hotkey(),
+            notify_on_completion: false,
+            proxy: None,
+            backends: HashMap::new(),
+            profiles: HashMap::new(),
         }
     }
 }
@@ -56,25 +398,367 @@ impl TTSConfig {
                 "max_concurrent must be positive".to_string(),
             ));
         }
+        if self.max_chunk_chars == 0 {
+            return Err(TTSError::Config(
+                "max_chunk_chars must be positive".to_string(),
+            ));
+        }
+        crate::naming::validate_template(&self.filename_template)?;
+        if let Some(accent) = &self.accent {
+            if gtts_voices::accent_tld(accent).is_none() {
+                return Err(TTSError::Config(format!("Unknown accent: {}", accent)));
+            }
+        }
+        if let Some(rate) = self.sample_rate {
+            if !(8_000..=192_000).contains(&rate) {
+                return Err(TTSError::Config(format!(
+                    "sample_rate must be between 8000 and 192000 Hz, got {}",
+                    rate
+                )));
+            }
+        }
+        if self
+            .channels
+            .is_some_and(|channels| !(1..=2).contains(&channels))
+        {
+            return Err(TTSError::Config("channels must be 1 or 2".to_string()));
+        }
+        let encoding = Encoding::parse(&self.output_format);
+        let converts =
+            self.sample_rate.is_some() || self.channels.is_some() || self.loudness_preset.is_some();
+        if converts && encoding.is_none() {
+            return Err(TTSError::Config(format!(
+                "sample_rate, channels and loudness_preset need output_format wav, pcm_s16le \
+                 or opus, not {:?}",
+                self.output_format
+            )));
+        }
+        if self.crossfade_ms > 0 && encoding.is_none() {
+            return Err(TTSError::Config(format!(
+                "crossfade_ms needs output_format wav, pcm_s16le or opus, not {:?}",
+                self.output_format
+            )));
+        }
+        if encoding == Some(Encoding::Opus) && !cfg!(feature = "opus") {
+            return Err(TTSError::Config(
+                "output_format \"opus\" needs the `opus` feature".to_string(),
+            ));
+        }
+        if convert::opus_bitrate(&self.opus_bitrate).is_none() {
+            return Err(TTSError::Config(format!(
+                "Unknown opus_bitrate {:?}, use telephony, voice, high or 6 to 510 kbit/s",
+                self.opus_bitrate
+            )));
+        }
+        if encoding == Some(Encoding::S16le) && self.sample_rate.is_none() {
+            return Err(TTSError::Config(
+                "output_format \"pcm_s16le\" has no header, so sample_rate must be set".to_string(),
+            ));
+        }
+        for (language, voice) in &self.language_voices {
+            voice.parse::<VoiceRef>().map_err(|e| {
+                TTSError::Config(format!("Invalid voice for language {}: {}", language, e))
+            })?;
+        }
         Ok(())
     }
 
+    /// Extension of saved audio files: `pcm` for `pcm_s16le`, otherwise
+    /// `output_format` itself
+    pub fn file_extension(&self) -> &str {
+        match Encoding::parse(&self.output_format) {
+            Some(format) => format.extension(),
+            None => &self.output_format,
+        }
+    }
+
+    /// Prosody and accent settings handed to the backends, and the audio
+    /// format the processor converts to
+    pub fn synthesis_options(&self) -> SynthesisOptions {
+        SynthesisOptions {
+            rate: self.rate.clone(),
+            pitch: self.pitch.clone(),
+            volume: self.volume.clone(),
+            accent: self.accent.clone(),
+            style: self.style.clone(),
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+            loudness_preset: self.loudness_preset,
+            opus_bitrate: (Encoding::parse(&self.output_format) == Some(Encoding::Opus))
+                .then(|| self.opus_bitrate.clone()),
+        }
+    }
+
+    /// [`synthesis_options`](Self::synthesis_options) with the prosody of
+    /// `voice_overrides` for `voice` where the global setting is neutral, or
+    /// `None` when the voice has no override
+    pub fn voice_options(&self, voice: &str) -> Option<SynthesisOptions> {
+        let (_, adjust) = self
+            .voice_overrides
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(voice))?;
+        let mut options = self.synthesis_options();
+        for (value, adjusted) in [
+            (&mut options.rate, &adjust.rate),
+            (&mut options.pitch, &adjust.pitch),
+            (&mut options.volume, &adjust.volume),
+        ] {
+            if let Some(adjusted) = adjusted.as_ref().filter(|_| ssml::is_neutral(value)) {
+                value.clone_from(adjusted);
+            }
+        }
+        Some(options)
+    }
+
+    /// Voice configured in `language_voices` for `language`, ignoring case
+    pub fn voice_for_language(&self, language: &str) -> Option<&str> {
+        self.language_voices
+            .iter()
+            .find(|(code, _)| code.eq_ignore_ascii_case(language))
+            .map(|(_, voice)| voice.as_str())
+    }
+
     /// Load configuration from JSON file
     pub fn from_json_file(path: &str) -> Result<Self, TTSError> {
         let content = std::fs::read_to_string(path)
             .map_err(|e| TTSError::Config(format!("Failed to read config file {}: {}", path, e)))?;
 
-        let config: TTSConfig = serde_json::from_str(&content)
-            .map_err(|e| TTSError::Config(format!("Invalid JSON in config file: {}", e)))?;
+        let config = ConfigFormat::Json.parse_config(&content)?;
+        config.validate()?;
+        Ok(config)
+    }
 
+    /// Load configuration from a JSON, TOML or YAML file, chosen by extension
+    pub fn from_file(path: &str) -> Result<Self, TTSError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| TTSError::Config(format!("Failed to read config file {}: {}", path, e)))?;
+
+        let config = ConfigFormat::from_path(Path::new(path)).parse_config(&content)?;
         config.validate()?;
         Ok(config)
     }
 
+    /// Configuration with the named profile merged over the base settings
+    pub fn profile(&self, name: &str) -> Result<TTSConfig, TTSError> {
+        let profile = self.profiles.get(name).ok_or_else(|| {
+            let mut available: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            available.sort_unstable();
+            TTSError::Config(format!(
+                "Unknown profile '{}'. Available: {}",
+                name,
+                available.join(", ")
+            ))
+        })?;
+
+        let mut config = self.clone();
+        let p = profile.clone();
+        if let Some(v) = p.backend {
+            config.backend = v;
+        }
+        if let Some(v) = p.default_voice {
+            config.default_voice = v;
+        }
+        if let Some(v) = p.output_format {
+            config.output_format = v;
+        }
+        if let Some(v) = p.output_directory {
+            config.output_directory = v;
+        }
+        if let Some(v) = p.rate {
+            config.rate = v;
+        }
+        if let Some(v) = p.pitch {
+            config.pitch = v;
+        }
+        if let Some(v) = p.volume {
+            config.volume = v;
+        }
+        if p.accent.is_some() {
+            config.accent = p.accent;
+        }
+        if let Some(v) = p.auto_play {
+            config.auto_play = v;
+        }
+        if let Some(v) = p.input_format {
+            config.input_format = v;
+        }
+        if let Some(v) = p.text_normalization {
+            config.text_normalization = v;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Apply `HELLO_TTS_*` overrides from the process environment
+    pub fn apply_env_overrides(&mut self) -> Result<(), TTSError> {
+        self.apply_overrides(std::env::vars())
+    }
+
+    /// Apply `HELLO_TTS_*` overrides from the given variables
+    ///
+    /// Variables without the prefix or with an unknown name are ignored.
+    /// `HELLO_TTS_BACKEND` is applied first, whatever the order of `vars`, so
+    /// `HELLO_TTS_API_KEY` always goes to the backend it selects.
+    pub fn apply_overrides<I>(&mut self, vars: I) -> Result<(), TTSError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut vars: Vec<_> = vars.into_iter().collect();
+        vars.sort_by_key(|(key, _)| key.strip_prefix(ENV_PREFIX) != Some("BACKEND"));
+        for (key, value) in vars {
+            let Some(name) = key.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            match name {
+                "BACKEND" => self.backend = value,
+                "DEFAULT_VOICE" | "VOICE" => self.default_voice = value,
+                "OUTPUT_DIR" | "OUTPUT_DIRECTORY" => self.output_directory = value,
+                "OUTPUT_FORMAT" => self.output_format = value,
+                "RATE" => self.rate = value,
+                "PITCH" => self.pitch = value,
+                "VOLUME" => self.volume = value,
+                "ACCENT" => self.accent = Some(value),
+                "STYLE" => self.style = Some(value),
+                "SAMPLE_RATE" => self.sample_rate = Some(parse_env(&key, &value)?),
+                "CHANNELS" => self.channels = Some(parse_env(&key, &value)?),
+                "OPUS_BITRATE" => self.opus_bitrate = value,
+                "LOUDNESS_PRESET" => self.loudness_preset = Some(parse_env(&key, &value)?),
+                "CROSSFADE_MS" => self.crossfade_ms = parse_env(&key, &value)?,
+                "PROXY" => self.proxy = Some(value),
+                "API_KEY" => {
+                    let backend = self.backend.clone();
+                    self.backend_config_mut(&backend).api_key = Some(value)
+                }
+                "AUTO_PLAY" => self.auto_play = parse_env(&key, &value)?,
+                "CACHE_VOICES" => self.cache_voices = parse_env(&key, &value)?,
+                "MAX_RETRIES" => self.max_retries = parse_env(&key, &value)?,
+                "TIMEOUT_SECS" => self.timeout = Duration::from_secs(parse_env(&key, &value)?),
+                "BATCH_SIZE" => self.batch_size = parse_env(&key, &value)?,
+                "MAX_CONCURRENT" => self.max_concurrent = parse_env(&key, &value)?,
+                "REQUESTS_PER_MINUTE" => self.requests_per_minute = Some(parse_env(&key, &value)?),
+                "RATE_LIMIT_BURST" => self.rate_limit_burst = parse_env(&key, &value)?,
+                "FILENAME_TEMPLATE" => self.filename_template = value,
+                "STABLE_FILENAMES" => self.stable_filenames = parse_env(&key, &value)?,
+                "DETERMINISTIC" => self.deterministic = parse_env(&key, &value)?,
+                "VOICE_RESOLUTION" => self.voice_resolution = parse_env(&key, &value)?,
+                "STRICT_VOICES" => self.strict_voices = parse_env(&key, &value)?,
+                "OUTPUT_MAX_SIZE_MB" => self.output_max_size_mb = Some(parse_env(&key, &value)?),
+                "OUTPUT_MAX_AGE_DAYS" => self.output_max_age_days = Some(parse_env(&key, &value)?),
+                "CLEANUP_ON_STARTUP" => self.cleanup_on_startup = parse_env(&key, &value)?,
+                "AUDIO_OUTPUT" => self.audio_output = value,
+                "TEMP_MAX_AGE_HOURS" => self.temp_max_age_hours = parse_env(&key, &value)?,
+                "MAX_CHARACTERS_PER_RUN" => {
+                    self.max_characters_per_run = Some(parse_env(&key, &value)?)
+                }
+                "MAX_CHARACTERS_PER_DAY" => {
+                    self.max_characters_per_day = Some(parse_env(&key, &value)?)
+                }
+                "QUOTA_FILE" => self.quota_file = Some(value),
+                "HOTKEY" => self.hotkey = value,
+                "LOG_FILE" => self.log_file = Some(value),
+                "LOG_MAX_FILES" => self.log_max_files = parse_env(&key, &value)?,
+                "HISTORY_FILE" => self.history_file = Some(value),
+                "JOBS_FILE" => self.jobs_file = Some(value),
+                "SYNC_TARGET" => self.sync_target = Some(value),
+                "ON_SYNTHESIS_COMPLETE" => self.hooks.on_synthesis_complete = vec![value],
+                "ON_BATCH_COMPLETE" => self.hooks.on_batch_complete = vec![value],
+                "NOTIFY_ON_COMPLETION" => self.notify_on_completion = parse_env(&key, &value)?,
+                "ESTIMATE_TIMINGS" => self.estimate_timings = parse_env(&key, &value)?,
+                "ALLOW_RAW_SSML" => self.allow_raw_ssml = parse_env(&key, &value)?,
+                "FALLBACK_BACKENDS" => {
+                    self.fallback_backends = value
+                        .split(',')
+                        .map(|name| name.trim().to_string())
+                        .filter(|name| !name.is_empty())
+                        .collect()
+                }
+                "CIRCUIT_FAILURE_THRESHOLD" => {
+                    self.circuit_failure_threshold = parse_env(&key, &value)?
+                }
+                "BACKEND_COOLDOWN_SECS" => self.backend_cooldown_secs = parse_env(&key, &value)?,
+                _ => self.apply_backend_override(name, value)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle `HELLO_TTS_<BACKEND>_<SETTING>` variables such as `HELLO_TTS_AZURE_REGION`
+    fn apply_backend_override(&mut self, name: &str, value: String) -> Result<(), TTSError> {
+        const SETTINGS: [&str; 7] = [
+            "_PRICE_PER_MILLION_CHARS",
+            "_MODEL_PATH",
+            "_API_KEY",
+            "_REGION",
+            "_ENDPOINT",
+            "_MODEL",
+            "_PROXY",
+        ];
+        let Some((backend, setting)) = SETTINGS.iter().find_map(|setting| {
+            name.strip_suffix(setting)
+                .filter(|backend| !backend.is_empty())
+                .map(|backend| (backend.to_lowercase(), *setting))
+        }) else {
+            return Ok(());
+        };
+        let section = self.backend_config_mut(&backend);
+        match setting {
+            "_PRICE_PER_MILLION_CHARS" => {
+                let key = format!("{}{}", ENV_PREFIX, name);
+                section.price_per_million_chars = Some(parse_env(&key, &value)?)
+            }
+            "_MODEL_PATH" => section.model_path = Some(PathBuf::from(value)),
+            "_API_KEY" => section.api_key = Some(value),
+            "_REGION" => section.region = Some(value),
+            "_ENDPOINT" => section.endpoint = Some(value),
+            "_MODEL" => section.model = Some(value),
+            _ => section.proxy = Some(value),
+        }
+        Ok(())
+    }
+
+    /// Proxy URL for the named backend, with secret references resolved
+    pub fn proxy_for(&self, backend: &str) -> Result<Option<String>, TTSError> {
+        match self
+            .backend_config(backend)
+            .and_then(|b| b.proxy.as_deref())
+        {
+            Some(proxy) => secrets::resolve(proxy).map(Some),
+            None => secrets::resolve_opt(self.proxy.as_deref()),
+        }
+    }
+
+    /// How unknown voices are treated: strict when `strict_voices` is set,
+    /// `voice_resolution` otherwise
+    pub fn effective_voice_resolution(&self) -> VoiceResolution {
+        if self.strict_voices {
+            VoiceResolution::Strict
+        } else {
+            self.voice_resolution
+        }
+    }
+
+    /// Settings for the named backend, if configured
+    pub fn backend_config(&self, name: &str) -> Option<&BackendConfig> {
+        self.backends.get(name)
+    }
+
+    /// Settings for the named backend, created empty if missing
+    pub fn backend_config_mut(&mut self, name: &str) -> &mut BackendConfig {
+        self.backends.entry(name.to_string()).or_default()
+    }
+
     /// Save configuration to JSON file
     pub fn to_json_file(&self, path: &str) -> Result<(), TTSError> {
-        let content = serde_json::to_string_pretty(self)
-            .map_err(|e| TTSError::Config(format!("Failed to serialize config: {}", e)))?;
+        self.write_file(path, ConfigFormat::Json)
+    }
+
+    /// Save configuration to a JSON, TOML or YAML file, chosen by extension
+    pub fn to_file(&self, path: &str) -> Result<(), TTSError> {
+        self.write_file(path, ConfigFormat::from_path(Path::new(path)))
+    }
+
+    fn write_file(&self, path: &str, format: ConfigFormat) -> Result<(), TTSError> {
+        let content = format.serialize(self)?;
 
         if let Some(parent) = std::path::Path::new(path).parent() {
             std::fs::create_dir_all(parent).map_err(|e| {
@@ -90,6 +774,264 @@ impl TTSConfig {
     }
 }
 
+/// Serialization format of a config file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Detect the format from a file extension, defaulting to JSON
+    pub fn from_path(path: &Path) -> Self {
+        match path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase())
+            .as_deref()
+        {
+            Some("toml") => ConfigFormat::Toml,
+            Some("yaml") | Some("yml") => ConfigFormat::Yaml,
+            _ => ConfigFormat::Json,
+        }
+    }
+
+    /// Parse a value in this format
+    pub fn parse<T: serde::de::DeserializeOwned>(self, content: &str) -> Result<T, TTSError> {
+        match self {
+            ConfigFormat::Json => serde_json::from_str(content)
+                .map_err(|e| TTSError::Config(format!("Invalid JSON in config file: {}", e))),
+            ConfigFormat::Toml => toml::from_str(content)
+                .map_err(|e| TTSError::Config(format!("Invalid TOML in config file: {}", e))),
+            ConfigFormat::Yaml => serde_yaml::from_str(content)
+                .map_err(|e| TTSError::Config(format!("Invalid YAML in config file: {}", e))),
+        }
+    }
+
+    /// Parse a [`TTSConfig`], migrating settings from older file layouts
+    ///
+    /// A top-level `api_key` (the flat layout used before per-backend sections)
+    /// is moved into the section of the configured backend.
+    pub fn parse_config(self, content: &str) -> Result<TTSConfig, TTSError> {
+        let mut value: serde_json::Value = self.parse(content)?;
+        migrate_legacy_fields(&mut value);
+        serde_json::from_value(value)
+            .map_err(|e| TTSError::Config(format!("Invalid config file: {}", e)))
+    }
+
+    /// Serialize a value in this format
+    pub fn serialize<T: Serialize>(self, value: &T) -> Result<String, TTSError> {
+        let result = match self {
+            ConfigFormat::Json => serde_json::to_string_pretty(value).map_err(|e| e.to_string()),
+            ConfigFormat::Toml => toml::to_string_pretty(value).map_err(|e| e.to_string()),
+            ConfigFormat::Yaml => serde_yaml::to_string(value).map_err(|e| e.to_string()),
+        };
+        result.map_err(|e| TTSError::Config(format!("Failed to serialize config: {}", e)))
+    }
+}
+
+fn migrate_legacy_fields(value: &mut serde_json::Value) {
+    let Some(root) = value.as_object_mut() else {
+        return;
+    };
+    let Some(api_key) = root.remove("api_key") else {
+        return;
+    };
+    let backend = root
+        .get("backend")
+        .and_then(|b| b.as_str())
+        .unwrap_or("edge")
+        .to_string();
+    let backends = root
+        .entry("backends")
+        .or_insert_with(|| serde_json::json!({}));
+    if let Some(backends) = backends.as_object_mut() {
+        let section = backends
+            .entry(backend)
+            .or_insert_with(|| serde_json::json!({}));
+        if let Some(section) = section.as_object_mut() {
+            section.entry("api_key").or_insert(api_key);
+        }
+    }
+}
+
+/// Prefix of environment variables that override configuration values
+pub const ENV_PREFIX: &str = "HELLO_TTS_";
+
+/// Environment variable naming an explicit config file
+pub const ENV_CONFIG_PATH: &str = "HELLO_TTS_CONFIG";
+
+/// Environment variable selecting a profile from the config file
+pub const ENV_PROFILE: &str = "HELLO_TTS_PROFILE";
+
+fn parse_env<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, TTSError> {
+    value
+        .parse()
+        .map_err(|_| TTSError::Config(format!("Invalid value for {}: {}", key, value)))
+}
+
+/// Load configuration in layers: built-in defaults, then the config file
+/// (`HELLO_TTS_CONFIG` or the first of `ConfigManager::DEFAULT_CONFIG_PATHS`),
+/// then the profile named by `HELLO_TTS_PROFILE`, then `HELLO_TTS_*`
+/// environment variables
+pub fn load_layered() -> Result<TTSConfig, TTSError> {
+    load_layered_with_profile(None)
+}
+
+/// Like [`load_layered`], selecting `profile` instead of `HELLO_TTS_PROFILE`
+pub fn load_layered_with_profile(profile: Option<&str>) -> Result<TTSConfig, TTSError> {
+    let path = std::env::var(ENV_CONFIG_PATH).ok();
+    let mut config = ConfigManager::load_config(path.as_deref())?;
+    let profile = profile
+        .map(str::to_string)
+        .or_else(|| std::env::var(ENV_PROFILE).ok());
+    if let Some(name) = profile {
+        config = config.profile(&name)?;
+    }
+    config.apply_env_overrides()?;
+    config.validate()?;
+    Ok(config)
+}
+
+/// Locates, loads and creates configuration files, and provides presets
+pub struct ConfigManager;
+
+impl ConfigManager {
+    /// Locations searched, in order, when no explicit config path is given
+    ///
+    /// `$XDG_CONFIG_HOME/hello-tts/config.{json,toml,yaml,yml}` (or `~/.config`)
+    /// is searched after these.
+    pub const DEFAULT_CONFIG_PATHS: &'static [&'static str] = &[
+        "./tts_config.json",
+        "./tts_config.toml",
+        "./tts_config.yaml",
+        "./tts_config.yml",
+        "~/.tts/config.json",
+    ];
+
+    /// Names of the built-in presets
+    pub const PRESETS: &'static [&'static str] = &[
+        "default",
+        "fast",
+        "slow",
+        "high_quality",
+        "batch_processing",
+        "whisper",
+        "excited",
+    ];
+
+    /// Candidate config file locations with `~` expanded, followed by XDG locations
+    pub fn default_config_paths() -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = Self::DEFAULT_CONFIG_PATHS
+            .iter()
+            .map(|p| expand_home(p))
+            .collect();
+        if let Some(dir) = xdg_config_dir() {
+            for ext in ["json", "toml", "yaml", "yml"] {
+                paths.push(dir.join(format!("config.{}", ext)));
+            }
+        }
+        paths
+    }
+
+    /// First existing config file among the default locations
+    pub fn find_config_path() -> Option<PathBuf> {
+        Self::default_config_paths()
+            .into_iter()
+            .find(|p| p.is_file())
+    }
+
+    /// Load configuration from `path`, the first default location found, or defaults
+    pub fn load_config(path: Option<&str>) -> Result<TTSConfig, TTSError> {
+        if let Some(path) = path {
+            return TTSConfig::from_file(path);
+        }
+        match Self::find_config_path() {
+            Some(path) => TTSConfig::from_file(&path.to_string_lossy()),
+            None => Ok(TTSConfig::default()),
+        }
+    }
+
+    /// Get a preset configuration by name
+    pub fn preset(name: &str) -> Result<TTSConfig, TTSError> {
+        let defaults = TTSConfig::default();
+        let config = match name {
+            "default" => defaults,
+            "fast" => TTSConfig {
+                rate: "+20%".to_string(),
+                max_concurrent: 5,
+                batch_size: 10,
+                ..defaults
+            },
+            "slow" => TTSConfig {
+                rate: "-20%".to_string(),
+                max_concurrent: 2,
+                batch_size: 3,
+                ..defaults
+            },
+            "high_quality" => TTSConfig {
+                output_format: "wav".to_string(),
+                cache_voices: true,
+                max_retries: 5,
+                ..defaults
+            },
+            "batch_processing" => TTSConfig {
+                max_concurrent: 8,
+                batch_size: 20,
+                cache_voices: true,
+                rate_limit_burst: 3,
+                ..defaults
+            },
+            "whisper" => TTSConfig {
+                rate: "-10%".to_string(),
+                volume: "50%".to_string(),
+                pitch: "-5%".to_string(),
+                ..defaults
+            },
+            "excited" => TTSConfig {
+                rate: "+15%".to_string(),
+                pitch: "+10%".to_string(),
+                volume: "110%".to_string(),
+                ..defaults
+            },
+            _ => {
+                return Err(TTSError::Config(format!(
+                    "Unknown preset '{}'. Available: {}",
+                    name,
+                    Self::PRESETS.join(", ")
+                )))
+            }
+        };
+        Ok(config)
+    }
+
+    /// Write the given preset to `path`
+    pub fn create_default_config(path: &str, preset: &str) -> Result<(), TTSError> {
+        Self::preset(preset)?.to_file(path)?;
+        info!("Created default configuration file: {}", path);
+        Ok(())
+    }
+}
+
+/// `hello-tts` directory under `$XDG_CONFIG_HOME`, or `~/.config` when unset
+fn xdg_config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("hello-tts"))
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(rest))
+            .unwrap_or_else(|| PathBuf::from(path)),
+        None => PathBuf::from(path),
+    }
+}
+
 // Used to parse shared/tts_config.json
 #[derive(Debug, Deserialize)]
 pub struct LanguageConfig {
@@ -101,11 +1043,80 @@ pub struct LanguageConfig {
     pub google_voice: Option<String>,
 }
 
+impl LanguageConfig {
+    /// Voice of the language on `backend`; backends other than gTTS use Edge's
+    pub fn voice(&self, backend: &str) -> Option<&str> {
+        match backend {
+            "google" => self.google_voice.as_deref(),
+            _ => self.edge_voice.as_deref(),
+        }
+    }
+}
+
+/// Copy of `shared/tts_config.json` built into the crate
+const EMBEDDED_LANGUAGES: &str = include_str!("../../shared/tts_config.json");
+
+/// Locations searched for the shared language list, in order
+///
+/// The repository's `shared/tts_config.json` next to this crate, then
+/// `$XDG_CONFIG_HOME/hello-tts/tts_config.json` (or `~/.config`).
+pub fn shared_config_paths() -> Vec<PathBuf> {
+    let mut paths = vec![Path::new(env!("CARGO_MANIFEST_DIR")).join("../shared/tts_config.json")];
+    if let Some(dir) = xdg_config_dir() {
+        paths.push(dir.join("tts_config.json"));
+    }
+    paths
+}
+
+/// The shared language list from the first of [`shared_config_paths`] found,
+/// or the copy built into the crate, whatever the working directory
+pub fn resolve_shared_config() -> Result<TTSConfigFile, TTSError> {
+    match shared_config_paths().into_iter().find(|p| p.is_file()) {
+        Some(path) => {
+            debug!("Loading languages from {}", path.display());
+            TTSConfigFile::load(&path)
+        }
+        None => Ok(TTSConfigFile::embedded()),
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct TTSConfigFile {
     pub languages: Vec<LanguageConfig>,
 }
 
+impl TTSConfigFile {
+    /// Read a language list such as `shared/tts_config.json`
+    pub fn load(path: &Path) -> Result<Self, TTSError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| TTSError::Config(format!("Failed to read {}: {}", path.display(), e)))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// The language list built into the crate
+    pub fn embedded() -> Self {
+        serde_json::from_str(EMBEDDED_LANGUAGES).expect("embedded tts_config.json is valid")
+    }
+
+    /// Language by its code (`fr-fr`) or language part (`fr`)
+    pub fn language(&self, code: &str) -> Option<&LanguageConfig> {
+        let code = code.to_lowercase();
+        self.languages
+            .iter()
+            .find(|l| l.code.eq_ignore_ascii_case(&code))
+            .or_else(|| {
+                self.languages
+                    .iter()
+                    .find(|l| l.code.split('-').next() == Some(code.as_str()))
+            })
+    }
+
+    /// Voice for a language code (`fr-fr`) or its language part (`fr`) on `backend`
+    pub fn voice_for(&self, code: &str, backend: &str) -> Option<&str> {
+        self.language(code)?.voice(backend)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -118,4 +1129,308 @@ mod tests {
         assert_eq!(config.max_retries, 3);
         assert_eq!(config.backend, "edge");
     }
+
+    #[test]
+    fn test_env_overrides() {
+        let mut config = TTSConfig::default();
+        let vars = [
+            ("HELLO_TTS_BACKEND", "google"),
+            ("HELLO_TTS_DEFAULT_VOICE", "fr"),
+            ("HELLO_TTS_MAX_RETRIES", "7"),
+            ("HELLO_TTS_API_KEY", "secret"),
+            ("PATH", "/usr/bin"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+        config.apply_overrides(vars).unwrap();
+
+        assert_eq!(config.backend, "google");
+        assert_eq!(config.default_voice, "fr");
+        assert_eq!(config.max_retries, 7);
+        assert_eq!(
+            config.backend_config("google").unwrap().api_key.as_deref(),
+            Some("secret")
+        );
+
+        for vars in [
+            [
+                ("HELLO_TTS_BACKEND", "azure"),
+                ("HELLO_TTS_API_KEY", "azure-key"),
+            ],
+            [
+                ("HELLO_TTS_API_KEY", "azure-key"),
+                ("HELLO_TTS_BACKEND", "azure"),
+            ],
+        ] {
+            let mut config = TTSConfig::default();
+            config
+                .apply_overrides(vars.map(|(k, v)| (k.to_string(), v.to_string())))
+                .unwrap();
+            assert_eq!(
+                config.backend_config("azure").unwrap().api_key.as_deref(),
+                Some("azure-key")
+            );
+            assert!(config.backend_config("edge").is_none());
+        }
+
+        let vars = [(
+            "HELLO_TTS_AZURE_REGION".to_string(),
+            "westeurope".to_string(),
+        )];
+        config.apply_overrides(vars).unwrap();
+        assert_eq!(
+            config.backend_config("azure").unwrap().region.as_deref(),
+            Some("westeurope")
+        );
+
+        let vars = [(
+            "HELLO_TTS_AZURE_PRICE_PER_MILLION_CHARS".to_string(),
+            "12.5".to_string(),
+        )];
+        config.apply_overrides(vars).unwrap();
+        assert_eq!(
+            config
+                .backend_config("azure")
+                .unwrap()
+                .price_per_million_chars,
+            Some(12.5)
+        );
+
+        let vars = [("HELLO_TTS_ACCENT".to_string(), "en-GB".to_string())];
+        config.apply_overrides(vars).unwrap();
+        assert_eq!(config.synthesis_options().accent.as_deref(), Some("en-GB"));
+        assert!(config.validate().is_ok());
+        config.accent = Some("en-XX".to_string());
+        assert!(config.validate().is_err());
+        config.accent = None;
+
+        let vars = [("HELLO_TTS_SAMPLE_RATE".to_string(), "16000".to_string())];
+        config.apply_overrides(vars).unwrap();
+        assert_eq!(config.synthesis_options().sample_rate, Some(16_000));
+        assert!(config.validate().is_err());
+        config.output_format = "wav".to_string();
+        assert!(config.validate().is_ok());
+        config.channels = Some(6);
+        assert!(config.validate().is_err());
+        config.channels = None;
+        config.sample_rate = None;
+        config.output_format = "pcm_s16le".to_string();
+        assert!(config.validate().is_err());
+        config.sample_rate = Some(16_000);
+        assert!(config.validate().is_ok());
+        assert_eq!(config.file_extension(), "pcm");
+        config.opus_bitrate = "loud".to_string();
+        assert!(config.validate().is_err());
+        config.opus_bitrate = default_opus_bitrate();
+        config.sample_rate = None;
+        config.output_format = "mp3".to_string();
+        let vars = [(
+            "HELLO_TTS_LOUDNESS_PRESET".to_string(),
+            "Podcast".to_string(),
+        )];
+        config.apply_overrides(vars).unwrap();
+        assert_eq!(
+            config.synthesis_options().loudness_preset,
+            Some(LoudnessPreset::Podcast)
+        );
+        assert!(config.validate().is_err());
+        config.loudness_preset = None;
+        let vars = [("HELLO_TTS_CROSSFADE_MS".to_string(), "30".to_string())];
+        config.apply_overrides(vars).unwrap();
+        assert!(config.validate().is_err());
+        config.crossfade_ms = 0;
+
+        let vars = [(
+            "HELLO_TTS_FALLBACK_BACKENDS".to_string(),
+            "google, mock,".to_string(),
+        )];
+        config.apply_overrides(vars).unwrap();
+        assert_eq!(config.fallback_backends, ["google", "mock"]);
+
+        let bad = [("HELLO_TTS_MAX_RETRIES".to_string(), "many".to_string())];
+        assert!(config.apply_overrides(bad).is_err());
+
+        let vars = [(
+            "HELLO_TTS_FILENAME_TEMPLATE".to_string(),
+            "{language}.mp3".to_string(),
+        )];
+        config.apply_overrides(vars).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_strict_voices_takes_precedence() {
+        let mut value = serde_json::to_value(TTSConfig::default()).unwrap();
+        let root = value.as_object_mut().unwrap();
+        root.insert("strict_voices".to_string(), serde_json::json!(true));
+        root.insert("voice_resolution".to_string(), serde_json::json!("lenient"));
+        let config = ConfigFormat::Json.parse_config(&value.to_string()).unwrap();
+        assert_eq!(config.effective_voice_resolution(), VoiceResolution::Strict);
+
+        let mut config = TTSConfig::default();
+        assert_eq!(
+            config.effective_voice_resolution(),
+            VoiceResolution::Lenient
+        );
+        let vars = [
+            ("HELLO_TTS_STRICT_VOICES", "true"),
+            ("HELLO_TTS_VOICE_RESOLUTION", "lenient"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+        config.apply_overrides(vars).unwrap();
+        assert_eq!(config.effective_voice_resolution(), VoiceResolution::Strict);
+    }
+
+    #[test]
+    fn test_config_formats_round_trip() {
+        let config = ConfigManager::preset("whisper").unwrap();
+        for format in [ConfigFormat::Json, ConfigFormat::Toml, ConfigFormat::Yaml] {
+            let content = format.serialize(&config).unwrap();
+            let parsed: TTSConfig = format.parse(&content).unwrap();
+            assert_eq!(parsed.volume, "50%");
+            assert_eq!(parsed.timeout, config.timeout);
+        }
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a.yml")),
+            ConfigFormat::Yaml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a.TOML")),
+            ConfigFormat::Toml
+        );
+        assert_eq!(ConfigFormat::from_path(Path::new("a")), ConfigFormat::Json);
+    }
+
+    #[test]
+    fn test_profiles() {
+        let toml = r#"
+            default_voice = "en-US-AriaNeural"
+            backend = "edge"
+            output_format = "mp3"
+            output_directory = "./output"
+            auto_play = true
+            cache_voices = true
+            max_retries = 3
+            timeout = { secs = 30, nanos = 0 }
+            rate = "0%"
+            pitch = "0%"
+            volume = "100%"
+            batch_size = 5
+            max_concurrent = 3
+
+            [profiles.podcast]
+            default_voice = "en-US-GuyNeural"
+            rate = "-5%"
+
+            [profiles.quick-draft]
+            backend = "google"
+        "#;
+        let config = ConfigFormat::Toml.parse_config(toml).unwrap();
+
+        let podcast = config.profile("podcast").unwrap();
+        assert_eq!(podcast.default_voice, "en-US-GuyNeural");
+        assert_eq!(podcast.rate, "-5%");
+        assert_eq!(podcast.backend, "edge");
+        assert_eq!(config.profile("quick-draft").unwrap().backend, "google");
+        assert!(config.profile("missing").is_err());
+    }
+
+    #[test]
+    fn test_voice_overrides() {
+        let toml = r#"
+            default_voice = "en-US-AriaNeural"
+            backend = "edge"
+            output_format = "mp3"
+            output_directory = "./output"
+            auto_play = true
+            cache_voices = true
+            max_retries = 3
+            timeout = { secs = 30, nanos = 0 }
+            rate = "0%"
+            pitch = "+5%"
+            volume = "100%"
+            batch_size = 5
+            max_concurrent = 3
+
+            [voice_overrides.en-GB-RyanNeural]
+            rate = "-10%"
+            pitch = "-2%"
+            volume = "+20%"
+        "#;
+        let config = ConfigFormat::Toml.parse_config(toml).unwrap();
+        let options = config.voice_options("en-gb-ryanneural").unwrap();
+        assert_eq!(options.rate, "-10%");
+        assert_eq!(options.volume, "+20%");
+        // The explicit global pitch wins over the voice's default
+        assert_eq!(options.pitch, "+5%");
+        assert!(config.voice_options("en-US-AriaNeural").is_none());
+    }
+
+    #[test]
+    fn test_backend_sections_and_migration() {
+        let legacy = r#"{
+            "default_voice": "en-US-AriaNeural", "backend": "azure", "output_format": "mp3",
+            "output_directory": "./output", "auto_play": true, "cache_voices": true,
+            "max_retries": 3, "timeout": {"secs": 30, "nanos": 0}, "rate": "0%",
+            "pitch": "0%", "volume": "100%", "batch_size": 5, "max_concurrent": 3,
+            "api_key": "legacy-key",
+            "backends": {
+                "azure": {"region": "eastus"},
+                "piper": {"model_path": "/models/en.onnx", "speaker": 3}
+            }
+        }"#;
+        let config = ConfigFormat::Json.parse_config(legacy).unwrap();
+
+        let azure = config.backend_config("azure").unwrap();
+        assert_eq!(azure.api_key.as_deref(), Some("legacy-key"));
+        assert_eq!(azure.region.as_deref(), Some("eastus"));
+        let piper = config.backend_config("piper").unwrap();
+        assert_eq!(piper.model_path, Some(PathBuf::from("/models/en.onnx")));
+        assert_eq!(piper.extra.get("speaker"), Some(&serde_json::json!(3)));
+
+        let saved = ConfigFormat::Json.serialize(&config).unwrap();
+        let reloaded = ConfigFormat::Json.parse_config(&saved).unwrap();
+        assert_eq!(reloaded.backend_config("azure"), Some(azure));
+    }
+
+    #[test]
+    fn test_proxy_precedence() {
+        let mut config = TTSConfig {
+            proxy: Some("http://global:8080".to_string()),
+            ..TTSConfig::default()
+        };
+        assert_eq!(
+            config.proxy_for("edge").unwrap().as_deref(),
+            Some("http://global:8080")
+        );
+        config.backend_config_mut("edge").proxy = Some("socks5://edge:1080".to_string());
+        assert_eq!(
+            config.proxy_for("edge").unwrap().as_deref(),
+            Some("socks5://edge:1080")
+        );
+    }
+
+    #[test]
+    fn test_presets() {
+        for name in ConfigManager::PRESETS {
+            let config = ConfigManager::preset(name).unwrap();
+            assert!(config.validate().is_ok());
+        }
+        assert_eq!(ConfigManager::preset("fast").unwrap().rate, "+20%");
+        assert!(ConfigManager::preset("nope").is_err());
+    }
+
+    #[test]
+    fn test_embedded_languages() {
+        let languages = TTSConfigFile::embedded();
+        let french = languages.language("FR").unwrap();
+        assert_eq!(french.code, "fr-fr");
+        assert!(french.text.is_some());
+        assert_eq!(languages.voice_for("fr", "google"), Some("fr"));
+        assert_eq!(
+            languages.voice_for("fr-fr", "mock"),
+            Some("fr-FR-DeniseNeural")
+        );
+        let resolved = resolve_shared_config().unwrap();
+        assert_eq!(resolved.languages.len(), languages.languages.len());
+    }
 }
@@ -0,0 +1,95 @@
+//! Resolution of credential references in config values.
+//!
+//! Credential fields may hold a literal value or a reference:
+//!
+//! - `env:VAR_NAME` reads the environment variable `VAR_NAME`
+//! - `keyring:service/user` reads the OS credential store (requires the
+//!   `keyring` feature)
+
+use crate::models::TTSError;
+
+const ENV_SCHEME: &str = "env:";
+const KEYRING_SCHEME: &str = "keyring:";
+
+/// Whether `value` is a reference rather than a literal secret
+pub fn is_reference(value: &str) -> bool {
+    value.starts_with(ENV_SCHEME) || value.starts_with(KEYRING_SCHEME)
+}
+
+/// Resolve a credential value, following `env:` and `keyring:` references
+pub fn resolve(value: &str) -> Result<String, TTSError> {
+    if let Some(var) = value.strip_prefix(ENV_SCHEME) {
+        return std::env::var(var).map_err(|_| {
+            TTSError::Config(format!(
+                "Environment variable {} referenced by secret is not set",
+                var
+            ))
+        });
+    }
+    if let Some(entry) = value.strip_prefix(KEYRING_SCHEME) {
+        let (service, user) = entry.split_once('/').ok_or_else(|| {
+            TTSError::Config(format!(
+                "Invalid keyring reference '{}', expected keyring:service/user",
+                value
+            ))
+        })?;
+        return read_keyring(service, user);
+    }
+    Ok(value.to_string())
+}
+
+/// Resolve an optional credential value
+pub fn resolve_opt(value: Option<&str>) -> Result<Option<String>, TTSError> {
+    value.map(resolve).transpose()
+}
+
+/// Mask a secret for display, leaving references readable
+pub fn redact(value: &str) -> String {
+    if is_reference(value) {
+        value.to_string()
+    } else {
+        "********".to_string()
+    }
+}
+
+#[cfg(feature = "keyring")]
+fn read_keyring(service: &str, user: &str) -> Result<String, TTSError> {
+    keyring::Entry::new(service, user)
+        .and_then(|entry| entry.get_password())
+        .map_err(|e| {
+            TTSError::Config(format!(
+                "Failed to read keyring entry {}/{}: {}",
+                service, user, e
+            ))
+        })
+}
+
+#[cfg(not(feature = "keyring"))]
+fn read_keyring(service: &str, user: &str) -> Result<String, TTSError> {
+    Err(TTSError::Config(format!(
+        "Cannot read keyring entry {}/{}: built without the `keyring` feature",
+        service, user
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_literal_and_env() {
+        assert_eq!(resolve("plain-key").unwrap(), "plain-key");
+
+        std::env::set_var("HELLO_TTS_TEST_SECRET", "from-env");
+        assert_eq!(resolve("env:HELLO_TTS_TEST_SECRET").unwrap(), "from-env");
+        assert!(resolve("env:HELLO_TTS_TEST_SECRET_MISSING").is_err());
+        assert_eq!(resolve_opt(None).unwrap(), None);
+    }
+
+    #[test]
+    fn test_invalid_keyring_reference_and_redaction() {
+        assert!(resolve("keyring:no-user").is_err());
+        assert_eq!(redact("env:AZURE_KEY"), "env:AZURE_KEY");
+        assert_eq!(redact("abc123"), "********");
+    }
+}
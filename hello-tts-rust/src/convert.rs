@@ -0,0 +1,367 @@
+//! Sample-rate and channel conversion of synthesized audio.
+//!
+//! Backends return compressed audio at their own rate, such as 24 kHz MP3,
+//! while telephony, speech recognition and embedded players often need one
+//! fixed PCM format. With `TTSConfig.output_format` set to `wav`,
+//! `pcm_s16le` or `opus` (see [`Encoding`]), the processor decodes the audio,
+//! downmixes it to `SynthesisOptions.channels`, resamples it to `sample_rate`
+//! with a windowed-sinc filter (which also removes what lies above the new
+//! Nyquist frequency, so 48 kHz to 8 kHz does not alias) and returns 16-bit
+//! samples in a WAV container, without any header for DACs and embedded
+//! players, or encoded as Ogg Opus with the `opus` feature.
+
+use crate::analysis;
+use crate::audio_player::AudioError;
+use crate::models::SynthesisOptions;
+use std::f64::consts::PI;
+
+/// Zero crossings of the sinc on each side of a resampled sample
+const SINC_ZEROS: f64 = 16.0;
+
+/// Interleaved 16-bit audio
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pcm {
+    pub samples: Vec<i16>,
+    pub channels: u16,
+    pub sample_rate: u32,
+}
+
+/// Opus bitrate presets for speech, in bits per second
+pub const OPUS_PRESETS: &[(&str, i32)] =
+    &[("telephony", 12_000), ("voice", 24_000), ("high", 48_000)];
+
+/// Bitrate of an Opus preset, or of a number of kbit/s such as `32`
+pub fn opus_bitrate(preset: &str) -> Option<i32> {
+    let preset = preset.trim();
+    if let Some((_, bitrate)) = OPUS_PRESETS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(preset))
+    {
+        return Some(*bitrate);
+    }
+    let kbps: i32 = preset
+        .trim_end_matches("kbps")
+        .trim_end_matches('k')
+        .parse()
+        .ok()?;
+    (6..=510).contains(&kbps).then_some(kbps * 1000)
+}
+
+/// Output formats the processor encodes itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// 16-bit PCM in a WAV container
+    Wav,
+    /// Headerless little-endian 16-bit samples
+    S16le,
+    /// Ogg Opus, which needs the `opus` feature
+    Opus,
+}
+
+impl Encoding {
+    /// The format `TTSConfig.output_format` names, if it is one of these
+    pub fn parse(output_format: &str) -> Option<Self> {
+        match output_format.to_ascii_lowercase().as_str() {
+            "wav" => Some(Self::Wav),
+            "pcm_s16le" => Some(Self::S16le),
+            "opus" => Some(Self::Opus),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Wav => "wav",
+            Self::S16le => "pcm",
+            Self::Opus => "opus",
+        }
+    }
+
+    /// MIME type of audio in this encoding
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Wav => "audio/wav",
+            Self::S16le => "audio/L16",
+            Self::Opus => "audio/ogg; codecs=opus",
+        }
+    }
+}
+
+/// MIME type of audio files with extension `extension`
+pub fn content_type(extension: &str) -> Option<&'static str> {
+    if extension == "mp3" {
+        return Some("audio/mpeg");
+    }
+    [Encoding::Wav, Encoding::S16le, Encoding::Opus]
+        .into_iter()
+        .find(|encoding| encoding.extension() == extension)
+        .map(Encoding::content_type)
+}
+
+/// Decode `audio` and encode it as `format` with the rate and channels of `options`
+///
+/// Raw samples carry no header, so `S16le` is mono unless `options.channels`
+/// says otherwise.
+pub fn convert(
+    audio: &[u8],
+    options: &SynthesisOptions,
+    format: Encoding,
+) -> Result<Vec<u8>, AudioError> {
+    encode(Pcm::decode(audio)?, options, format)
+}
+
+/// Encode `pcm` as `format` with the rate and channels of `options`
+pub fn encode(
+    mut pcm: Pcm,
+    options: &SynthesisOptions,
+    format: Encoding,
+) -> Result<Vec<u8>, AudioError> {
+    let channels = match format {
+        Encoding::Wav => options.channels,
+        Encoding::S16le => Some(options.channels.unwrap_or(1)),
+        Encoding::Opus => Some(options.channels.unwrap_or(1).min(2)),
+    };
+    if let Some(channels) = channels {
+        pcm = pcm.with_channels(channels);
+    }
+    if let Some(sample_rate) = options.sample_rate {
+        pcm = pcm.resample(sample_rate);
+    }
+    if let Some(preset) = options.loudness_preset {
+        pcm = analysis::normalize_loudness(pcm, preset);
+    }
+    match format {
+        Encoding::Wav => Ok(pcm.to_wav()),
+        Encoding::S16le => Ok(pcm.to_le_bytes()),
+        Encoding::Opus => encode_opus(pcm, options),
+    }
+}
+
+#[cfg(all(feature = "opus", not(target_arch = "wasm32")))]
+fn encode_opus(pcm: Pcm, options: &SynthesisOptions) -> Result<Vec<u8>, AudioError> {
+    let preset = options.opus_bitrate.as_deref().unwrap_or("voice");
+    let bitrate = opus_bitrate(preset)
+        .ok_or_else(|| AudioError::Encode(format!("Unknown Opus bitrate: {}", preset)))?;
+    crate::opus::encode(pcm, bitrate)
+}
+
+#[cfg(not(all(feature = "opus", not(target_arch = "wasm32"))))]
+fn encode_opus(_pcm: Pcm, _options: &SynthesisOptions) -> Result<Vec<u8>, AudioError> {
+    Err(AudioError::Encode(
+        "Opus output needs the `opus` feature".to_string(),
+    ))
+}
+
+impl Pcm {
+    /// Decode MP3, WAV, OGG or FLAC audio
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn decode(audio: &[u8]) -> Result<Self, AudioError> {
+        use rodio::Source;
+
+        let decoder = rodio::Decoder::new(std::io::Cursor::new(audio.to_vec()))
+            .map_err(|e| AudioError::Decode(e.to_string()))?;
+        let channels = decoder.channels();
+        let sample_rate = decoder.sample_rate();
+        Ok(Self {
+            samples: decoder.collect(),
+            channels,
+            sample_rate,
+        })
+    }
+
+    /// Decoding is not available in the browser
+    #[cfg(target_arch = "wasm32")]
+    pub fn decode(_audio: &[u8]) -> Result<Self, AudioError> {
+        Err(AudioError::Decode(
+            "Audio decoding is not supported on wasm32".to_string(),
+        ))
+    }
+
+    /// Read headerless little-endian 16-bit samples
+    pub fn from_le_bytes(bytes: &[u8], channels: u16, sample_rate: u32) -> Self {
+        Self {
+            samples: bytes
+                .chunks_exact(2)
+                .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+                .collect(),
+            channels,
+            sample_rate,
+        }
+    }
+
+    pub fn to_le_bytes(&self) -> Vec<u8> {
+        self.samples.iter().flat_map(|s| s.to_le_bytes()).collect()
+    }
+
+    /// Mix down to mono by averaging, or copy channels to reach `channels`
+    pub fn with_channels(self, channels: u16) -> Self {
+        let from = self.channels.max(1) as usize;
+        let to = channels.max(1) as usize;
+        if from == to {
+            return self;
+        }
+        let mut samples = Vec::with_capacity(self.samples.len() / from * to);
+        for frame in self.samples.chunks_exact(from) {
+            if to == 1 {
+                let sum: i32 = frame.iter().map(|&s| i32::from(s)).sum();
+                samples.push((sum / from as i32) as i16);
+            } else {
+                samples.extend((0..to).map(|c| frame[c.min(from - 1)]));
+            }
+        }
+        Self {
+            samples,
+            channels: to as u16,
+            sample_rate: self.sample_rate,
+        }
+    }
+
+    /// Resample every channel to `sample_rate`
+    pub fn resample(self, sample_rate: u32) -> Self {
+        if sample_rate == self.sample_rate || self.sample_rate == 0 || sample_rate == 0 {
+            return self;
+        }
+        let channels = self.channels.max(1) as usize;
+        let frames = self.samples.len() / channels;
+        let step = f64::from(self.sample_rate) / f64::from(sample_rate);
+        // Downsampling lowers the cutoff to the new Nyquist frequency
+        let cutoff = (1.0 / step).min(1.0);
+        let half_width = SINC_ZEROS / cutoff;
+        let out_frames = (frames as f64 / step).ceil() as usize;
+
+        let mut samples = vec![0; out_frames * channels];
+        for n in 0..out_frames {
+            let t = n as f64 * step;
+            let first = (t - half_width).ceil().max(0.0) as usize;
+            let last = ((t + half_width).floor() as usize).min(frames.saturating_sub(1));
+            for c in 0..channels {
+                let mut sum = 0.0;
+                for k in first..=last {
+                    let x = t - k as f64;
+                    let window = 0.5 * (1.0 + (PI * x / half_width).cos());
+                    sum += f64::from(self.samples[k * channels + c])
+                        * cutoff
+                        * sinc(cutoff * x)
+                        * window;
+                }
+                samples[n * channels + c] = sum.round().clamp(-32768.0, 32767.0) as i16;
+            }
+        }
+        Self {
+            samples,
+            channels: self.channels,
+            sample_rate,
+        }
+    }
+
+    /// Raw little-endian samples in a 16-bit PCM WAV container
+    pub fn to_wav(&self) -> Vec<u8> {
+        let data_len = (self.samples.len() * 2) as u32;
+        let block_align = self.channels * 2;
+        let mut wav = Vec::with_capacity(44 + data_len as usize);
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&self.channels.to_le_bytes());
+        wav.extend_from_slice(&self.sample_rate.to_le_bytes());
+        wav.extend_from_slice(&(self.sample_rate * u32::from(block_align)).to_le_bytes());
+        wav.extend_from_slice(&block_align.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        wav.extend(self.to_le_bytes());
+        wav
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-9 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(freq: f64, sample_rate: u32, frames: usize) -> Vec<i16> {
+        (0..frames)
+            .map(|n| (8000.0 * (2.0 * PI * freq * n as f64 / sample_rate as f64).sin()) as i16)
+            .collect()
+    }
+
+    fn peak(samples: &[i16]) -> i16 {
+        samples
+            .iter()
+            .map(|s| s.saturating_abs())
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn test_downmix_and_resample() {
+        let stereo = Pcm {
+            samples: vec![100, 300, -50, 50],
+            channels: 2,
+            sample_rate: 48_000,
+        };
+        let mono = stereo.with_channels(1);
+        assert_eq!(mono.samples, [200, 0]);
+        assert_eq!(mono.clone().with_channels(2).samples, [200, 200, 0, 0]);
+
+        // A 1 kHz tone survives 48 kHz to 16 kHz, a 6 kHz one is filtered out
+        let low = Pcm {
+            samples: tone(1000.0, 48_000, 4800),
+            channels: 1,
+            sample_rate: 48_000,
+        }
+        .resample(16_000);
+        assert_eq!(low.samples.len(), 1600);
+        assert!(peak(&low.samples[100..1500]) > 7500);
+        let high = Pcm {
+            samples: tone(6000.0, 48_000, 4800),
+            channels: 1,
+            sample_rate: 48_000,
+        }
+        .resample(8_000);
+        assert!(peak(&high.samples[50..750]) < 400);
+    }
+
+    #[test]
+    fn test_convert_round_trips_through_wav() {
+        let wav = Pcm {
+            samples: tone(440.0, 24_000, 2400),
+            channels: 1,
+            sample_rate: 24_000,
+        }
+        .to_wav();
+        let options = SynthesisOptions {
+            sample_rate: Some(8_000),
+            channels: Some(2),
+            ..SynthesisOptions::default()
+        };
+        let converted = convert(&wav, &options, Encoding::Wav).unwrap();
+        let converted = Pcm::decode(&converted).unwrap();
+        assert_eq!(converted.sample_rate, 8_000);
+        assert_eq!(converted.channels, 2);
+        assert_eq!(converted.samples.len(), 1600);
+
+        let options = SynthesisOptions {
+            sample_rate: Some(16_000),
+            ..SynthesisOptions::default()
+        };
+        let raw = convert(&wav, &options, Encoding::S16le).unwrap();
+        assert_eq!(raw.len(), 1600 * 2);
+        let pcm = Pcm::from_le_bytes(&raw, 1, 16_000);
+        assert_eq!(pcm.to_le_bytes(), raw);
+        assert_eq!(Encoding::parse("PCM_S16LE"), Some(Encoding::S16le));
+        assert_eq!(Encoding::parse("mp3"), None);
+        assert_eq!(opus_bitrate("Voice"), Some(24_000));
+        assert_eq!(opus_bitrate("32k"), Some(32_000));
+        assert_eq!(opus_bitrate("1000"), None);
+    }
+}
@@ -0,0 +1,110 @@
+//! Background speaking on a global hotkey.
+//!
+//! [`SpeakDaemon`] keeps one processor and one audio player warm and speaks
+//! the current selection (or the clipboard) whenever the hotkey from
+//! `TTSConfig.hotkey` is pressed. Pressing the hotkey while speech is playing
+//! stops it. Requires the `hotkey` feature.
+
+use crate::audio_player::AudioPlayer;
+use crate::clipboard::Clipboard;
+use crate::models::TTSError;
+use crate::tts_client::TTSProcessor;
+use global_hotkey::hotkey::HotKey;
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use std::future::Future;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// How often hotkey events are checked
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Speaks the selection when a global hotkey is pressed
+pub struct SpeakDaemon {
+    processor: TTSProcessor,
+    player: AudioPlayer,
+    clipboard: Clipboard,
+    voice: String,
+    hotkey: HotKey,
+}
+
+/// Parse a hotkey such as `CmdOrCtrl+Alt+S`
+pub fn parse_hotkey(hotkey: &str) -> Result<HotKey, TTSError> {
+    hotkey
+        .parse()
+        .map_err(|e| TTSError::Config(format!("Invalid hotkey '{}': {}", hotkey, e)))
+}
+
+impl SpeakDaemon {
+    /// Prepare the daemon, opening the audio device and clipboard up front
+    pub fn new(processor: TTSProcessor, voice: &str) -> Result<Self, TTSError> {
+        let hotkey = parse_hotkey(&processor.config().hotkey)?;
+        Ok(Self {
+            processor,
+            player: AudioPlayer::new()?,
+            clipboard: Clipboard::new()?,
+            voice: voice.to_string(),
+            hotkey,
+        })
+    }
+
+    /// Register the hotkey and handle presses until `shutdown` completes
+    pub async fn run(mut self, shutdown: impl Future<Output = ()>) -> Result<(), TTSError> {
+        let manager = GlobalHotKeyManager::new().map_err(hotkey_error)?;
+        manager.register(self.hotkey).map_err(hotkey_error)?;
+        if let Err(e) = self.processor.warm_up().await {
+            warn!("Warm-up failed, the first utterance may be slow: {}", e);
+        }
+        info!(hotkey = %self.processor.config().hotkey, "Speak daemon ready");
+
+        tokio::pin!(shutdown);
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => break,
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            }
+            while let Ok(event) = GlobalHotKeyEvent::receiver().try_recv() {
+                if event.id == self.hotkey.id() && event.state == HotKeyState::Pressed {
+                    if let Err(e) = self.on_hotkey().await {
+                        warn!("Speaking the selection failed: {}", e);
+                    }
+                }
+            }
+        }
+
+        manager.unregister(self.hotkey).map_err(hotkey_error)?;
+        Ok(())
+    }
+
+    async fn on_hotkey(&mut self) -> Result<(), TTSError> {
+        if self.player.is_playing() {
+            self.player.stop();
+            return Ok(());
+        }
+        let Some(text) = self.clipboard.selection()? else {
+            warn!("Nothing selected or copied");
+            return Ok(());
+        };
+        let audio = self
+            .processor
+            .synthesize_text(text.trim(), &self.voice)
+            .await?;
+        self.player.queue_audio_data(audio)?;
+        Ok(())
+    }
+}
+
+fn hotkey_error(e: global_hotkey::Error) -> TTSError {
+    TTSError::Config(format!("Cannot register the global hotkey: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hotkey() {
+        assert!(parse_hotkey("CmdOrCtrl+Alt+S").is_ok());
+        assert!(parse_hotkey("Ctrl+Shift+F9").is_ok());
+        assert!(parse_hotkey("Ctrl+Nope").is_err());
+    }
+}
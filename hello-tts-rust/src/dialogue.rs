@@ -0,0 +1,357 @@
+//! Dialogue scripts rendered with one voice per speaker.
+//!
+//! Scripts are either plain text or JSON. In plain text, every line starts
+//! with the speaker's name; lines without a speaker continue the previous
+//! line, and `@voice` lines map speakers to voices:
+//!
+//! ```text
+//! # Lines starting with '#' are comments
+//! @voice Alice = en-US-AriaNeural
+//! @voice Bob = en-GB-RyanNeural
+//! Alice: Did you hear that?
+//! Bob: Hear what?
+//! ```
+//!
+//! The JSON form is `{"voices": {"Alice": "en-US-AriaNeural"}, "lines":
+//! [{"speaker": "Alice", "text": "Did you hear that?"}]}`; a line may carry
+//! its own `voice`.
+
+use crate::audio::Segment;
+use crate::models::TTSError;
+use crate::tts_client::TTSProcessor;
+use futures_util::stream::{self, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::time::Duration;
+use tracing::instrument;
+
+/// A parsed dialogue script
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Dialogue {
+    /// Voice for each speaker
+    #[serde(default)]
+    pub voices: HashMap<String, String>,
+    pub lines: Vec<DialogueLine>,
+}
+
+/// One line spoken by one speaker
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DialogueLine {
+    pub speaker: String,
+    pub text: String,
+    /// Voice for this line only, overriding the speaker's voice
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voice: Option<String>,
+}
+
+/// How a dialogue is rendered
+#[derive(Debug, Clone)]
+pub struct DialogueOptions {
+    /// Silence inserted between lines
+    pub gap: Duration,
+    /// Speaker voices taking precedence over those in the script
+    pub voices: HashMap<String, String>,
+}
+
+impl Default for DialogueOptions {
+    fn default() -> Self {
+        Self {
+            gap: Duration::from_millis(400),
+            voices: HashMap::new(),
+        }
+    }
+}
+
+/// A line of a rendered dialogue
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RenderedLine {
+    pub speaker: String,
+    pub voice: String,
+    pub bytes: usize,
+}
+
+/// Audio of a whole dialogue
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedDialogue {
+    pub audio: Vec<u8>,
+    pub lines: Vec<RenderedLine>,
+}
+
+impl Dialogue {
+    /// Parse a JSON document or a plain text script
+    pub fn parse(input: &str) -> Result<Self, TTSError> {
+        let dialogue = if input.trim_start().starts_with('{') {
+            serde_json::from_str(input)?
+        } else {
+            Self::parse_script(input)?
+        };
+        if dialogue.lines.is_empty() {
+            return Err(TTSError::Config("Dialogue has no lines".to_string()));
+        }
+        Ok(dialogue)
+    }
+
+    /// Parse the `Speaker: line` text format
+    pub fn parse_script(input: &str) -> Result<Self, TTSError> {
+        let mut dialogue = Dialogue::default();
+        for (number, raw) in input.lines().enumerate() {
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(mapping) = line.strip_prefix("@voice") {
+                let (speaker, voice) = mapping.split_once('=').ok_or_else(|| {
+                    TTSError::Config(format!(
+                        "Line {}: expected `@voice Speaker = voice-name`",
+                        number + 1
+                    ))
+                })?;
+                dialogue
+                    .voices
+                    .insert(speaker.trim().to_string(), voice.trim().to_string());
+                continue;
+            }
+            match line.split_once(':') {
+                Some((speaker, text)) if is_speaker(speaker) => {
+                    dialogue.lines.push(DialogueLine {
+                        speaker: speaker.trim().to_string(),
+                        text: text.trim().to_string(),
+                        voice: None,
+                    });
+                }
+                _ => match dialogue.lines.last_mut() {
+                    Some(previous) => {
+                        previous.text.push(' ');
+                        previous.text.push_str(line);
+                    }
+                    None => {
+                        return Err(TTSError::Config(format!(
+                            "Line {}: expected `Speaker: text`",
+                            number + 1
+                        )))
+                    }
+                },
+            }
+        }
+        Ok(dialogue)
+    }
+
+    /// Speakers in order of appearance
+    pub fn speakers(&self) -> Vec<&str> {
+        let mut seen = BTreeSet::new();
+        self.lines
+            .iter()
+            .map(|line| line.speaker.as_str())
+            .filter(|speaker| seen.insert(*speaker))
+            .collect()
+    }
+
+    /// Voice for every line, failing if any speaker has no voice
+    pub fn resolve_voices(
+        &self,
+        overrides: &HashMap<String, String>,
+    ) -> Result<Vec<String>, TTSError> {
+        let mut missing = BTreeSet::new();
+        let voices: Vec<String> = self
+            .lines
+            .iter()
+            .map(|line| {
+                line.voice
+                    .as_ref()
+                    .or_else(|| overrides.get(&line.speaker))
+                    .or_else(|| self.voices.get(&line.speaker))
+                    .cloned()
+                    .unwrap_or_else(|| {
+                        missing.insert(line.speaker.clone());
+                        String::new()
+                    })
+            })
+            .collect();
+        if !missing.is_empty() {
+            return Err(TTSError::Config(format!(
+                "No voice for speaker(s): {}",
+                missing.into_iter().collect::<Vec<_>>().join(", ")
+            )));
+        }
+        Ok(voices)
+    }
+}
+
+/// A speaker name is short and does not look like a sentence or a URL
+fn is_speaker(name: &str) -> bool {
+    let name = name.trim();
+    !name.is_empty()
+        && name.chars().count() <= 40
+        && !name.contains("//")
+        && !name.ends_with(['.', '!', '?'])
+}
+
+/// Silent MPEG-2 Layer III audio (24 kHz, mono, 48 kbit/s) lasting at least `duration`
+///
+/// Frames with empty side information decode to silence, so they can be
+/// concatenated with the MP3 produced by the Edge and Google backends.
+pub fn mp3_silence(duration: Duration) -> Vec<u8> {
+    const FRAME_BYTES: usize = 144;
+    const FRAME_MS: u128 = 24;
+    let frames = duration.as_millis().div_ceil(FRAME_MS) as usize;
+    let mut frame = [0u8; FRAME_BYTES];
+    frame[..4].copy_from_slice(&[0xFF, 0xF3, 0x64, 0xC0]);
+    frame.repeat(frames)
+}
+
+/// Render every line of a dialogue with its speaker's voice into one audio stream
+///
+/// Up to `TTSConfig.max_concurrent` lines are synthesized at once; lines
+/// are joined in script order with `options.gap` of silence in between.
+#[instrument(skip_all, fields(backend = processor.backend_name(), lines = dialogue.lines.len()))]
+pub async fn render(
+    processor: &TTSProcessor,
+    dialogue: &Dialogue,
+    options: &DialogueOptions,
+) -> Result<RenderedDialogue, TTSError> {
+    let voices = dialogue.resolve_voices(&options.voices)?;
+    let unconverted = processor.unconverted();
+    let parts: Vec<Vec<u8>> = stream::iter(dialogue.lines.iter().zip(&voices))
+        .map(|(line, voice)| unconverted.synthesize_text(&line.text, voice))
+        .buffered(processor.config().max_concurrent.max(1))
+        .try_collect()
+        .await?;
+
+    let mut segments = Vec::new();
+    let mut lines = Vec::new();
+    for (index, ((line, voice), audio)) in dialogue.lines.iter().zip(voices).zip(parts).enumerate()
+    {
+        if index > 0 {
+            segments.push(Segment::Silence(options.gap));
+        }
+        lines.push(RenderedLine {
+            speaker: line.speaker.clone(),
+            voice,
+            bytes: audio.len(),
+        });
+        segments.push(Segment::Audio(audio));
+    }
+    Ok(RenderedDialogue {
+        audio: processor.join_audio(segments)?,
+        lines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis;
+    use crate::backends::mock::MockBackend;
+    use crate::config::TTSConfig;
+    use std::time::Instant;
+
+    #[test]
+    fn test_parse_script() {
+        let script = "# A scene\n@voice Alice = en-US-AriaNeural\nAlice: Did you hear that?\nBob: Hear what?\n  It was nothing.\n\nAlice: Never mind.";
+        let dialogue = Dialogue::parse(script).unwrap();
+        assert_eq!(dialogue.lines.len(), 3);
+        assert_eq!(dialogue.lines[1].text, "Hear what? It was nothing.");
+        assert_eq!(dialogue.speakers(), ["Alice", "Bob"]);
+        assert_eq!(dialogue.voices["Alice"], "en-US-AriaNeural");
+
+        let err = dialogue.resolve_voices(&HashMap::new()).unwrap_err();
+        assert!(err.to_string().contains("Bob"));
+        let overrides = HashMap::from([("Bob".to_string(), "en-GB-RyanNeural".to_string())]);
+        assert_eq!(
+            dialogue.resolve_voices(&overrides).unwrap(),
+            ["en-US-AriaNeural", "en-GB-RyanNeural", "en-US-AriaNeural"]
+        );
+
+        assert!(Dialogue::parse("Just a sentence without a speaker.").is_err());
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let json = r#"{"voices": {"A": "en-US-AriaNeural"},
+            "lines": [{"speaker": "A", "text": "Hi"}, {"speaker": "B", "text": "Yo", "voice": "fr"}]}"#;
+        let dialogue = Dialogue::parse(json).unwrap();
+        assert_eq!(
+            dialogue.resolve_voices(&HashMap::new()).unwrap(),
+            ["en-US-AriaNeural", "fr"]
+        );
+    }
+
+    #[test]
+    fn test_mp3_silence() {
+        let silence = mp3_silence(Duration::from_millis(50));
+        assert_eq!(silence.len(), 3 * 144);
+        assert_eq!(&silence[144..146], &[0xFF, 0xF3]);
+        assert!(mp3_silence(Duration::ZERO).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_render_dialogue() {
+        let backend = MockBackend::new().with_latency(Duration::from_millis(50));
+        let processor = TTSProcessor::with_backend(TTSConfig::default(), Box::new(backend.clone()));
+        let dialogue =
+            Dialogue::parse("@voice A = va\n@voice B = vb\nA: One\nB: Two\nA: Three").unwrap();
+        let options = DialogueOptions {
+            gap: Duration::from_millis(24),
+            ..DialogueOptions::default()
+        };
+
+        let started = Instant::now();
+        let rendered = render(&processor, &dialogue, &options).await.unwrap();
+        assert!(started.elapsed() < Duration::from_millis(140));
+
+        let gap = mp3_silence(options.gap);
+        let expected = [b"One".as_slice(), &gap, b"Two", &gap, b"Three"].concat();
+        assert_eq!(rendered.audio, expected);
+        assert_eq!(rendered.lines[1].voice, "vb");
+        assert_eq!(backend.calls().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_dialogue_is_converted_after_joining() {
+        let second = mp3_silence(Duration::from_secs(1));
+        let backend = MockBackend::new()
+            .then_audio(second.clone())
+            .then_audio(second);
+        let config = TTSConfig {
+            output_format: "wav".to_string(),
+            sample_rate: Some(8_000),
+            channels: Some(1),
+            max_concurrent: 1,
+            ..TTSConfig::default()
+        };
+        let processor = TTSProcessor::with_backend(config, Box::new(backend));
+        let dialogue = Dialogue::parse("@voice A = va\n@voice B = vb\nA: One\nB: Two").unwrap();
+        let options = DialogueOptions {
+            gap: Duration::from_millis(500),
+            ..DialogueOptions::default()
+        };
+        let rendered = render(&processor, &dialogue, &options).await.unwrap();
+        assert_eq!(&rendered.audio[..4], b"RIFF");
+        let stats = analysis::analyze(&rendered.audio).unwrap();
+        assert_eq!((stats.sample_rate, stats.channels), (8_000, 1));
+        assert!((2400..2700).contains(&stats.duration_ms));
+    }
+
+    #[tokio::test]
+    async fn test_dialogue_lines_are_crossfaded() {
+        let second = mp3_silence(Duration::from_secs(1));
+        let backend = MockBackend::new()
+            .then_audio(second.clone())
+            .then_audio(second);
+        let config = TTSConfig {
+            output_format: "wav".to_string(),
+            crossfade_ms: 200,
+            max_concurrent: 1,
+            ..TTSConfig::default()
+        };
+        let processor = TTSProcessor::with_backend(config, Box::new(backend));
+        let dialogue = Dialogue::parse("@voice A = va\n@voice B = vb\nA: One\nB: Two").unwrap();
+        let options = DialogueOptions {
+            gap: Duration::ZERO,
+            ..DialogueOptions::default()
+        };
+        let rendered = render(&processor, &dialogue, &options).await.unwrap();
+        let stats = analysis::analyze(&rendered.audio).unwrap();
+        assert!((1700..2000).contains(&stats.duration_ms));
+    }
+}
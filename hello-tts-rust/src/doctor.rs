@@ -0,0 +1,245 @@
+//! Environment diagnostics for the `doctor` subcommand.
+//!
+//! [`diagnose`] checks everything synthesis and playback depend on: the
+//! configuration, the Edge endpoint, the edge-tts and gtts-cli tools, the
+//! audio output device and the output directory, then runs the
+//! [`health_check`](crate::backends::TTSBackend::health_check) of the
+//! configured backend and its fallbacks. Checks that the configured backend
+//! needs are marked required; when one of them fails the backend is unusable
+//! and [`DoctorReport::is_usable`] returns `false`.
+
+use crate::audio_player::AudioPlayer;
+use crate::backends::edge_voices::VOICES_URL;
+use crate::backends::BUILTIN_BACKENDS;
+use crate::config::TTSConfig;
+use crate::tts_client::TTSProcessor;
+use serde::Serialize;
+use std::path::Path;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// How long a single probe may take
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Outcome of a check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    /// Failed, but the configured backend does not need it
+    Warning,
+    Failed,
+}
+
+/// One diagnostic
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// The configured backend cannot work if this check fails
+    pub required: bool,
+}
+
+impl Check {
+    fn new(name: &str, required: bool, result: Result<String, String>) -> Self {
+        let (status, detail) = match result {
+            Ok(detail) => (CheckStatus::Ok, detail),
+            Err(detail) if required => (CheckStatus::Failed, detail),
+            Err(detail) => (CheckStatus::Warning, detail),
+        };
+        Self {
+            name: name.to_string(),
+            status,
+            detail,
+            required,
+        }
+    }
+}
+
+/// All checks for one configuration
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DoctorReport {
+    pub backend: String,
+    pub checks: Vec<Check>,
+}
+
+impl DoctorReport {
+    /// Whether every check the configured backend needs passed
+    pub fn is_usable(&self) -> bool {
+        self.checks.iter().all(|c| c.status != CheckStatus::Failed)
+    }
+
+    /// Aligned table with one check per row
+    pub fn to_table(&self) -> String {
+        let width = self.checks.iter().map(|c| c.name.len()).fold(5, usize::max);
+        let mut table = format!("{:width$}  {:7}  Details\n", "Check", "Status");
+        for check in &self.checks {
+            let status = match check.status {
+                CheckStatus::Ok => "ok",
+                CheckStatus::Warning => "warning",
+                CheckStatus::Failed => "FAILED",
+            };
+            table.push_str(&format!(
+                "{:width$}  {:7}  {}\n",
+                check.name, status, check.detail
+            ));
+        }
+        table
+    }
+}
+
+/// Run every check for `config`
+pub async fn diagnose(config: &TTSConfig) -> DoctorReport {
+    let backend = config.backend.as_str();
+    let edge = backend == "edge";
+    let known = BUILTIN_BACKENDS.contains(&backend);
+
+    let mut checks = vec![
+        Check::new(
+            "config",
+            true,
+            config
+                .validate()
+                .map(|_| "valid".to_string())
+                .map_err(|e| e.to_string()),
+        ),
+        Check::new(
+            "backend",
+            true,
+            if known {
+                Ok(format!("{} selected", backend))
+            } else {
+                Err(format!("unknown backend {:?}", backend))
+            },
+        ),
+        Check::new("edge endpoint", edge, edge_endpoint(config).await),
+        Check::new(
+            "edge-tts",
+            edge,
+            match tool_version("edge-tts", &["--version"]).await {
+                Ok(version) => Ok(version),
+                Err(_) => tool_version("python", &["-m", "edge_tts", "--version"])
+                    .await
+                    .map_err(|_| "neither edge-tts nor python -m edge_tts works".to_string()),
+            },
+        ),
+        Check::new(
+            "gtts-cli",
+            backend == "google",
+            tool_version("gtts-cli", &["--version"]).await,
+        ),
+        Check::new("audio output", false, audio_output(&config.audio_output)),
+        Check::new(
+            "output directory",
+            false,
+            output_dir(Path::new(&config.output_directory)),
+        ),
+    ];
+    match TTSProcessor::try_new(Some(config.clone())) {
+        Ok(processor) => {
+            for health in processor.health_check().await {
+                checks.push(Check::new(
+                    &format!("{} health", health.backend),
+                    health.backend == backend,
+                    health.error.map_or(Ok("healthy".to_string()), Err),
+                ));
+            }
+        }
+        Err(e) => checks.push(Check::new("processor", true, Err(e.to_string()))),
+    }
+    DoctorReport {
+        backend: backend.to_string(),
+        checks,
+    }
+}
+
+/// Fetch the Edge voice list through the configured proxy
+async fn edge_endpoint(config: &TTSConfig) -> Result<String, String> {
+    let mut client = reqwest::Client::builder().timeout(PROBE_TIMEOUT);
+    if let Some(proxy) = config.proxy_for("edge").map_err(|e| e.to_string())? {
+        client = client.proxy(reqwest::Proxy::all(&proxy).map_err(|e| e.to_string())?);
+    }
+    let client = client.build().map_err(|e| e.to_string())?;
+    let response = client
+        .get(VOICES_URL)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.without_url().to_string())?;
+    Ok(format!("reachable (HTTP {})", response.status().as_u16()))
+}
+
+/// First output line of `program args`, if it runs successfully
+async fn tool_version(program: &str, args: &[&str]) -> Result<String, String> {
+    let run = Command::new(program).args(args).kill_on_drop(true).output();
+    let output = tokio::time::timeout(PROBE_TIMEOUT, run)
+        .await
+        .map_err(|_| format!("{} timed out", program))?
+        .map_err(|e| format!("{} not found: {}", program, e))?;
+    if !output.status.success() {
+        return Err(format!("{} exited with {}", program, output.status));
+    }
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Ok(text.lines().next().unwrap_or(program).trim().to_string())
+}
+
+/// Open the device selected by `TTSConfig.audio_output`
+fn audio_output(audio_output: &str) -> Result<String, String> {
+    let player = match audio_output {
+        "null" => return Ok("disabled (audio_output = null)".to_string()),
+        "stdout" | "-" => return Ok("written to stdout".to_string()),
+        file if file.starts_with("file:") => return Ok(format!("appended to {}", &file[5..])),
+        "auto" | "default" => AudioPlayer::new(),
+        device => AudioPlayer::with_device(device),
+    };
+    player
+        .map(|_| format!("{} device available", audio_output))
+        .map_err(|e| e.to_string())
+}
+
+/// Create `dir` if needed and write and delete a probe file in it
+fn output_dir(dir: &Path) -> Result<String, String> {
+    let probe = dir.join(format!(".hello-tts-doctor-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(dir)
+        .and_then(|_| std::fs::write(&probe, b""))
+        .and_then(|_| std::fs::remove_file(&probe))
+        .map(|_| format!("{} is writable", dir.display()))
+        .map_err(|e| format!("cannot write to {}: {}", dir.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_failures_make_backend_unusable() {
+        let mut report = DoctorReport {
+            backend: "google".to_string(),
+            checks: vec![
+                Check::new("gtts-cli", true, Ok("2.5.1".to_string())),
+                Check::new("edge-tts", false, Err("edge-tts not found".to_string())),
+            ],
+        };
+        assert_eq!(report.checks[1].status, CheckStatus::Warning);
+        assert!(report.is_usable());
+        assert!(report
+            .to_table()
+            .contains("edge-tts  warning  edge-tts not found"));
+
+        report.checks[0] = Check::new("gtts-cli", true, Err("not found".to_string()));
+        assert!(!report.is_usable());
+    }
+
+    #[test]
+    fn test_output_dir_probe() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-doctor-{}", uuid::Uuid::new_v4()));
+        assert!(output_dir(&dir).is_ok());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
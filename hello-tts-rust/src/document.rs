@@ -0,0 +1,427 @@
+//! Chapterized documents for podcast and audiobook rendering.
+//!
+//! [`render`] writes one audio file per chapter, an M3U playlist and a
+//! `chapters.json` file describing the chapters. The metadata is updated after
+//! every chapter, so an interrupted render can be resumed without synthesizing
+//! finished chapters again.
+
+use crate::analysis;
+use crate::models::TTSError;
+use crate::naming::fnv1a;
+use crate::text::InputFormat;
+use crate::tts_client::TTSProcessor;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::{info, instrument};
+
+/// Name of the chapter metadata file in a render directory
+pub const METADATA_FILE: &str = "chapters.json";
+/// Name of the playlist in a render directory
+pub const PLAYLIST_FILE: &str = "playlist.m3u";
+
+/// A document made of chapters
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+    #[serde(default)]
+    pub title: Option<String>,
+    pub chapters: Vec<Chapter>,
+}
+
+/// A chapter with optional introductory text and sections
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Chapter {
+    pub title: String,
+    /// Text read before the first section
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub sections: Vec<Section>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Section {
+    #[serde(default)]
+    pub title: Option<String>,
+    pub text: String,
+}
+
+/// Where and how a document is rendered
+#[derive(Debug, Clone, Default)]
+pub struct DocumentOptions {
+    /// Directory for chapter files, playlist and metadata; defaults to a
+    /// directory named after the document in `TTSConfig.output_directory`
+    pub output_dir: Option<PathBuf>,
+    /// Skip chapters whose audio from a previous run is still up to date
+    pub resume: bool,
+}
+
+/// Metadata of a rendered chapter, as stored in `chapters.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChapterInfo {
+    pub index: usize,
+    pub title: String,
+    /// Audio file name, relative to the render directory
+    pub file: String,
+    pub bytes: usize,
+    /// Hash of voice and chapter text, used to detect changed chapters on resume
+    pub hash: String,
+    /// Length of the audio, when it could be decoded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+/// Contents of `chapters.json`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DocumentMetadata {
+    #[serde(default)]
+    pub title: Option<String>,
+    pub voice: String,
+    pub chapters: Vec<ChapterInfo>,
+}
+
+/// Result of [`render`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RenderedDocument {
+    pub output_dir: PathBuf,
+    pub playlist: PathBuf,
+    pub metadata: DocumentMetadata,
+    /// Chapters reused from a previous run
+    pub resumed: usize,
+}
+
+impl Document {
+    /// Parse a JSON document or Markdown, where `#` headings start chapters
+    /// and `##` headings start sections
+    pub fn parse(input: &str) -> Result<Self, TTSError> {
+        let document = if input.trim_start().starts_with('{') {
+            serde_json::from_str(input)?
+        } else {
+            Self::from_markdown(input)
+        };
+        if document.chapters.is_empty() {
+            return Err(TTSError::Config("Document has no chapters".to_string()));
+        }
+        Ok(document)
+    }
+
+    /// Split Markdown into chapters at `#` headings and sections at `##` headings
+    ///
+    /// Text before the first chapter heading becomes an untitled first chapter.
+    pub fn from_markdown(input: &str) -> Self {
+        let mut document = Document::default();
+        let mut in_code_block = false;
+        for line in input.lines() {
+            if line.trim_start().starts_with("```") {
+                in_code_block = !in_code_block;
+            }
+            if !in_code_block {
+                if let Some(title) = line.strip_prefix("# ") {
+                    document.chapters.push(Chapter {
+                        title: title.trim().to_string(),
+                        ..Chapter::default()
+                    });
+                    continue;
+                }
+                if let Some(title) = line.strip_prefix("## ") {
+                    current_chapter(&mut document).sections.push(Section {
+                        title: Some(title.trim().to_string()),
+                        text: String::new(),
+                    });
+                    continue;
+                }
+            }
+            let chapter = current_chapter(&mut document);
+            let text = match chapter.sections.last_mut() {
+                Some(section) => &mut section.text,
+                None => &mut chapter.text,
+            };
+            text.push_str(line);
+            text.push('\n');
+        }
+        document
+            .chapters
+            .retain(|c| !c.title.is_empty() || !c.speakable_text().trim().is_empty());
+        if document.title.is_none() && document.chapters.len() == 1 {
+            document.title = Some(document.chapters[0].title.clone());
+        }
+        document
+    }
+}
+
+fn current_chapter(document: &mut Document) -> &mut Chapter {
+    if document.chapters.is_empty() {
+        document.chapters.push(Chapter::default());
+    }
+    document.chapters.last_mut().unwrap()
+}
+
+impl Chapter {
+    /// Chapter and section titles followed by their text, as Markdown
+    pub fn speakable_text(&self) -> String {
+        let mut out = String::new();
+        if !self.title.is_empty() {
+            let _ = writeln!(out, "# {}\n", self.title);
+        }
+        push_paragraph(&mut out, &self.text);
+        for section in &self.sections {
+            if let Some(title) = &section.title {
+                let _ = writeln!(out, "## {}\n", title);
+            }
+            push_paragraph(&mut out, &section.text);
+        }
+        out
+    }
+
+    /// Stable hash of this chapter's text spoken by `voice`
+    pub fn hash(&self, voice: &str) -> String {
+        format!(
+            "{:016x}",
+            fnv1a(format!("{}\0{}", voice, self.speakable_text()).as_bytes())
+        )
+    }
+}
+
+fn push_paragraph(out: &mut String, text: &str) {
+    let text = text.trim();
+    if !text.is_empty() {
+        out.push_str(text);
+        out.push_str("\n\n");
+    }
+}
+
+/// File name for chapter `index`, e.g. `03_the-storm.mp3`
+pub fn chapter_file_name(index: usize, title: &str, ext: &str) -> String {
+    let slug = slug(title);
+    if slug.is_empty() {
+        format!("{:02}.{}", index + 1, ext)
+    } else {
+        format!("{:02}_{}.{}", index + 1, slug, ext)
+    }
+}
+
+/// Lowercase words joined by `-`, keeping letters of any script
+pub fn slug(title: &str) -> String {
+    title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .take(8)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+impl DocumentMetadata {
+    /// Read `chapters.json` from a render directory, if present
+    pub fn load(dir: &Path) -> Result<Option<Self>, TTSError> {
+        match std::fs::read_to_string(dir.join(METADATA_FILE)) {
+            Ok(content) => Ok(Some(serde_json::from_str(&content)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<(), TTSError> {
+        std::fs::write(dir.join(METADATA_FILE), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Extended M3U playlist of the chapters
+    pub fn to_m3u(&self) -> String {
+        let mut out = String::from("#EXTM3U\n");
+        if let Some(title) = &self.title {
+            let _ = writeln!(out, "#PLAYLIST:{}", title);
+        }
+        for chapter in &self.chapters {
+            let seconds = chapter.duration_ms.map_or(-1, |ms| (ms / 1000) as i64);
+            let _ = writeln!(
+                out,
+                "#EXTINF:{},{}\n{}",
+                seconds, chapter.title, chapter.file
+            );
+        }
+        out
+    }
+}
+
+/// Render a document to one audio file per chapter, plus a playlist and chapter metadata
+///
+/// With `options.resume`, chapters whose file and hash in an existing
+/// `chapters.json` still match are not synthesized again. The metadata is
+/// saved after every chapter, so an interrupted render loses at most the
+/// chapter in progress.
+#[instrument(
+    skip_all,
+    fields(backend = processor.backend_name(), voice = %voice, chapters = document.chapters.len())
+)]
+pub async fn render(
+    processor: &TTSProcessor,
+    document: &Document,
+    voice: &str,
+    options: &DocumentOptions,
+) -> Result<RenderedDocument, TTSError> {
+    let output_dir = options.output_dir.clone().unwrap_or_else(|| {
+        let name = slug(document.title.as_deref().unwrap_or_default());
+        let name = if name.is_empty() {
+            "document".to_string()
+        } else {
+            name
+        };
+        processor.output_manager().dir().join(name)
+    });
+    fs::create_dir_all(&output_dir).await?;
+    let previous = if options.resume {
+        DocumentMetadata::load(&output_dir)?.unwrap_or_default()
+    } else {
+        DocumentMetadata::default()
+    };
+
+    let mut metadata = DocumentMetadata {
+        title: document.title.clone(),
+        voice: voice.to_string(),
+        chapters: Vec::new(),
+    };
+    let mut resumed = 0;
+    for (index, chapter) in document.chapters.iter().enumerate() {
+        let file = chapter_file_name(index, &chapter.title, processor.config().file_extension());
+        let hash = chapter.hash(voice);
+        let finished = previous
+            .chapters
+            .iter()
+            .find(|c| c.file == file && c.hash == hash && output_dir.join(&file).exists());
+        let info = match finished {
+            Some(info) => {
+                info!("Skipping finished chapter {}", file);
+                resumed += 1;
+                ChapterInfo {
+                    index,
+                    ..info.clone()
+                }
+            }
+            None => {
+                info!(
+                    "Rendering chapter {}/{}: {}",
+                    index + 1,
+                    document.chapters.len(),
+                    chapter.title
+                );
+                let text = chapter.speakable_text();
+                let audio = processor
+                    .synthesize_formatted(&text, voice, InputFormat::Markdown)
+                    .await?;
+                fs::write(output_dir.join(&file), &audio).await?;
+                ChapterInfo {
+                    index,
+                    title: chapter.title.clone(),
+                    file,
+                    bytes: audio.len(),
+                    hash,
+                    duration_ms: analysis::measure(&processor.decodable(&audio))
+                        .map(|s| s.duration_ms),
+                }
+            }
+        };
+        metadata.chapters.push(info);
+
+        // Keep later chapters of the previous run so a second interruption
+        // does not forget them
+        let mut checkpoint = metadata.clone();
+        checkpoint.chapters.extend(
+            previous
+                .chapters
+                .iter()
+                .filter(|c| c.index > index)
+                .cloned(),
+        );
+        checkpoint.save(&output_dir)?;
+    }
+
+    metadata.save(&output_dir)?;
+    let playlist = output_dir.join(PLAYLIST_FILE);
+    fs::write(&playlist, metadata.to_m3u()).await?;
+    Ok(RenderedDocument {
+        output_dir,
+        playlist,
+        metadata,
+        resumed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::mock::MockBackend;
+    use crate::config::TTSConfig;
+
+    #[test]
+    fn test_markdown_chapters() {
+        let markdown = "Preface text.\n\n# The Start\nIt began.\n## Morning\nSun.\n```\n# not a heading\n```\n# The End\nDone.\n";
+        let document = Document::parse(markdown).unwrap();
+        let titles: Vec<&str> = document.chapters.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["", "The Start", "The End"]);
+        assert_eq!(document.chapters[1].sections.len(), 1);
+        assert!(document.chapters[1].sections[0]
+            .text
+            .contains("# not a heading"));
+        assert_eq!(
+            document.chapters[1].speakable_text(),
+            "# The Start\n\nIt began.\n\n## Morning\n\nSun.\n```\n# not a heading\n```\n\n"
+        );
+        assert_ne!(
+            document.chapters[1].hash("a"),
+            document.chapters[1].hash("b")
+        );
+    }
+
+    #[test]
+    fn test_file_names_and_playlist() {
+        assert_eq!(
+            chapter_file_name(2, "The Storm!", "mp3"),
+            "03_the-storm.mp3"
+        );
+        assert_eq!(chapter_file_name(0, "", "mp3"), "01.mp3");
+        assert_eq!(slug("Глава первая"), "глава-первая");
+
+        let metadata = DocumentMetadata {
+            title: Some("Book".to_string()),
+            voice: "v".to_string(),
+            chapters: vec![ChapterInfo {
+                index: 0,
+                title: "One".to_string(),
+                file: "01_one.mp3".to_string(),
+                bytes: 3,
+                hash: "0".to_string(),
+                duration_ms: None,
+            }],
+        };
+        assert_eq!(
+            metadata.to_m3u(),
+            "#EXTM3U\n#PLAYLIST:Book\n#EXTINF:-1,One\n01_one.mp3\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_render_document_resumes() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-doc-{}", uuid::Uuid::new_v4()));
+        let document = Document::parse("# One\nFirst.\n# Two\nSecond.\n").unwrap();
+        let options = DocumentOptions {
+            output_dir: Some(dir.clone()),
+            resume: true,
+        };
+
+        let backend = MockBackend::new();
+        let processor = TTSProcessor::with_backend(TTSConfig::default(), Box::new(backend.clone()));
+        let rendered = render(&processor, &document, "v", &options).await.unwrap();
+        assert_eq!(rendered.metadata.chapters[1].file, "02_two.mp3");
+        assert!(dir.join("01_one.mp3").exists());
+        let playlist = std::fs::read_to_string(&rendered.playlist).unwrap();
+        assert!(playlist.ends_with("#EXTINF:-1,Two\n02_two.mp3\n"));
+
+        std::fs::remove_file(dir.join("02_two.mp3")).unwrap();
+        let rendered = render(&processor, &document, "v", &options).await.unwrap();
+        assert_eq!(rendered.resumed, 1);
+        assert_eq!(backend.calls().len(), 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
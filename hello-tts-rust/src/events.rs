@@ -0,0 +1,73 @@
+//! Progress events emitted by [`TTSProcessor`](crate::TTSProcessor).
+//!
+//! Frontends register an [`EventHandler`] with
+//! [`TTSProcessor::subscribe`](crate::TTSProcessor::subscribe) to drive
+//! progress bars and status lines without parsing log output.
+
+use std::time::Duration;
+
+/// Something that happened while the processor was working
+#[derive(Debug, Clone, PartialEq)]
+pub enum TTSEvent {
+    /// Synthesis of a text began; `chunks` requests will be sent to the backend
+    SynthesisStarted {
+        backend: &'static str,
+        voice: String,
+        text_chars: usize,
+        chunks: usize,
+    },
+    /// Audio for chunk `index` (zero-based) of `total` arrived from the backend
+    ChunkReceived {
+        index: usize,
+        total: usize,
+        bytes: usize,
+    },
+    /// All chunks were synthesized
+    SynthesisCompleted { duration: Duration, bytes: usize },
+    /// A retryable backend failure occurred and the request will be sent again
+    Retry {
+        attempt: u32,
+        delay: Duration,
+        error: String,
+    },
+    /// The voice list was served from the cache
+    CacheHit,
+    /// A retired or missing voice was replaced, see [`crate::voice_remap`]
+    VoiceSubstituted {
+        backend: &'static str,
+        requested: String,
+        substitute: String,
+    },
+    /// A backend's circuit opened; requests skip it for `cooldown`, see
+    /// [`crate::health`]
+    CircuitOpened {
+        backend: String,
+        error: String,
+        cooldown: Duration,
+    },
+    /// A backend's circuit closed again after a successful request or health check
+    CircuitClosed { backend: String },
+    /// A batch or audiobook job ended; `failed` is set when it stopped with an
+    /// error or left items failed or pending
+    JobFinished {
+        job: &'static str,
+        summary: String,
+        failed: bool,
+    },
+}
+
+/// Receiver of [`TTSEvent`]s
+///
+/// Implemented for closures, so `processor.subscribe(|e: &TTSEvent| ...)` works.
+pub trait EventHandler: Send + Sync {
+    fn on_event(&self, event: &TTSEvent);
+}
+
+impl<F> EventHandler for F
+where
+    F: Fn(&TTSEvent) + Send + Sync,
+{
+    fn on_event(&self, event: &TTSEvent) {
+        self(event)
+    }
+}
@@ -0,0 +1,249 @@
+//! Language-learning flashcards rendered as one audio file.
+//!
+//! Every card is a (source, translation) pair read from CSV or TSV, one card
+//! per row. The rendered audio speaks the source text, pauses so the learner
+//! can answer, speaks the translation and pauses again before the next card.
+//! Lines starting with `#` (such as Anki's `#separator:tab`) are skipped, as
+//! is a header row like `source,translation`.
+
+use crate::audio::Segment;
+use crate::config::TTSConfigFile;
+use crate::models::TTSError;
+use crate::tts_client::TTSProcessor;
+use futures_util::stream::{self, StreamExt, TryStreamExt};
+use serde::Serialize;
+use std::time::Duration;
+use tracing::instrument;
+
+const HEADER_NAMES: [&str; 5] = ["source", "front", "question", "text", "word"];
+
+/// One flashcard
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Flashcard {
+    pub source: String,
+    pub translation: String,
+}
+
+/// Voices and pauses of a flashcard rendering
+#[derive(Debug, Clone)]
+pub struct FlashcardOptions {
+    pub source_voice: String,
+    pub translation_voice: String,
+    /// Silence between the source text and its translation
+    pub answer_pause: Duration,
+    /// Silence after the translation, before the next card
+    pub card_gap: Duration,
+}
+
+impl FlashcardOptions {
+    pub fn new(source_voice: &str, translation_voice: &str) -> Self {
+        Self {
+            source_voice: source_voice.to_string(),
+            translation_voice: translation_voice.to_string(),
+            answer_pause: Duration::from_millis(2000),
+            card_gap: Duration::from_millis(3000),
+        }
+    }
+
+    /// Options using the voices `languages` lists for two language codes
+    /// (e.g. `en-us` and `fr`) and `backend`
+    pub fn from_languages(
+        languages: &TTSConfigFile,
+        source: &str,
+        translation: &str,
+        backend: &str,
+    ) -> Result<Self, TTSError> {
+        let voice = |code: &str| {
+            languages.voice_for(code, backend).ok_or_else(|| {
+                TTSError::Config(format!(
+                    "No {} voice configured for language {}",
+                    backend, code
+                ))
+            })
+        };
+        Ok(Self::new(voice(source)?, voice(translation)?))
+    }
+}
+
+/// Audio of a whole deck
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedFlashcards {
+    pub audio: Vec<u8>,
+    pub cards: usize,
+}
+
+/// Parse CSV or TSV rows into cards; the delimiter is a tab if the first
+/// row contains one and a comma otherwise
+pub fn parse(input: &str) -> Result<Vec<Flashcard>, TTSError> {
+    let rows: Vec<(usize, &str)> = input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .collect();
+    let delimiter = detect_delimiter(rows.first().map_or("", |(_, line)| *line));
+
+    let mut cards = Vec::new();
+    for (position, (number, line)) in rows.into_iter().enumerate() {
+        let fields = split_row(line, delimiter);
+        if position == 0 && HEADER_NAMES.contains(&fields[0].trim().to_lowercase().as_str()) {
+            continue;
+        }
+        match fields.as_slice() {
+            [source, translation, ..]
+                if !source.trim().is_empty() && !translation.trim().is_empty() =>
+            {
+                cards.push(Flashcard {
+                    source: source.trim().to_string(),
+                    translation: translation.trim().to_string(),
+                })
+            }
+            _ => {
+                return Err(TTSError::Config(format!(
+                    "Line {}: expected source and translation separated by {:?}",
+                    number + 1,
+                    delimiter
+                )))
+            }
+        }
+    }
+    if cards.is_empty() {
+        return Err(TTSError::Config("No flashcards found".to_string()));
+    }
+    Ok(cards)
+}
+
+/// Tab if `first_row` contains one, comma otherwise
+pub(crate) fn detect_delimiter(first_row: &str) -> char {
+    if first_row.contains('\t') {
+        '\t'
+    } else {
+        ','
+    }
+}
+
+/// Split a row at `delimiter`, honouring double-quoted fields with `""` escapes
+pub(crate) fn split_row(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        let field = fields.last_mut().unwrap();
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted || field.trim().is_empty() => quoted = !quoted,
+            c if c == delimiter && !quoted => fields.push(String::new()),
+            c => field.push(c),
+        }
+    }
+    fields
+}
+
+/// Render flashcards into one audio file: source, pause, translation, pause
+#[instrument(skip_all, fields(backend = processor.backend_name(), cards = cards.len()))]
+pub async fn render(
+    processor: &TTSProcessor,
+    cards: &[Flashcard],
+    options: &FlashcardOptions,
+) -> Result<RenderedFlashcards, TTSError> {
+    let unconverted = processor.unconverted();
+    let parts: Vec<Vec<u8>> = stream::iter(cards)
+        .flat_map(|card| {
+            stream::iter([
+                (&card.source, &options.source_voice),
+                (&card.translation, &options.translation_voice),
+            ])
+        })
+        .map(|(text, voice)| unconverted.synthesize_text(text, voice))
+        .buffered(processor.config().max_concurrent.max(1))
+        .try_collect()
+        .await?;
+
+    let mut segments = Vec::new();
+    let mut parts = parts.into_iter();
+    while let (Some(source), Some(translation)) = (parts.next(), parts.next()) {
+        if !segments.is_empty() {
+            segments.push(Segment::Silence(options.card_gap));
+        }
+        segments.push(Segment::Audio(source));
+        segments.push(Segment::Silence(options.answer_pause));
+        segments.push(Segment::Audio(translation));
+    }
+    Ok(RenderedFlashcards {
+        audio: processor.join_audio(segments)?,
+        cards: cards.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::mock::MockBackend;
+    use crate::config::TTSConfig;
+    use crate::dialogue;
+
+    #[test]
+    fn test_parse_csv_and_tsv() {
+        let csv = "source,translation\nhello,bonjour\n\"yes, please\",\"oui, s'il vous plaît\"\n";
+        let cards = parse(csv).unwrap();
+        assert_eq!(cards.len(), 2);
+        assert_eq!(cards[1].source, "yes, please");
+        assert_eq!(cards[1].translation, "oui, s'il vous plaît");
+
+        let tsv = "#separator:tab\n#html:false\ncat\tle chat\textra\n";
+        let cards = parse(tsv).unwrap();
+        assert_eq!(
+            cards,
+            [Flashcard {
+                source: "cat".to_string(),
+                translation: "le chat".to_string()
+            }]
+        );
+
+        assert!(parse("only one column\n").is_err());
+        assert_eq!(split_row(r#""say ""hi""",x"#, ','), ["say \"hi\"", "x"]);
+    }
+
+    #[test]
+    fn test_voices_from_languages() {
+        let languages: TTSConfigFile = serde_json::from_str(
+            r#"{"languages": [
+                {"code": "en-us", "name": "English", "edge_voice": "en-US-AriaNeural", "google_voice": "en"},
+                {"code": "fr-fr", "name": "French", "edge_voice": "fr-FR-DeniseNeural", "google_voice": "fr"}
+            ]}"#,
+        )
+        .unwrap();
+        let options = FlashcardOptions::from_languages(&languages, "en-US", "fr", "edge").unwrap();
+        assert_eq!(options.source_voice, "en-US-AriaNeural");
+        assert_eq!(options.translation_voice, "fr-FR-DeniseNeural");
+        assert!(FlashcardOptions::from_languages(&languages, "en", "de", "edge").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_render_flashcards() {
+        let backend = MockBackend::new();
+        let processor = TTSProcessor::with_backend(TTSConfig::default(), Box::new(backend.clone()));
+        let cards = parse("cat\tle chat\ndog\tle chien\n").unwrap();
+        let mut options = FlashcardOptions::new("en", "fr");
+        options.answer_pause = Duration::from_millis(24);
+        options.card_gap = Duration::from_millis(48);
+
+        let rendered = render(&processor, &cards, &options).await.unwrap();
+        let voices: Vec<String> = backend.calls().into_iter().map(|c| c.voice).collect();
+        assert_eq!(voices, ["en", "fr", "en", "fr"]);
+        let silence = |ms| dialogue::mp3_silence(Duration::from_millis(ms));
+        let expected = [
+            b"cat".to_vec(),
+            silence(24),
+            b"le chat".to_vec(),
+            silence(48),
+            b"dog".to_vec(),
+            silence(24),
+            b"le chien".to_vec(),
+        ]
+        .concat();
+        assert_eq!(rendered.audio, expected);
+    }
+}
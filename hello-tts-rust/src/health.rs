@@ -0,0 +1,182 @@
+//! Backend health and the fallback chain's circuit breaker.
+//!
+//! When `TTSConfig.fallback_backends` is set, the processor tries the
+//! configured backend first and then each fallback in order. Every backend has
+//! a circuit that opens after `TTSConfig.circuit_failure_threshold`
+//! consecutive requests failed with a retryable error (network, timeout, rate
+//! limit or outage) once their retries were used up, or as soon as its
+//! [`health_check`](crate::backends::TTSBackend::health_check) fails. While a
+//! circuit is open the backend is skipped, so a dead service does not cost
+//! every request a round of retries; when all circuits are open requests fail
+//! at once. After `TTSConfig.backend_cooldown_secs` the circuit is half open:
+//! the next request is let through and closes it on success or opens it again
+//! on failure. Opening and closing emit
+//! [`TTSEvent::CircuitOpened`](crate::TTSEvent::CircuitOpened) and
+//! [`TTSEvent::CircuitClosed`](crate::TTSEvent::CircuitClosed).
+
+use serde::Serialize;
+#[cfg(not(target_arch = "wasm32"))]
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Mutex;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Duration, Instant};
+
+/// State of a backend's circuit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Requests go through
+    Closed,
+    /// Requests skip the backend until the cooldown ends
+    Open,
+    /// The cooldown ended; the next request decides whether the circuit closes
+    HalfOpen,
+}
+
+/// Health of one backend as last seen by the processor
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BackendHealth {
+    pub backend: String,
+    /// The circuit is not open
+    pub healthy: bool,
+    pub circuit: CircuitState,
+    pub consecutive_failures: u32,
+    /// Last error, while failures are counted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Seconds until the open circuit lets a request through again
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_in_secs: Option<u64>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Default)]
+struct Circuit {
+    failures: u32,
+    error: Option<String>,
+    opened: Option<Instant>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Circuits of all backends, shared by a processor and its clones
+#[derive(Debug)]
+pub(crate) struct HealthTracker {
+    threshold: u32,
+    cooldown: Duration,
+    circuits: Mutex<HashMap<String, Circuit>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl HealthTracker {
+    /// A `threshold` of 0 keeps every circuit closed
+    pub(crate) fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            circuits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Count a failed request; returns whether the circuit opened
+    pub(crate) fn record_failure(&self, backend: &str, error: &str) -> bool {
+        self.fail(backend, error, false)
+    }
+
+    /// Open the circuit after a failed health check; returns whether it opened
+    pub(crate) fn mark_down(&self, backend: &str, error: &str) -> bool {
+        self.fail(backend, error, true)
+    }
+
+    fn fail(&self, backend: &str, error: &str, open: bool) -> bool {
+        if self.threshold == 0 {
+            return false;
+        }
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = circuits.entry(backend.to_string()).or_default();
+        circuit.failures += 1;
+        circuit.error = Some(error.to_string());
+        let was_open = circuit
+            .opened
+            .is_some_and(|at| at.elapsed() < self.cooldown);
+        if (open || circuit.failures >= self.threshold) && !was_open {
+            circuit.opened = Some(Instant::now());
+            return true;
+        }
+        false
+    }
+
+    /// Reset the failure count; returns whether an open or half-open circuit closed
+    pub(crate) fn record_success(&self, backend: &str) -> bool {
+        let mut circuits = self.circuits.lock().unwrap();
+        circuits
+            .remove(backend)
+            .is_some_and(|circuit| circuit.opened.is_some())
+    }
+
+    /// Whether requests should skip `backend`
+    pub(crate) fn is_open(&self, backend: &str) -> bool {
+        self.status(backend).circuit == CircuitState::Open
+    }
+
+    pub(crate) fn status(&self, backend: &str) -> BackendHealth {
+        let circuits = self.circuits.lock().unwrap();
+        let circuit = circuits.get(backend);
+        let remaining = circuit
+            .and_then(|c| c.opened)
+            .map(|at| self.cooldown.saturating_sub(at.elapsed()));
+        let state = match remaining {
+            Some(remaining) if !remaining.is_zero() => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+            None => CircuitState::Closed,
+        };
+        BackendHealth {
+            backend: backend.to_string(),
+            healthy: state != CircuitState::Open,
+            circuit: state,
+            consecutive_failures: circuit.map_or(0, |c| c.failures),
+            error: circuit.and_then(|c| c.error.clone()),
+            retry_in_secs: remaining
+                .filter(|_| state == CircuitState::Open)
+                .map(|r| r.as_secs()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circuit_opens_after_threshold() {
+        let tracker = HealthTracker::new(2, Duration::from_secs(60));
+        assert!(!tracker.record_failure("edge", "timeout"));
+        assert!(!tracker.is_open("edge"));
+        assert!(tracker.record_failure("edge", "connection refused"));
+        assert!(tracker.is_open("edge"));
+        assert!(!tracker.record_failure("edge", "still down"));
+        assert!(!tracker.is_open("google"));
+
+        let status = tracker.status("edge");
+        assert_eq!(status.consecutive_failures, 3);
+        assert_eq!(status.error.as_deref(), Some("still down"));
+        assert!(status.retry_in_secs.is_some_and(|secs| secs <= 60));
+        assert!(tracker.record_success("edge"));
+        assert_eq!(tracker.status("edge").circuit, CircuitState::Closed);
+
+        assert!(tracker.mark_down("google", "gtts-cli missing"));
+        assert!(tracker.is_open("google"));
+        assert!(!HealthTracker::new(0, Duration::from_secs(60)).mark_down("edge", "x"));
+    }
+
+    #[test]
+    fn test_half_open_after_cooldown() {
+        let tracker = HealthTracker::new(1, Duration::ZERO);
+        assert!(tracker.record_failure("edge", "timeout"));
+        assert_eq!(tracker.status("edge").circuit, CircuitState::HalfOpen);
+        // A failure in the half-open state opens the circuit again
+        assert!(tracker.record_failure("edge", "timeout"));
+        assert!(tracker.record_success("edge"));
+        assert!(!tracker.record_success("edge"));
+    }
+}
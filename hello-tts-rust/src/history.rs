@@ -0,0 +1,241 @@
+//! Synthesis history in a local SQLite database.
+//!
+//! With the `history` feature, [`TTSProcessor`](crate::TTSProcessor) records
+//! every text it synthesizes with `synthesize_and_play` or `batch::synthesize`
+//! in `TTSConfig.history_file` (by default [`HISTORY_FILE`] in the output
+//! directory): when, a hash and a copy of the text, voice, backend, audio and
+//! synthesis durations, and where the audio was saved. The CLI's `history`
+//! subcommand lists and searches the entries and replays one from its saved
+//! file, or synthesizes it again when the file is gone.
+
+use crate::checksums::sha256_hex;
+use crate::config::TTSConfig;
+use crate::models::TTSError;
+use rusqlite::{params, Connection, Params, Row};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Default name of the history database in the output directory
+pub const HISTORY_FILE: &str = ".hello-tts-history.sqlite";
+
+/// One recorded synthesis
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistoryEntry {
+    pub id: i64,
+    /// Local time, RFC 3339
+    pub created_at: String,
+    pub text: String,
+    /// SHA-256 of the text
+    pub text_hash: String,
+    pub voice: String,
+    pub backend: String,
+    /// Length of the audio, when it could be decoded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// Time spent synthesizing
+    pub elapsed_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_path: Option<PathBuf>,
+}
+
+/// A synthesis to record
+#[derive(Debug, Clone, Copy)]
+pub struct NewEntry<'a> {
+    pub text: &'a str,
+    pub voice: &'a str,
+    pub backend: &'a str,
+    pub duration_ms: Option<u64>,
+    pub elapsed_ms: u64,
+    pub output_path: Option<&'a Path>,
+}
+
+/// An open history database
+pub struct History {
+    conn: Connection,
+}
+
+impl History {
+    /// Open or create the database at `path`
+    pub fn open(path: &Path) -> Result<Self, TTSError> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path).map_err(sql_error)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                created_at TEXT NOT NULL,
+                text TEXT NOT NULL,
+                text_hash TEXT NOT NULL,
+                voice TEXT NOT NULL,
+                backend TEXT NOT NULL,
+                duration_ms INTEGER,
+                elapsed_ms INTEGER NOT NULL,
+                output_path TEXT
+            );
+            CREATE INDEX IF NOT EXISTS history_text_hash ON history (text_hash);",
+        )
+        .map_err(sql_error)?;
+        Ok(Self { conn })
+    }
+
+    /// The database configured by `TTSConfig.history_file`
+    pub fn from_config(config: &TTSConfig) -> Result<Self, TTSError> {
+        Self::open(&Self::path(config))
+    }
+
+    /// `TTSConfig.history_file`, or [`HISTORY_FILE`] in the output directory
+    pub fn path(config: &TTSConfig) -> PathBuf {
+        config
+            .history_file
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| Path::new(&config.output_directory).join(HISTORY_FILE))
+    }
+
+    /// Record a synthesis and return its id
+    pub fn record(&self, entry: &NewEntry) -> Result<i64, TTSError> {
+        self.conn
+            .execute(
+                "INSERT INTO history (created_at, text, text_hash, voice, backend,
+                    duration_ms, elapsed_ms, output_path)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    chrono::Local::now().to_rfc3339(),
+                    entry.text,
+                    sha256_hex(entry.text.as_bytes()),
+                    entry.voice,
+                    entry.backend,
+                    entry.duration_ms,
+                    entry.elapsed_ms,
+                    entry.output_path.map(|p| p.to_string_lossy().into_owned()),
+                ],
+            )
+            .map_err(sql_error)?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// The newest `limit` entries, newest first
+    pub fn list(&self, limit: usize) -> Result<Vec<HistoryEntry>, TTSError> {
+        self.query("1 = 1", [], limit)
+    }
+
+    /// The newest `limit` entries whose text or voice contains `query`, ignoring case
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<HistoryEntry>, TTSError> {
+        let pattern = format!(
+            "%{}%",
+            query
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        self.query(
+            "text LIKE ?1 ESCAPE '\\' OR voice LIKE ?1 ESCAPE '\\'",
+            [pattern],
+            limit,
+        )
+    }
+
+    pub fn get(&self, id: i64) -> Result<Option<HistoryEntry>, TTSError> {
+        Ok(self.query("id = ?1", [id], 1)?.pop())
+    }
+
+    /// The newest entry for `text` and `voice` whose audio file still exists
+    pub fn find_cached(&self, text: &str, voice: &str) -> Result<Option<HistoryEntry>, TTSError> {
+        let entries = self.query(
+            "text_hash = ?1 AND voice = ?2 AND output_path IS NOT NULL",
+            [sha256_hex(text.as_bytes()), voice.to_string()],
+            100,
+        )?;
+        Ok(entries.into_iter().find(|entry| {
+            entry.text == text && entry.output_path.as_ref().is_some_and(|path| path.exists())
+        }))
+    }
+
+    fn query<P: Params>(
+        &self,
+        filter: &str,
+        params: P,
+        limit: usize,
+    ) -> Result<Vec<HistoryEntry>, TTSError> {
+        let sql = format!(
+            "SELECT id, created_at, text, text_hash, voice, backend, duration_ms, elapsed_ms,
+                output_path
+             FROM history WHERE {} ORDER BY id DESC LIMIT {}",
+            filter, limit
+        );
+        let mut statement = self.conn.prepare(&sql).map_err(sql_error)?;
+        let rows = statement
+            .query_map(params, entry_from_row)
+            .map_err(sql_error)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(sql_error)
+    }
+}
+
+fn entry_from_row(row: &Row) -> rusqlite::Result<HistoryEntry> {
+    Ok(HistoryEntry {
+        id: row.get(0)?,
+        created_at: row.get(1)?,
+        text: row.get(2)?,
+        text_hash: row.get(3)?,
+        voice: row.get(4)?,
+        backend: row.get(5)?,
+        duration_ms: row.get(6)?,
+        elapsed_ms: row.get(7)?,
+        output_path: row.get::<_, Option<String>>(8)?.map(PathBuf::from),
+    })
+}
+
+fn sql_error(e: rusqlite::Error) -> TTSError {
+    TTSError::Io(std::io::Error::other(format!("History database: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_list_search() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-history-{}", uuid::Uuid::new_v4()));
+        let history = History::open(&dir.join("history.sqlite")).unwrap();
+        let entry = |text, voice| NewEntry {
+            text,
+            voice,
+            backend: "mock",
+            duration_ms: Some(1200),
+            elapsed_ms: 30,
+            output_path: None,
+        };
+        history
+            .record(&entry("Hello world", "en-US-AriaNeural"))
+            .unwrap();
+        history.record(&entry("100% sure", "fr")).unwrap();
+        let id = history.record(&entry("Bonjour", "fr")).unwrap();
+
+        let entries = history.list(10).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].id, id);
+        assert_eq!(entries[0].text_hash, sha256_hex(b"Bonjour"));
+        assert_eq!(history.search("hello", 10).unwrap()[0].text, "Hello world");
+        assert_eq!(history.search("fr", 10).unwrap().len(), 2);
+        assert_eq!(history.search("%", 10).unwrap()[0].text, "100% sure");
+        assert_eq!(history.get(id).unwrap().unwrap().duration_ms, Some(1200));
+        assert!(history.get(id + 1).unwrap().is_none());
+
+        let audio = dir.join("bonjour.mp3");
+        assert!(history.find_cached("Bonjour", "fr").unwrap().is_none());
+        std::fs::write(&audio, b"audio").unwrap();
+        let cached = history
+            .record(&NewEntry {
+                output_path: Some(&audio),
+                ..entry("Bonjour", "fr")
+            })
+            .unwrap();
+        assert_eq!(
+            history.find_cached("Bonjour", "fr").unwrap().unwrap().id,
+            cached
+        );
+        assert!(history.find_cached("Bonjour", "fr-CA").unwrap().is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+//! User-defined commands and HTTP calls run when work completes.
+//!
+//! The `[hooks]` section of the config lists hooks per event:
+//!
+//! ```toml
+//! [hooks]
+//! on_synthesis_complete = ["ffmpeg -y -i {output_path} {output_path}.ogg"]
+//! on_batch_complete = ["https://ci.example.com/tts?dir={output_dir}"]
+//! ```
+//!
+//! A hook starting with `http://` or `https://` is a URL that receives the
+//! variables as a JSON object in a `POST`; anything else is a shell command.
+//! `{name}` placeholders are replaced by the variables, shell-quoted in
+//! commands and percent-encoded in URLs, and commands also find them in
+//! `HELLO_TTS_<NAME>` environment variables. A failing hook is logged and
+//! does not fail the work it reports on.
+//!
+//! `on_synthesis_complete` runs for every text synthesized by
+//! [`synthesize_and_play`](crate::TTSProcessor::synthesize_and_play) and for
+//! every batch item, with `output_path`, `voice`, `backend`, `duration`
+//! (seconds), `duration_ms` and `bytes`. `on_batch_complete` runs when a
+//! batch ends, with `output_dir`, `voice`, `duration`, `synthesized`,
+//! `completed`, `failed` and `pending`. Unknown values are empty.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Time a hook may take before it is given up on
+pub const HOOK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Hooks per event
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub on_synthesis_complete: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub on_batch_complete: Vec<String>,
+}
+
+impl HooksConfig {
+    pub fn is_empty(&self) -> bool {
+        self.on_synthesis_complete.is_empty() && self.on_batch_complete.is_empty()
+    }
+}
+
+/// Variables of one event, in the order they are documented
+pub type Vars = Vec<(&'static str, String)>;
+
+/// `template` with each `{name}` replaced by `escape(value)`
+pub fn render(template: &str, vars: &Vars, escape: impl Fn(&str) -> String) -> String {
+    let mut out = template.to_string();
+    for (name, value) in vars {
+        out = out.replace(&format!("{{{}}}", name), &escape(value));
+    }
+    out
+}
+
+/// Run `hooks` one after the other with `vars`
+pub async fn run(event: &str, hooks: &[String], vars: &Vars) {
+    for hook in hooks {
+        debug!("Running {} hook {}", event, hook);
+        let result = if hook.starts_with("http://") || hook.starts_with("https://") {
+            call(hook, vars).await
+        } else {
+            execute(hook, vars).await
+        };
+        if let Err(e) = result {
+            warn!("{} hook {:?} failed: {}", event, hook, e);
+        }
+    }
+}
+
+async fn call(url: &str, vars: &Vars) -> Result<(), String> {
+    let url = render(url, vars, percent_encode);
+    let body: serde_json::Map<String, serde_json::Value> = vars
+        .iter()
+        .map(|(name, value)| (name.to_string(), serde_json::Value::from(value.as_str())))
+        .collect();
+    reqwest::Client::new()
+        .post(&url)
+        .json(&body)
+        .timeout(HOOK_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn execute(command: &str, vars: &Vars) -> Result<(), String> {
+    let line = render(command, vars, shell_quote);
+    let mut command = crate::sync::shell(&line);
+    for (name, value) in vars {
+        command.env(format!("HELLO_TTS_{}", name.to_ascii_uppercase()), value);
+    }
+    command
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true);
+    let output = tokio::time::timeout(HOOK_TIMEOUT, command.output())
+        .await
+        .map_err(|_| format!("timed out after {:?}", HOOK_TIMEOUT))?
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("{} {}", output.status, stderr.trim()));
+    }
+    Ok(())
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn execute(_command: &str, _vars: &Vars) -> Result<(), String> {
+    Err("command hooks cannot run in the browser".to_string())
+}
+
+/// Single-quoted for POSIX shells, double-quoted for `cmd`
+fn shell_quote(value: &str) -> String {
+    if cfg!(windows) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let vars: Vars = vec![
+            ("output_path", "out/it's here.mp3".to_string()),
+            ("voice", "en-US-AriaNeural".to_string()),
+        ];
+        assert_eq!(
+            render("play {output_path} # {voice}", &vars, shell_quote),
+            r"play 'out/it'\''s here.mp3' # 'en-US-AriaNeural'"
+        );
+        assert_eq!(
+            render(
+                "https://h/done?f={output_path}&x={other}",
+                &vars,
+                percent_encode
+            ),
+            "https://h/done?f=out/it%27s%20here.mp3&x={other}"
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_command_hook() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-hook-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let marker = dir.join("voice.txt");
+        let vars: Vars = vec![
+            ("voice", "en-GB-Sonia".to_string()),
+            ("output_path", marker.to_string_lossy().into_owned()),
+        ];
+        let hooks = [
+            "exit 1".to_string(),
+            "printf %s \"$HELLO_TTS_VOICE\" > {output_path}".to_string(),
+        ];
+        run("on_synthesis_complete", &hooks, &vars).await;
+        assert_eq!(std::fs::read_to_string(&marker).unwrap(), "en-GB-Sonia");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,417 @@
+//! Persistent queue of asynchronous server jobs (`server` feature).
+//!
+//! `POST /synthesize?async=true` stores its request in a SQLite database,
+//! `TTSConfig.jobs_file` (by default [`JOBS_FILE`] in the output directory),
+//! so queued jobs survive a restart. Jobs run highest priority first, then in
+//! the order they were queued. A job that fails with a retryable error (see
+//! [`ErrorKind::is_retryable`]) is queued again after a growing delay until
+//! its [`RetryPolicy`] runs out of attempts. Jobs found running at startup
+//! were interrupted and are queued again.
+
+use crate::config::TTSConfig;
+use crate::models::{ErrorKind, TTSError};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Default name of the job database in the output directory
+pub const JOBS_FILE: &str = ".hello-tts-jobs.sqlite";
+/// Finished jobs kept in the database; older ones are deleted
+pub const MAX_FINISHED_JOBS: usize = 1000;
+
+/// Where a job is in its life
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+impl JobStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Running => "running",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        match s {
+            "running" => Self::Running,
+            "completed" => Self::Completed,
+            "failed" => Self::Failed,
+            _ => Self::Queued,
+        }
+    }
+}
+
+/// How often a job failing with a retryable error is tried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts in total, including the first
+    pub max_attempts: u32,
+    /// Delay before the second attempt; it doubles for every later one
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay after failed attempt number `attempt` (one-based)
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.backoff * 2u32.saturating_pow(attempt.saturating_sub(1).min(16))
+    }
+}
+
+/// A queued request and what became of it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Job {
+    pub id: String,
+    pub status: JobStatus,
+    /// Higher priorities run first
+    pub priority: i32,
+    /// Attempts started so far
+    pub attempts: u32,
+    /// Local time, RFC 3339
+    pub created_at: String,
+    pub updated_at: String,
+    /// The queued request
+    #[serde(skip)]
+    pub request: serde_json::Value,
+    /// Fields of the result once the job completed
+    #[serde(flatten)]
+    pub result: serde_json::Map<String, serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ErrorKind>,
+}
+
+/// An open job database
+pub struct JobQueue {
+    conn: Mutex<Connection>,
+    retry: RetryPolicy,
+}
+
+impl JobQueue {
+    /// Open or create the database at `path` and queue interrupted jobs again
+    pub fn open(path: &Path, retry: RetryPolicy) -> Result<Self, TTSError> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path).map_err(sql_error)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS jobs (
+                seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                id TEXT NOT NULL UNIQUE,
+                status TEXT NOT NULL,
+                priority INTEGER NOT NULL,
+                attempts INTEGER NOT NULL,
+                run_after INTEGER NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                request TEXT NOT NULL,
+                result TEXT,
+                error TEXT,
+                kind TEXT
+            );
+            CREATE INDEX IF NOT EXISTS jobs_queue ON jobs (status, priority, seq);
+            UPDATE jobs SET status = 'queued', run_after = 0
+                WHERE status IN ('queued', 'running');",
+        )
+        .map_err(sql_error)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+            retry,
+        })
+    }
+
+    /// The database configured by `TTSConfig.jobs_file`
+    pub fn from_config(config: &TTSConfig) -> Result<Self, TTSError> {
+        Self::open(&Self::path(config), RetryPolicy::default())
+    }
+
+    /// `TTSConfig.jobs_file`, or [`JOBS_FILE`] in the output directory
+    pub fn path(config: &TTSConfig) -> PathBuf {
+        config
+            .jobs_file
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| Path::new(&config.output_directory).join(JOBS_FILE))
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry
+    }
+
+    /// Queue `request` and return its job
+    pub fn push(&self, request: &serde_json::Value, priority: i32) -> Result<Job, TTSError> {
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = chrono::Local::now().to_rfc3339();
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO jobs (id, status, priority, attempts, run_after, created_at,
+                    updated_at, request)
+                 VALUES (?1, 'queued', ?2, 0, 0, ?3, ?3, ?4)",
+                params![id, priority, now, request.to_string()],
+            )
+            .map_err(sql_error)?;
+        self.require(&id)
+    }
+
+    /// Mark the next job that is due as running and return it
+    pub fn claim(&self) -> Result<Option<Job>, TTSError> {
+        let conn = self.conn.lock().unwrap();
+        let id: Option<String> = conn
+            .query_row(
+                "SELECT id FROM jobs WHERE status = 'queued' AND run_after <= ?1
+                 ORDER BY priority DESC, seq LIMIT 1",
+                [unix_ms()],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sql_error)?;
+        let Some(id) = id else { return Ok(None) };
+        conn.execute(
+            "UPDATE jobs SET status = 'running', attempts = attempts + 1, updated_at = ?2
+             WHERE id = ?1",
+            params![id, chrono::Local::now().to_rfc3339()],
+        )
+        .map_err(sql_error)?;
+        drop(conn);
+        self.get(&id)
+    }
+
+    /// Record the result of a running job
+    pub fn complete(&self, id: &str, result: &serde_json::Value) -> Result<Job, TTSError> {
+        self.finish(
+            id,
+            JobStatus::Completed,
+            Some(result.to_string()),
+            None,
+            None,
+            0,
+        )?;
+        self.require(id)
+    }
+
+    /// Record a failed attempt; a retryable error with attempts left queues
+    /// the job again after [`RetryPolicy::delay`]
+    pub fn fail(&self, id: &str, error: &str, kind: ErrorKind) -> Result<Job, TTSError> {
+        let job = self.require(id)?;
+        let kind_name = serde_json::to_value(kind)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string));
+        if kind.is_retryable() && job.attempts < self.retry.max_attempts {
+            let run_after = unix_ms() + self.retry.delay(job.attempts).as_millis() as i64;
+            self.finish(
+                id,
+                JobStatus::Queued,
+                None,
+                Some(error),
+                kind_name,
+                run_after,
+            )?;
+        } else {
+            self.finish(id, JobStatus::Failed, None, Some(error), kind_name, 0)?;
+        }
+        self.require(id)
+    }
+
+    fn finish(
+        &self,
+        id: &str,
+        status: JobStatus,
+        result: Option<String>,
+        error: Option<&str>,
+        kind: Option<String>,
+        run_after: i64,
+    ) -> Result<(), TTSError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE jobs SET status = ?2, result = ?3, error = ?4, kind = ?5, run_after = ?6,
+                updated_at = ?7
+             WHERE id = ?1",
+            params![
+                id,
+                status.as_str(),
+                result,
+                error,
+                kind,
+                run_after,
+                chrono::Local::now().to_rfc3339()
+            ],
+        )
+        .map_err(sql_error)?;
+        conn.execute(
+            "DELETE FROM jobs WHERE status IN ('completed', 'failed') AND seq NOT IN (
+                SELECT seq FROM jobs WHERE status IN ('completed', 'failed')
+                ORDER BY seq DESC LIMIT ?1)",
+            [MAX_FINISHED_JOBS as i64],
+        )
+        .map_err(sql_error)?;
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<Job>, TTSError> {
+        Ok(self.query("id = ?1", [id], 1)?.pop())
+    }
+
+    fn require(&self, id: &str) -> Result<Job, TTSError> {
+        self.get(id)?
+            .ok_or_else(|| TTSError::Config(format!("No job {}", id)))
+    }
+
+    /// The newest `limit` jobs, optionally only those with `status`
+    pub fn list(&self, status: Option<JobStatus>, limit: usize) -> Result<Vec<Job>, TTSError> {
+        match status {
+            Some(status) => self.query("status = ?1", [status.as_str()], limit),
+            None => self.query("1 = 1", [], limit),
+        }
+    }
+
+    /// Jobs waiting to run
+    pub fn queued(&self) -> Result<usize, TTSError> {
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT COUNT(*) FROM jobs WHERE status = 'queued'",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|count| count as usize)
+            .map_err(sql_error)
+    }
+
+    fn query(
+        &self,
+        filter: &str,
+        params: impl rusqlite::Params,
+        limit: usize,
+    ) -> Result<Vec<Job>, TTSError> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn
+            .prepare(&format!(
+                "SELECT id, status, priority, attempts, created_at, updated_at, request, result,
+                    error, kind
+                 FROM jobs WHERE {} ORDER BY seq DESC LIMIT {}",
+                filter, limit
+            ))
+            .map_err(sql_error)?;
+        let jobs = statement
+            .query_map(params, job_from_row)
+            .map_err(sql_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(sql_error)?;
+        Ok(jobs)
+    }
+}
+
+fn job_from_row(row: &Row) -> rusqlite::Result<Job> {
+    let json =
+        |text: Option<String>| -> Option<serde_json::Value> { serde_json::from_str(&text?).ok() };
+    let result = match json(row.get(7)?) {
+        Some(serde_json::Value::Object(fields)) => fields,
+        _ => serde_json::Map::new(),
+    };
+    Ok(Job {
+        id: row.get(0)?,
+        status: JobStatus::parse(&row.get::<_, String>(1)?),
+        priority: row.get(2)?,
+        attempts: row.get(3)?,
+        created_at: row.get(4)?,
+        updated_at: row.get(5)?,
+        request: json(row.get(6)?).unwrap_or_default(),
+        result,
+        error: row.get(8)?,
+        kind: row
+            .get::<_, Option<String>>(9)?
+            .and_then(|kind| serde_json::from_value(serde_json::Value::String(kind)).ok()),
+    })
+}
+
+fn unix_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+fn sql_error(e: rusqlite::Error) -> TTSError {
+    TTSError::Io(std::io::Error::other(format!("Job database: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_queue(retry: RetryPolicy) -> (JobQueue, PathBuf) {
+        let path =
+            std::env::temp_dir().join(format!("hello-tts-jobs-{}.sqlite", uuid::Uuid::new_v4()));
+        (JobQueue::open(&path, retry).unwrap(), path)
+    }
+
+    #[test]
+    fn test_priority_retry_and_restart() {
+        let retry = RetryPolicy {
+            max_attempts: 2,
+            backoff: Duration::ZERO,
+        };
+        let (queue, path) = temp_queue(retry);
+        let low = queue.push(&serde_json::json!({"text": "low"}), 0).unwrap();
+        let high = queue.push(&serde_json::json!({"text": "high"}), 5).unwrap();
+
+        let job = queue.claim().unwrap().unwrap();
+        assert_eq!((job.id.as_str(), job.attempts), (high.id.as_str(), 1));
+        assert_eq!(job.request["text"], "high");
+        let job = queue.fail(&job.id, "busy", ErrorKind::Unavailable).unwrap();
+        assert_eq!(job.status, JobStatus::Queued);
+        let job = queue.claim().unwrap().unwrap();
+        let job = queue.fail(&job.id, "busy", ErrorKind::Unavailable).unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(job.kind, Some(ErrorKind::Unavailable));
+
+        let job = queue.claim().unwrap().unwrap();
+        assert_eq!(job.id, low.id);
+        assert!(queue.claim().unwrap().is_none());
+        drop(queue);
+
+        // The running job was interrupted
+        let queue = JobQueue::open(&path, retry).unwrap();
+        assert_eq!(queue.queued().unwrap(), 1);
+        let job = queue.claim().unwrap().unwrap();
+        let job = queue
+            .complete(&job.id, &serde_json::json!({"url": "/audio/a.mp3"}))
+            .unwrap();
+        assert_eq!(job.attempts, 2);
+        let json = serde_json::to_value(&job).unwrap();
+        assert_eq!(json["status"], "completed");
+        assert_eq!(json["url"], "/audio/a.mp3");
+        assert_eq!(queue.list(Some(JobStatus::Failed), 10).unwrap().len(), 1);
+        assert_eq!(queue.list(None, 10).unwrap()[0].id, high.id);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_retry_delay_doubles() {
+        let retry = RetryPolicy::default();
+        assert_eq!(retry.delay(1), Duration::from_secs(5));
+        assert_eq!(retry.delay(3), Duration::from_secs(20));
+    }
+}
@@ -3,22 +3,176 @@
 //! This crate provides a Rust client for both Microsoft Edge TTS and Google TTS services,
 //! demonstrating text-to-speech functionality with audio playback capabilities.
 
+pub mod alignment;
+pub mod analysis;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod anki;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod assets;
+pub mod audio;
 pub mod audio_player;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod audiobook;
 pub mod backends;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod batch;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod bench;
+pub mod catalog;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod checksums;
+pub mod clipboard;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod clock;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod compare;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod config;
+pub mod convert;
+#[cfg(all(feature = "hotkey", not(target_arch = "wasm32")))]
+pub mod daemon;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod dialogue;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod doctor;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod document;
+pub mod events;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod flashcards;
+pub mod health;
+#[cfg(feature = "history")]
+pub mod history;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod hooks;
+#[cfg(feature = "server")]
+pub mod jobs;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod localization;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod logging;
+#[cfg(all(feature = "mail", not(target_arch = "wasm32")))]
+pub mod mail;
+pub mod metrics;
 pub mod models;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod naming;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod narration;
+pub mod notifications;
+#[cfg(all(feature = "opus", not(target_arch = "wasm32")))]
+pub mod opus;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod output;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod output_target;
+pub mod pdf;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod podcast;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod quota;
+pub mod rate_limit;
+pub mod readability;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod run_report;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod sources;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod sweep;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod sync;
+#[cfg(feature = "otel")]
+pub mod telemetry;
+pub mod text;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod tts_client;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod usage;
+pub mod validation;
+pub mod voice_ref;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod voice_remap;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod watch;
+#[cfg(feature = "wasm")]
+pub mod web;
 
-pub use audio_player::{AudioError, AudioPlayer};
-pub use config::{TTSConfig, TTSConfigFile};
-pub use models::{TTSError, Voice};
+pub use analysis::{AudioStats, LoudnessPreset};
+#[cfg(not(target_arch = "wasm32"))]
+pub use anki::{AnkiExport, AnkiOptions};
+#[cfg(not(target_arch = "wasm32"))]
+pub use assets::{AssetExport, AssetOptions, Engine};
+#[cfg(not(target_arch = "wasm32"))]
+pub use audio_player::AudioPlayer;
+pub use audio_player::{
+    AudioError, AudioSink, CallbackSink, FileSink, NullAudioPlayer, StdoutSink,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use audiobook::{AudiobookLayout, AudiobookOptions};
+#[cfg(not(target_arch = "wasm32"))]
+pub use batch::{BatchManifest, BatchOptions, BatchReport};
+#[cfg(not(target_arch = "wasm32"))]
+pub use bench::{BenchOptions, BenchReport, BenchTarget};
+pub use catalog::{CatalogFormat, GenderPreference, VoiceCatalog};
+#[cfg(not(target_arch = "wasm32"))]
+pub use checksums::ChecksumManifest;
+#[cfg(not(target_arch = "wasm32"))]
+pub use compare::{CompareOptions, Comparison};
+#[cfg(not(target_arch = "wasm32"))]
+pub use config::{ConfigManager, TTSConfig, TTSConfigFile};
+#[cfg(not(target_arch = "wasm32"))]
+pub use dialogue::{Dialogue, DialogueOptions};
+#[cfg(not(target_arch = "wasm32"))]
+pub use document::{Document, DocumentOptions};
+pub use events::{EventHandler, TTSEvent};
+#[cfg(not(target_arch = "wasm32"))]
+pub use flashcards::{Flashcard, FlashcardOptions};
+pub use health::BackendHealth;
+#[cfg(not(target_arch = "wasm32"))]
+pub use localization::{SheetOptions, SheetReport};
+pub use metrics::MetricsSnapshot;
+pub use models::{
+    ErrorKind, SynthesisOptions, SynthesisResult, SynthesizedAudio, TTSError, Viseme, Voice,
+    WordBoundary,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use narration::NarrationOptions;
+#[cfg(not(target_arch = "wasm32"))]
+pub use output::OutputManager;
+#[cfg(not(target_arch = "wasm32"))]
+pub use podcast::{Feed, PodcastOptions};
+pub use rate_limit::RateLimiter;
+#[cfg(not(target_arch = "wasm32"))]
+pub use sources::TextSource;
+#[cfg(not(target_arch = "wasm32"))]
+pub use sweep::SweepOptions;
+pub use text::InputFormat;
+pub use tokio_util::sync::CancellationToken;
+#[cfg(not(target_arch = "wasm32"))]
 pub use tts_client::TTSProcessor;
+#[cfg(not(target_arch = "wasm32"))]
+pub use usage::Usage;
+pub use validation::{ValidationOptions, ValidationProblem, ValidationReport};
+pub use voice_ref::VoiceRef;
 
 /// Re-export commonly used types
 pub mod prelude {
+    pub use crate::backends::TTSBackend;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub use crate::sources::TextSource;
+    #[cfg(not(target_arch = "wasm32"))]
     pub use crate::{
-        AudioError, AudioPlayer,
-        TTSProcessor, TTSConfig, TTSError, Voice, TTSConfigFile,
+        AnkiExport, AnkiOptions, AssetExport, AssetOptions, AudioPlayer, AudiobookOptions,
+        BatchManifest, BatchOptions, BatchReport, BenchOptions, BenchReport, BenchTarget,
+        CompareOptions, Comparison, ConfigManager, Dialogue, DialogueOptions, Document,
+        DocumentOptions, Engine, Feed, Flashcard, FlashcardOptions, NarrationOptions,
+        OutputManager, PodcastOptions, SheetOptions, SheetReport, SweepOptions, TTSConfig,
+        TTSConfigFile, TTSProcessor,
+    };
+    pub use crate::{
+        AudioError, CancellationToken, ErrorKind, EventHandler, InputFormat, SynthesisResult,
+        TTSError, TTSEvent, ValidationOptions, ValidationReport, Voice, VoiceRef,
     };
-    pub use crate::backends::TTSBackend;
 }
@@ -0,0 +1,336 @@
+//! Audio assets from localization spreadsheets.
+//!
+//! [`render_sheet`] reads a CSV or TSV file with a header row, speaks the text
+//! column of every row with the voice from the voice column (or a default
+//! voice) and names each file after the id column. Unknown voices and empty
+//! texts are reported before anything is synthesized; rows with problems are
+//! skipped and listed in `summary.csv` in the output directory together with
+//! every rendered file.
+
+use crate::batch::{self, BatchOptions};
+use crate::batch::{BatchItem, BatchManifest, BatchReport, ItemStatus};
+use crate::flashcards::{detect_delimiter, split_row};
+use crate::models::TTSError;
+use crate::tts_client::TTSProcessor;
+use serde::Serialize;
+use std::path::PathBuf;
+use tokio::fs;
+use tracing::warn;
+
+/// Name of the row summary written to the output directory
+pub const SUMMARY_FILE: &str = "summary.csv";
+
+/// Which columns of a sheet to use, by header name
+#[derive(Debug, Clone)]
+pub struct SheetOptions {
+    pub text_column: String,
+    /// Column with the voice of each row; rows without one use the default voice
+    pub voice_column: Option<String>,
+    /// Column with a key naming each row's file, e.g. `menu.start` -> `menu_start.mp3`
+    pub id_column: Option<String>,
+    /// Directory for the audio and the summary; defaults to
+    /// `TTSConfig.output_directory`
+    pub output_dir: Option<PathBuf>,
+    /// Skip rows rendered by an earlier run
+    pub resume: bool,
+}
+
+impl Default for SheetOptions {
+    fn default() -> Self {
+        Self {
+            text_column: "text".to_string(),
+            voice_column: None,
+            id_column: None,
+            output_dir: None,
+            resume: false,
+        }
+    }
+}
+
+/// One data row of a sheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct SheetRow {
+    /// Line number in the file, counting from 1
+    pub line: usize,
+    pub item: BatchItem,
+}
+
+/// Outcome for one row
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RowResult {
+    pub line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub voice: String,
+    /// Audio file relative to the output directory, empty if none was made
+    pub file: String,
+    /// `ok`, `skipped`, `failed` or `pending`
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of [`render_sheet`]
+#[derive(Debug, Clone, Serialize)]
+pub struct SheetReport {
+    pub rows: Vec<RowResult>,
+    pub summary: PathBuf,
+    pub batch: BatchReport,
+}
+
+impl SheetReport {
+    pub fn failed(&self) -> usize {
+        self.rows.iter().filter(|r| r.status != "ok").count()
+    }
+
+    /// `line,id,voice,file,status,error` with one row per sheet row
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("line,id,voice,file,status,error\n");
+        for row in &self.rows {
+            let fields = [
+                row.line.to_string(),
+                row.id.clone().unwrap_or_default(),
+                row.voice.clone(),
+                row.file.clone(),
+                row.status.to_string(),
+                row.error.clone().unwrap_or_default(),
+            ];
+            let fields: Vec<String> = fields.iter().map(|f| quote(f)).collect();
+            out.push_str(&fields.join(","));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Read the data rows of a CSV/TSV sheet whose first non-empty line is a header
+pub fn parse_sheet(input: &str, options: &SheetOptions) -> Result<Vec<SheetRow>, TTSError> {
+    let mut lines = input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    let (_, header) = lines
+        .next()
+        .ok_or_else(|| TTSError::Config("The sheet is empty".to_string()))?;
+    let delimiter = detect_delimiter(header);
+    let header = split_row(header, delimiter);
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(name))
+            .ok_or_else(|| TTSError::Config(format!("The sheet has no {:?} column", name)))
+    };
+    let text = column(&options.text_column)?;
+    let voice = options.voice_column.as_deref().map(column).transpose()?;
+    let id = options.id_column.as_deref().map(column).transpose()?;
+
+    Ok(lines
+        .map(|(index, line)| {
+            let cells = split_row(line, delimiter);
+            let cell = |column: Option<usize>| {
+                column
+                    .and_then(|c| cells.get(c))
+                    .map(|c| c.trim().to_string())
+                    .filter(|c| !c.is_empty())
+            };
+            SheetRow {
+                line: index + 1,
+                item: BatchItem {
+                    text: cell(Some(text)).unwrap_or_default(),
+                    voice: cell(voice),
+                    id: cell(id),
+                    ..BatchItem::default()
+                },
+            }
+        })
+        .collect())
+}
+
+/// Row results from the problems found up front and the batch of the other rows
+pub(crate) fn row_results(
+    rows: &[SheetRow],
+    problems: &[Option<String>],
+    manifest: &BatchManifest,
+    batch: &BatchReport,
+    default_voice: &str,
+) -> Vec<RowResult> {
+    let mut rendered = manifest.items.iter().zip(&batch.items);
+    rows.iter()
+        .zip(problems)
+        .map(|(row, problem)| {
+            let mut result = RowResult {
+                line: row.line,
+                id: row.item.id.clone(),
+                voice: row.item.voice(default_voice).to_string(),
+                file: String::new(),
+                status: "skipped",
+                error: problem.clone(),
+            };
+            if problem.is_none() {
+                let (_, report) = rendered.next().expect("one batch item per valid row");
+                result.file = report.file.clone();
+                (result.status, result.error) = match &report.status {
+                    ItemStatus::Failed { error } => ("failed", Some(error.clone())),
+                    ItemStatus::Pending => ("pending", None),
+                    _ => ("ok", None),
+                };
+            }
+            result
+        })
+        .collect()
+}
+
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Render every row of a localization sheet to an audio file
+///
+/// Voices are checked against [`TTSProcessor::list_voices`] before
+/// anything is synthesized. Rows with an unknown voice, no text, or a
+/// missing or repeated id are skipped; the others are synthesized with
+/// [`batch::synthesize`]. Every row's outcome is
+/// written to [`SUMMARY_FILE`] in the output directory.
+pub async fn render_sheet(
+    processor: &TTSProcessor,
+    input: &str,
+    voice: &str,
+    options: &SheetOptions,
+) -> Result<SheetReport, TTSError> {
+    let rows = parse_sheet(input, options)?;
+    render_rows(processor, &rows, voice, options).await
+}
+
+pub(crate) async fn render_rows(
+    processor: &TTSProcessor,
+    rows: &[SheetRow],
+    voice: &str,
+    options: &SheetOptions,
+) -> Result<SheetReport, TTSError> {
+    if rows.is_empty() {
+        return Err(TTSError::Config("The sheet has no rows".to_string()));
+    }
+    let voices = processor.list_voices().await?;
+    let ext = processor.config().file_extension();
+    let mut files = std::collections::HashSet::new();
+    let problems: Vec<Option<String>> = rows
+        .iter()
+        .enumerate()
+        .map(|(index, row)| {
+            let row_voice = row.item.voice(voice);
+            if row.item.text.is_empty() {
+                Some("No text".to_string())
+            } else if !voices.is_empty()
+                && !voices
+                    .iter()
+                    .any(|v| v.name.eq_ignore_ascii_case(row_voice))
+            {
+                Some(format!("Voice not found: {}", row_voice))
+            } else if options.id_column.is_some() && row.item.id.is_none() {
+                Some("No id".to_string())
+            } else if row.item.id.is_some() && !files.insert(row.item.file_name(index, ext)) {
+                Some("Duplicate id".to_string())
+            } else {
+                None
+            }
+        })
+        .collect();
+    let skipped = problems.iter().flatten().count();
+    if skipped > 0 {
+        warn!("Skipping {} of {} rows with problems", skipped, rows.len());
+    }
+
+    let manifest = BatchManifest {
+        items: rows
+            .iter()
+            .zip(&problems)
+            .filter(|(_, problem)| problem.is_none())
+            .map(|(row, _)| row.item.clone())
+            .collect(),
+    };
+    let batch_options = BatchOptions {
+        output_dir: options.output_dir.clone(),
+        resume: options.resume,
+        upload: None,
+    };
+    let batch = batch::synthesize(processor, &manifest, voice, &batch_options).await?;
+    let report = SheetReport {
+        rows: row_results(rows, &problems, &manifest, &batch, voice),
+        summary: batch.output_dir.join(SUMMARY_FILE),
+        batch,
+    };
+    fs::write(&report.summary, report.to_csv()).await?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::mock::MockBackend;
+    use crate::config::TTSConfig;
+    use crate::models::Voice;
+
+    #[test]
+    fn test_parse_sheet_by_column_name() {
+        let input =
+            "key,msg,voice\nmenu.start,\"Start, now\",en-US-AriaNeural\n\nmenu.quit,Quit,\n";
+        let options = SheetOptions {
+            text_column: "msg".to_string(),
+            voice_column: Some("voice".to_string()),
+            id_column: Some("key".to_string()),
+            ..SheetOptions::default()
+        };
+        let rows = parse_sheet(input, &options).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].item.text, "Start, now");
+        assert_eq!(rows[0].item.voice.as_deref(), Some("en-US-AriaNeural"));
+        assert_eq!((rows[1].line, rows[1].item.voice.as_deref()), (4, None));
+        assert_eq!(rows[1].item.file_name(1, "mp3"), "menu_quit.mp3");
+
+        let missing = SheetOptions {
+            text_column: "english".to_string(),
+            ..SheetOptions::default()
+        };
+        assert!(parse_sheet(input, &missing).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_render_sheet_reports_row_errors() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-sheet-{}", uuid::Uuid::new_v4()));
+        let backend = MockBackend::new().with_voices(vec![Voice::new(
+            "en-US-AriaNeural".to_string(),
+            "Aria".to_string(),
+            "en-US".to_string(),
+            "Female".to_string(),
+        )]);
+        let processor = TTSProcessor::with_backend(TTSConfig::default(), Box::new(backend.clone()));
+        let sheet = "key\tmsg\tvoice\nmenu.start\tStart\t\nmenu.quit\tQuit\tde-DE-Nobody\n\
+                     menu.help\t\t\nmenu.start\tBegin\t\n";
+        let options = SheetOptions {
+            text_column: "msg".to_string(),
+            voice_column: Some("voice".to_string()),
+            id_column: Some("key".to_string()),
+            output_dir: Some(dir.clone()),
+            resume: false,
+        };
+        let report = render_sheet(&processor, sheet, "en-US-AriaNeural", &options)
+            .await
+            .unwrap();
+        assert_eq!(backend.calls().len(), 1);
+        assert_eq!(report.failed(), 3);
+        assert!(dir.join("menu_start.mp3").exists());
+        assert_eq!(
+            std::fs::read_to_string(&report.summary).unwrap(),
+            "line,id,voice,file,status,error\n\
+             2,menu.start,en-US-AriaNeural,menu_start.mp3,ok,\n\
+             3,menu.quit,de-DE-Nobody,,skipped,Voice not found: de-DE-Nobody\n\
+             4,menu.help,en-US-AriaNeural,,skipped,No text\n\
+             5,menu.start,en-US-AriaNeural,,skipped,Duplicate id\n"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,171 @@
+//! Per-run log files.
+//!
+//! With `TTSConfig.log_file` set, e.g. to `logs/hello-tts.log`, [`start_run`]
+//! opens a new log for the run next to it, named after its start time and
+//! command (`logs/hello-tts-20261016-125100-batch.log`), and deletes the oldest
+//! run logs beyond `TTSConfig.log_max_files`. [`LogWriter`] copies every line
+//! to stderr and to the current run's log, so it can be given to `env_logger`
+//! as its target before the configuration is loaded. A run's report is saved
+//! beside its log with [`RunLog::save_report`], making a bundle to attach to
+//! bug reports.
+
+use crate::config::TTSConfig;
+use crate::models::TTSError;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+static CURRENT: Mutex<Option<File>> = Mutex::new(None);
+
+/// Writes to stderr and to the log of the current run, if one was started
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LogWriter;
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        io::stderr().write_all(buf)?;
+        if let Some(file) = CURRENT.lock().unwrap().as_mut() {
+            // A full disk should not stop the run
+            let _ = file.write_all(buf);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(file) = CURRENT.lock().unwrap().as_mut() {
+            let _ = file.flush();
+        }
+        io::stderr().flush()
+    }
+}
+
+/// The log file of a run
+#[derive(Debug, Clone)]
+pub struct RunLog {
+    path: PathBuf,
+}
+
+impl RunLog {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Save `report` as JSON beside the log, as `<log name>.report.json`
+    pub fn save_report<T: Serialize>(&self, report: &T) -> Result<PathBuf, TTSError> {
+        let path = self.path.with_extension("report.json");
+        std::fs::write(&path, serde_json::to_string_pretty(report)?)?;
+        Ok(path)
+    }
+}
+
+/// Start logging the run of `command` to a new file, if `config.log_file` is set
+pub fn start_run(config: &TTSConfig, command: &str) -> Result<Option<RunLog>, TTSError> {
+    let Some(log_file) = &config.log_file else {
+        return Ok(None);
+    };
+    let path = run_path(Path::new(log_file), command, Local::now());
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let file = File::options().create(true).append(true).open(&path)?;
+    *CURRENT.lock().unwrap() = Some(file);
+    prune(Path::new(log_file), config.log_max_files)?;
+    Ok(Some(RunLog { path }))
+}
+
+/// `logs/hello-tts.log` becomes `logs/hello-tts-<date>-<time>-<command>.log`
+fn run_path(log_file: &Path, command: &str, started: DateTime<Local>) -> PathBuf {
+    let (stem, ext) = name_parts(log_file);
+    log_file.with_file_name(format!(
+        "{}-{}-{}.{}",
+        stem,
+        started.format("%Y%m%d-%H%M%S"),
+        command,
+        ext
+    ))
+}
+
+fn name_parts(log_file: &Path) -> (String, String) {
+    let stem = log_file
+        .file_stem()
+        .map_or("hello-tts".into(), |s| s.to_string_lossy());
+    let ext = log_file
+        .extension()
+        .map_or("log".into(), |e| e.to_string_lossy());
+    (stem.into_owned(), ext.into_owned())
+}
+
+/// Delete all but the newest `keep` run logs and their reports; `0` keeps all
+fn prune(log_file: &Path, keep: usize) -> io::Result<()> {
+    if keep == 0 {
+        return Ok(());
+    }
+    let (stem, ext) = name_parts(log_file);
+    let dir = match log_file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let prefix = format!("{}-", stem);
+    let suffix = format!(".{}", ext);
+    let mut logs: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(&prefix) && name.ends_with(&suffix))
+        })
+        .collect();
+    // Names start with the same stem and a fixed-width timestamp
+    logs.sort();
+    let excess = logs.len().saturating_sub(keep);
+    for path in &logs[..excess] {
+        std::fs::remove_file(path)?;
+        let _ = std::fs::remove_file(path.with_extension("report.json"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_run_paths_and_pruning() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-logs-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let log_file = dir.join("hello-tts.log");
+        let started = Local.with_ymd_and_hms(2026, 10, 16, 12, 51, 0).unwrap();
+        let path = run_path(&log_file, "batch", started);
+        assert_eq!(path, dir.join("hello-tts-20261016-125100-batch.log"));
+
+        for minute in 0..4 {
+            let started = Local.with_ymd_and_hms(2026, 10, 16, 12, minute, 0).unwrap();
+            let path = run_path(&log_file, "demo", started);
+            std::fs::write(&path, "log").unwrap();
+            RunLog { path }.save_report(&minute).unwrap();
+        }
+        std::fs::write(dir.join("other.log"), "kept").unwrap();
+        prune(&log_file, 2).unwrap();
+        let mut names: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "hello-tts-20261016-120200-demo.log",
+                "hello-tts-20261016-120200-demo.report.json",
+                "hello-tts-20261016-120300-demo.log",
+                "hello-tts-20261016-120300-demo.report.json",
+                "other.log",
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
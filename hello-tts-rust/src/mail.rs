@@ -0,0 +1,419 @@
+//! Speak new emails of an IMAP mailbox.
+//!
+//! [`MailWatcher::poll`] connects to the server over TLS, speaks every unseen
+//! email of the mailbox to an audio file and marks it as seen, so each email
+//! is spoken once. Only the text of an email is spoken, with its sender and
+//! subject; HTML-only emails are converted to text. Requires the `mail`
+//! feature.
+
+use crate::document::slug;
+use crate::models::TTSError;
+use crate::tts_client::TTSProcessor;
+use mail_parser::MessageParser;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_native_tls::native_tls;
+use tracing::{info, warn};
+
+/// Port of IMAP over TLS
+pub const IMAPS_PORT: u16 = 993;
+
+/// Mailbox to watch and where its emails are spoken to
+#[derive(Debug, Clone)]
+pub struct MailOptions {
+    pub server: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+    /// Mailbox to watch, e.g. `INBOX`
+    pub mailbox: String,
+    /// Directory for the audio files
+    pub output_dir: PathBuf,
+}
+
+/// An email as spoken
+#[derive(Debug, Clone, PartialEq)]
+pub struct Email {
+    pub uid: u32,
+    pub from: Option<String>,
+    pub subject: Option<String>,
+    pub text: String,
+}
+
+/// An email spoken by [`MailWatcher::poll`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpokenEmail {
+    pub uid: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    pub file: PathBuf,
+    pub bytes: usize,
+}
+
+impl Email {
+    /// Parse a raw RFC 5322 message; `None` if it has no text
+    pub fn parse(uid: u32, raw: &[u8]) -> Option<Self> {
+        let message = MessageParser::default().parse(raw)?;
+        let text = message.body_text(0)?.trim().to_string();
+        if text.is_empty() {
+            return None;
+        }
+        let from = message
+            .from()
+            .and_then(|from| from.first())
+            .and_then(|address| {
+                address
+                    .name()
+                    .or_else(|| address.address())
+                    .map(str::to_string)
+            });
+        Some(Self {
+            uid,
+            from,
+            subject: message.subject().map(|s| s.trim().to_string()),
+            text,
+        })
+    }
+
+    /// Sender, subject and text as spoken
+    pub fn speakable_text(&self) -> String {
+        let mut intro = String::new();
+        if let Some(from) = &self.from {
+            intro.push_str(&format!("Email from {}. ", from));
+        }
+        if let Some(subject) = self.subject.as_deref().filter(|s| !s.is_empty()) {
+            intro.push_str(&format!("Subject: {}.", subject.trim_end_matches('.')));
+        }
+        match intro.trim_end() {
+            "" => self.text.clone(),
+            intro => format!("{}\n\n{}", intro, self.text),
+        }
+    }
+
+    /// Audio file name, e.g. `000042-lunch-on-friday.mp3`
+    pub fn file_name(&self, ext: &str) -> String {
+        match slug(self.subject.as_deref().unwrap_or_default()) {
+            subject if subject.is_empty() => format!("{:06}.{}", self.uid, ext),
+            subject => format!("{:06}-{}.{}", self.uid, subject, ext),
+        }
+    }
+}
+
+/// Polls a mailbox and speaks its new emails
+pub struct MailWatcher {
+    options: MailOptions,
+    /// Emails that could not be spoken, not retried until restart
+    failed: HashSet<u32>,
+}
+
+impl MailWatcher {
+    pub fn new(options: MailOptions) -> Self {
+        Self {
+            options,
+            failed: HashSet::new(),
+        }
+    }
+
+    /// Speak the unseen emails of the mailbox with `voice`
+    ///
+    /// Spoken emails and emails without text are marked as seen. An email
+    /// that fails to synthesize stays unseen and is skipped by later polls.
+    pub async fn poll(
+        &mut self,
+        processor: &TTSProcessor,
+        voice: &str,
+    ) -> Result<Vec<SpokenEmail>, TTSError> {
+        let tcp = TcpStream::connect((self.options.server.as_str(), self.options.port)).await?;
+        let connector = native_tls::TlsConnector::new().map_err(tls_error)?;
+        let tls = tokio_native_tls::TlsConnector::from(connector)
+            .connect(&self.options.server, tcp)
+            .await
+            .map_err(tls_error)?;
+        let client = ImapClient::new(tls).await?;
+        self.speak_new(client, processor, voice).await
+    }
+
+    async fn speak_new<S: AsyncRead + AsyncWrite + Unpin>(
+        &mut self,
+        mut client: ImapClient<S>,
+        processor: &TTSProcessor,
+        voice: &str,
+    ) -> Result<Vec<SpokenEmail>, TTSError> {
+        client
+            .login(&self.options.username, &self.options.password)
+            .await?;
+        client
+            .command(&format!("SELECT {}", quote(&self.options.mailbox)))
+            .await?;
+        let mut uids = client.search_unseen().await?;
+        uids.retain(|uid| !self.failed.contains(uid));
+        tokio::fs::create_dir_all(&self.options.output_dir).await?;
+
+        let mut spoken = Vec::new();
+        for uid in uids {
+            let Some(email) = Email::parse(uid, &client.fetch(uid).await?) else {
+                info!(uid, "Skipping email without text");
+                client.mark_seen(uid).await?;
+                continue;
+            };
+            let audio = match processor
+                .synthesize_text(&email.speakable_text(), voice)
+                .await
+            {
+                Ok(audio) => audio,
+                Err(e @ TTSError::Cancelled) => return Err(e),
+                Err(e) => {
+                    warn!(uid, "Email could not be spoken: {}", e);
+                    self.failed.insert(uid);
+                    continue;
+                }
+            };
+            let file = self
+                .options
+                .output_dir
+                .join(email.file_name(processor.config().file_extension()));
+            tokio::fs::write(&file, &audio).await?;
+            client.mark_seen(uid).await?;
+            spoken.push(SpokenEmail {
+                uid,
+                from: email.from,
+                subject: email.subject,
+                file,
+                bytes: audio.len(),
+            });
+        }
+        client.command("LOGOUT").await?;
+        Ok(spoken)
+    }
+}
+
+fn tls_error(e: native_tls::Error) -> TTSError {
+    TTSError::Io(std::io::Error::other(format!("TLS error: {}", e)))
+}
+
+/// The few IMAP4rev1 commands the watcher needs
+struct ImapClient<S> {
+    stream: BufReader<S>,
+    tag: u32,
+}
+
+/// Untagged lines and literals of a command's response
+#[derive(Debug, Default)]
+struct Response {
+    lines: Vec<String>,
+    literals: Vec<Vec<u8>>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> ImapClient<S> {
+    /// Wrap a connection and read the server greeting
+    async fn new(stream: S) -> Result<Self, TTSError> {
+        let mut client = Self {
+            stream: BufReader::new(stream),
+            tag: 0,
+        };
+        let greeting = client.read_line().await?;
+        if !greeting.starts_with("* OK") && !greeting.starts_with("* PREAUTH") {
+            return Err(TTSError::Config(format!(
+                "Unexpected IMAP greeting: {}",
+                greeting
+            )));
+        }
+        Ok(client)
+    }
+
+    async fn login(&mut self, username: &str, password: &str) -> Result<(), TTSError> {
+        let command = format!("LOGIN {} {}", quote(username), quote(password));
+        self.command(&command).await.map(|_| ())
+    }
+
+    async fn search_unseen(&mut self) -> Result<Vec<u32>, TTSError> {
+        let response = self.command("UID SEARCH UNSEEN").await?;
+        Ok(response
+            .lines
+            .iter()
+            .filter_map(|line| line.strip_prefix("* SEARCH"))
+            .flat_map(|uids| uids.split_whitespace().filter_map(|uid| uid.parse().ok()))
+            .collect())
+    }
+
+    /// The raw message, without marking it as seen
+    async fn fetch(&mut self, uid: u32) -> Result<Vec<u8>, TTSError> {
+        let mut response = self
+            .command(&format!("UID FETCH {} BODY.PEEK[]", uid))
+            .await?;
+        response
+            .literals
+            .pop()
+            .ok_or_else(|| TTSError::Config(format!("IMAP server returned no email {}", uid)))
+    }
+
+    async fn mark_seen(&mut self, uid: u32) -> Result<(), TTSError> {
+        let command = format!("UID STORE {} +FLAGS (\\Seen)", uid);
+        self.command(&command).await.map(|_| ())
+    }
+
+    /// Send a command and read its response up to the tagged status
+    async fn command(&mut self, command: &str) -> Result<Response, TTSError> {
+        self.tag += 1;
+        let tag = format!("A{:04}", self.tag);
+        let stream = self.stream.get_mut();
+        stream
+            .write_all(format!("{} {}\r\n", tag, command).as_bytes())
+            .await?;
+        stream.flush().await?;
+
+        let mut response = Response::default();
+        loop {
+            let line = self.read_line().await?;
+            if let Some(size) = literal_size(&line) {
+                let mut literal = vec![0; size];
+                self.stream.read_exact(&mut literal).await?;
+                response.literals.push(literal);
+            }
+            if let Some(status) = line.strip_prefix(&tag) {
+                let status = status.trim_start();
+                if status.starts_with("OK") {
+                    return Ok(response);
+                }
+                // Never echo the password of a failed LOGIN
+                let verb = command
+                    .split_whitespace()
+                    .take(2)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let verb = if verb.starts_with("LOGIN") {
+                    "LOGIN"
+                } else {
+                    &verb
+                };
+                return Err(TTSError::Config(format!(
+                    "IMAP {} failed: {}",
+                    verb, status
+                )));
+            }
+            response.lines.push(line);
+        }
+    }
+
+    async fn read_line(&mut self) -> Result<String, TTSError> {
+        let mut line = Vec::new();
+        if self.stream.read_until(b'\n', &mut line).await? == 0 {
+            return Err(TTSError::Io(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+        Ok(String::from_utf8_lossy(&line).trim_end().to_string())
+    }
+}
+
+/// Size of the literal announced at the end of a line, as in `{312}`
+fn literal_size(line: &str) -> Option<usize> {
+    line.strip_suffix('}')?.rsplit_once('{')?.1.parse().ok()
+}
+
+/// IMAP quoted string
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::mock::MockBackend;
+    use crate::config::TTSConfig;
+
+    const EMAIL: &str = "From: Ann Lee <ann@example.com>\r\n\
+                         Subject: Lunch on Friday\r\n\
+                         Content-Type: text/plain; charset=utf-8\r\n\
+                         Content-Transfer-Encoding: quoted-printable\r\n\
+                         \r\n\
+                         Shall we meet at noon? Caf=C3=A9 on the corner.\r\n";
+
+    #[test]
+    fn test_parse_email() {
+        let email = Email::parse(42, EMAIL.as_bytes()).unwrap();
+        assert_eq!(email.from.as_deref(), Some("Ann Lee"));
+        assert_eq!(email.file_name("mp3"), "000042-lunch-on-friday.mp3");
+        assert_eq!(
+            email.speakable_text(),
+            "Email from Ann Lee. Subject: Lunch on Friday.\n\n\
+             Shall we meet at noon? Café on the corner."
+        );
+        assert!(Email::parse(1, b"Subject: Empty\r\n\r\n").is_none());
+        assert_eq!(literal_size("* 1 FETCH (UID 7 BODY[] {312}"), Some(312));
+        assert_eq!(quote(r#"pa"ss\"#), r#""pa\"ss\\""#);
+    }
+
+    #[tokio::test]
+    async fn test_speak_new_emails() {
+        let (client_side, server_side) = tokio::io::duplex(4096);
+        let server = tokio::spawn(async move {
+            let mut server = BufReader::new(server_side);
+            server
+                .get_mut()
+                .write_all(b"* OK IMAP ready\r\n")
+                .await
+                .unwrap();
+            let mut commands = Vec::new();
+            let mut line = String::new();
+            while server.read_line(&mut line).await.unwrap() > 0 {
+                let (tag, command) = line.trim_end().split_once(' ').unwrap();
+                let reply = match command {
+                    "UID SEARCH UNSEEN" => "* SEARCH 7 9\r\n".to_string(),
+                    "UID FETCH 7 BODY.PEEK[]" => {
+                        format!(
+                            "* 1 FETCH (UID 7 BODY[] {{{}}}\r\n{})\r\n",
+                            EMAIL.len(),
+                            EMAIL
+                        )
+                    }
+                    "UID FETCH 9 BODY.PEEK[]" => {
+                        "* 2 FETCH (UID 9 BODY[] {4}\r\n\r\n\r\n)\r\n".into()
+                    }
+                    _ => String::new(),
+                };
+                let status = format!("{}{} OK done\r\n", reply, tag);
+                server.get_mut().write_all(status.as_bytes()).await.unwrap();
+                commands.push(command.to_string());
+                line.clear();
+            }
+            commands
+        });
+
+        let dir = std::env::temp_dir().join(format!("hello-tts-mail-{}", uuid::Uuid::new_v4()));
+        let backend = MockBackend::new();
+        let processor = TTSProcessor::with_backend(TTSConfig::default(), Box::new(backend.clone()));
+        let mut watcher = MailWatcher::new(MailOptions {
+            server: "imap.example.com".to_string(),
+            port: IMAPS_PORT,
+            username: "ann".to_string(),
+            password: "secret".to_string(),
+            mailbox: "INBOX".to_string(),
+            output_dir: dir.clone(),
+        });
+        let client = ImapClient::new(client_side).await.unwrap();
+        let spoken = watcher.speak_new(client, &processor, "v").await.unwrap();
+        assert_eq!(spoken.len(), 1);
+        assert!(spoken[0].file.ends_with("000007-lunch-on-friday.mp3"));
+        assert!(backend.calls()[0].text.starts_with("Email from Ann Lee."));
+
+        let commands = server.await.unwrap();
+        assert_eq!(
+            commands,
+            [
+                "LOGIN \"ann\" \"secret\"",
+                "SELECT \"INBOX\"",
+                "UID SEARCH UNSEEN",
+                "UID FETCH 7 BODY.PEEK[]",
+                "UID STORE 7 +FLAGS (\\Seen)",
+                "UID FETCH 9 BODY.PEEK[]",
+                "UID STORE 9 +FLAGS (\\Seen)",
+                "LOGOUT",
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,265 @@
+//! In-process counters and latency histograms for [`TTSProcessor`](crate::TTSProcessor).
+//!
+//! Read them with [`TTSProcessor::metrics_snapshot`](crate::TTSProcessor::metrics_snapshot)
+//! or render them in the Prometheus text format with
+//! [`MetricsSnapshot::to_prometheus`].
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Upper bounds, in seconds, of the latency histogram buckets
+pub const LATENCY_BUCKETS: [f64; 8] = [0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Name, help text and accessor of a per-backend counter
+type CounterSpec = (&'static str, &'static str, fn(&BackendMetrics) -> u64);
+
+/// Thread-safe metrics recorder shared by a processor and its frontends
+#[derive(Debug, Default)]
+pub struct Metrics {
+    state: Mutex<MetricsSnapshot>,
+}
+
+/// Point-in-time copy of the recorded metrics
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MetricsSnapshot {
+    /// Per-backend counters keyed by backend name
+    pub backends: BTreeMap<String, BackendMetrics>,
+    pub voice_cache_hits: u64,
+    pub voice_cache_misses: u64,
+}
+
+/// Counters for a single backend
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BackendMetrics {
+    /// Texts synthesized successfully, however many requests they took
+    pub syntheses: u64,
+    /// Requests sent to the backend, including retries
+    pub requests: u64,
+    pub failures: u64,
+    pub retries: u64,
+    /// Audio bytes produced
+    pub bytes: u64,
+    /// Characters of successful requests, the unit paid backends bill by
+    #[serde(default)]
+    pub characters: u64,
+    /// Latency of individual backend requests
+    pub latency: Histogram,
+}
+
+/// Non-cumulative latency histogram over [`LATENCY_BUCKETS`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Histogram {
+    /// Observations per bucket; the last entry counts values above every bound
+    pub buckets: Vec<u64>,
+    pub sum_seconds: f64,
+    pub count: u64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: vec![0; LATENCY_BUCKETS.len() + 1],
+            sum_seconds: 0.0,
+            count: 0,
+        }
+    }
+}
+
+impl Histogram {
+    pub fn observe(&mut self, value: Duration) {
+        let seconds = value.as_secs_f64();
+        let index = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.buckets[index] += 1;
+        self.sum_seconds += seconds;
+        self.count += 1;
+    }
+
+    /// Mean observed value, if anything was observed
+    pub fn mean(&self) -> Option<Duration> {
+        (self.count > 0).then(|| Duration::from_secs_f64(self.sum_seconds / self.count as f64))
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        self.state.lock().unwrap().clone()
+    }
+
+    pub fn reset(&self) {
+        *self.state.lock().unwrap() = MetricsSnapshot::default();
+    }
+
+    /// Record one backend request and its outcome
+    pub fn record_request(&self, backend: &str, latency: Duration, bytes: Option<usize>) {
+        self.with_backend(backend, |m| {
+            m.requests += 1;
+            m.latency.observe(latency);
+            match bytes {
+                Some(bytes) => m.bytes += bytes as u64,
+                None => m.failures += 1,
+            }
+        });
+    }
+
+    pub fn record_retry(&self, backend: &str) {
+        self.with_backend(backend, |m| m.retries += 1);
+    }
+
+    pub fn record_characters(&self, backend: &str, characters: usize) {
+        self.with_backend(backend, |m| m.characters += characters as u64);
+    }
+
+    pub fn record_synthesis(&self, backend: &str) {
+        self.with_backend(backend, |m| m.syntheses += 1);
+    }
+
+    pub fn record_voice_cache(&self, hit: bool) {
+        let mut state = self.state.lock().unwrap();
+        if hit {
+            state.voice_cache_hits += 1;
+        } else {
+            state.voice_cache_misses += 1;
+        }
+    }
+
+    fn with_backend(&self, backend: &str, update: impl FnOnce(&mut BackendMetrics)) {
+        let mut state = self.state.lock().unwrap();
+        update(state.backends.entry(backend.to_string()).or_default());
+    }
+}
+
+impl MetricsSnapshot {
+    /// Share of voice list lookups served from the cache, between 0 and 1
+    pub fn cache_hit_rate(&self) -> Option<f64> {
+        let total = self.voice_cache_hits + self.voice_cache_misses;
+        (total > 0).then(|| self.voice_cache_hits as f64 / total as f64)
+    }
+
+    /// Render the snapshot in the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let counters: [CounterSpec; 6] = [
+            ("syntheses_total", "Texts synthesized", |m| m.syntheses),
+            ("requests_total", "Requests sent to the backend", |m| {
+                m.requests
+            }),
+            ("failures_total", "Failed backend requests", |m| m.failures),
+            ("retries_total", "Retried backend requests", |m| m.retries),
+            ("audio_bytes_total", "Audio bytes produced", |m| m.bytes),
+            ("characters_total", "Characters synthesized", |m| {
+                m.characters
+            }),
+        ];
+        for (name, help, value) in counters {
+            let _ = writeln!(out, "# HELP hello_tts_{} {}", name, help);
+            let _ = writeln!(out, "# TYPE hello_tts_{} counter", name);
+            for (backend, metrics) in &self.backends {
+                let _ = writeln!(
+                    out,
+                    "hello_tts_{}{{backend=\"{}\"}} {}",
+                    name,
+                    backend,
+                    value(metrics)
+                );
+            }
+        }
+
+        out.push_str("# HELP hello_tts_voice_cache_lookups_total Voice list lookups\n");
+        out.push_str("# TYPE hello_tts_voice_cache_lookups_total counter\n");
+        let _ = writeln!(
+            out,
+            "hello_tts_voice_cache_lookups_total{{result=\"hit\"}} {}",
+            self.voice_cache_hits
+        );
+        let _ = writeln!(
+            out,
+            "hello_tts_voice_cache_lookups_total{{result=\"miss\"}} {}",
+            self.voice_cache_misses
+        );
+
+        out.push_str("# HELP hello_tts_request_duration_seconds Backend request latency\n");
+        out.push_str("# TYPE hello_tts_request_duration_seconds histogram\n");
+        for (backend, metrics) in &self.backends {
+            let histogram = &metrics.latency;
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(&histogram.buckets) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "hello_tts_request_duration_seconds_bucket{{backend=\"{}\",le=\"{}\"}} {}",
+                    backend, bound, cumulative
+                );
+            }
+            let _ = writeln!(
+                out,
+                "hello_tts_request_duration_seconds_bucket{{backend=\"{}\",le=\"+Inf\"}} {}",
+                backend, histogram.count
+            );
+            let _ = writeln!(
+                out,
+                "hello_tts_request_duration_seconds_sum{{backend=\"{}\"}} {}",
+                backend, histogram.sum_seconds
+            );
+            let _ = writeln!(
+                out,
+                "hello_tts_request_duration_seconds_count{{backend=\"{}\"}} {}",
+                backend, histogram.count
+            );
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_snapshot() {
+        let metrics = Metrics::new();
+        metrics.record_request("edge", Duration::from_millis(300), Some(1000));
+        metrics.record_request("edge", Duration::from_secs(60), None);
+        metrics.record_retry("edge");
+        metrics.record_synthesis("edge");
+        metrics.record_voice_cache(false);
+        metrics.record_voice_cache(true);
+        metrics.record_voice_cache(true);
+
+        let snapshot = metrics.snapshot();
+        let edge = &snapshot.backends["edge"];
+        assert_eq!(edge.requests, 2);
+        assert_eq!(edge.failures, 1);
+        assert_eq!(edge.bytes, 1000);
+        assert_eq!(edge.latency.buckets[2], 1);
+        assert_eq!(edge.latency.buckets[LATENCY_BUCKETS.len()], 1);
+        assert!((snapshot.cache_hit_rate().unwrap() - 2.0 / 3.0).abs() < 1e-9);
+
+        metrics.reset();
+        assert_eq!(metrics.snapshot(), MetricsSnapshot::default());
+    }
+
+    #[test]
+    fn test_prometheus_rendering() {
+        let metrics = Metrics::new();
+        metrics.record_request("google", Duration::from_millis(200), Some(10));
+        let text = metrics.snapshot().to_prometheus();
+        assert!(text.contains("hello_tts_requests_total{backend=\"google\"} 1\n"));
+        assert!(text.contains(
+            "hello_tts_request_duration_seconds_bucket{backend=\"google\",le=\"0.1\"} 0\n"
+        ));
+        assert!(text.contains(
+            "hello_tts_request_duration_seconds_bucket{backend=\"google\",le=\"0.25\"} 1\n"
+        ));
+        assert!(text.contains("hello_tts_request_duration_seconds_count{backend=\"google\"} 1\n"));
+    }
+}
@@ -1,4 +1,100 @@
+use crate::analysis::{AudioStats, LoudnessPreset};
+use crate::audio_player::AudioError;
+use crate::text::TextWarning;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+
+/// Boxed error kept as the source of a [`TTSError::Backend`]
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Failure class of a [`TTSError`], for branching without matching messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// Connection, DNS or TLS failure talking to a service
+    Network,
+    /// The request took too long
+    Timeout,
+    /// The service rejected the request because of rate limits
+    RateLimited,
+    /// The service is unavailable
+    Unavailable,
+    /// A tool or module the backend runs, such as `edge-tts`, is not installed
+    NotInstalled,
+    /// Credentials are missing or were rejected
+    Authentication,
+    /// The request was rejected as invalid (text, voice or options)
+    InvalidRequest,
+    VoiceNotFound,
+    Config,
+    Io,
+    /// A response could not be parsed
+    Parse,
+    /// Audio could not be decoded or played
+    Audio,
+    /// Synthesis failed for another reason
+    Synthesis,
+    /// The operation was cancelled through its `CancellationToken`
+    Cancelled,
+    /// A character limit configured in `TTSConfig` was reached
+    QuotaExceeded,
+}
+
+impl ErrorKind {
+    /// Whether an operation failing with this kind may succeed when retried
+    pub fn is_retryable(self) -> bool {
+        matches!(
+            self,
+            ErrorKind::Network
+                | ErrorKind::Timeout
+                | ErrorKind::RateLimited
+                | ErrorKind::Unavailable
+        )
+    }
+
+    /// Whether the fallback chain tries the next backend after this kind:
+    /// retryable failures, and backends whose tool is not installed
+    pub fn falls_back(self) -> bool {
+        self.is_retryable() || self == ErrorKind::NotInstalled
+    }
+
+    /// Classify an HTTP status code
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            401 | 403 => ErrorKind::Authentication,
+            404 => ErrorKind::VoiceNotFound,
+            408 | 504 => ErrorKind::Timeout,
+            429 => ErrorKind::RateLimited,
+            400..=499 => ErrorKind::InvalidRequest,
+            500..=599 => ErrorKind::Unavailable,
+            _ => ErrorKind::Synthesis,
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ErrorKind::Network => "network",
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::RateLimited => "rate limited",
+            ErrorKind::Unavailable => "unavailable",
+            ErrorKind::NotInstalled => "not installed",
+            ErrorKind::Authentication => "authentication",
+            ErrorKind::InvalidRequest => "invalid request",
+            ErrorKind::VoiceNotFound => "voice not found",
+            ErrorKind::Config => "configuration",
+            ErrorKind::Io => "io",
+            ErrorKind::Parse => "parse",
+            ErrorKind::Audio => "audio",
+            ErrorKind::Synthesis => "synthesis",
+            ErrorKind::Cancelled => "cancelled",
+            ErrorKind::QuotaExceeded => "quota exceeded",
+        };
+        f.write_str(name)
+    }
+}
 
 /// Custom error type for TTS operations
 #[derive(Debug, thiserror::Error)]
@@ -9,12 +105,108 @@ pub enum TTSError {
     Io(#[from] std::io::Error),
     #[error("JSON parsing error: {0}")]
     Json(#[from] serde_json::Error),
+    #[error("Audio error: {0}")]
+    Audio(#[from] AudioError),
     #[error("TTS synthesis failed: {0}")]
     Synthesis(String),
     #[error("Voice not found: {0}")]
     VoiceNotFound(String),
     #[error("Invalid configuration: {0}")]
     Config(String),
+    #[error("Operation cancelled")]
+    Cancelled,
+    #[error("Character quota exceeded: {0}")]
+    QuotaExceeded(String),
+    /// A synthesis option the backend cannot honour, rather than ignore
+    #[error("Unsupported option: {0}")]
+    UnsupportedOption(String),
+    #[error("{backend} backend error ({kind}): {message}")]
+    Backend {
+        backend: &'static str,
+        kind: ErrorKind,
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+}
+
+impl TTSError {
+    /// Error raised by a backend, without an underlying cause
+    pub fn backend_error(
+        backend: &'static str,
+        kind: ErrorKind,
+        message: impl Into<String>,
+    ) -> Self {
+        TTSError::Backend {
+            backend,
+            kind,
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// Error raised by a backend, keeping `source` in the error chain
+    pub fn backend_error_with_source(
+        backend: &'static str,
+        kind: ErrorKind,
+        message: impl Into<String>,
+        source: impl Into<BoxError>,
+    ) -> Self {
+        TTSError::Backend {
+            backend,
+            kind,
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    /// Wrap a reqwest error from a backend, classifying it by status or cause
+    pub fn from_http(backend: &'static str, error: reqwest::Error) -> Self {
+        let kind = if error.is_timeout() {
+            ErrorKind::Timeout
+        } else if let Some(status) = error.status() {
+            ErrorKind::from_status(status.as_u16())
+        } else if error.is_decode() {
+            ErrorKind::Parse
+        } else {
+            ErrorKind::Network
+        };
+        TTSError::backend_error_with_source(backend, kind, error.to_string(), error)
+    }
+
+    /// Failure class of this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            TTSError::Network(e) if e.is_timeout() => ErrorKind::Timeout,
+            TTSError::Network(e) => e
+                .status()
+                .map(|s| ErrorKind::from_status(s.as_u16()))
+                .unwrap_or(ErrorKind::Network),
+            TTSError::Io(_) => ErrorKind::Io,
+            TTSError::Json(_) => ErrorKind::Parse,
+            TTSError::Audio(_) => ErrorKind::Audio,
+            TTSError::Synthesis(_) => ErrorKind::Synthesis,
+            TTSError::VoiceNotFound(_) => ErrorKind::VoiceNotFound,
+            TTSError::Config(_) => ErrorKind::Config,
+            TTSError::Cancelled => ErrorKind::Cancelled,
+            TTSError::QuotaExceeded(_) => ErrorKind::QuotaExceeded,
+            TTSError::UnsupportedOption(_) => ErrorKind::InvalidRequest,
+            TTSError::Backend { kind, .. } => *kind,
+        }
+    }
+
+    /// Whether retrying the failed operation may succeed
+    pub fn is_retryable(&self) -> bool {
+        self.kind().is_retryable()
+    }
+
+    /// Name of the backend that raised the error, when known
+    pub fn backend(&self) -> Option<&'static str> {
+        match self {
+            TTSError::Backend { backend, .. } => Some(backend),
+            _ => None,
+        }
+    }
 }
 
 /// Voice information structure
@@ -25,6 +217,21 @@ pub struct Voice {
     pub locale: String,
     pub gender: String,
     pub description: Option<String>,
+    /// Native output sample rate, if the backend reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_rate_hz: Option<u32>,
+    /// Speaking styles the voice supports (e.g. "cheerful", "whispering")
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub styles: Vec<String>,
+    /// Personality tags (e.g. "Friendly", "Confident")
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub personalities: Vec<String>,
+    /// Content the voice is suited for (e.g. "News", "Novel")
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub content_categories: Vec<String>,
+    /// Release status reported by the backend (e.g. "GA", "Preview")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
 }
 
 impl Voice {
@@ -35,11 +242,18 @@ impl Voice {
             locale,
             gender,
             description: None,
+            sample_rate_hz: None,
+            styles: Vec::new(),
+            personalities: Vec::new(),
+            content_categories: Vec::new(),
+            status: None,
         }
     }
 
     pub fn matches_language(&self, lang_code: &str) -> bool {
-        self.locale.to_lowercase().starts_with(&lang_code.to_lowercase())
+        self.locale
+            .to_lowercase()
+            .starts_with(&lang_code.to_lowercase())
     }
 
     pub fn language_code(&self) -> &str {
@@ -47,6 +261,91 @@ impl Voice {
     }
 }
 
+/// Timing of a spoken word (or sentence) within synthesized audio
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WordBoundary {
+    /// Start of the word, in milliseconds from the start of the audio
+    pub offset_ms: u64,
+    pub duration_ms: u64,
+    pub text: String,
+}
+
+/// Mouth shape shown from `offset_ms` on, for lip-sync and avatars
+///
+/// `id` follows the Azure/Edge viseme set (0 = silence, 1-21 = mouth
+/// positions).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Viseme {
+    pub offset_ms: u64,
+    pub id: u8,
+}
+
+/// Audio together with the boundary timings reported by the backend
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SynthesizedAudio {
+    pub audio: Vec<u8>,
+    /// Empty when the backend does not report timings
+    pub boundaries: Vec<WordBoundary>,
+    /// Empty when the backend does not report visemes
+    pub visemes: Vec<Viseme>,
+}
+
+/// Prosody and accent a backend applies to every request, and the PCM
+/// format the processor converts its audio to
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SynthesisOptions {
+    /// Speaking rate such as `+20%`
+    pub rate: String,
+    pub pitch: String,
+    pub volume: String,
+    /// Regional accent for backends that have them: an accent locale such as
+    /// `en-GB` or, for Google, a Translate domain such as `co.uk`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accent: Option<String>,
+    /// Speaking style such as `cheerful`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<String>,
+    /// Resample the audio to this rate in Hz, see [`crate::convert`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<u32>,
+    /// Mix the audio down (or up) to this many channels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channels: Option<u16>,
+    /// Opus bitrate preset or kbit/s, set for `opus` output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opus_bitrate: Option<String>,
+    /// Normalize the converted audio to this loudness
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loudness_preset: Option<LoudnessPreset>,
+}
+
+/// Outcome of a successful synthesis request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SynthesisResult {
+    /// Name of the backend that produced the audio
+    pub backend: String,
+    pub voice: String,
+    /// Where the audio was written, if it was saved
+    pub output_path: Option<PathBuf>,
+    /// Size of the produced audio in bytes
+    pub bytes: usize,
+    /// Wall-clock time spent synthesizing, in milliseconds
+    pub elapsed_ms: u64,
+    /// Viseme timings, collected when `TTSConfig.visemes` is set
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub visemes: Vec<Viseme>,
+    /// Word timings, collected when `TTSConfig.word_timings` is set
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub boundaries: Vec<WordBoundary>,
+    /// Problems with the input text found before synthesis, see
+    /// [`crate::text::sanitize`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<TextWarning>,
+    /// Duration, peak and loudness, when the audio could be decoded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<AudioStats>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -67,6 +366,35 @@ mod tests {
         assert_eq!(voice.language_code(), "en");
     }
 
+    #[test]
+    fn test_error_kind_and_retryability() {
+        let err = TTSError::backend_error("edge", ErrorKind::RateLimited, "too many requests");
+        assert_eq!(err.kind(), ErrorKind::RateLimited);
+        assert!(err.is_retryable());
+        assert_eq!(err.backend(), Some("edge"));
+        assert_eq!(
+            err.to_string(),
+            "edge backend error (rate limited): too many requests"
+        );
+
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "edge-tts");
+        let err = TTSError::backend_error_with_source(
+            "edge",
+            ErrorKind::Unavailable,
+            "not installed",
+            io,
+        );
+        assert!(std::error::Error::source(&err).is_some());
+
+        assert!(!ErrorKind::NotInstalled.is_retryable());
+        assert!(ErrorKind::NotInstalled.falls_back());
+
+        let err = TTSError::VoiceNotFound("xx-XX-Nobody".to_string());
+        assert!(!err.is_retryable());
+        assert_eq!(err.backend(), None);
+        assert_eq!(ErrorKind::from_status(503), ErrorKind::Unavailable);
+    }
+
     #[test]
     fn test_voice_matches_language() {
         let voice = Voice::new(
@@ -80,4 +408,13 @@ mod tests {
         assert!(voice.matches_language("en-US"));
         assert!(!voice.matches_language("fr"));
     }
+
+    #[test]
+    fn test_voice_serde_is_backward_compatible() {
+        let json = r#"{"name":"en","display_name":"English","locale":"en","gender":"Unknown","description":null}"#;
+        let voice: Voice = serde_json::from_str(json).unwrap();
+        assert!(voice.styles.is_empty());
+        assert_eq!(voice.sample_rate_hz, None);
+        assert_eq!(serde_json::to_string(&voice).unwrap(), json);
+    }
 }
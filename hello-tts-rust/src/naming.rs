@@ -0,0 +1,317 @@
+//! Output filenames built from templates such as
+//! `{lang}_{backend}_{voice}_{date}_{hash}.{ext}`.
+//!
+//! | Placeholder   | Value                                                    |
+//! |---------------|----------------------------------------------------------|
+//! | `{lang}`      | Language part of the voice locale (`en`)                 |
+//! | `{locale}`    | Voice locale (`en-US`)                                   |
+//! | `{voice}`     | Voice name                                               |
+//! | `{backend}`   | Backend name                                             |
+//! | `{date}`      | Local date, `YYYYMMDD`                                   |
+//! | `{time}`      | Local time, `HHMMSS`                                     |
+//! | `{timestamp}` | Seconds since the Unix epoch                             |
+//! | `{hash}`      | 16 hex digits derived from backend, voice and text       |
+//! | `{ext}`       | File extension of the output format                      |
+//!
+//! With stable names enabled, `{date}`, `{time}` and `{timestamp}` are
+//! replaced by the hash as well, so the same request always maps to the same
+//! file and an existing file is overwritten. Otherwise a counter is appended
+//! when the rendered name is already taken. `TTSConfig.deterministic`
+//! implies stable names.
+
+use crate::clock::{self, Clock, SystemClock};
+use crate::config::TTSConfig;
+use crate::models::TTSError;
+use chrono::{DateTime, Local};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Template producing the historical `<lang>_rust_<backend>_<timestamp>.mp3` names
+pub const DEFAULT_TEMPLATE: &str = "{lang}_rust_{backend}_{timestamp}.{ext}";
+
+const PLACEHOLDERS: [&str; 9] = [
+    "lang",
+    "locale",
+    "voice",
+    "backend",
+    "date",
+    "time",
+    "timestamp",
+    "hash",
+    "ext",
+];
+
+/// What a generated file contains
+#[derive(Debug, Clone, Copy)]
+pub struct NameFields<'a> {
+    pub backend: &'a str,
+    pub voice: &'a str,
+    pub text: &'a str,
+    /// Extension without the leading dot, e.g. `mp3`
+    pub ext: &'a str,
+}
+
+/// Renders output filenames from a validated template
+#[derive(Debug, Clone)]
+pub struct FileNamer {
+    template: String,
+    stable: bool,
+    clock: Arc<dyn Clock>,
+}
+
+impl FileNamer {
+    /// Create a namer, rejecting unknown or unterminated placeholders
+    pub fn new(template: &str, stable: bool) -> Result<Self, TTSError> {
+        validate_template(template)?;
+        Ok(Self {
+            template: template.to_string(),
+            stable,
+            clock: Arc::new(SystemClock),
+        })
+    }
+
+    /// Namer for `TTSConfig.filename_template` and `TTSConfig.stable_filenames`,
+    /// with the clock of [`clock::from_config`]
+    pub fn from_config(config: &TTSConfig) -> Result<Self, TTSError> {
+        let namer = Self::new(
+            &config.filename_template,
+            config.stable_filenames || config.deterministic,
+        )?;
+        Ok(namer.with_clock(clock::from_config(config)))
+    }
+
+    /// This namer telling the time with `clock`
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+
+    /// Render the filename for `fields` at the clock's current time
+    pub fn render(&self, fields: &NameFields) -> String {
+        self.render_at(fields, self.clock.now())
+    }
+
+    /// Render the filename for `fields` as if it were `now`
+    pub fn render_at(&self, fields: &NameFields, now: DateTime<Local>) -> String {
+        let hash = format!("{:016x}", content_hash(fields));
+        let mut out = String::new();
+        let mut rest = self.template.as_str();
+        while let Some(start) = rest.find('{') {
+            out.push_str(&rest[..start]);
+            let Some(len) = rest[start..].find('}') else {
+                break;
+            };
+            let end = start + len;
+            let value = match &rest[start + 1..end] {
+                "lang" => fields
+                    .voice
+                    .split('-')
+                    .next()
+                    .unwrap_or("unknown")
+                    .to_string(),
+                "locale" => locale(fields.voice),
+                "voice" => fields.voice.to_string(),
+                "backend" => fields.backend.to_string(),
+                "hash" => hash.clone(),
+                "ext" => fields.ext.to_string(),
+                "date" | "time" | "timestamp" if self.stable => hash.clone(),
+                "date" => now.format("%Y%m%d").to_string(),
+                "time" => now.format("%H%M%S").to_string(),
+                "timestamp" => now.timestamp().to_string(),
+                _ => String::new(),
+            };
+            out.push_str(&sanitize(&value));
+            rest = &rest[end + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
+
+    /// Path in `dir` for `fields`, avoiding existing files unless names are stable
+    pub fn path_in(&self, dir: &Path, fields: &NameFields) -> PathBuf {
+        let path = dir.join(self.render(fields));
+        if self.stable {
+            path
+        } else {
+            unique_path(path)
+        }
+    }
+}
+
+impl Default for FileNamer {
+    fn default() -> Self {
+        Self {
+            template: DEFAULT_TEMPLATE.to_string(),
+            stable: false,
+            clock: Arc::new(SystemClock),
+        }
+    }
+}
+
+/// Check that every `{...}` in `template` is a known placeholder
+pub fn validate_template(template: &str) -> Result<(), TTSError> {
+    if template.trim().is_empty() {
+        return Err(TTSError::Config(
+            "filename_template cannot be empty".to_string(),
+        ));
+    }
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}').ok_or_else(|| {
+            TTSError::Config(format!(
+                "Unterminated placeholder in filename_template: {}",
+                template
+            ))
+        })?;
+        let name = &rest[start + 1..start + end];
+        if !PLACEHOLDERS.contains(&name) {
+            return Err(TTSError::Config(format!(
+                "Unknown placeholder {{{}}} in filename_template (expected one of {})",
+                name,
+                PLACEHOLDERS.join(", ")
+            )));
+        }
+        rest = &rest[start + end + 1..];
+    }
+    Ok(())
+}
+
+/// 64-bit FNV-1a, stable across Rust releases unlike `DefaultHasher`
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn content_hash(fields: &NameFields) -> u64 {
+    fnv1a(format!("{}\0{}\0{}", fields.backend, fields.voice, fields.text).as_bytes())
+}
+
+/// Locale prefix of a voice name such as `en-US-AriaNeural`
+pub(crate) fn locale(voice: &str) -> String {
+    let parts: Vec<&str> = voice.split('-').collect();
+    match parts.as_slice() {
+        [lang, region, ..] if region.len() == 2 => format!("{}-{}", lang, region),
+        [lang, ..] => lang.to_string(),
+        [] => "unknown".to_string(),
+    }
+}
+
+/// Replace characters that are unsafe in filenames
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// `path`, or `path` with `_1`, `_2`, ... before the extension if it exists
+fn unique_path(path: PathBuf) -> PathBuf {
+    if !path.exists() {
+        return path;
+    }
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|n| path.with_file_name(format!("{}_{}{}", stem, n, ext)))
+        .find(|candidate| !candidate.exists())
+        .unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const FIELDS: NameFields = NameFields {
+        backend: "edge",
+        voice: "en-US-AriaNeural",
+        text: "Hello",
+        ext: "mp3",
+    };
+
+    #[test]
+    fn test_render_template() {
+        let now = Local.with_ymd_and_hms(2024, 5, 6, 7, 8, 9).unwrap();
+        let namer = FileNamer::new("{locale}_{voice}_{date}_{time}.{ext}", false).unwrap();
+        assert_eq!(
+            namer.render_at(&FIELDS, now),
+            "en-US_en-US-AriaNeural_20240506_070809.mp3"
+        );
+        assert_eq!(
+            FileNamer::default().render_at(&FIELDS, now),
+            format!("en_rust_edge_{}.mp3", now.timestamp())
+        );
+    }
+
+    #[test]
+    fn test_stable_names_ignore_time() {
+        let namer = FileNamer::new("{lang}_{timestamp}.{ext}", true).unwrap();
+        let earlier = Local.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let first = namer.render_at(&FIELDS, earlier);
+        assert_eq!(first, namer.render(&FIELDS));
+        assert_ne!(
+            first,
+            namer.render(&NameFields {
+                text: "Bye",
+                ..FIELDS
+            })
+        );
+    }
+
+    #[test]
+    fn test_render_with_clock() {
+        let namer = FileNamer::new("{date}_{time}.{ext}", false)
+            .unwrap()
+            .with_clock(Arc::new(clock::FixedClock(
+                Local.with_ymd_and_hms(2024, 5, 6, 7, 8, 9).unwrap(),
+            )));
+        assert_eq!(namer.render(&FIELDS), "20240506_070809.mp3");
+
+        let config = TTSConfig {
+            filename_template: "{lang}_{timestamp}.{ext}".to_string(),
+            deterministic: true,
+            ..TTSConfig::default()
+        };
+        let namer = FileNamer::from_config(&config).unwrap();
+        let hashed = FileNamer::new("{lang}_{hash}.{ext}", false).unwrap();
+        assert_eq!(namer.render(&FIELDS), hashed.render(&FIELDS));
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        assert!(FileNamer::new("{language}.mp3", false).is_err());
+        assert!(FileNamer::new("{lang.mp3", false).is_err());
+        assert!(FileNamer::new("", false).is_err());
+    }
+
+    #[test]
+    fn test_fnv1a_is_stable() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn test_collisions_get_a_counter() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-naming-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let namer = FileNamer::new("{lang}_{hash}.{ext}", false).unwrap();
+        let first = namer.path_in(&dir, &FIELDS);
+        std::fs::write(&first, b"").unwrap();
+        let second = namer.path_in(&dir, &FIELDS);
+        assert_eq!(
+            second.file_name().unwrap().to_string_lossy(),
+            format!("{}_1.mp3", first.file_stem().unwrap().to_string_lossy())
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,335 @@
+//! Narration aligned to an SRT subtitle timeline.
+//!
+//! For dubbing and video narration, [`parse_srt`] reads the cues of a SubRip
+//! file and [`render`] speaks each one. [`assemble`] speeds up clips longer
+//! than their cue by up to `NarrationOptions.max_speed` without changing their
+//! pitch, and places every clip at its cue's start in one track that is silent
+//! between cues. A clip that is still too long runs on into the gap before the
+//! next cue, and is cut short with a fade where it would overlap it.
+
+use crate::audio;
+use crate::config::TTSConfig;
+use crate::convert::Pcm;
+use crate::convert::{self, Encoding};
+use crate::models::TTSError;
+use crate::tts_client::TTSProcessor;
+use futures_util::stream::{self, StreamExt, TryStreamExt};
+use serde::Serialize;
+use std::time::Duration;
+use tracing::instrument;
+
+/// Fade at the end of a clip cut short by the next cue
+const CUT_FADE_MS: u64 = 20;
+
+/// One subtitle cue
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Cue {
+    /// Sequence number from the file
+    pub index: usize,
+    pub start: Duration,
+    pub end: Duration,
+    /// Text with its lines joined and formatting tags removed
+    pub text: String,
+}
+
+/// Voice and fitting of a narration
+#[derive(Debug, Clone)]
+pub struct NarrationOptions {
+    pub voice: String,
+    /// Fastest a clip may be played to fit its cue, e.g. `1.5`
+    pub max_speed: f64,
+}
+
+impl NarrationOptions {
+    pub fn new(voice: &str) -> Self {
+        Self {
+            voice: voice.to_string(),
+            max_speed: 1.5,
+        }
+    }
+}
+
+/// How a cue's clip was fitted into the track
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NarratedCue {
+    pub index: usize,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    /// Length of the synthesized speech before fitting
+    pub speech_ms: u64,
+    /// Playback speed the clip was stretched to; `1.0` when it fitted
+    pub speed: f64,
+    /// The clip was cut short where the next cue starts
+    pub truncated: bool,
+}
+
+/// Audio of a whole narration track
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedNarration {
+    pub audio: Vec<u8>,
+    pub cues: Vec<NarratedCue>,
+}
+
+/// Parse the cues of a SubRip (`.srt`) file, in file order
+pub fn parse_srt(input: &str) -> Result<Vec<Cue>, TTSError> {
+    let input = input.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let mut cues = Vec::new();
+    for block in input.split("\n\n").filter(|b| !b.trim().is_empty()) {
+        let mut lines = block.trim().lines();
+        let mut first = lines.next().unwrap_or_default().trim();
+        // The sequence number is optional in practice
+        let index = match first.parse::<usize>() {
+            Ok(index) => {
+                first = lines.next().unwrap_or_default().trim();
+                index
+            }
+            Err(_) => cues.len() + 1,
+        };
+        let (start, end) = parse_timing(first).ok_or_else(|| {
+            TTSError::Config(format!("Invalid SRT timing for cue {}: {:?}", index, first))
+        })?;
+        let text = lines
+            .map(|line| strip_tags(line.trim()))
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !text.is_empty() {
+            cues.push(Cue {
+                index,
+                start,
+                end,
+                text,
+            });
+        }
+    }
+    if cues.is_empty() {
+        return Err(TTSError::Config("No subtitle cues found".to_string()));
+    }
+    Ok(cues)
+}
+
+/// `00:00:01,500 --> 00:00:03,000`, optionally followed by position settings
+fn parse_timing(line: &str) -> Option<(Duration, Duration)> {
+    let (start, rest) = line.split_once("-->")?;
+    let end = rest.split_whitespace().next()?;
+    let (start, end) = (parse_timestamp(start.trim())?, parse_timestamp(end)?);
+    (start <= end).then_some((start, end))
+}
+
+fn parse_timestamp(timestamp: &str) -> Option<Duration> {
+    let (clock, millis) = timestamp.split_once([',', '.'])?;
+    let parts: Vec<u64> = clock
+        .split(':')
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+    let [hours, minutes, seconds] = parts[..] else {
+        return None;
+    };
+    let millis: u64 = millis.parse().ok()?;
+    Some(Duration::from_millis(
+        ((hours * 60 + minutes) * 60 + seconds) * 1000 + millis,
+    ))
+}
+
+/// Remove `<i>`-style tags and `{\an8}`-style overrides
+fn strip_tags(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut closing = None;
+    for c in line.chars() {
+        match (closing, c) {
+            (None, '<') => closing = Some('>'),
+            (None, '{') => closing = Some('}'),
+            (None, c) => text.push(c),
+            (Some(end), c) if c == end => closing = None,
+            _ => {}
+        }
+    }
+    text.trim().to_string()
+}
+
+/// Fit each clip into its cue and place it in one track
+///
+/// `clips` are the decoded speech of `cues`, in the same order; they are
+/// converted to the rate and channels of the first one. The track lasts
+/// until the last cue or clip ends.
+pub fn assemble(
+    cues: &[Cue],
+    clips: Vec<Pcm>,
+    options: &NarrationOptions,
+) -> (Pcm, Vec<NarratedCue>) {
+    let (channels, sample_rate) = clips
+        .first()
+        .map_or((1, audio::DEFAULT_SAMPLE_RATE), |pcm| {
+            (pcm.channels.max(1), pcm.sample_rate)
+        });
+    let width = channels as usize;
+    let frames_at = |time| audio::frames_in(time, sample_rate);
+    let mut track = Pcm {
+        samples: Vec::new(),
+        channels,
+        sample_rate,
+    };
+    let mut fitted = Vec::with_capacity(cues.len());
+    for (index, (cue, clip)) in cues.iter().zip(clips).enumerate() {
+        let clip = clip.with_channels(channels).resample(sample_rate);
+        let speech = clip.samples.len() / width;
+        let window = frames_at(cue.end.saturating_sub(cue.start)).max(1);
+        let speed = if speech > window {
+            (speech as f64 / window as f64).min(options.max_speed.max(1.0))
+        } else {
+            1.0
+        };
+        let mut clip = audio::time_stretch(clip, speed);
+
+        let start = frames_at(cue.start);
+        let next = cues.get(index + 1).map(|next| frames_at(next.start));
+        let room = next.map_or(usize::MAX, |next| next.saturating_sub(start));
+        let truncated = clip.samples.len() / width > room;
+        if truncated {
+            clip.samples.truncate(room * width);
+            fade_out(
+                &mut clip.samples,
+                frames_at(Duration::from_millis(CUT_FADE_MS)),
+                width,
+            );
+        }
+
+        let end = (start * width + clip.samples.len()).max(frames_at(cue.end) * width);
+        if track.samples.len() < end {
+            track.samples.resize(end, 0);
+        }
+        for (out, sample) in track.samples[start * width..].iter_mut().zip(&clip.samples) {
+            *out = out.saturating_add(*sample);
+        }
+        fitted.push(NarratedCue {
+            index: cue.index,
+            start_ms: cue.start.as_millis() as u64,
+            end_ms: cue.end.as_millis() as u64,
+            speech_ms: (speech as u64 * 1000) / u64::from(sample_rate.max(1)),
+            speed,
+            truncated,
+        });
+    }
+    (track, fitted)
+}
+
+fn fade_out(samples: &mut [i16], frames: usize, width: usize) {
+    let frames = frames.min(samples.len() / width);
+    let start = samples.len() - frames * width;
+    for (i, sample) in samples[start..].iter_mut().enumerate() {
+        let gain = 1.0 - (i / width) as f64 / frames as f64;
+        *sample = (f64::from(*sample) * gain).round() as i16;
+    }
+}
+
+/// Speak every subtitle cue and fit the clips into one track on the cues' timeline
+///
+/// The track is encoded in the output format when it is `wav`,
+/// `pcm_s16le` or `opus`, and as WAV otherwise, since the crate has no
+/// MP3 encoder.
+#[instrument(skip_all, fields(backend = processor.backend_name(), cues = cues.len()))]
+pub async fn render(
+    processor: &TTSProcessor,
+    cues: &[Cue],
+    options: &NarrationOptions,
+) -> Result<RenderedNarration, TTSError> {
+    let unconverted = processor.unconverted();
+    let clips: Vec<Vec<u8>> = stream::iter(cues)
+        .map(|cue| unconverted.synthesize_text(&cue.text, &options.voice))
+        .buffered(processor.config().max_concurrent.max(1))
+        .try_collect()
+        .await?;
+    let clips = clips
+        .iter()
+        .map(|clip| Pcm::decode(clip))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (track, cues) = assemble(cues, clips, options);
+    Ok(RenderedNarration {
+        audio: convert::encode(
+            track,
+            &processor.config().synthesis_options(),
+            encoding(processor.config()),
+        )?,
+        cues,
+    })
+}
+
+/// Encoding of [`render`] tracks with `config`
+pub fn encoding(config: &TTSConfig) -> Encoding {
+    Encoding::parse(&config.output_format).unwrap_or(Encoding::Wav)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis;
+    use crate::backends::mock::MockBackend;
+    use crate::dialogue;
+
+    #[test]
+    fn test_parse_srt() {
+        let srt = concat!(
+            "\u{feff}1\r\n00:00:01,000 --> 00:00:02,500\r\n<i>Hello</i> there,\r\n",
+            "{\\an8}friend.\r\n\r\n2\r\n00:01:00.250 --> 00:01:02,000 X1:10\r\nBye.\r\n"
+        );
+        let cues = parse_srt(srt).unwrap();
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[0].text, "Hello there, friend.");
+        assert_eq!(cues[0].end, Duration::from_millis(2500));
+        assert_eq!(cues[1].start, Duration::from_millis(60_250));
+        assert!(parse_srt("1\n00:00:02,000 --> 00:00:01,000\nBackwards\n").is_err());
+        assert!(parse_srt("").is_err());
+    }
+
+    #[test]
+    fn test_assemble_fits_clips() {
+        let cue = |index, start, end| Cue {
+            index,
+            start: Duration::from_millis(start),
+            end: Duration::from_millis(end),
+            text: String::new(),
+        };
+        let clip = |ms: usize| Pcm {
+            samples: vec![1000; ms * 10],
+            channels: 1,
+            sample_rate: 10_000,
+        };
+        let cues = [cue(1, 100, 500), cue(2, 600, 800), cue(3, 900, 1500)];
+        let options = NarrationOptions::new("v");
+        let (track, fitted) = assemble(&cues, vec![clip(300), clip(600), clip(100)], &options);
+
+        // Fits, is stretched to the limit and cut at the next cue, and is padded
+        assert_eq!(fitted[0].speed, 1.0);
+        assert_eq!(fitted[1].speed, 1.5);
+        assert_eq!((fitted[1].speech_ms, fitted[1].truncated), (600, true));
+        assert!(!fitted[2].truncated);
+        assert_eq!(track.samples.len(), 15_000);
+        assert_eq!(track.samples[999], 0);
+        assert_eq!(track.samples[1000], 1000);
+        assert_eq!(track.samples[5000], 0);
+        assert_eq!(track.samples[12_000], 0);
+    }
+
+    #[tokio::test]
+    async fn test_render_narration_follows_cues() {
+        let backend = MockBackend::new()
+            .then_audio(dialogue::mp3_silence(Duration::from_millis(500)))
+            .then_audio(dialogue::mp3_silence(Duration::from_secs(2)));
+        let config = TTSConfig {
+            max_concurrent: 1,
+            ..TTSConfig::default()
+        };
+        let processor = TTSProcessor::with_backend(config, Box::new(backend));
+        let cues = parse_srt(
+            "1\n00:00:01,000 --> 00:00:02,000\nOne\n\n2\n00:00:03,000 --> 00:00:04,000\nTwo\n",
+        )
+        .unwrap();
+        let options = NarrationOptions::new("v");
+        let rendered = render(&processor, &cues, &options).await.unwrap();
+        assert_eq!(encoding(processor.config()), Encoding::Wav);
+        assert_eq!(rendered.cues[0].speed, 1.0);
+        assert_eq!(rendered.cues[1].speed, 1.5);
+        let stats = analysis::analyze(&rendered.audio).unwrap();
+        assert!((4300..4500).contains(&stats.duration_ms));
+    }
+}
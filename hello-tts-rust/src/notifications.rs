@@ -0,0 +1,69 @@
+//! Desktop notifications when long jobs end.
+//!
+//! With `TTSConfig.notify_on_completion` set, the processor subscribes a
+//! [`DesktopNotifier`], which shows a notification for every
+//! [`TTSEvent::JobFinished`] of a batch or audiobook. Showing notifications
+//! requires the `notifications` feature; a notification that cannot be shown
+//! is logged and does not fail the job.
+
+use crate::events::{EventHandler, TTSEvent};
+use crate::models::TTSError;
+use tracing::warn;
+
+/// Show a desktop notification
+#[cfg(feature = "notifications")]
+pub fn show(summary: &str, body: &str) -> Result<(), TTSError> {
+    notify_rust::Notification::new()
+        .appname("hello-tts")
+        .summary(summary)
+        .body(body)
+        .show()
+        .map(|_| ())
+        .map_err(|e| TTSError::Config(format!("Cannot show a notification: {}", e)))
+}
+
+#[cfg(not(feature = "notifications"))]
+pub fn show(_summary: &str, _body: &str) -> Result<(), TTSError> {
+    Err(TTSError::Config(
+        "Cannot show a notification: built without the `notifications` feature".to_string(),
+    ))
+}
+
+/// Title of the notification for a finished job
+pub fn title(job: &str, failed: bool) -> String {
+    if failed {
+        format!("{} failed", job)
+    } else {
+        format!("{} finished", job)
+    }
+}
+
+/// Event handler showing a notification when a job ends
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DesktopNotifier;
+
+impl EventHandler for DesktopNotifier {
+    fn on_event(&self, event: &TTSEvent) {
+        if let TTSEvent::JobFinished {
+            job,
+            summary,
+            failed,
+        } = event
+        {
+            if let Err(e) = show(&title(job, *failed), summary) {
+                warn!("{}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_title() {
+        assert_eq!(title("Batch", false), "Batch finished");
+        assert_eq!(title("Audiobook", true), "Audiobook failed");
+    }
+}
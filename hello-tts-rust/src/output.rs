@@ -0,0 +1,187 @@
+//! Output directory handling and housekeeping.
+//!
+//! Bare file names handed to the processor are placed in
+//! `TTSConfig.output_directory`. [`OutputManager::cleanup`] removes audio files
+//! older than `output_max_age_days` and then the oldest files until the
+//! directory fits in `output_max_size_mb`.
+
+use crate::config::TTSConfig;
+use crate::models::TTSError;
+use serde::Serialize;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Extensions of the files [`OutputManager::cleanup`] may delete
+const AUDIO_EXTENSIONS: [&str; 9] = [
+    "mp3", "wav", "ogg", "opus", "flac", "m4a", "aac", "webm", "pcm",
+];
+
+/// Resolves output paths and enforces size and age limits on the output directory
+#[derive(Debug, Clone)]
+pub struct OutputManager {
+    dir: PathBuf,
+    max_bytes: Option<u64>,
+    max_age: Option<Duration>,
+}
+
+/// What a cleanup run removed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CleanupReport {
+    pub removed: Vec<PathBuf>,
+    pub freed_bytes: u64,
+    /// Size of the audio files left in the directory
+    pub remaining_bytes: u64,
+}
+
+impl OutputManager {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_bytes: None,
+            max_age: None,
+        }
+    }
+
+    /// Manager for `TTSConfig.output_directory` and its housekeeping limits
+    pub fn from_config(config: &TTSConfig) -> Self {
+        Self {
+            dir: PathBuf::from(&config.output_directory),
+            max_bytes: config.output_max_size_mb.map(|mb| mb * 1024 * 1024),
+            max_age: config
+                .output_max_age_days
+                .map(|days| Duration::from_secs(days * 24 * 60 * 60)),
+        }
+    }
+
+    /// Keep the directory under `max_bytes` of audio
+    pub fn with_max_bytes(mut self, max_bytes: Option<u64>) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Remove audio files older than `max_age`
+    pub fn with_max_age(mut self, max_age: Option<Duration>) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Place bare file names in the output directory
+    ///
+    /// Absolute paths and paths with a directory part are returned unchanged.
+    pub fn resolve(&self, path: &Path) -> PathBuf {
+        let mut components = path.components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) => self.dir.join(path),
+            _ => path.to_path_buf(),
+        }
+    }
+
+    /// Remove expired files, then the oldest ones until the size limit is met
+    ///
+    /// Only files with an audio extension directly inside the directory are
+    /// considered. A missing directory is not an error.
+    pub fn cleanup(&self) -> Result<CleanupReport, TTSError> {
+        let mut report = CleanupReport::default();
+        if self.max_bytes.is_none() && self.max_age.is_none() {
+            report.remaining_bytes = self.audio_files()?.iter().map(|f| f.1).sum();
+            return Ok(report);
+        }
+
+        let now = SystemTime::now();
+        let mut files = self.audio_files()?;
+        files.sort_by_key(|(_, _, modified)| *modified);
+
+        let mut total: u64 = files.iter().map(|f| f.1).sum();
+        for (path, size, modified) in files {
+            let expired = self
+                .max_age
+                .is_some_and(|max_age| now.duration_since(modified).unwrap_or_default() > max_age);
+            let over_size = self.max_bytes.is_some_and(|max_bytes| total > max_bytes);
+            if !expired && !over_size {
+                continue;
+            }
+            std::fs::remove_file(&path)?;
+            total -= size;
+            report.freed_bytes += size;
+            report.removed.push(path);
+        }
+        report.remaining_bytes = total;
+        Ok(report)
+    }
+
+    fn audio_files(&self) -> Result<Vec<(PathBuf, u64, SystemTime)>, TTSError> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut files = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let path = entry.path();
+            let is_audio = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| AUDIO_EXTENSIONS.contains(&e.to_lowercase().as_str()));
+            let metadata = entry.metadata()?;
+            if is_audio && metadata.is_file() {
+                files.push((path, metadata.len(), metadata.modified()?));
+            }
+        }
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let manager = OutputManager::new("out");
+        assert_eq!(manager.resolve(Path::new("a.mp3")), Path::new("out/a.mp3"));
+        assert_eq!(manager.resolve(Path::new("x/a.mp3")), Path::new("x/a.mp3"));
+        assert_eq!(manager.resolve(Path::new("./a.mp3")), Path::new("./a.mp3"));
+    }
+
+    #[test]
+    fn test_cleanup_enforces_size_limit() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-output-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (i, name) in ["old.mp3", "mid.wav", "new.mp3"].iter().enumerate() {
+            let path = dir.join(name);
+            std::fs::write(&path, [0u8; 100]).unwrap();
+            let modified = SystemTime::now() - Duration::from_secs(100 * (3 - i as u64));
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        }
+        std::fs::write(dir.join("notes.txt"), [0u8; 500]).unwrap();
+
+        let report = OutputManager::new(&dir)
+            .with_max_bytes(Some(150))
+            .cleanup()
+            .unwrap();
+        assert_eq!(
+            report.removed,
+            vec![dir.join("old.mp3"), dir.join("mid.wav")]
+        );
+        assert_eq!(report.freed_bytes, 200);
+        assert_eq!(report.remaining_bytes, 100);
+        assert!(dir.join("notes.txt").exists());
+
+        let report = OutputManager::new(&dir)
+            .with_max_age(Some(Duration::from_secs(10)))
+            .cleanup()
+            .unwrap();
+        assert_eq!(report.removed, vec![dir.join("new.mp3")]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
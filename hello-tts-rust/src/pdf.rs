@@ -0,0 +1,93 @@
+//! Text of PDF files.
+//!
+//! Extraction requires the `pdf` feature. Pages are joined with
+//! [`PAGE_BREAK`], which
+//! [`TTSProcessor::prepare_text`](crate::TTSProcessor::prepare_text) treats
+//! as a chunk boundary, so no chunk spans two pages. Lines matching a skip
+//! pattern, such as running headers, footers and page numbers, are dropped.
+
+use crate::models::TTSError;
+use regex::Regex;
+use std::path::Path;
+
+/// Form feed separating pages
+pub const PAGE_BREAK: char = '\u{c}';
+
+/// Text of each page of the PDF in `bytes`
+#[cfg(feature = "pdf")]
+pub fn extract_pages(bytes: &[u8]) -> Result<Vec<String>, TTSError> {
+    // pdf-extract panics on some fonts and encodings it does not support
+    std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem_by_pages(bytes))
+        .map_err(|_| TTSError::Config("Cannot read the PDF: unsupported content".to_string()))?
+        .map_err(|e| TTSError::Config(format!("Cannot read the PDF: {}", e)))
+}
+
+#[cfg(not(feature = "pdf"))]
+pub fn extract_pages(_bytes: &[u8]) -> Result<Vec<String>, TTSError> {
+    Err(TTSError::Config(
+        "Cannot read PDF files: built without the `pdf` feature".to_string(),
+    ))
+}
+
+/// Pages joined with [`PAGE_BREAK`], without the lines matching `skip`
+///
+/// A word hyphenated across a line break is joined again.
+pub fn join_pages(pages: &[String], skip: Option<&Regex>) -> String {
+    let pages: Vec<String> = pages
+        .iter()
+        .map(|page| {
+            let lines: Vec<&str> = page
+                .lines()
+                .map(str::trim)
+                .filter(|line| !skip.is_some_and(|skip| skip.is_match(line)))
+                .collect();
+            let mut text = String::new();
+            for line in lines {
+                match text.strip_suffix('-') {
+                    Some(joined) if line.starts_with(char::is_lowercase) => {
+                        text.truncate(joined.len());
+                    }
+                    _ if !text.is_empty() => text.push('\n'),
+                    _ => {}
+                }
+                text.push_str(line);
+            }
+            text.trim().to_string()
+        })
+        .filter(|page| !page.is_empty())
+        .collect();
+    pages.join(&format!("\n{}\n", PAGE_BREAK))
+}
+
+/// Text of the PDF file at `path`, see [`join_pages`]
+pub fn read_text(path: &Path, skip: Option<&Regex>) -> Result<String, TTSError> {
+    let pages = extract_pages(&std::fs::read(path)?)?;
+    let text = join_pages(&pages, skip);
+    if text.is_empty() {
+        return Err(TTSError::Config(format!(
+            "{} has no text; scanned PDFs need OCR first",
+            path.display()
+        )));
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_pages() {
+        let pages = [
+            "Annual Report\nThe results were excep-\ntional this year.\n\n- 1 -\n".to_string(),
+            "Annual Report\n- 2 -\n".to_string(),
+            "Annual Report\nCosts fell.\n- 3 -".to_string(),
+        ];
+        let skip = Regex::new(r"^(Annual Report|- \d+ -)$").unwrap();
+        assert_eq!(
+            join_pages(&pages, Some(&skip)),
+            "The results were exceptional this year.\n\u{c}\nCosts fell."
+        );
+        assert!(join_pages(&pages, None).starts_with("Annual Report\nThe results"));
+    }
+}
@@ -0,0 +1,476 @@
+//! Podcasts made from RSS and Atom feeds.
+//!
+//! [`Feed::parse`] reads the entries of an RSS 2.0 or Atom feed. [`render`]
+//! speaks each entry into an episode file with the batch engine, so entries
+//! finished by an earlier run are not synthesized again, and writes a podcast
+//! RSS feed with an enclosure per episode. Episodes are kept in
+//! `episodes.json` and stay in the podcast after their entry leaves the source
+//! feed.
+
+use crate::batch::{self, BatchItem, BatchManifest, BatchOptions, ItemStatus};
+use crate::convert;
+use crate::document::slug;
+use crate::models::TTSError;
+use crate::naming::fnv1a;
+use crate::sources::{attribute, element_content, element_text, first_tag};
+use crate::text::ssml::escape;
+use crate::text::{to_plain_text, InputFormat};
+use crate::tts_client::TTSProcessor;
+use chrono::DateTime;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::instrument;
+
+/// Name of the episode list in a podcast directory
+pub const EPISODES_FILE: &str = "episodes.json";
+/// Name of the podcast feed in a podcast directory
+pub const FEED_FILE: &str = "feed.xml";
+
+/// A parsed RSS or Atom feed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Feed {
+    pub title: String,
+    pub link: Option<String>,
+    pub description: Option<String>,
+    /// Entries in feed order, usually newest first
+    pub entries: Vec<FeedEntry>,
+}
+
+/// One item of an RSS feed or entry of an Atom feed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FeedEntry {
+    /// `guid` or `id`, else the link or title
+    pub id: String,
+    pub title: String,
+    pub link: Option<String>,
+    /// Publication date in RFC 2822 form, as podcast feeds use it
+    pub published: Option<String>,
+    /// Content or summary as plain text
+    pub text: String,
+}
+
+/// Where and how a podcast is rendered
+#[derive(Debug, Clone, Default)]
+pub struct PodcastOptions {
+    /// Directory for episodes and the podcast feed; defaults to a directory
+    /// named after the feed in `TTSConfig.output_directory`
+    pub output_dir: Option<PathBuf>,
+    /// URL the directory is published at, prefixed to enclosure URLs
+    pub base_url: Option<String>,
+    /// Speak at most this many entries from the top of the feed
+    pub max_episodes: Option<usize>,
+}
+
+/// An episode of the podcast, as stored in `episodes.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Episode {
+    pub id: String,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published: Option<String>,
+    /// Audio file, relative to the podcast directory
+    pub file: String,
+    pub bytes: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+/// Contents of `episodes.json`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PodcastState {
+    pub episodes: Vec<Episode>,
+}
+
+/// Result of [`render`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RenderedPodcast {
+    pub output_dir: PathBuf,
+    pub feed: PathBuf,
+    /// Episodes synthesized by this run
+    pub new_episodes: usize,
+    /// Entries that could not be synthesized
+    pub failed: usize,
+    pub episodes: Vec<Episode>,
+}
+
+impl Feed {
+    /// Parse an RSS 2.0 or Atom feed
+    pub fn parse(xml: &str) -> Result<Self, TTSError> {
+        let entry = Regex::new(r"(?is)<(item|entry)\b[^>]*>(.*?)</(item|entry)\s*>").unwrap();
+        let head = &xml[..entry.find(xml).map_or(xml.len(), |m| m.start())];
+        if !head.contains("<rss") && !head.contains("<feed") && !head.contains("<rdf:RDF") {
+            return Err(TTSError::Config("Not an RSS or Atom feed".to_string()));
+        }
+        let entries = entry
+            .captures_iter(xml)
+            .filter_map(|captures| FeedEntry::parse(&captures[2]))
+            .collect();
+        Ok(Self {
+            title: element_text(head, "title").unwrap_or_default(),
+            link: link(head),
+            description: element_text(head, "description")
+                .or_else(|| element_text(head, "subtitle")),
+            entries,
+        })
+    }
+}
+
+impl FeedEntry {
+    /// An entry from the XML inside `<item>` or `<entry>`; `None` without text
+    fn parse(xml: &str) -> Option<Self> {
+        let title = element_text(xml, "title").unwrap_or_default();
+        let content = ["content:encoded", "content", "description", "summary"]
+            .iter()
+            .find_map(|name| element_content(xml, name).filter(|c| !c.trim().is_empty()))
+            .unwrap_or_default();
+        // Escaped HTML is decoded first, then stripped
+        let content = if content.contains('<') {
+            content
+        } else {
+            to_plain_text(&content, InputFormat::Html, false)
+        };
+        let text = to_plain_text(&content, InputFormat::Html, true)
+            .trim()
+            .to_string();
+        if title.is_empty() && text.is_empty() {
+            return None;
+        }
+        let link = link(xml);
+        let id = ["guid", "id"]
+            .iter()
+            .find_map(|name| element_text(xml, name))
+            .or_else(|| link.clone())
+            .unwrap_or_else(|| title.clone());
+        let published = ["pubDate", "published", "updated", "dc:date"]
+            .iter()
+            .find_map(|name| element_text(xml, name))
+            .map(|date| rfc2822(&date));
+        Some(Self {
+            id,
+            title,
+            link,
+            published,
+            text,
+        })
+    }
+
+    /// Title and text as spoken in the episode
+    pub fn speakable_text(&self) -> String {
+        let title = self.title.trim();
+        if title.is_empty() {
+            return self.text.clone();
+        }
+        let end = if title.ends_with(['.', '!', '?', ':']) {
+            ""
+        } else {
+            "."
+        };
+        format!("{}{}\n\n{}", title, end, self.text)
+    }
+
+    /// Episode file name that stays the same when the feed is reordered,
+    /// e.g. `the-storm-1a2b3c4d.mp3`
+    pub fn file_name(&self, ext: &str) -> String {
+        let hash = format!("{:016x}", fnv1a(self.id.as_bytes()));
+        match slug(&self.title) {
+            slug if slug.is_empty() => format!("{}.{}", &hash[..8], ext),
+            slug => format!("{}-{}.{}", slug, &hash[..8], ext),
+        }
+    }
+}
+
+/// RSS `<link>` text or the `href` of an Atom `<link>`
+fn link(xml: &str) -> Option<String> {
+    element_text(xml, "link").or_else(|| attribute(&first_tag(xml, "link")?, "href"))
+}
+
+/// An Atom (RFC 3339) date in RFC 2822 form; other dates are kept as they are
+fn rfc2822(date: &str) -> String {
+    DateTime::parse_from_rfc3339(date).map_or_else(|_| date.to_string(), |d| d.to_rfc2822())
+}
+
+/// Fetch a feed from an `http(s)` URL, or read it from a file
+pub async fn fetch(source: &str) -> Result<String, TTSError> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        let response = reqwest::get(source)
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| TTSError::from_http("podcast", e))?;
+        response
+            .text()
+            .await
+            .map_err(|e| TTSError::from_http("podcast", e))
+    } else {
+        Ok(tokio::fs::read_to_string(source).await?)
+    }
+}
+
+impl PodcastState {
+    /// Read `episodes.json` from a podcast directory, if present
+    pub fn load(dir: &Path) -> Result<Option<Self>, TTSError> {
+        match std::fs::read_to_string(dir.join(EPISODES_FILE)) {
+            Ok(content) => Ok(Some(serde_json::from_str(&content)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<(), TTSError> {
+        std::fs::write(dir.join(EPISODES_FILE), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Podcast RSS with an enclosure per episode, at `base_url` if given
+    pub fn to_rss(&self, feed: &Feed, base_url: Option<&str>) -> String {
+        let mut out = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <rss version=\"2.0\" xmlns:itunes=\"http://www.itunes.com/dtds/podcast-1.0.dtd\">\n\
+             <channel>\n",
+        );
+        let _ = writeln!(out, "<title>{}</title>", escape(&feed.title));
+        if let Some(link) = &feed.link {
+            let _ = writeln!(out, "<link>{}</link>", escape(link));
+        }
+        let description = feed.description.as_deref().unwrap_or(&feed.title);
+        let _ = writeln!(out, "<description>{}</description>", escape(description));
+        let base = base_url.map(|url| format!("{}/", url.trim_end_matches('/')));
+        for episode in &self.episodes {
+            out.push_str("<item>\n");
+            let _ = writeln!(out, "<title>{}</title>", escape(&episode.title));
+            let _ = writeln!(
+                out,
+                "<guid isPermaLink=\"false\">{}</guid>",
+                escape(&episode.id)
+            );
+            if let Some(link) = &episode.link {
+                let _ = writeln!(out, "<link>{}</link>", escape(link));
+            }
+            if let Some(published) = &episode.published {
+                let _ = writeln!(out, "<pubDate>{}</pubDate>", escape(published));
+            }
+            let extension = episode.file.rsplit_once('.').map_or("", |(_, ext)| ext);
+            let _ = writeln!(
+                out,
+                "<enclosure url=\"{}{}\" length=\"{}\" type=\"{}\"/>",
+                escape(base.as_deref().unwrap_or_default()),
+                escape(&episode.file),
+                episode.bytes,
+                convert::content_type(extension).unwrap_or("audio/mpeg")
+            );
+            if let Some(duration_ms) = episode.duration_ms {
+                let _ = writeln!(
+                    out,
+                    "<itunes:duration>{}</itunes:duration>",
+                    duration_ms / 1000
+                );
+            }
+            out.push_str("</item>\n");
+        }
+        out.push_str("</channel>\n</rss>\n");
+        out
+    }
+}
+
+/// Speak the entries of `feed` as podcast episodes and write the podcast
+/// feed with an enclosure per episode
+///
+/// Episodes go through the batch engine with resume, so entries spoken
+/// by an earlier run into the same directory are not synthesized again.
+/// Episodes of earlier runs stay in the podcast after their entry leaves
+/// the feed; entries that fail are left out until a later run.
+#[instrument(skip_all, fields(backend = processor.backend_name(), voice = %voice))]
+pub async fn render(
+    processor: &TTSProcessor,
+    feed: &Feed,
+    voice: &str,
+    options: &PodcastOptions,
+) -> Result<RenderedPodcast, TTSError> {
+    let output_dir = options.output_dir.clone().unwrap_or_else(|| {
+        let name = format!("podcast_{}", slug(&feed.title));
+        processor
+            .output_manager()
+            .dir()
+            .join(name.trim_end_matches('_'))
+    });
+    let count = feed.entries.len();
+    let entries = &feed.entries[..options.max_episodes.map_or(count, |max| max.min(count))];
+    let ext = processor.config().file_extension();
+    let manifest = BatchManifest {
+        items: entries
+            .iter()
+            .map(|entry| BatchItem {
+                text: entry.speakable_text(),
+                output: Some(entry.file_name(ext)),
+                id: Some(entry.id.clone()),
+                ..BatchItem::default()
+            })
+            .collect(),
+    };
+    let mut state = PodcastState::load(&output_dir)?.unwrap_or_default();
+    let report = if manifest.items.is_empty() {
+        fs::create_dir_all(&output_dir).await?;
+        None
+    } else {
+        let batch = BatchOptions {
+            output_dir: Some(output_dir.clone()),
+            resume: true,
+            upload: None,
+        };
+        Some(batch::synthesize(processor, &manifest, voice, &batch).await?)
+    };
+
+    let mut new_episodes = 0;
+    let mut failed = 0;
+    let mut current = Vec::new();
+    for (entry, item) in entries.iter().zip(report.iter().flat_map(|r| &r.items)) {
+        let bytes = match &item.status {
+            ItemStatus::Synthesized { bytes } => {
+                new_episodes += 1;
+                *bytes
+            }
+            ItemStatus::Completed { bytes } | ItemStatus::Deduplicated { bytes, .. } => *bytes,
+            ItemStatus::Failed { .. } | ItemStatus::Pending => {
+                failed += 1;
+                continue;
+            }
+        };
+        current.push(Episode {
+            id: entry.id.clone(),
+            title: entry.title.clone(),
+            link: entry.link.clone(),
+            published: entry.published.clone(),
+            file: item.file.clone(),
+            bytes,
+            duration_ms: item.duration_ms,
+        });
+    }
+    // Feed order first, then episodes whose entries left the feed
+    state
+        .episodes
+        .retain(|old| !current.iter().any(|e| e.id == old.id));
+    current.append(&mut state.episodes);
+    state.episodes = current;
+    state.save(&output_dir)?;
+
+    let feed_path = output_dir.join(FEED_FILE);
+    fs::write(&feed_path, state.to_rss(feed, options.base_url.as_deref())).await?;
+    Ok(RenderedPodcast {
+        output_dir,
+        feed: feed_path,
+        new_episodes,
+        failed,
+        episodes: state.episodes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::mock::MockBackend;
+    use crate::config::TTSConfig;
+
+    #[test]
+    fn test_parse_rss_and_atom() {
+        let rss = r#"<?xml version="1.0"?><rss version="2.0"><channel>
+            <title>Daily &amp; Weekly</title><link>https://example.com</link>
+            <item><title><![CDATA[The Storm]]></title><guid>post-2</guid>
+              <pubDate>Tue, 13 Oct 2026 08:00:00 +0000</pubDate>
+              <description>&lt;p&gt;Rain &amp;amp; wind.&lt;/p&gt;</description></item>
+            <item><title>Calm</title><link>https://example.com/1</link>
+              <content:encoded><![CDATA[<p>Sun.</p><script>x()</script>]]></content:encoded></item>
+            </channel></rss>"#;
+        let feed = Feed::parse(rss).unwrap();
+        assert_eq!(feed.title, "Daily & Weekly");
+        assert_eq!(feed.entries.len(), 2);
+        assert_eq!(feed.entries[0].title, "The Storm");
+        assert_eq!(feed.entries[0].text, "Rain & wind.");
+        assert_eq!(feed.entries[1].id, "https://example.com/1");
+        assert_eq!(feed.entries[1].speakable_text(), "Calm.\n\nSun.");
+
+        let atom = r#"<feed xmlns="http://www.w3.org/2005/Atom"><title>Blog</title>
+            <entry><title>Hello</title><id>urn:1</id><link href="https://b.org/1"/>
+            <published>2026-10-13T08:00:00Z</published><summary>Hi there.</summary></entry>
+            </feed>"#;
+        let feed = Feed::parse(atom).unwrap();
+        let entry = &feed.entries[0];
+        assert_eq!(
+            (entry.id.as_str(), entry.link.as_deref()),
+            ("urn:1", Some("https://b.org/1"))
+        );
+        assert_eq!(
+            entry.published.as_deref(),
+            Some("Tue, 13 Oct 2026 08:00:00 +0000")
+        );
+        assert!(entry.file_name("mp3").starts_with("hello-"));
+        assert!(Feed::parse("<html><body>Hi</body></html>").is_err());
+    }
+
+    #[test]
+    fn test_podcast_rss() {
+        let state = PodcastState {
+            episodes: vec![Episode {
+                id: "post-2".to_string(),
+                title: "Storm & rain".to_string(),
+                link: None,
+                published: None,
+                file: "storm.mp3".to_string(),
+                bytes: 1200,
+                duration_ms: Some(61_500),
+            }],
+        };
+        let feed = Feed {
+            title: "Daily".to_string(),
+            ..Feed::default()
+        };
+        let rss = state.to_rss(&feed, Some("https://cdn.example.com/pod/"));
+        assert!(rss.contains("<title>Storm &amp; rain</title>"));
+        assert!(rss.contains(
+            "<enclosure url=\"https://cdn.example.com/pod/storm.mp3\" length=\"1200\" \
+             type=\"audio/mpeg\"/>"
+        ));
+        assert!(rss.contains("<itunes:duration>61</itunes:duration>"));
+    }
+
+    #[tokio::test]
+    async fn test_render_podcast() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-podcast-{}", uuid::Uuid::new_v4()));
+        let backend = MockBackend::new();
+        let processor = TTSProcessor::with_backend(TTSConfig::default(), Box::new(backend.clone()));
+        let rss =
+            |items: &str| format!("<rss><channel><title>Daily</title>{items}</channel></rss>");
+        let item = |id: &str| {
+            let description = format!("<description>About {id}.</description>");
+            format!("<item><title>{id}</title><guid>{id}</guid>{description}</item>")
+        };
+        let options = PodcastOptions {
+            output_dir: Some(dir.clone()),
+            base_url: Some("https://example.com/pod".to_string()),
+            max_episodes: None,
+        };
+
+        let feed = Feed::parse(&rss(&item("one"))).unwrap();
+        let podcast = render(&processor, &feed, "en-US-AriaNeural", &options)
+            .await
+            .unwrap();
+        assert_eq!(podcast.new_episodes, 1);
+        assert_eq!(backend.calls()[0].text, "one.\n\nAbout one.");
+
+        // The next run speaks only the new entry and keeps the old episode
+        let feed = Feed::parse(&rss(&format!("{}{}", item("two"), item("one")))).unwrap();
+        let podcast = render(&processor, &feed, "en-US-AriaNeural", &options)
+            .await
+            .unwrap();
+        assert_eq!((podcast.new_episodes, backend.calls().len()), (1, 2));
+        let ids: Vec<&str> = podcast.episodes.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, ["two", "one"]);
+        let xml = std::fs::read_to_string(&podcast.feed).unwrap();
+        let url = format!("https://example.com/pod/{}", podcast.episodes[0].file);
+        assert!(xml.contains(&format!("url=\"{}\"", url)));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,224 @@
+//! Character limits protecting users of paid backends from runaway jobs.
+//!
+//! [`TTSProcessor`](crate::TTSProcessor) reserves the characters of every
+//! chunk with [`Quota::reserve`] before sending it, gives them back if the
+//! chunk fails or is cancelled, and fails with
+//! [`TTSError::QuotaExceeded`] instead once `TTSConfig.max_characters_per_run`
+//! or `TTSConfig.max_characters_per_day` would be exceeded. The daily count is
+//! kept in a small JSON file so it survives restarts; it is not locked, so
+//! concurrent processes may overshoot the daily limit slightly.
+
+use crate::config::TTSConfig;
+use crate::models::TTSError;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+
+/// Default name of the daily counter file in the output directory
+pub const QUOTA_FILE: &str = ".hello-tts-quota.json";
+
+/// Characters counted for one day, as stored in the quota file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DailyUsage {
+    /// Local date, e.g. `2024-05-01`
+    pub date: String,
+    pub characters: u64,
+}
+
+impl DailyUsage {
+    /// Counter in `path` for `today`, starting from zero on a new day
+    pub fn load(path: &Path, today: &str) -> Result<Self, TTSError> {
+        let usage = match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e.into()),
+        };
+        if usage.date == today {
+            Ok(usage)
+        } else {
+            Ok(Self {
+                date: today.to_string(),
+                characters: 0,
+            })
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), TTSError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Run and daily character limits shared by a processor and its clones
+#[derive(Debug)]
+pub struct Quota {
+    per_run: Option<u64>,
+    per_day: Option<u64>,
+    path: PathBuf,
+    used: Mutex<u64>,
+}
+
+impl Quota {
+    pub fn from_config(config: &TTSConfig) -> Self {
+        let path = config
+            .quota_file
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| Path::new(&config.output_directory).join(QUOTA_FILE));
+        Self {
+            per_run: config.max_characters_per_run,
+            per_day: config.max_characters_per_day,
+            path,
+            used: Mutex::new(0),
+        }
+    }
+
+    /// Characters reserved by this run so far
+    pub fn used(&self) -> u64 {
+        *self.used.lock().unwrap()
+    }
+
+    /// Count `characters` against the limits, or fail without counting them
+    /// if that would exceed one
+    ///
+    /// The characters are given back when the returned [`Reservation`] is
+    /// dropped without being committed.
+    pub fn reserve(&self, characters: usize) -> Result<Reservation<'_>, TTSError> {
+        let characters = characters as u64;
+        let mut used = self.used.lock().unwrap();
+        if let Some(limit) = self.per_run {
+            if *used + characters > limit {
+                return Err(TTSError::QuotaExceeded(format!(
+                    "{} of {} characters per run used, {} more requested",
+                    used, limit, characters
+                )));
+            }
+        }
+        let mut day = None;
+        if let Some(limit) = self.per_day {
+            let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+            let mut daily = DailyUsage::load(&self.path, &today)?;
+            if daily.characters + characters > limit {
+                return Err(TTSError::QuotaExceeded(format!(
+                    "{} of {} characters per day used, {} more requested",
+                    daily.characters, limit, characters
+                )));
+            }
+            daily.characters += characters;
+            daily.save(&self.path)?;
+            day = Some(today);
+        }
+        *used += characters;
+        Ok(Reservation {
+            quota: self,
+            characters,
+            day,
+            committed: false,
+        })
+    }
+
+    /// Take back characters reserved on `day`; the daily count is left alone
+    /// once the day is over
+    fn release(&self, characters: u64, day: Option<&str>) {
+        let mut used = self.used.lock().unwrap();
+        *used = used.saturating_sub(characters);
+        let Some(day) = day else {
+            return;
+        };
+        let released = DailyUsage::load(&self.path, day).and_then(|mut daily| {
+            daily.characters = daily.characters.saturating_sub(characters);
+            daily.save(&self.path)
+        });
+        if let Err(e) = released {
+            warn!(
+                "Could not give back {} characters of the daily quota: {}",
+                characters, e
+            );
+        }
+    }
+}
+
+/// Characters counted by [`Quota::reserve`], given back on drop unless committed
+#[must_use = "the characters are given back when the reservation is dropped"]
+#[derive(Debug)]
+pub struct Reservation<'a> {
+    quota: &'a Quota,
+    characters: u64,
+    /// Day of the daily count the characters were added to
+    day: Option<String>,
+    committed: bool,
+}
+
+impl Reservation<'_> {
+    /// Keep the characters counted, once they were actually sent
+    pub fn commit(mut self) {
+        self.committed = true;
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        if !self.committed {
+            self.quota.release(self.characters, self.day.as_deref());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ErrorKind;
+
+    #[test]
+    fn test_run_and_daily_limits() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-quota-{}", uuid::Uuid::new_v4()));
+        let config = TTSConfig {
+            max_characters_per_run: Some(10),
+            max_characters_per_day: Some(15),
+            quota_file: Some(dir.join("quota.json").display().to_string()),
+            ..TTSConfig::default()
+        };
+        let quota = Quota::from_config(&config);
+        quota.reserve(6).unwrap().commit();
+        assert_eq!(
+            quota.reserve(5).unwrap_err().kind(),
+            ErrorKind::QuotaExceeded
+        );
+        quota.reserve(4).unwrap().commit();
+        assert_eq!(quota.used(), 10);
+
+        // A new run starts from zero but shares the daily count
+        let quota = Quota::from_config(&config);
+        assert!(quota.reserve(6).is_err());
+        quota.reserve(5).unwrap().commit();
+
+        let stale = DailyUsage {
+            date: "2000-01-01".to_string(),
+            characters: 15,
+        };
+        stale.save(&dir.join("quota.json")).unwrap();
+        quota.reserve(5).unwrap().commit();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_dropped_reservation_is_given_back() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-quota-{}", uuid::Uuid::new_v4()));
+        let config = TTSConfig {
+            max_characters_per_run: Some(10),
+            max_characters_per_day: Some(10),
+            quota_file: Some(dir.join("quota.json").display().to_string()),
+            ..TTSConfig::default()
+        };
+        let quota = Quota::from_config(&config);
+        drop(quota.reserve(8).unwrap());
+        assert_eq!(quota.used(), 0);
+        quota.reserve(10).unwrap().commit();
+        assert_eq!(quota.used(), 10);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
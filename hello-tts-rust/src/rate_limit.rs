@@ -0,0 +1,106 @@
+//! Token-bucket throttling of backend requests.
+//!
+//! The free Edge and Google endpoints reject clients that send requests in
+//! quick succession, so [`TTSProcessor`](crate::TTSProcessor) can pass every
+//! backend call through a [`RateLimiter`] set up from
+//! `TTSConfig.requests_per_minute`.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Token bucket allowing `requests_per_minute` requests with a small burst
+#[derive(Debug)]
+pub struct RateLimiter {
+    capacity: f64,
+    /// Tokens added per second
+    refill_rate: f64,
+    state: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// Create a limiter for `requests_per_minute` requests, allowing `burst`
+    /// requests back to back before spacing kicks in
+    ///
+    /// Returns `None` when `requests_per_minute` is zero, meaning unlimited.
+    pub fn new(requests_per_minute: u32, burst: u32) -> Option<Self> {
+        if requests_per_minute == 0 {
+            return None;
+        }
+        let capacity = f64::from(burst.max(1));
+        Some(Self {
+            capacity,
+            refill_rate: f64::from(requests_per_minute) / 60.0,
+            state: Mutex::new(Bucket {
+                tokens: capacity,
+                last_refill: Instant::now(),
+            }),
+        })
+    }
+
+    /// Wait until a request may be sent, then consume a token
+    ///
+    /// The token is taken before waiting, leaving the bucket in debt, so the
+    /// lock is not held while sleeping and waiting callers still go in order.
+    pub async fn acquire(&self) {
+        let wait = {
+            let mut bucket = self.state.lock().unwrap();
+            let now = Instant::now();
+            let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * self.refill_rate).min(self.capacity);
+            bucket.last_refill = now;
+            bucket.tokens -= 1.0;
+            if bucket.tokens >= 0.0 {
+                return;
+            }
+            Duration::from_secs_f64(-bucket.tokens / self.refill_rate)
+        };
+        tracing::debug!("Rate limit reached, waiting {:?}", wait);
+        tokio::time::sleep(wait).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zero_rate_is_unlimited() {
+        assert!(RateLimiter::new(0, 1).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_spaced() {
+        // 1200 requests per minute is one every 50ms
+        let limiter = RateLimiter::new(1200, 2).unwrap();
+        let start = Instant::now();
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert!(start.elapsed() < Duration::from_millis(40));
+
+        limiter.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(45));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_callers_are_spaced() {
+        let limiter = std::sync::Arc::new(RateLimiter::new(1200, 1).unwrap());
+        let start = Instant::now();
+        let tasks: Vec<_> = (0..3)
+            .map(|_| {
+                let limiter = limiter.clone();
+                tokio::spawn(async move { limiter.acquire().await })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        // The first request goes at once, the others 50ms apart
+        assert!(start.elapsed() >= Duration::from_millis(95));
+    }
+}
@@ -0,0 +1,252 @@
+//! Main content of web pages, for `speak --url`.
+//!
+//! [`extract`] finds the article in a page the way reader views do: it drops
+//! scripts, navigation, sidebars and other boilerplate, looks in the largest
+//! `<article>` (or `<main>`, or `<body>`), and keeps the headings and the
+//! paragraphs that are long enough and not mostly links.
+
+use crate::models::TTSError;
+use crate::text::{to_plain_text, InputFormat};
+use regex::Regex;
+use serde::Serialize;
+use std::sync::OnceLock;
+
+/// Paragraphs with fewer characters of text are taken for boilerplate
+pub const MIN_PARAGRAPH_CHARS: usize = 40;
+/// Paragraphs with a larger share of link text are taken for navigation
+pub const MAX_LINK_DENSITY: f64 = 0.5;
+
+/// Article text of a web page
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Article {
+    pub title: Option<String>,
+    /// Headings and paragraphs, separated by blank lines
+    pub text: String,
+}
+
+impl Article {
+    /// Title and text as spoken
+    pub fn speakable_text(&self) -> String {
+        match &self.title {
+            Some(title) if !self.text.starts_with(title.as_str()) => {
+                format!("{}.\n\n{}", title.trim_end_matches('.'), self.text)
+            }
+            _ => self.text.clone(),
+        }
+    }
+}
+
+/// Fetch `url` and extract its article; plain text responses are taken as
+/// they are
+pub async fn fetch(url: &str) -> Result<Article, TTSError> {
+    let client = reqwest::Client::builder()
+        .user_agent(concat!("hello-tts/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| TTSError::from_http("url", e))?;
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| TTSError::from_http("url", e))?;
+    let plain = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/plain"));
+    let body = response
+        .text()
+        .await
+        .map_err(|e| TTSError::from_http("url", e))?;
+    let article = if plain {
+        Article {
+            title: None,
+            text: body.trim().to_string(),
+        }
+    } else {
+        extract(&body)
+    };
+    if article.text.is_empty() {
+        return Err(TTSError::Config(format!("{} has no readable text", url)));
+    }
+    Ok(article)
+}
+
+/// Article of an HTML page
+pub fn extract(html: &str) -> Article {
+    let title = title(html);
+    let html = boilerplate().replace_all(html, " ");
+    let scope = largest(&html, "article")
+        .or_else(|| largest(&html, "main"))
+        .or_else(|| largest(&html, "body"))
+        .unwrap_or(&html);
+
+    let mut blocks: Vec<(bool, String)> = Vec::new();
+    for captures in block_pattern().captures_iter(scope) {
+        let heading = captures[1].to_ascii_lowercase().starts_with('h');
+        let text = to_plain_text(&captures[2], InputFormat::Html, false);
+        let text = text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        let long_enough = heading || text.chars().count() >= MIN_PARAGRAPH_CHARS;
+        if long_enough && link_density(&captures[2], text) <= MAX_LINK_DENSITY {
+            blocks.push((heading, text.to_string()));
+        }
+    }
+    // Headings only count when a paragraph follows them
+    while blocks.last().is_some_and(|(heading, _)| *heading) {
+        blocks.pop();
+    }
+    if title
+        .as_deref()
+        .is_some_and(|t| blocks.first().is_some_and(|(_, b)| b == t))
+    {
+        blocks.remove(0);
+    }
+
+    let text = if blocks.is_empty() {
+        to_plain_text(scope, InputFormat::Html, true)
+    } else {
+        blocks
+            .into_iter()
+            .map(|(heading, text)| match text.chars().last() {
+                Some('.' | '!' | '?' | ':') => text,
+                _ if heading => format!("{}.", text),
+                _ => text,
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    };
+    Article { title, text }
+}
+
+/// `og:title`, else `<title>` without a trailing site name, else the first `<h1>`
+fn title(html: &str) -> Option<String> {
+    static OG_TITLE: OnceLock<Regex> = OnceLock::new();
+    let og_title = OG_TITLE.get_or_init(|| {
+        Regex::new(r#"(?is)<meta\b[^>]*property\s*=\s*["']og:title["'][^>]*>"#).unwrap()
+    });
+    let from_meta = og_title.find(html).and_then(|tag| {
+        let content = Regex::new(r#"(?is)\bcontent\s*=\s*"([^"]*)""#).unwrap();
+        Some(content.captures(tag.as_str())?[1].to_string())
+    });
+    let from_title = || {
+        let title = element(html, "title")?;
+        // "Article name | Site" and "Article name - Site"
+        let name = [" | ", " - ", " — ", " :: "]
+            .iter()
+            .filter_map(|separator| title.rsplit_once(separator).map(|(name, _)| name))
+            .find(|name| name.split_whitespace().count() >= 3);
+        Some(name.unwrap_or(&title).to_string())
+    };
+    from_meta
+        .map(|t| to_plain_text(&t, InputFormat::Html, false))
+        .or_else(from_title)
+        .or_else(|| element(html, "h1"))
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+fn element(html: &str, name: &str) -> Option<String> {
+    let pattern = Regex::new(&format!(r"(?is)<{0}\b[^>]*>(.*?)</{0}\s*>", name)).unwrap();
+    let text = to_plain_text(&pattern.captures(html)?[1], InputFormat::Html, false);
+    Some(text.trim().to_string())
+}
+
+/// Content of the longest element named `name`
+fn largest<'a>(html: &'a str, name: &str) -> Option<&'a str> {
+    let pattern = Regex::new(&format!(r"(?is)<{0}\b[^>]*>(.*?)</{0}\s*>", name)).unwrap();
+    pattern
+        .captures_iter(html)
+        .filter_map(|captures| captures.get(1))
+        .map(|content| content.as_str())
+        .max_by_key(|content| content.len())
+}
+
+/// Share of the text of a block that is inside links
+fn link_density(html: &str, text: &str) -> f64 {
+    static LINK: OnceLock<Regex> = OnceLock::new();
+    let link = LINK.get_or_init(|| Regex::new(r"(?is)<a\b[^>]*>(.*?)</a\s*>").unwrap());
+    let linked: usize = link
+        .captures_iter(html)
+        .map(|c| {
+            to_plain_text(&c[1], InputFormat::Html, false)
+                .trim()
+                .chars()
+                .count()
+        })
+        .sum();
+    linked as f64 / text.chars().count().max(1) as f64
+}
+
+/// Elements that never hold the article, and elements whose class or id
+/// marks them as page furniture
+fn boilerplate() -> &'static Regex {
+    static BOILERPLATE: OnceLock<Regex> = OnceLock::new();
+    BOILERPLATE.get_or_init(|| {
+        let elements = "script|style|noscript|template|svg|iframe|form|button|nav|header|footer|\
+                        aside|figure";
+        let furniture = "comment|sidebar|related|share|social|promo|advert|cookie|newsletter|\
+                         subscribe|breadcrumb|menu";
+        let element = format!(r"<({0})\b[^>]*>.*?</({0})\s*>", elements);
+        let marked = format!(
+            r#"<(p|div|section|ul|ol)\b[^>]*\b(class|id)\s*=\s*["'][^"']*({})[^"']*["'][^>]*>"#,
+            furniture
+        ) + r"(?s:.*?)</(p|div|section|ul|ol)\s*>";
+        Regex::new(&format!(r"(?is)<!--.*?-->|{}|{}", element, marked)).unwrap()
+    })
+}
+
+fn block_pattern() -> &'static Regex {
+    static BLOCK: OnceLock<Regex> = OnceLock::new();
+    BLOCK.get_or_init(|| {
+        Regex::new(r"(?is)<(h[1-6]|p|blockquote|li)\b[^>]*>(.*?)</(?:h[1-6]|p|blockquote|li)\s*>")
+            .unwrap()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_article() {
+        let html = r#"<html><head><title>Storm hits the coast tonight | Daily News</title>
+            <script>track()</script></head><body>
+            <nav><a href="/">Home</a> <a href="/world">World</a></nav>
+            <div class="sidebar"><p>Most read: ten things you will not believe today.</p></div>
+            <article><h1>Storm hits the coast tonight</h1>
+            <p>A strong storm reached the coast on Tuesday evening, bringing heavy rain.</p>
+            <h2>What comes next</h2>
+            <p>Forecasters expect the wind to ease by Thursday, &quot;calm&quot; skies after.</p>
+            <p><a href="/a">Read more</a> <a href="/b">about storms on our weather page</a></p>
+            <p>Share this</p>
+            <h2>Related</h2>
+            </article>
+            <div id="comments"><p>I live there and the rain is much worse than described!</p>
+            </div>
+            </body></html>"#;
+        let article = extract(html);
+        assert_eq!(
+            article.title.as_deref(),
+            Some("Storm hits the coast tonight")
+        );
+        assert_eq!(
+            article.text,
+            "A strong storm reached the coast on Tuesday evening, bringing heavy rain.\n\n\
+             What comes next.\n\n\
+             Forecasters expect the wind to ease by Thursday, \"calm\" skies after."
+        );
+        assert!(article
+            .speakable_text()
+            .starts_with("Storm hits the coast tonight.\n\nA strong"));
+    }
+
+    #[test]
+    fn test_extract_without_paragraphs() {
+        let article = extract("<body><div>Just a short note.</div></body>");
+        assert_eq!(article.title, None);
+        assert_eq!(article.text, "Just a short note.");
+    }
+}
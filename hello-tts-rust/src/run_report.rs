@@ -0,0 +1,206 @@
+//! Machine-readable reports of CLI runs.
+//!
+//! `speak`, `batch` and `demo` take `--report report.json` to write a
+//! [`RunReport`] listing every item with its status, audio length, size and
+//! warnings, so CI jobs can assert on a run without parsing logs. The process
+//! then exits with the report's [`exit_code`](RunReport::exit_code):
+//! [`EXIT_OK`], [`EXIT_FAILED`] when the run failed or no item succeeded, or
+//! [`EXIT_PARTIAL`] when only some items failed.
+
+use crate::batch::{ItemReport, ItemStatus};
+use crate::models::{SynthesisResult, TTSError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Every item succeeded or was skipped
+pub const EXIT_OK: i32 = 0;
+/// The run failed, or every item that ran failed
+pub const EXIT_FAILED: i32 = 1;
+/// Some items failed and others succeeded; 2 is taken by usage errors
+pub const EXIT_PARTIAL: i32 = 3;
+
+/// What happened to one item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemOutcome {
+    Succeeded,
+    Failed,
+    /// Not synthesized, e.g. a dry run or a batch item still pending
+    Skipped,
+}
+
+/// One synthesized text of a run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportItem {
+    /// Batch item id or file, demo language, or output file of `speak`
+    pub id: String,
+    pub voice: String,
+    pub status: ItemOutcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_path: Option<PathBuf>,
+    #[serde(default)]
+    pub bytes: usize,
+    /// Length of the audio, when it could be decoded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// Time spent synthesizing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elapsed_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ReportItem {
+    pub fn new(id: impl Into<String>, voice: impl Into<String>, status: ItemOutcome) -> Self {
+        Self {
+            id: id.into(),
+            voice: voice.into(),
+            status,
+            output_path: None,
+            bytes: 0,
+            duration_ms: None,
+            elapsed_ms: None,
+            warnings: Vec::new(),
+            error: None,
+        }
+    }
+
+    pub fn failed(id: impl Into<String>, voice: impl Into<String>, error: impl ToString) -> Self {
+        Self {
+            error: Some(error.to_string()),
+            ..Self::new(id, voice, ItemOutcome::Failed)
+        }
+    }
+
+    pub fn from_result(id: impl Into<String>, result: &SynthesisResult) -> Self {
+        Self {
+            output_path: result.output_path.clone(),
+            bytes: result.bytes,
+            duration_ms: result.stats.as_ref().map(|stats| stats.duration_ms),
+            elapsed_ms: Some(result.elapsed_ms),
+            warnings: result.warnings.iter().map(ToString::to_string).collect(),
+            ..Self::new(id, &result.voice, ItemOutcome::Succeeded)
+        }
+    }
+
+    /// A batch item, found in `output_dir`
+    pub fn from_batch_item(item: &ItemReport, output_dir: &Path) -> Self {
+        let id = item.id.clone().unwrap_or_else(|| item.file.clone());
+        let mut report = match &item.status {
+            ItemStatus::Synthesized { bytes }
+            | ItemStatus::Completed { bytes }
+            | ItemStatus::Deduplicated { bytes, .. } => Self {
+                output_path: Some(output_dir.join(&item.file)),
+                bytes: *bytes,
+                ..Self::new(id, &item.voice, ItemOutcome::Succeeded)
+            },
+            ItemStatus::Failed { error } => Self::failed(id, &item.voice, error),
+            ItemStatus::Pending => Self::new(id, &item.voice, ItemOutcome::Skipped),
+        };
+        report.duration_ms = item.duration_ms;
+        report
+    }
+}
+
+/// Results of one CLI run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunReport {
+    pub command: String,
+    pub backend: String,
+    pub started_at: DateTime<Utc>,
+    pub elapsed_ms: u64,
+    pub exit_code: i32,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    /// Problems of the run rather than of one item
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// Error that ended the run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub items: Vec<ReportItem>,
+}
+
+impl RunReport {
+    /// An empty report of a run starting now
+    pub fn new(command: &str, backend: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            backend: backend.to_string(),
+            started_at: Utc::now(),
+            elapsed_ms: 0,
+            exit_code: EXIT_OK,
+            succeeded: 0,
+            failed: 0,
+            skipped: 0,
+            warnings: Vec::new(),
+            error: None,
+            items: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, item: ReportItem) {
+        match item.status {
+            ItemOutcome::Succeeded => self.succeeded += 1,
+            ItemOutcome::Failed => self.failed += 1,
+            ItemOutcome::Skipped => self.skipped += 1,
+        }
+        self.items.push(item);
+    }
+
+    pub fn warn(&mut self, warning: impl Into<String>) {
+        self.warnings.push(warning.into());
+    }
+
+    /// Record the end of the run, with the error that ended it if any, and
+    /// set its exit code
+    pub fn finish(&mut self, error: Option<String>) {
+        self.elapsed_ms = (Utc::now() - self.started_at).num_milliseconds().max(0) as u64;
+        self.exit_code = if self.failed > 0 && self.succeeded > 0 {
+            EXIT_PARTIAL
+        } else if error.is_some() || self.failed > 0 {
+            EXIT_FAILED
+        } else {
+            EXIT_OK
+        };
+        self.error = error;
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), TTSError> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes() {
+        let mut report = RunReport::new("batch", "mock");
+        report.push(ReportItem::new("a", "v", ItemOutcome::Skipped));
+        report.finish(None);
+        assert_eq!(report.exit_code, EXIT_OK);
+
+        report.push(ReportItem::failed("b", "v", "rejected"));
+        report.finish(None);
+        assert_eq!(report.exit_code, EXIT_FAILED);
+
+        report.push(ReportItem::new("c", "v", ItemOutcome::Succeeded));
+        report.finish(Some("1 batch items failed".to_string()));
+        assert_eq!(report.exit_code, EXIT_PARTIAL);
+        assert_eq!((report.succeeded, report.failed, report.skipped), (1, 1, 1));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["items"][1]["status"], "failed");
+        assert_eq!(json["items"][1]["error"], "rejected");
+    }
+}
@@ -0,0 +1,899 @@
+//! Small HTTP gateway in front of a [`TTSProcessor`] (`server` feature).
+//!
+//! Routes:
+//!
+//! - `POST /synthesize` takes `{"text": ..., "voice": ..., "input_format": ..., "backend": ...,
+//!   "save": false}` and returns the audio bytes, or with `"save": true` writes the audio to the
+//!   output directory and returns a JSON body with its URL
+//!   (synthesis taking longer than `TTSConfig.timeout` is cancelled)
+//! - `POST /synthesize?async=true` queues the request as a job, saving its audio as with
+//!   `"save": true`, and answers `202 Accepted` with the job; an optional `"webhook"` URL in
+//!   the body receives the finished [`Job`] as a JSON `POST`, and an optional `"priority"`
+//!   puts it ahead of jobs with lower ones (see [`crate::jobs`] for persistence and retries)
+//! - `GET /jobs/{id}` reports a job's status and, once it completed, its audio URL
+//! - `GET /jobs` lists the newest jobs, optionally filtered with `?status=` and `?limit=`
+//! - An `"output"` of `s3://`, `gs://` or `az://` form in the body (see
+//!   [`crate::output_target`]) writes the audio there instead of the output directory and
+//!   implies `"save": true`, with the object's URL as the returned `url`
+//! - `GET /audio/{name}` serves audio saved by `/synthesize`
+//! - `GET /voices` lists the backend's voices, optionally filtered with `?language=`
+//! - `GET /health` reports liveness, the configured backend and the health
+//!   of it and its fallbacks
+//! - `GET /metrics` exposes [`MetricsSnapshot::to_prometheus`](crate::metrics::MetricsSnapshot::to_prometheus)
+//! - `GET /ws/synthesize` upgrades to a WebSocket for streaming synthesis
+//!
+//! On the WebSocket, each text frame is a request with the same fields as
+//! `POST /synthesize` (`save` is ignored). The reply is a `start` event, then
+//! for every chunk a `chunk` event, its `boundary` events and one binary frame
+//! of audio, and finally a `done` or `error` event. Events are JSON objects
+//! tagged with `type`:
+//!
+//! ```json
+//! {"type": "start", "backend": "edge", "voice": "en-US-AriaNeural", "chunks": 2}
+//! {"type": "chunk", "index": 0, "text": "Hello there.", "bytes": 12345}
+//! {"type": "boundary", "chunk": 0, "offset_ms": 100, "duration_ms": 350, "text": "Hello"}
+//! {"type": "done", "chunks": 2, "bytes": 23456}
+//! ```
+
+use crate::convert::content_type;
+use crate::jobs::{Job, JobQueue, JobStatus};
+use crate::models::{ErrorKind, TTSError};
+use crate::naming::{FileNamer, NameFields};
+use crate::output_target::OutputTarget;
+use crate::text::InputFormat;
+use crate::tts_client::TTSProcessor;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Semaphore};
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::AbortOnDropHandle;
+use tracing::{info, warn};
+
+/// Shared state of the HTTP handlers
+struct ServerState {
+    processor: TTSProcessor,
+    output_dir: PathBuf,
+    jobs: JobQueue,
+    /// Limits running jobs to `TTSConfig.max_concurrent`; the others wait queued
+    workers: Semaphore,
+    http: reqwest::Client,
+}
+
+/// Body of `POST /synthesize`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SynthesizeRequest {
+    pub text: String,
+    /// Voice name; the configured default voice when omitted
+    pub voice: Option<String>,
+    /// Format of `text`; the configured input format when omitted
+    pub input_format: Option<InputFormat>,
+    /// Backend to synthesize with; the configured backend when omitted
+    pub backend: Option<String>,
+    /// Save the audio and return its URL instead of the audio bytes
+    #[serde(default)]
+    pub save: bool,
+    /// Object storage URL to save the audio to instead of the output directory
+    pub output: Option<String>,
+    /// URL receiving the finished job of `POST /synthesize?async=true`
+    pub webhook: Option<String>,
+    /// Queue position of `POST /synthesize?async=true`; higher runs first
+    #[serde(default)]
+    pub priority: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct SynthesizeQuery {
+    #[serde(default, rename = "async")]
+    run_async: bool,
+}
+
+/// Response of `POST /synthesize` with `"save": true`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedAudio {
+    pub url: String,
+    pub backend: String,
+    pub voice: String,
+    pub bytes: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct JobsQuery {
+    status: Option<JobStatus>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VoicesQuery {
+    language: Option<String>,
+}
+
+/// Build the router serving `processor`
+///
+/// Jobs queued by an earlier run start right away when this is called
+/// inside a Tokio runtime.
+pub fn router(processor: TTSProcessor) -> Result<Router, TTSError> {
+    let output_dir = PathBuf::from(&processor.config().output_directory);
+    let max_concurrent = processor.config().max_concurrent.max(1);
+    let jobs = JobQueue::from_config(processor.config())?;
+    let queued = jobs.queued()?;
+    let state = Arc::new(ServerState {
+        processor,
+        output_dir,
+        jobs,
+        workers: Semaphore::new(max_concurrent),
+        http: reqwest::Client::new(),
+    });
+    if queued > 0 && tokio::runtime::Handle::try_current().is_ok() {
+        info!("Resuming {} queued jobs", queued);
+        for _ in 0..queued.min(max_concurrent) {
+            spawn_worker(state.clone(), Duration::ZERO);
+        }
+    }
+
+    Ok(Router::new()
+        .route("/synthesize", post(synthesize))
+        .route("/jobs", get(list_jobs))
+        .route("/jobs/{id}", get(job))
+        .route("/audio/{name}", get(saved_audio))
+        .route("/voices", get(voices))
+        .route("/health", get(health))
+        .route("/metrics", get(metrics))
+        .route("/ws/synthesize", get(ws_synthesize))
+        .with_state(state))
+}
+
+/// Serve `processor` on `addr` until the process is stopped
+pub async fn serve(processor: TTSProcessor, addr: SocketAddr) -> Result<(), TTSError> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    if let Err(e) = processor.warm_up().await {
+        warn!("Warm-up failed, the first request may be slow: {}", e);
+    }
+    info!(
+        "Serving {} backend on http://{}",
+        processor.backend_name(),
+        addr
+    );
+    axum::serve(listener, router(processor)?).await?;
+    Ok(())
+}
+
+async fn synthesize(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<SynthesizeQuery>,
+    Json(request): Json<SynthesizeRequest>,
+) -> Result<Response, ApiError> {
+    if request.text.trim().is_empty() {
+        return Err(ApiError::bad_request("text cannot be empty"));
+    }
+    if let Some(webhook) = &request.webhook {
+        if !query.run_async {
+            return Err(ApiError::bad_request("webhook needs ?async=true"));
+        }
+        match reqwest::Url::parse(webhook) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+            _ => {
+                return Err(ApiError::bad_request(
+                    "webhook must be an http or https URL",
+                ))
+            }
+        }
+    }
+    if let Some(output) = &request.output {
+        if OutputTarget::parse(output)?.is_local() {
+            return Err(ApiError::bad_request(
+                "output must be an s3://, gs:// or az:// URL",
+            ));
+        }
+    }
+    if query.run_async {
+        return enqueue(state, request);
+    }
+
+    if !request.save && request.output.is_none() {
+        let (audio, _, _) = synthesize_audio(&state.processor, request).await?;
+        let extension = state.processor.config().file_extension();
+        let content_type = content_type(extension).unwrap_or("audio/mpeg");
+        return Ok(([(header::CONTENT_TYPE, content_type)], audio).into_response());
+    }
+    Ok(Json(synthesize_and_save(&state, request).await?).into_response())
+}
+
+/// Audio of `request` with the backend and voice it was synthesized with
+async fn synthesize_audio(
+    processor: &TTSProcessor,
+    request: SynthesizeRequest,
+) -> Result<(Vec<u8>, String, String), ApiError> {
+    let (processor, _deadline) = with_deadline(processor);
+    let voice = request
+        .voice
+        .unwrap_or_else(|| processor.config().default_voice.clone());
+    let format = request
+        .input_format
+        .unwrap_or(processor.config().input_format);
+    let backend = request
+        .backend
+        .unwrap_or_else(|| processor.backend_name().to_string());
+    let audio = processor
+        .synthesize_formatted_with_backend(&request.text, &voice, format, &backend)
+        .await?;
+    Ok((audio, backend, voice))
+}
+
+async fn synthesize_and_save(
+    state: &ServerState,
+    request: SynthesizeRequest,
+) -> Result<SavedAudio, ApiError> {
+    let output = request.output.clone();
+    let text = request.text.clone();
+    let (audio, backend, voice) = synthesize_audio(&state.processor, request).await?;
+    let extension = state.processor.config().file_extension();
+    if let Some(output) = output {
+        let target = OutputTarget::parse(&output)?;
+        let content_type = content_type(extension).unwrap_or("audio/mpeg");
+        target.write(&audio, content_type).await?;
+        return Ok(SavedAudio {
+            url: target.to_string(),
+            backend,
+            voice,
+            bytes: audio.len(),
+        });
+    }
+    tokio::fs::create_dir_all(&state.output_dir).await?;
+    // Deterministic servers name files by content, so a repeated request maps to the same URL
+    let name = if state.processor.config().deterministic {
+        let fields = NameFields {
+            backend: &backend,
+            voice: &voice,
+            text: &text,
+            ext: extension,
+        };
+        FileNamer::new("{hash}.{ext}", true)?.render(&fields)
+    } else {
+        format!("{}.{}", uuid::Uuid::new_v4(), extension)
+    };
+    tokio::fs::write(state.output_dir.join(&name), &audio).await?;
+    Ok(SavedAudio {
+        url: format!("/audio/{}", name),
+        backend,
+        voice,
+        bytes: audio.len(),
+    })
+}
+
+/// Queue `request` as a job and answer with it
+fn enqueue(state: Arc<ServerState>, request: SynthesizeRequest) -> Result<Response, ApiError> {
+    let body = serde_json::to_value(&request)
+        .map_err(|e| ApiError::bad_request(&format!("invalid request: {}", e)))?;
+    let job = state.jobs.push(&body, request.priority)?;
+    let location = format!("/jobs/{}", job.id);
+    spawn_worker(state, Duration::ZERO);
+    Ok((
+        StatusCode::ACCEPTED,
+        [(header::LOCATION, location)],
+        Json(job),
+    )
+        .into_response())
+}
+
+/// Run queued jobs after `delay` until none is due, taking one of the
+/// `TTSConfig.max_concurrent` worker slots
+fn spawn_worker(state: Arc<ServerState>, delay: Duration) {
+    tokio::spawn(async move {
+        tokio::time::sleep(delay).await;
+        let Ok(_permit) = state.workers.acquire().await else {
+            return;
+        };
+        loop {
+            match state.jobs.claim() {
+                Ok(Some(job)) => run_job(&state, job).await,
+                Ok(None) => break,
+                Err(e) => {
+                    warn!("Cannot read the job queue: {}", e);
+                    break;
+                }
+            }
+        }
+    });
+}
+
+async fn run_job(state: &Arc<ServerState>, job: Job) {
+    let finished = match serde_json::from_value::<SynthesizeRequest>(job.request) {
+        Ok(request) => {
+            let webhook = request.webhook.clone();
+            let finished = match synthesize_and_save(state, request).await {
+                Ok(saved) => serde_json::to_value(saved)
+                    .map_err(|e| TTSError::Config(e.to_string()))
+                    .and_then(|saved| state.jobs.complete(&job.id, &saved)),
+                Err(e) => state.jobs.fail(&job.id, &e.message, e.kind),
+            };
+            finished.map(|job| (job, webhook))
+        }
+        Err(e) => state
+            .jobs
+            .fail(
+                &job.id,
+                &format!("invalid request: {}", e),
+                ErrorKind::InvalidRequest,
+            )
+            .map(|job| (job, None)),
+    };
+    let (job, webhook) = match finished {
+        Ok(finished) => finished,
+        Err(e) => {
+            warn!("Cannot record the end of job {}: {}", job.id, e);
+            return;
+        }
+    };
+    if job.status == JobStatus::Queued {
+        let delay = state.jobs.retry_policy().delay(job.attempts);
+        info!("Job {} failed, retrying in {:?}", job.id, delay);
+        spawn_worker(state.clone(), delay);
+        return;
+    }
+
+    let Some(webhook) = webhook else { return };
+    let sent = state
+        .http
+        .post(&webhook)
+        .json(&job)
+        .timeout(state.processor.config().timeout)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    if let Err(e) = sent {
+        warn!("Webhook {} of job {} failed: {}", webhook, job.id, e);
+    }
+}
+
+async fn list_jobs(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<JobsQuery>,
+) -> Result<Response, ApiError> {
+    let jobs = state.jobs.list(query.status, query.limit.unwrap_or(100))?;
+    Ok(Json(jobs).into_response())
+}
+
+async fn job(
+    State(state): State<Arc<ServerState>>,
+    Path(id): Path<String>,
+) -> Result<Response, ApiError> {
+    match state.jobs.get(&id)? {
+        Some(job) => Ok(Json(job).into_response()),
+        None => Err(ApiError::not_found("no such job")),
+    }
+}
+
+/// Clone of `processor` that is cancelled once `TTSConfig.timeout` has
+/// passed, unless the returned handle is dropped first
+fn with_deadline(processor: &TTSProcessor) -> (TTSProcessor, AbortOnDropHandle<()>) {
+    let token = CancellationToken::new();
+    let timeout = processor.config().timeout;
+    let deadline = tokio::spawn({
+        let token = token.clone();
+        async move {
+            tokio::time::sleep(timeout).await;
+            token.cancel();
+        }
+    });
+    (
+        processor.with_cancellation(token),
+        AbortOnDropHandle::new(deadline),
+    )
+}
+
+async fn saved_audio(
+    State(state): State<Arc<ServerState>>,
+    Path(name): Path<String>,
+) -> Result<Response, ApiError> {
+    let content_type = name.rsplit_once('.').and_then(|(_, ext)| content_type(ext));
+    let valid = content_type.is_some()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+        && !name.contains("..");
+    let Some(content_type) = content_type.filter(|_| valid) else {
+        return Err(ApiError::not_found("no such audio file"));
+    };
+    match tokio::fs::read(state.output_dir.join(&name)).await {
+        Ok(audio) => Ok(([(header::CONTENT_TYPE, content_type)], audio).into_response()),
+        Err(_) => Err(ApiError::not_found("no such audio file")),
+    }
+}
+
+async fn voices(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<VoicesQuery>,
+) -> Result<Response, ApiError> {
+    let mut voices = state.processor.list_voices().await?;
+    if let Some(language) = &query.language {
+        voices.retain(|v| v.matches_language(language));
+    }
+    Ok(Json(voices).into_response())
+}
+
+async fn health(State(state): State<Arc<ServerState>>) -> Response {
+    let processor = &state.processor;
+    Json(serde_json::json!({
+        "status": "ok",
+        "backend": processor.backend_name(),
+        "backends": processor.backend_health(),
+        "version": env!("CARGO_PKG_VERSION"),
+    }))
+    .into_response()
+}
+
+async fn metrics(State(state): State<Arc<ServerState>>) -> Response {
+    let snapshot = state.processor.metrics_snapshot();
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        snapshot.to_prometheus(),
+    )
+        .into_response()
+}
+
+/// Frame sent to a WebSocket client
+#[derive(Debug)]
+enum StreamFrame {
+    Event(serde_json::Value),
+    Audio(Vec<u8>),
+}
+
+impl From<StreamFrame> for Message {
+    fn from(frame: StreamFrame) -> Self {
+        match frame {
+            StreamFrame::Event(event) => Message::Text(event.to_string().into()),
+            StreamFrame::Audio(audio) => Message::Binary(audio.into()),
+        }
+    }
+}
+
+async fn ws_synthesize(State(state): State<Arc<ServerState>>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| handle_socket(state, socket))
+}
+
+async fn handle_socket(state: Arc<ServerState>, mut socket: WebSocket) {
+    while let Some(Ok(message)) = socket.recv().await {
+        let request = match message {
+            Message::Text(text) => serde_json::from_str::<SynthesizeRequest>(&text),
+            Message::Close(_) => break,
+            _ => continue,
+        };
+        let request = match request {
+            Ok(request) => request,
+            Err(e) => {
+                let error = ApiError::bad_request(&format!("invalid request: {}", e));
+                if socket.send(error.into_frame().into()).await.is_err() {
+                    break;
+                }
+                continue;
+            }
+        };
+
+        let (tx, mut rx) = mpsc::channel(8);
+        let producer = stream_synthesis(&state.processor, request, tx);
+        let consumer = async {
+            while let Some(frame) = rx.recv().await {
+                if socket.send(frame.into()).await.is_err() {
+                    return false;
+                }
+            }
+            true
+        };
+        let ((), connected) = tokio::join!(producer, consumer);
+        if !connected {
+            break;
+        }
+    }
+}
+
+/// Synthesize `request`, sending events and audio to `tx` chunk by chunk in order
+async fn stream_synthesis(
+    processor: &TTSProcessor,
+    request: SynthesizeRequest,
+    tx: mpsc::Sender<StreamFrame>,
+) {
+    let voice = request
+        .voice
+        .unwrap_or_else(|| processor.config().default_voice.clone());
+    let format = request
+        .input_format
+        .unwrap_or(processor.config().input_format);
+    let chunks = processor.prepare_text(&request.text, &voice, format);
+    let mut frames = vec![StreamFrame::Event(serde_json::json!({
+        "type": "start",
+        "backend": processor.backend_name(),
+        "voice": voice,
+        "chunks": chunks.len(),
+    }))];
+    let mut total_bytes = 0;
+
+    let mut parts = processor
+        .synthesize_chunks(chunks.clone(), &voice)
+        .enumerate();
+    for frame in frames.drain(..) {
+        if tx.send(frame).await.is_err() {
+            return;
+        }
+    }
+    while let Some((index, part)) = parts.next().await {
+        let synthesized = match part {
+            Ok(synthesized) => synthesized,
+            Err(e) => {
+                let _ = tx.send(ApiError::from(e).into_frame()).await;
+                return;
+            }
+        };
+        total_bytes += synthesized.audio.len();
+        frames.push(StreamFrame::Event(serde_json::json!({
+            "type": "chunk",
+            "index": index,
+            "text": chunks[index],
+            "bytes": synthesized.audio.len(),
+        })));
+        frames.extend(synthesized.boundaries.into_iter().map(|b| {
+            StreamFrame::Event(serde_json::json!({
+                "type": "boundary",
+                "chunk": index,
+                "offset_ms": b.offset_ms,
+                "duration_ms": b.duration_ms,
+                "text": b.text,
+            }))
+        }));
+        frames.push(StreamFrame::Audio(synthesized.audio));
+        for frame in frames.drain(..) {
+            if tx.send(frame).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    frames.push(StreamFrame::Event(serde_json::json!({
+        "type": "done",
+        "chunks": chunks.len(),
+        "bytes": total_bytes,
+    })));
+    for frame in frames {
+        if tx.send(frame).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// JSON error body `{"error": ..., "kind": ...}` with a matching status code
+struct ApiError {
+    status: StatusCode,
+    kind: ErrorKind,
+    message: String,
+}
+
+impl ApiError {
+    fn bad_request(message: &str) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            kind: ErrorKind::InvalidRequest,
+            message: message.to_string(),
+        }
+    }
+
+    fn not_found(message: &str) -> Self {
+        Self {
+            status: StatusCode::NOT_FOUND,
+            kind: ErrorKind::InvalidRequest,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<TTSError> for ApiError {
+    fn from(error: TTSError) -> Self {
+        let kind = error.kind();
+        let status = match kind {
+            ErrorKind::InvalidRequest | ErrorKind::Config => StatusCode::BAD_REQUEST,
+            ErrorKind::VoiceNotFound => StatusCode::NOT_FOUND,
+            ErrorKind::RateLimited | ErrorKind::QuotaExceeded => StatusCode::TOO_MANY_REQUESTS,
+            ErrorKind::Timeout | ErrorKind::Cancelled => StatusCode::GATEWAY_TIMEOUT,
+            ErrorKind::Network
+            | ErrorKind::Unavailable
+            | ErrorKind::NotInstalled
+            | ErrorKind::Authentication => StatusCode::BAD_GATEWAY,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Self {
+            status,
+            kind,
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<std::io::Error> for ApiError {
+    fn from(error: std::io::Error) -> Self {
+        TTSError::Io(error).into()
+    }
+}
+
+impl ApiError {
+    fn into_frame(self) -> StreamFrame {
+        StreamFrame::Event(serde_json::json!({
+            "type": "error",
+            "error": self.message,
+            "kind": self.kind,
+        }))
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(serde_json::json!({ "error": self.message, "kind": self.kind })),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::TTSBackend;
+    use crate::config::TTSConfig;
+    use crate::models::Voice;
+    use async_trait::async_trait;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    struct EchoBackend;
+
+    #[async_trait]
+    impl TTSBackend for EchoBackend {
+        fn name(&self) -> &'static str {
+            "echo"
+        }
+
+        async fn synthesize_text(&self, text: &str, voice: &str) -> Result<Vec<u8>, TTSError> {
+            if voice == "missing" {
+                return Err(TTSError::VoiceNotFound(voice.to_string()));
+            }
+            Ok(text.as_bytes().to_vec())
+        }
+
+        async fn list_voices(&self) -> Result<Vec<Voice>, TTSError> {
+            Ok(vec![Voice::new(
+                "en-US-Test".to_string(),
+                "Test".to_string(),
+                "en-US".to_string(),
+                "Female".to_string(),
+            )])
+        }
+
+        async fn save_audio(&self, _audio_data: &[u8], _filename: &str) -> Result<(), TTSError> {
+            Ok(())
+        }
+    }
+
+    fn test_router() -> Router {
+        let config = TTSConfig {
+            text_normalization: crate::text::NormalizationConfig::disabled(),
+            output_directory: std::env::temp_dir()
+                .join("hello-tts-server-test")
+                .to_string_lossy()
+                .into_owned(),
+            jobs_file: Some(
+                std::env::temp_dir()
+                    .join(format!(
+                        "hello-tts-server-jobs-{}.sqlite",
+                        uuid::Uuid::new_v4()
+                    ))
+                    .to_string_lossy()
+                    .into_owned(),
+            ),
+            ..TTSConfig::default()
+        };
+        router(TTSProcessor::with_backend(config, Box::new(EchoBackend))).unwrap()
+    }
+
+    fn post_json(uri: &str, body: serde_json::Value) -> Request<Body> {
+        Request::post(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_synthesize_returns_audio() {
+        let response = test_router()
+            .oneshot(post_json(
+                "/synthesize",
+                serde_json::json!({"text": "Hi there"}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "audio/mpeg");
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"Hi there");
+    }
+
+    #[tokio::test]
+    async fn test_synthesize_saves_file() {
+        let router = test_router();
+        let response = router
+            .clone()
+            .oneshot(post_json(
+                "/synthesize",
+                serde_json::json!({"text": "Saved", "save": true}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let saved: SavedAudio = serde_json::from_slice(&body).unwrap();
+        assert_eq!(saved.bytes, 5);
+
+        let response = router
+            .oneshot(Request::get(&saved.url).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_synthesize_with_other_backend() {
+        let response = test_router()
+            .oneshot(post_json(
+                "/synthesize",
+                serde_json::json!({"text": "Hi", "backend": "mock", "save": true}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let saved: SavedAudio = serde_json::from_slice(&body).unwrap();
+        assert_eq!(saved.backend, "mock");
+
+        let response = test_router()
+            .oneshot(post_json(
+                "/synthesize",
+                serde_json::json!({"text": "Hi", "backend": "nope"}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_errors_map_to_status_codes() {
+        let response = test_router()
+            .oneshot(post_json(
+                "/synthesize",
+                serde_json::json!({"text": "Hi", "voice": "missing"}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = test_router()
+            .oneshot(post_json("/synthesize", serde_json::json!({"text": " "})))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = test_router()
+            .oneshot(post_json(
+                "/synthesize",
+                serde_json::json!({"text": "Hi", "output": "/tmp/hi.mp3"}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_async_job_with_webhook() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let receiver = Router::new().route(
+            "/hook",
+            post(move |Json(job): Json<serde_json::Value>| async move {
+                tx.send(job).await.unwrap();
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let hook = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, receiver).await });
+
+        let router = test_router();
+        let response = router
+            .clone()
+            .oneshot(post_json(
+                "/synthesize?async=true",
+                serde_json::json!({"text": "Later", "webhook": hook}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let location = response.headers()[header::LOCATION]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let job = rx.recv().await.unwrap();
+        assert_eq!(job["status"], "completed");
+        assert_eq!(job["bytes"], 5);
+        assert_eq!(format!("/jobs/{}", job["id"].as_str().unwrap()), location);
+        let response = router
+            .oneshot(Request::get(&location).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let polled: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(polled, job);
+
+        let response = test_router()
+            .oneshot(post_json(
+                "/synthesize",
+                serde_json::json!({"text": "Hi", "webhook": "http://example.com/hook"}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_stream_synthesis_frames() {
+        let config = TTSConfig {
+            max_chunk_chars: 12,
+            text_normalization: crate::text::NormalizationConfig::disabled(),
+            ..TTSConfig::default()
+        };
+        let processor = TTSProcessor::with_backend(config, Box::new(EchoBackend));
+        let request: SynthesizeRequest =
+            serde_json::from_str(r#"{"text": "One two. Three four."}"#).unwrap();
+        let (tx, mut rx) = mpsc::channel(16);
+        stream_synthesis(&processor, request, tx).await;
+
+        let mut frames = Vec::new();
+        while let Some(frame) = rx.recv().await {
+            frames.push(frame);
+        }
+        let types: Vec<String> = frames
+            .iter()
+            .map(|f| match f {
+                StreamFrame::Event(e) => e["type"].as_str().unwrap().to_string(),
+                StreamFrame::Audio(_) => "audio".to_string(),
+            })
+            .collect();
+        assert_eq!(types, ["start", "chunk", "audio", "chunk", "audio", "done"]);
+        assert!(matches!(&frames[2], StreamFrame::Audio(a) if a == b"One two."));
+    }
+
+    #[tokio::test]
+    async fn test_voices_and_health() {
+        let response = test_router()
+            .oneshot(
+                Request::get("/voices?language=fr")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"[]");
+
+        let response = test_router()
+            .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
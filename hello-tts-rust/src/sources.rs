@@ -0,0 +1,350 @@
+//! Text sources for document rendering.
+//!
+//! A [`TextSource`] reads one input format and hands out its chapters in
+//! reading order, so [`document::render`](crate::document::render) can ingest
+//! plain text, Markdown and EPUB books alike. [`open`] picks the source for a
+//! file by its extension.
+
+use crate::document::{Chapter, Document};
+use crate::models::TTSError;
+use crate::text::{to_plain_text, InputFormat};
+use regex::Regex;
+use std::collections::{HashMap, VecDeque};
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::sync::OnceLock;
+use zip::ZipArchive;
+
+/// A document format that yields chapters
+pub trait TextSource {
+    /// Title of the whole source, if it has one
+    fn title(&self) -> Option<&str>;
+
+    /// The next chapter, or `None` after the last one
+    fn next_chapter(&mut self) -> Result<Option<Chapter>, TTSError>;
+
+    /// Read the remaining chapters into a [`Document`]
+    fn to_document(&mut self) -> Result<Document, TTSError> {
+        let mut chapters = Vec::new();
+        while let Some(chapter) = self.next_chapter()? {
+            chapters.push(chapter);
+        }
+        if chapters.is_empty() {
+            return Err(TTSError::Config("Document has no chapters".to_string()));
+        }
+        Ok(Document {
+            title: self.title().map(str::to_string),
+            chapters,
+        })
+    }
+}
+
+/// The source for `path`: EPUB for `.epub`, plain text for `.txt`, and
+/// Markdown or a JSON document otherwise
+pub fn open(path: &Path) -> Result<Box<dyn TextSource>, TTSError> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("epub") => Ok(Box::new(EpubSource::open(path)?)),
+        Some("txt" | "text") => {
+            let title = path.file_stem().map(|s| s.to_string_lossy().into_owned());
+            Ok(Box::new(PlainSource::new(
+                title,
+                std::fs::read_to_string(path)?,
+            )))
+        }
+        _ => Ok(Box::new(MarkdownSource::parse(&std::fs::read_to_string(
+            path,
+        )?)?)),
+    }
+}
+
+/// Plain text read as a single chapter
+#[derive(Debug, Clone)]
+pub struct PlainSource {
+    title: Option<String>,
+    text: Option<String>,
+}
+
+impl PlainSource {
+    pub fn new(title: Option<String>, text: String) -> Self {
+        Self {
+            title,
+            text: Some(text),
+        }
+    }
+}
+
+impl TextSource for PlainSource {
+    fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    fn next_chapter(&mut self) -> Result<Option<Chapter>, TTSError> {
+        Ok(self
+            .text
+            .take()
+            .filter(|text| !text.trim().is_empty())
+            .map(|text| Chapter {
+                title: self.title.clone().unwrap_or_default(),
+                text,
+                sections: Vec::new(),
+            }))
+    }
+}
+
+/// Markdown split at `#` and `##` headings, see [`Document::from_markdown`]
+#[derive(Debug, Clone)]
+pub struct MarkdownSource {
+    title: Option<String>,
+    chapters: VecDeque<Chapter>,
+}
+
+impl MarkdownSource {
+    /// Parse Markdown, or a JSON document as [`Document::parse`] does
+    pub fn parse(input: &str) -> Result<Self, TTSError> {
+        let document = Document::parse(input)?;
+        Ok(Self {
+            title: document.title,
+            chapters: document.chapters.into(),
+        })
+    }
+}
+
+impl TextSource for MarkdownSource {
+    fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    fn next_chapter(&mut self) -> Result<Option<Chapter>, TTSError> {
+        Ok(self.chapters.pop_front())
+    }
+}
+
+/// The XHTML documents of an EPUB book, one chapter each, in spine order
+///
+/// Each chapter is titled by its first heading or `<title>`; documents
+/// without text, such as cover pages, are skipped.
+pub struct EpubSource {
+    archive: ZipArchive<Cursor<Vec<u8>>>,
+    title: Option<String>,
+    /// Archive paths of the remaining spine documents
+    spine: VecDeque<String>,
+}
+
+impl EpubSource {
+    pub fn open(path: &Path) -> Result<Self, TTSError> {
+        Self::from_bytes(std::fs::read(path)?)
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, TTSError> {
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).map_err(epub_error)?;
+        let container = read_entry(&mut archive, "META-INF/container.xml")?;
+        let opf_path = first_tag(&container, "rootfile")
+            .and_then(|tag| attribute(&tag, "full-path"))
+            .ok_or_else(|| TTSError::Config("EPUB container names no package".to_string()))?;
+        let opf = read_entry(&mut archive, &opf_path)?;
+        let base = opf_path.rsplit_once('/').map_or("", |(dir, _)| dir);
+
+        let manifest: HashMap<String, String> = tags(&opf, "item")
+            .into_iter()
+            .filter_map(|tag| Some((attribute(&tag, "id")?, attribute(&tag, "href")?)))
+            .collect();
+        let spine = tags(&opf, "itemref")
+            .into_iter()
+            .filter_map(|tag| manifest.get(&attribute(&tag, "idref")?).cloned())
+            .map(|href| join_path(base, &href))
+            .collect();
+        Ok(Self {
+            archive,
+            title: element_text(&opf, "dc:title"),
+            spine,
+        })
+    }
+}
+
+impl TextSource for EpubSource {
+    fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    fn next_chapter(&mut self) -> Result<Option<Chapter>, TTSError> {
+        while let Some(path) = self.spine.pop_front() {
+            let xhtml = read_entry(&mut self.archive, &path)?;
+            let heading = heading_pattern().find(&xhtml);
+            let title = heading
+                .map(|h| to_plain_text(h.as_str(), InputFormat::Html, false))
+                .or_else(|| element_text(&xhtml, "title"))
+                .unwrap_or_default();
+            // The title is spoken as the chapter heading, not again in the text
+            let body = match heading {
+                Some(h) => format!("{}{}", &xhtml[..h.start()], &xhtml[h.end()..]),
+                None => xhtml,
+            };
+            let text = to_plain_text(&body, InputFormat::Html, true);
+            if !text.trim().is_empty() {
+                return Ok(Some(Chapter {
+                    title: title.trim().to_string(),
+                    text,
+                    sections: Vec::new(),
+                }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn read_entry(archive: &mut ZipArchive<Cursor<Vec<u8>>>, name: &str) -> Result<String, TTSError> {
+    let mut entry = archive.by_name(name).map_err(epub_error)?;
+    let mut content = String::new();
+    entry.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+fn epub_error(e: zip::result::ZipError) -> TTSError {
+    TTSError::Config(format!("Invalid EPUB: {}", e))
+}
+
+fn heading_pattern() -> &'static Regex {
+    static HEADING: OnceLock<Regex> = OnceLock::new();
+    HEADING.get_or_init(|| Regex::new(r"(?is)<h[1-3]\b[^>]*>.*?</h[1-3]\s*>").unwrap())
+}
+
+/// Opening tags named `name`, e.g. `<item id="c1" href="c1.xhtml"/>`
+pub(crate) fn tags(xml: &str, name: &str) -> Vec<String> {
+    let pattern = Regex::new(&format!(r"(?is)<{}\b[^>]*>", regex::escape(name))).unwrap();
+    pattern
+        .find_iter(xml)
+        .map(|m| m.as_str().to_string())
+        .collect()
+}
+
+pub(crate) fn first_tag(xml: &str, name: &str) -> Option<String> {
+    tags(xml, name).into_iter().next()
+}
+
+pub(crate) fn attribute(tag: &str, name: &str) -> Option<String> {
+    let pattern = format!(
+        r#"(?i)\s{}\s*=\s*("([^"]*)"|'([^']*)')"#,
+        regex::escape(name)
+    );
+    let captures = Regex::new(&pattern).unwrap().captures(tag)?;
+    let value = captures.get(2).or_else(|| captures.get(3))?.as_str();
+    Some(to_plain_text(value, InputFormat::Html, false))
+}
+
+/// Text of the first element named `name`, without its tags
+pub(crate) fn element_text(xml: &str, name: &str) -> Option<String> {
+    let text = to_plain_text(&element_content(xml, name)?, InputFormat::Html, false);
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Content of the first element named `name`, with a CDATA section unwrapped
+pub(crate) fn element_content(xml: &str, name: &str) -> Option<String> {
+    let name = regex::escape(name);
+    let pattern = Regex::new(&format!(r"(?is)<{}\b[^>]*>(.*?)</{}\s*>", name, name)).unwrap();
+    let content = pattern.captures(xml)?.get(1)?.as_str().trim();
+    let content = content
+        .strip_prefix("<![CDATA[")
+        .and_then(|c| c.strip_suffix("]]>"))
+        .unwrap_or(content);
+    Some(content.to_string())
+}
+
+/// Resolve `href` from the package document against its directory `base`
+fn join_path(base: &str, href: &str) -> String {
+    let href = href
+        .split('#')
+        .next()
+        .unwrap_or_default()
+        .replace("%20", " ");
+    let mut parts: Vec<&str> = base.split('/').filter(|p| !p.is_empty()).collect();
+    for part in href.split('/') {
+        match part {
+            ".." => {
+                parts.pop();
+            }
+            "." | "" => {}
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    fn epub() -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default();
+        let files = [
+            (
+                "META-INF/container.xml",
+                r#"<container><rootfiles><rootfile full-path="OEBPS/content.opf"
+                   media-type="application/oebps-package+xml"/></rootfiles></container>"#,
+            ),
+            (
+                "OEBPS/content.opf",
+                r#"<package><metadata><dc:title>Tales &amp; Tides</dc:title></metadata>
+                   <manifest>
+                     <item id="cover" href="cover.xhtml" media-type="application/xhtml+xml"/>
+                     <item id="c1" href="text/one.xhtml" media-type="application/xhtml+xml"/>
+                     <item id="c2" href="text/two.xhtml#start" media-type="application/xhtml+xml"/>
+                   </manifest>
+                   <spine><itemref idref="cover"/><itemref idref="c2"/><itemref idref="c1"/></spine>
+                   </package>"#,
+            ),
+            (
+                "OEBPS/cover.xhtml",
+                "<html><body><img src=\"cover.jpg\"/></body></html>",
+            ),
+            (
+                "OEBPS/text/one.xhtml",
+                "<html><head><title>One</title></head><body><p>First.</p></body></html>",
+            ),
+            (
+                "OEBPS/text/two.xhtml",
+                "<html><body><h1>The <em>Storm</em></h1><p>Rain &amp; wind.</p></body></html>",
+            ),
+        ];
+        for (name, content) in files {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_epub_chapters_in_spine_order() {
+        let document = EpubSource::from_bytes(epub())
+            .unwrap()
+            .to_document()
+            .unwrap();
+        assert_eq!(document.title.as_deref(), Some("Tales & Tides"));
+        let titles: Vec<&str> = document.chapters.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["The Storm", "One"]);
+        assert_eq!(document.chapters[0].text.trim(), "Rain & wind.");
+        assert!(EpubSource::from_bytes(b"not a zip".to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_plain_and_markdown_sources() {
+        let mut plain = PlainSource::new(Some("notes".to_string()), "Hello.".to_string());
+        let document = plain.to_document().unwrap();
+        assert_eq!(document.chapters[0].title, "notes");
+        assert!(plain.next_chapter().unwrap().is_none());
+        assert!(PlainSource::new(None, " \n".to_string())
+            .to_document()
+            .is_err());
+
+        let mut markdown = MarkdownSource::parse("# One\nFirst.\n# Two\nSecond.\n").unwrap();
+        assert_eq!(markdown.next_chapter().unwrap().unwrap().title, "One");
+        assert_eq!(markdown.to_document().unwrap().chapters.len(), 1);
+    }
+}
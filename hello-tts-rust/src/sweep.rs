@@ -0,0 +1,525 @@
+//! Prosody and style sweeps: one text spoken in many ways.
+//!
+//! [`prosody`] writes one sample per rate and pitch, labeled with both, and an
+//! `index.html` page with the samples in a grid, so prosody settings can be
+//! tuned by ear instead of by trial runs. [`styles`] does the same for the
+//! speaking styles of an expressive voice.
+
+use crate::compare;
+use crate::compare::escape;
+use crate::document;
+use crate::models::TTSError;
+use crate::tts_client::TTSProcessor;
+use futures_util::stream::{self, StreamExt};
+use serde::Serialize;
+use std::fmt::Write;
+use std::path::PathBuf;
+use tokio::fs;
+use tracing::{instrument, warn};
+
+/// Most values a single range may expand to
+pub const MAX_RANGE_VALUES: usize = 50;
+
+/// Values and output directory of a sweep
+#[derive(Debug, Clone, Default)]
+pub struct SweepOptions {
+    /// Rates such as `-20%`; empty for just the configured rate
+    pub rates: Vec<String>,
+    /// Pitches such as `+5Hz`; empty for just the configured pitch
+    pub pitches: Vec<String>,
+    /// Styles such as `cheerful` for a style sweep; empty for every style
+    /// the voice lists
+    pub styles: Vec<String>,
+    /// Directory for the samples and index page; defaults to a directory
+    /// named after the text in `TTSConfig.output_directory`
+    pub output_dir: Option<PathBuf>,
+}
+
+/// One cell of a sweep
+#[derive(Debug, Clone, Serialize)]
+pub struct SweepSample {
+    pub rate: String,
+    pub pitch: String,
+    /// Sample file, relative to the sweep directory
+    pub file: String,
+    pub bytes: usize,
+    /// Why the sample could not be synthesized
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of [`prosody`]
+#[derive(Debug, Clone, Serialize)]
+pub struct Sweep {
+    pub text: String,
+    pub voice: String,
+    pub output_dir: PathBuf,
+    pub index: PathBuf,
+    /// Samples by rate, then by pitch
+    pub samples: Vec<SweepSample>,
+}
+
+/// One style of a style sweep
+#[derive(Debug, Clone, Serialize)]
+pub struct StyleSample {
+    pub style: String,
+    /// Sample file, relative to the sweep directory
+    pub file: String,
+    pub bytes: usize,
+    /// Why the sample could not be synthesized
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of [`styles`]
+#[derive(Debug, Clone, Serialize)]
+pub struct StyleSweep {
+    pub text: String,
+    pub voice: String,
+    pub output_dir: PathBuf,
+    pub index: PathBuf,
+    pub samples: Vec<StyleSample>,
+}
+
+/// Parse a comma separated list of prosody values and ranges
+///
+/// A range `FROM..TO:STEP` such as `-20%..+20%:10%` expands to signed
+/// values in the unit of its ends; other items are taken as they are.
+pub fn parse_values(spec: &str) -> Result<Vec<String>, TTSError> {
+    let mut values = Vec::new();
+    for item in spec
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+    {
+        match item.split_once("..") {
+            Some((from, rest)) => values.extend(parse_range(item, from, rest)?),
+            None => values.push(item.to_string()),
+        }
+    }
+    if values.is_empty() {
+        return Err(TTSError::Config(format!("No prosody values in {:?}", spec)));
+    }
+    Ok(values)
+}
+
+fn parse_range(item: &str, from: &str, rest: &str) -> Result<Vec<String>, TTSError> {
+    let invalid = |reason: &str| {
+        TTSError::Config(format!(
+            "Invalid range {:?}: {}; use FROM..TO:STEP, e.g. -20%..+20%:10%",
+            item, reason
+        ))
+    };
+    let (to, step) = rest
+        .split_once(':')
+        .ok_or_else(|| invalid("missing :STEP"))?;
+    let ((from, unit), (to, to_unit), (step, step_unit)) = (
+        split_unit(from).ok_or_else(|| invalid("bad start"))?,
+        split_unit(to).ok_or_else(|| invalid("bad end"))?,
+        split_unit(step).ok_or_else(|| invalid("bad step"))?,
+    );
+    if to_unit != unit || !(step_unit.is_empty() || step_unit == unit) {
+        return Err(invalid("units differ"));
+    }
+    if step <= 0 {
+        return Err(invalid("the step must be positive"));
+    }
+    let count = (from.abs_diff(to) / step.unsigned_abs()) as usize + 1;
+    if count > MAX_RANGE_VALUES {
+        return Err(invalid(&format!("more than {} values", MAX_RANGE_VALUES)));
+    }
+    let step = if to < from { -step } else { step };
+    Ok((0..count as i32)
+        .map(|i| format!("{:+}{}", from + i * step, unit))
+        .collect())
+}
+
+/// `-20%` as `(-20, "%")`
+fn split_unit(value: &str) -> Option<(i32, &str)> {
+    let value = value.trim();
+    let end = value
+        .char_indices()
+        .find(|&(i, c)| !(c.is_ascii_digit() || (i == 0 && (c == '+' || c == '-'))))
+        .map_or(value.len(), |(i, _)| i);
+    Some((value[..end].parse().ok()?, &value[end..]))
+}
+
+/// Sample file name for a rate and pitch, e.g. `rate-10pct_pitch+5Hz.mp3`
+pub fn sample_file_name(rate: &str, pitch: &str, ext: &str) -> String {
+    let label = |value: &str| -> String {
+        value
+            .trim()
+            .replace('%', "pct")
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '+' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    };
+    format!("rate{}_pitch{}.{}", label(rate), label(pitch), ext)
+}
+
+/// Sample file name for a style, e.g. `style_cheerful.mp3`
+pub fn style_file_name(style: &str, ext: &str) -> String {
+    crate::compare::sample_file_name(&format!("style_{}", style.trim()), ext)
+}
+
+/// Start of an index page, up to the table
+fn page_start(title: &str, voice: &str, text: &str) -> String {
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n<style>\n\
+         body {{ font-family: sans-serif; margin: 2em; }}\n\
+         td, th {{ padding: 0.4em 1em; text-align: left; }}\n\
+         </style>\n</head>\n<body>\n<h1>{title}</h1>\n"
+    );
+    let _ = writeln!(out, "<p>Voice <code>{}</code></p>", escape(voice));
+    let _ = writeln!(out, "<blockquote>{}</blockquote>", escape(text));
+    out
+}
+
+fn player(file: &str, error: Option<&str>) -> String {
+    match error {
+        Some(error) => format!("<em>{}</em>", escape(error)),
+        None => format!(
+            "<audio controls preload=\"none\" src=\"{}\"></audio>",
+            escape(file)
+        ),
+    }
+}
+
+impl Sweep {
+    /// HTML page with a row per rate and a column per pitch
+    pub fn to_html(&self) -> String {
+        let mut pitches: Vec<&str> = Vec::new();
+        let mut rates: Vec<&str> = Vec::new();
+        for sample in &self.samples {
+            if !pitches.contains(&sample.pitch.as_str()) {
+                pitches.push(&sample.pitch);
+            }
+            if !rates.contains(&sample.rate.as_str()) {
+                rates.push(&sample.rate);
+            }
+        }
+
+        let mut out = page_start("Prosody sweep", &self.voice, &self.text);
+        out.push_str("<table>\n<tr><th>Rate \\ Pitch</th>");
+        for pitch in &pitches {
+            let _ = write!(out, "<th>{}</th>", escape(pitch));
+        }
+        out.push_str("</tr>\n");
+        for rate in rates {
+            let _ = write!(out, "<tr><th>{}</th>", escape(rate));
+            for pitch in &pitches {
+                let cell = self
+                    .samples
+                    .iter()
+                    .find(|s| s.rate == rate && s.pitch == *pitch)
+                    .map(|sample| player(&sample.file, sample.error.as_deref()))
+                    .unwrap_or_default();
+                let _ = write!(out, "<td>{}</td>", cell);
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</table>\n</body>\n</html>\n");
+        out
+    }
+}
+
+impl StyleSweep {
+    /// HTML page with a row and an audio player per style
+    pub fn to_html(&self) -> String {
+        let mut out = page_start("Style sweep", &self.voice, &self.text);
+        out.push_str("<table>\n<tr><th>Style</th><th>Sample</th></tr>\n");
+        for sample in &self.samples {
+            let _ = writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td></tr>",
+                escape(&sample.style),
+                player(&sample.file, sample.error.as_deref())
+            );
+        }
+        out.push_str("</table>\n</body>\n</html>\n");
+        out
+    }
+}
+
+/// Speak `text` with `voice` at every rate and pitch of `options` and
+/// write an HTML page with the samples in a grid
+///
+/// A combination that fails, e.g. one the backend cannot speak, is
+/// listed with its error instead of a sample.
+#[instrument(skip_all, fields(backend = processor.backend_name(), voice = %voice))]
+pub async fn prosody(
+    processor: &TTSProcessor,
+    text: &str,
+    voice: &str,
+    options: &SweepOptions,
+) -> Result<Sweep, TTSError> {
+    let output_dir = options.output_dir.clone().unwrap_or_else(|| {
+        let name = format!("sweep_{}", document::slug(text));
+        processor
+            .output_manager()
+            .dir()
+            .join(name.trim_end_matches('_'))
+    });
+    fs::create_dir_all(&output_dir).await?;
+
+    let or_configured = |values: &[String], configured: &String| match values {
+        [] => vec![configured.clone()],
+        values => values.to_vec(),
+    };
+    let pitches = or_configured(&options.pitches, &processor.config().pitch);
+    let grid: Vec<(String, String)> = or_configured(&options.rates, &processor.config().rate)
+        .into_iter()
+        .flat_map(|rate| {
+            pitches
+                .iter()
+                .map(move |pitch| (rate.clone(), pitch.clone()))
+        })
+        .collect();
+    let results: Vec<Result<Vec<u8>, TTSError>> = stream::iter(&grid)
+        .map(|(rate, pitch)| {
+            let processor = processor.with_prosody(rate, pitch);
+            async move { processor.synthesize_text(text, voice).await }
+        })
+        .buffered(processor.config().max_concurrent.max(1))
+        .collect()
+        .await;
+    let mut samples = Vec::new();
+    for ((rate, pitch), result) in grid.into_iter().zip(results) {
+        let file = sample_file_name(&rate, &pitch, processor.config().file_extension());
+        let (bytes, error) = match result {
+            Ok(audio) => {
+                fs::write(output_dir.join(&file), &audio).await?;
+                (audio.len(), None)
+            }
+            Err(e) => {
+                warn!("Rate {} and pitch {} failed: {}", rate, pitch, e);
+                (0, Some(e.to_string()))
+            }
+        };
+        samples.push(SweepSample {
+            rate,
+            pitch,
+            file,
+            bytes,
+            error,
+        });
+    }
+
+    let index = output_dir.join(compare::INDEX_FILE);
+    let sweep = Sweep {
+        text: text.to_string(),
+        voice: voice.to_string(),
+        output_dir,
+        index,
+        samples,
+    };
+    fs::write(&sweep.index, sweep.to_html()).await?;
+    Ok(sweep)
+}
+
+/// Speak `text` with `voice` in every style of `options.styles`, or
+/// every style the voice lists, and write an HTML page to compare them
+///
+/// A style that fails, e.g. on a backend that cannot speak in styles,
+/// is listed with its error instead of a sample.
+#[instrument(skip_all, fields(backend = processor.backend_name(), voice = %voice))]
+pub async fn styles(
+    processor: &TTSProcessor,
+    text: &str,
+    voice: &str,
+    options: &SweepOptions,
+) -> Result<StyleSweep, TTSError> {
+    let styles = if options.styles.is_empty() {
+        let voices = processor.list_voices().await?;
+        let listed = voices
+            .into_iter()
+            .find(|v| v.name.eq_ignore_ascii_case(voice))
+            .ok_or_else(|| TTSError::VoiceNotFound(voice.to_string()))?;
+        if listed.styles.is_empty() {
+            return Err(TTSError::Config(format!(
+                "Voice {} lists no speaking styles",
+                voice
+            )));
+        }
+        listed.styles
+    } else {
+        options.styles.clone()
+    };
+    let output_dir = options.output_dir.clone().unwrap_or_else(|| {
+        let name = format!("styles_{}", document::slug(voice));
+        processor
+            .output_manager()
+            .dir()
+            .join(name.trim_end_matches('_'))
+    });
+    fs::create_dir_all(&output_dir).await?;
+
+    let results: Vec<Result<Vec<u8>, TTSError>> = stream::iter(&styles)
+        .map(|style| {
+            let processor = processor.with_style(style);
+            async move { processor.synthesize_text(text, voice).await }
+        })
+        .buffered(processor.config().max_concurrent.max(1))
+        .collect()
+        .await;
+    let mut samples = Vec::new();
+    for (style, result) in styles.into_iter().zip(results) {
+        let file = style_file_name(&style, processor.config().file_extension());
+        let (bytes, error) = match result {
+            Ok(audio) => {
+                fs::write(output_dir.join(&file), &audio).await?;
+                (audio.len(), None)
+            }
+            Err(e) => {
+                warn!("Style {} failed: {}", style, e);
+                (0, Some(e.to_string()))
+            }
+        };
+        samples.push(StyleSample {
+            style,
+            file,
+            bytes,
+            error,
+        });
+    }
+
+    let index = output_dir.join(compare::INDEX_FILE);
+    let sweep = StyleSweep {
+        text: text.to_string(),
+        voice: voice.to_string(),
+        output_dir,
+        index,
+        samples,
+    };
+    fs::write(&sweep.index, sweep.to_html()).await?;
+    Ok(sweep)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::mock::MockBackend;
+    use crate::config::TTSConfig;
+    use crate::models::Voice;
+
+    #[test]
+    fn test_parse_values() {
+        assert_eq!(
+            parse_values("-20%..+20%:10%").unwrap(),
+            ["-20%", "-10%", "+0%", "+10%", "+20%"]
+        );
+        assert_eq!(
+            parse_values("+10Hz..0Hz:5, slow").unwrap(),
+            ["+10Hz", "+5Hz", "+0Hz", "slow"]
+        );
+        assert!(parse_values("-20%..+20Hz:10").is_err());
+        assert!(parse_values("-20%..+20%").is_err());
+        assert!(parse_values("0%..100%:0%").is_err());
+        assert!(parse_values("0%..1000%:1%").is_err());
+        assert!(parse_values(" , ").is_err());
+    }
+
+    #[test]
+    fn test_index_page() {
+        let sample = |rate: &str, pitch: &str, error: Option<&str>| SweepSample {
+            rate: rate.to_string(),
+            pitch: pitch.to_string(),
+            file: sample_file_name(rate, pitch, "mp3"),
+            bytes: 10,
+            error: error.map(str::to_string),
+        };
+        let sweep = Sweep {
+            text: "Hi".to_string(),
+            voice: "en-US-AriaNeural".to_string(),
+            output_dir: PathBuf::from("out"),
+            index: PathBuf::from("out/index.html"),
+            samples: vec![
+                sample("-10%", "+0Hz", None),
+                sample("-10%", "+5Hz", None),
+                sample("+10%", "+0Hz", Some("Too fast")),
+                sample("+10%", "+5Hz", None),
+            ],
+        };
+        let html = sweep.to_html();
+        assert!(html.contains("<th>+0Hz</th><th>+5Hz</th></tr>"));
+        assert!(html.contains("<tr><th>+10%</th><td><em>Too fast</em></td>"));
+        assert!(html.contains("src=\"rate-10pct_pitch+5Hz.mp3\""));
+    }
+
+    #[tokio::test]
+    async fn test_sweep_prosody() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-sweep-{}", uuid::Uuid::new_v4()));
+        let backend = MockBackend::new();
+        let processor = TTSProcessor::with_backend(TTSConfig::default(), Box::new(backend.clone()));
+        let options = SweepOptions {
+            rates: parse_values("-10%..+10%:10%").unwrap(),
+            pitches: vec!["+5Hz".to_string()],
+            output_dir: Some(dir.clone()),
+            ..SweepOptions::default()
+        };
+        let sweep = prosody(&processor, "Hi", "en-US-AriaNeural", &options)
+            .await
+            .unwrap();
+        assert_eq!(sweep.samples.len(), 3);
+        assert_eq!(sweep.samples[0].file, "rate-10pct_pitch+5Hz.mp3");
+        assert!(dir.join(&sweep.samples[2].file).exists());
+        let rates: Vec<String> = backend
+            .calls()
+            .into_iter()
+            .map(|call| call.options.unwrap().rate)
+            .collect();
+        assert_eq!(rates, ["-10%", "+0%", "+10%"]);
+        assert!(std::fs::read_to_string(dir.join("index.html"))
+            .unwrap()
+            .contains("+5Hz"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sweep_styles() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-styles-{}", uuid::Uuid::new_v4()));
+        let mut aria = Voice::new(
+            "en-US-AriaNeural".into(),
+            "Aria".into(),
+            "en-US".into(),
+            "Female".into(),
+        );
+        aria.styles = vec!["cheerful".to_string(), "sad".to_string()];
+        let backend = MockBackend::new().with_voices(vec![aria]);
+        let processor = TTSProcessor::with_backend(TTSConfig::default(), Box::new(backend.clone()));
+        let options = SweepOptions {
+            output_dir: Some(dir.clone()),
+            ..SweepOptions::default()
+        };
+        let sweep = styles(&processor, "Hi", "en-US-AriaNeural", &options)
+            .await
+            .unwrap();
+        assert_eq!(sweep.samples[1].file, "style_sad.mp3");
+        assert!(dir.join(&sweep.samples[0].file).exists());
+        let sent: Vec<Option<String>> = backend
+            .calls()
+            .into_iter()
+            .map(|call| call.options.unwrap().style)
+            .collect();
+        assert_eq!(
+            sent,
+            [Some("cheerful".to_string()), Some("sad".to_string())]
+        );
+        assert!(std::fs::read_to_string(dir.join("index.html"))
+            .unwrap()
+            .contains("sad"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let err = styles(&processor, "Hi", "en-US-GuyNeural", &options)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TTSError::VoiceNotFound(_)));
+    }
+}
@@ -0,0 +1,279 @@
+//! Pushing an output directory to another host after a run.
+//!
+//! A [`SyncTarget`] is one of
+//!
+//! - `sftp://[user@]host[:port]/remote/dir`: every file is uploaded with the
+//!   system `sftp` client in batch mode, so authentication comes from the SSH
+//!   agent, keys and `~/.ssh/config`
+//! - `rsync:DESTINATION`: `rsync -az --partial` copies the directory to any
+//!   destination rsync accepts, such as `user@host:/srv/audio/` or
+//!   `rsync://host/module/`
+//! - `command:COMMAND`: a shell command run with `{output_dir}` replaced by
+//!   the directory, which is also in `HELLO_TTS_OUTPUT_DIR`
+//!
+//! `TTSConfig.sync_target` sets the target for `batch` and `audiobook` runs.
+
+use crate::models::TTSError;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::info;
+
+/// Where an output directory is pushed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncTarget {
+    Sftp {
+        /// `host` or `user@host`
+        destination: String,
+        port: Option<u16>,
+        remote_dir: String,
+    },
+    Rsync(String),
+    Command(String),
+}
+
+impl std::str::FromStr for SyncTarget {
+    type Err = TTSError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(rest) = s.strip_prefix("sftp://") {
+            let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+            let (destination, port) = match authority.rsplit_once(':') {
+                Some((destination, port)) => {
+                    let port = port
+                        .parse()
+                        .map_err(|_| TTSError::Config(format!("Invalid SFTP port in {}", s)))?;
+                    (destination, Some(port))
+                }
+                None => (authority, None),
+            };
+            if destination.is_empty() {
+                return Err(TTSError::Config(format!("No host in {}", s)));
+            }
+            return Ok(Self::Sftp {
+                destination: destination.to_string(),
+                port,
+                remote_dir: format!("/{}", path.trim_end_matches('/')),
+            });
+        }
+        match s.split_once(':') {
+            Some(("rsync", destination)) if !destination.is_empty() => {
+                Ok(Self::Rsync(destination.to_string()))
+            }
+            Some(("command", command)) if !command.trim().is_empty() => {
+                Ok(Self::Command(command.to_string()))
+            }
+            _ => Err(TTSError::Config(format!(
+                "Invalid sync target {:?}; use sftp://host/dir, rsync:DESTINATION or \
+                 command:COMMAND",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for SyncTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sftp {
+                destination,
+                port: Some(port),
+                remote_dir,
+            } => write!(f, "sftp://{}:{}{}", destination, port, remote_dir),
+            Self::Sftp {
+                destination,
+                remote_dir,
+                ..
+            } => write!(f, "sftp://{}{}", destination, remote_dir),
+            Self::Rsync(destination) => write!(f, "rsync:{}", destination),
+            Self::Command(command) => write!(f, "command:{}", command),
+        }
+    }
+}
+
+/// Push the contents of `dir` to `target`
+pub async fn push(target: &SyncTarget, dir: &Path) -> Result<(), TTSError> {
+    let mut command;
+    let mut script = None;
+    match target {
+        SyncTarget::Sftp {
+            destination,
+            port,
+            remote_dir,
+        } => {
+            command = Command::new("sftp");
+            command.args(["-b", "-"]);
+            if let Some(port) = port {
+                command.args(["-P", &port.to_string()]);
+            }
+            command.arg(destination);
+            script = Some(sftp_script(dir, remote_dir)?);
+        }
+        SyncTarget::Rsync(destination) => {
+            let mut source = dir.as_os_str().to_owned();
+            source.push("/");
+            command = Command::new("rsync");
+            command
+                .args(["-az", "--partial"])
+                .arg(source)
+                .arg(destination);
+        }
+        SyncTarget::Command(line) => {
+            let line = line.replace("{output_dir}", &dir.to_string_lossy());
+            command = shell(&line);
+            command.env("HELLO_TTS_OUTPUT_DIR", dir);
+        }
+    }
+    info!("Syncing {} to {}", dir.display(), target);
+
+    let failed = |detail: String| {
+        TTSError::Io(std::io::Error::other(format!(
+            "Sync to {} failed: {}",
+            target, detail
+        )))
+    };
+    let mut child = command
+        .stdin(if script.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| failed(e.to_string()))?;
+    if let (Some(script), Some(mut stdin)) = (script, child.stdin.take()) {
+        stdin.write_all(script.as_bytes()).await?;
+    }
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(failed(match stderr.trim() {
+            "" => output.status.to_string(),
+            stderr => stderr.to_string(),
+        }));
+    }
+    Ok(())
+}
+
+/// Command running `line` in the platform's shell
+#[cfg(unix)]
+pub(crate) fn shell(line: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.args(["-c", line]);
+    command
+}
+
+#[cfg(windows)]
+pub(crate) fn shell(line: &str) -> Command {
+    let mut command = Command::new("cmd");
+    command.args(["/C", line]);
+    command
+}
+
+/// `sftp` batch commands creating `remote_dir` and its subdirectories and
+/// uploading every file of `dir`; a leading `-` lets `mkdir` fail for
+/// directories that already exist
+fn sftp_script(dir: &Path, remote_dir: &str) -> Result<String, TTSError> {
+    let mut script = String::new();
+    let mut prefix = String::new();
+    for part in remote_dir.split('/').filter(|p| !p.is_empty()) {
+        prefix = format!("{}/{}", prefix, part);
+        script.push_str(&format!("-mkdir {}\n", quote(&prefix)));
+    }
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        let mut entries: Vec<_> =
+            std::fs::read_dir(dir.join(&relative))?.collect::<Result<_, _>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let relative = relative.join(entry.file_name());
+            let remote = format!(
+                "{}/{}",
+                remote_dir.trim_end_matches('/'),
+                relative.to_string_lossy().replace('\\', "/")
+            );
+            if entry.file_type()?.is_dir() {
+                script.push_str(&format!("-mkdir {}\n", quote(&remote)));
+                pending.push(relative);
+            } else {
+                let local = dir.join(&relative);
+                script.push_str(&format!(
+                    "put {} {}\n",
+                    quote(&local.to_string_lossy()),
+                    quote(&remote)
+                ));
+            }
+        }
+    }
+    Ok(script)
+}
+
+fn quote(path: &str) -> String {
+    format!("\"{}\"", path.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_targets() {
+        let target: SyncTarget = "sftp://farm@render-01:2222/srv/audio/".parse().unwrap();
+        assert_eq!(
+            target,
+            SyncTarget::Sftp {
+                destination: "farm@render-01".to_string(),
+                port: Some(2222),
+                remote_dir: "/srv/audio".to_string(),
+            }
+        );
+        assert_eq!(target.to_string(), "sftp://farm@render-01:2222/srv/audio");
+        assert_eq!(
+            "rsync:host:/srv/".parse::<SyncTarget>().unwrap(),
+            SyncTarget::Rsync("host:/srv/".to_string())
+        );
+        assert!("scp://host/dir".parse::<SyncTarget>().is_err());
+        assert!("sftp://host:x/dir".parse::<SyncTarget>().is_err());
+    }
+
+    #[test]
+    fn test_sftp_script() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-sync-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("ch 1")).unwrap();
+        std::fs::write(dir.join("a.mp3"), b"a").unwrap();
+        std::fs::write(dir.join("ch 1").join("b.mp3"), b"b").unwrap();
+        let script = sftp_script(&dir, "/srv/audio").unwrap();
+        let local = dir.to_string_lossy();
+        assert_eq!(
+            script,
+            format!(
+                "-mkdir \"/srv\"\n-mkdir \"/srv/audio\"\nput \"{0}/a.mp3\" \"/srv/audio/a.mp3\"\n\
+                 -mkdir \"/srv/audio/ch 1\"\nput \"{0}/ch 1/b.mp3\" \"/srv/audio/ch 1/b.mp3\"\n",
+                local
+            )
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_command_target() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-sync-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let target: SyncTarget =
+            "command:touch {output_dir}/done && test -n \"$HELLO_TTS_OUTPUT_DIR\""
+                .parse()
+                .unwrap();
+        push(&target, &dir).await.unwrap();
+        assert!(dir.join("done").exists());
+
+        let failing: SyncTarget = "command:echo nope >&2; exit 3".parse().unwrap();
+        let err = push(&failing, &dir).await.unwrap_err();
+        assert!(err.to_string().contains("nope"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
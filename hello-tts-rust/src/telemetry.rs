@@ -0,0 +1,85 @@
+//! OpenTelemetry export of spans and synthesis metrics (`otel` feature).
+//!
+//! Spans emitted by the processor, backends and audio player carry `backend`,
+//! `voice`, `text_len` and `latency_ms` fields. [`init`] forwards them to an
+//! OpenTelemetry tracer; metrics are recorded through the global meter
+//! provider, so they are exported once the application installs one.
+
+use crate::models::{SynthesizedAudio, TTSError};
+use opentelemetry::metrics::{Counter, Histogram};
+use opentelemetry::trace::Tracer;
+use opentelemetry::{global, KeyValue};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+struct Instruments {
+    duration: Histogram<f64>,
+    bytes: Counter<u64>,
+    errors: Counter<u64>,
+}
+
+fn instruments() -> &'static Instruments {
+    static INSTRUMENTS: OnceLock<Instruments> = OnceLock::new();
+    INSTRUMENTS.get_or_init(|| {
+        let meter = global::meter("hello-tts");
+        Instruments {
+            duration: meter
+                .f64_histogram("tts.synthesis.duration")
+                .with_unit("s")
+                .with_description("Time spent synthesizing a text")
+                .build(),
+            bytes: meter
+                .u64_counter("tts.synthesis.bytes")
+                .with_unit("By")
+                .with_description("Audio bytes produced")
+                .build(),
+            errors: meter
+                .u64_counter("tts.synthesis.errors")
+                .with_description("Failed synthesis requests")
+                .build(),
+        }
+    })
+}
+
+/// Record the outcome of one synthesis request
+pub(crate) fn record_synthesis(
+    backend: &'static str,
+    duration: Duration,
+    result: &Result<SynthesizedAudio, TTSError>,
+) {
+    let instruments = instruments();
+    match result {
+        Ok(synthesized) => {
+            let attributes = [KeyValue::new("backend", backend)];
+            instruments
+                .duration
+                .record(duration.as_secs_f64(), &attributes);
+            instruments
+                .bytes
+                .add(synthesized.audio.len() as u64, &attributes);
+        }
+        Err(e) => instruments.errors.add(
+            1,
+            &[
+                KeyValue::new("backend", backend),
+                KeyValue::new("kind", e.kind().to_string()),
+            ],
+        ),
+    }
+}
+
+/// Install a global `tracing` subscriber that exports spans through `tracer`
+/// and prints events to stderr
+pub fn init<T>(tracer: T) -> Result<(), TTSError>
+where
+    T: Tracer + Send + Sync + 'static,
+    T::Span: Send + Sync,
+{
+    tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .try_init()
+        .map_err(|e| TTSError::Config(format!("Failed to install tracing subscriber: {}", e)))
+}
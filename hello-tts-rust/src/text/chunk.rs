@@ -0,0 +1,147 @@
+//! Splitting of long documents into backend-sized chunks.
+
+use super::sentences::split_sentences;
+
+/// Split `text` into chunks of at most `max_chars` characters
+///
+/// Paragraph boundaries are preferred, then sentence boundaries as found for
+/// `language` (a language code, locale or voice name), then word boundaries;
+/// only a single word longer than `max_chars` is cut mid-word.
+pub fn split_into_chunks(text: &str, max_chars: usize, language: &str) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+
+    for piece in pieces(text, max_chars, language) {
+        let separator = if current.is_empty() { 0 } else { 1 };
+        if char_len(&current) + separator + char_len(&piece) > max_chars && !current.is_empty() {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(&piece);
+    }
+
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Break text into units that each fit in `max_chars`
+fn pieces(text: &str, max_chars: usize, language: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    for paragraph in text.split("\n\n") {
+        let paragraph = paragraph.split_whitespace().collect::<Vec<_>>().join(" ");
+        if paragraph.is_empty() {
+            continue;
+        }
+        if char_len(&paragraph) <= max_chars {
+            out.push(paragraph);
+            continue;
+        }
+        for sentence in split_sentences(&paragraph, language) {
+            if char_len(sentence) <= max_chars {
+                out.push(sentence.to_string());
+            } else {
+                out.extend(split_words(sentence, max_chars));
+            }
+        }
+    }
+    out
+}
+
+fn split_words(text: &str, max_chars: usize) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        if char_len(word) > max_chars {
+            if !current.is_empty() {
+                out.push(std::mem::take(&mut current));
+            }
+            let chars: Vec<char> = word.chars().collect();
+            out.extend(
+                chars
+                    .chunks(max_chars)
+                    .map(|c| c.iter().collect::<String>()),
+            );
+            continue;
+        }
+        if !current.is_empty() && char_len(&current) + 1 + char_len(word) > max_chars {
+            out.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() {
+        out.push(current);
+    }
+    out
+}
+
+fn char_len(s: &str) -> usize {
+    s.chars().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_text_is_single_chunk() {
+        assert_eq!(
+            split_into_chunks("Hello world.", 100, "en"),
+            vec!["Hello world."]
+        );
+        assert!(split_into_chunks("  \n\n ", 100, "en").is_empty());
+    }
+
+    #[test]
+    fn test_split_on_sentences_and_words() {
+        let text = "First sentence here. Second one follows! Third?\n\nNew paragraph.";
+        let chunks = split_into_chunks(text, 25, "en");
+        assert_eq!(
+            chunks,
+            vec![
+                "First sentence here.",
+                "Second one follows!",
+                "Third? New paragraph."
+            ]
+        );
+        assert!(chunks.iter().all(|c| c.chars().count() <= 25));
+
+        let chunks = split_into_chunks("aaaa bbbb cccc", 9, "en");
+        assert_eq!(chunks, vec!["aaaa bbbb", "cccc"]);
+    }
+
+    #[test]
+    fn test_cjk_sentences() {
+        let chunks = split_into_chunks("你好。这是一个测试。再见！", 7, "zh-CN");
+        assert_eq!(chunks, vec!["你好。", "这是一个测试。", "再见！"]);
+    }
+
+    #[test]
+    fn test_rtl_text_is_kept_intact() {
+        // Arabic question mark, an embedded Latin word and a right-to-left mark
+        let text = "مرحبا بكم في TTS؟ هذا اختبار.\u{200F} شكرا!";
+        assert_eq!(
+            crate::text::split_sentences(text, "ar"),
+            vec!["مرحبا بكم في TTS؟", "هذا اختبار.\u{200F}", "شكرا!"]
+        );
+        let chunks = split_into_chunks(text, 20, "ar-SA");
+        assert_eq!(
+            chunks,
+            vec!["مرحبا بكم في TTS؟", "هذا اختبار.\u{200F} شكرا!"]
+        );
+        assert_eq!(chunks.join(" "), text);
+
+        let text = "שלום עולם. מה שלומך?";
+        assert_eq!(
+            split_into_chunks(text, 12, "he-IL"),
+            vec!["שלום עולם.", "מה שלומך?"]
+        );
+    }
+}
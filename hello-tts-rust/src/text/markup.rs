@@ -0,0 +1,229 @@
+//! Markdown and HTML input handling.
+//!
+//! Markup is reduced to plain text before synthesis. When structure is kept,
+//! headings and list items are turned into sentences of their own and blocks
+//! are separated by blank lines, which backends render as pauses.
+
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Format of the text handed to the processor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputFormat {
+    #[default]
+    Plain,
+    Markdown,
+    Html,
+}
+
+impl InputFormat {
+    /// Guess the input format from a file extension, defaulting to plain text
+    pub fn from_path(path: &Path) -> Self {
+        match path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase())
+            .as_deref()
+        {
+            Some("md") | Some("markdown") => InputFormat::Markdown,
+            Some("html") | Some("htm") | Some("xhtml") => InputFormat::Html,
+            _ => InputFormat::Plain,
+        }
+    }
+}
+
+impl std::str::FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "plain" | "text" | "txt" => Ok(InputFormat::Plain),
+            "markdown" | "md" => Ok(InputFormat::Markdown),
+            "html" | "htm" => Ok(InputFormat::Html),
+            other => Err(format!("Unknown input format: {}", other)),
+        }
+    }
+}
+
+/// Marks the end of a structural element that should be read as a sentence
+const SENTENCE_END: char = '\u{1}';
+
+/// Convert `input` to speakable plain text
+///
+/// With `structure` set, headings and list items become standalone sentences
+/// separated by paragraph breaks; otherwise markup is only stripped.
+pub fn to_plain_text(input: &str, format: InputFormat, structure: bool) -> String {
+    match format {
+        InputFormat::Plain => input.to_string(),
+        InputFormat::Markdown => finish(&markdown_to_marked_text(input), structure),
+        InputFormat::Html => finish(&html_to_marked_text(input), structure),
+    }
+}
+
+fn markdown_to_marked_text(input: &str) -> String {
+    let mut out = String::new();
+    let mut in_code_block = false;
+
+    for event in Parser::new(input) {
+        match event {
+            Event::Start(Tag::Heading { .. }) => out.push_str("\n\n"),
+            Event::End(TagEnd::Heading(_)) => {
+                out.push(SENTENCE_END);
+                out.push_str("\n\n");
+            }
+            Event::Start(Tag::Item) if !out.ends_with('\n') => out.push('\n'),
+            Event::End(TagEnd::Item) => {
+                out.push(SENTENCE_END);
+                out.push('\n');
+            }
+            Event::End(TagEnd::Paragraph) | Event::End(TagEnd::BlockQuote(_)) => {
+                out.push_str("\n\n")
+            }
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            Event::Text(text) if !in_code_block => out.push_str(&text),
+            Event::Code(code) => out.push_str(&code),
+            Event::Html(html) | Event::InlineHtml(html) => {
+                out.push_str(&html_to_marked_text(&html))
+            }
+            Event::SoftBreak => out.push(' '),
+            Event::HardBreak => out.push('\n'),
+            _ => {}
+        }
+    }
+
+    out
+}
+
+fn html_to_marked_text(input: &str) -> String {
+    static DROPPED: OnceLock<Regex> = OnceLock::new();
+    static SENTENCE_TAGS: OnceLock<Regex> = OnceLock::new();
+    static BLOCK_TAGS: OnceLock<Regex> = OnceLock::new();
+    static ANY_TAG: OnceLock<Regex> = OnceLock::new();
+
+    let dropped = DROPPED.get_or_init(|| {
+        Regex::new(r"(?is)<!--.*?-->|<(script|style|head|pre|nav|footer)\b[^>]*>.*?</(script|style|head|pre|nav|footer)>").unwrap()
+    });
+    let sentence_tags =
+        SENTENCE_TAGS.get_or_init(|| Regex::new(r"(?i)</(h[1-6]|li|dt|caption)\s*>").unwrap());
+    let block_tags = BLOCK_TAGS.get_or_init(|| {
+        Regex::new(r"(?i)</?(h[1-6]|p|div|br|li|ul|ol|dl|dt|dd|tr|table|section|article|blockquote|header|main)\b[^>]*>").unwrap()
+    });
+    let any_tag = ANY_TAG.get_or_init(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+
+    let text = dropped.replace_all(input, " ");
+    let text = sentence_tags.replace_all(&text, format!("{}\n\n", SENTENCE_END));
+    let text = block_tags.replace_all(&text, "\n\n");
+    let text = any_tag.replace_all(&text, "");
+    decode_entities(&text)
+}
+
+pub(super) fn decode_entities(text: &str) -> String {
+    static ENTITY: OnceLock<Regex> = OnceLock::new();
+    let re = ENTITY.get_or_init(|| Regex::new(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[a-zA-Z]+);").unwrap());
+    re.replace_all(text, |caps: &regex::Captures| {
+        let entity = &caps[1];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ if entity.starts_with("#x") || entity.starts_with("#X") => {
+                u32::from_str_radix(&entity[2..], 16)
+                    .ok()
+                    .and_then(char::from_u32)
+            }
+            _ if entity.starts_with('#') => entity[1..].parse().ok().and_then(char::from_u32),
+            _ => None,
+        };
+        decoded
+            .map(String::from)
+            .unwrap_or_else(|| caps[0].to_string())
+    })
+    .into_owned()
+}
+
+/// Resolve sentence markers and tidy whitespace
+fn finish(marked: &str, structure: bool) -> String {
+    let mut out = String::with_capacity(marked.len());
+    for c in marked.chars() {
+        if c == SENTENCE_END {
+            let last = out.trim_end().chars().last();
+            if structure && last.is_some_and(|c| !is_terminal_punctuation(c)) {
+                let trimmed = out.trim_end().len();
+                out.truncate(trimmed);
+                out.push('.');
+            }
+        } else {
+            out.push(c);
+        }
+    }
+
+    let mut paragraphs = Vec::new();
+    for block in out.split("\n\n") {
+        let lines: Vec<String> = block
+            .lines()
+            .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|line| !line.is_empty())
+            .collect();
+        if !lines.is_empty() {
+            paragraphs.push(lines.join(if structure { "\n" } else { " " }));
+        }
+    }
+
+    paragraphs.join(if structure { "\n\n" } else { " " })
+}
+
+fn is_terminal_punctuation(c: char) -> bool {
+    matches!(c, '.' | '!' | '?' | ':' | ';' | '。' | '！' | '？' | '…')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_structure() {
+        let md = "# Title\n\nSome *emphasis* and a [link](https://example.com).\n\n- first\n- second!\n\n```\ncode\n```\n";
+        assert_eq!(
+            to_plain_text(md, InputFormat::Markdown, true),
+            "Title.\n\nSome emphasis and a link.\n\nfirst.\nsecond!"
+        );
+        assert_eq!(
+            to_plain_text(md, InputFormat::Markdown, false),
+            "Title Some emphasis and a link. first second!"
+        );
+    }
+
+    #[test]
+    fn test_html_stripping() {
+        let html = "<html><head><title>x</title></head><body><h1>News</h1><script>var a;</script><p>Fish &amp; chips &#8364;5</p><ul><li>One</li></ul></body></html>";
+        assert_eq!(
+            to_plain_text(html, InputFormat::Html, true),
+            "News.\n\nFish & chips €5\n\nOne."
+        );
+    }
+
+    #[test]
+    fn test_input_format_detection() {
+        assert_eq!(
+            InputFormat::from_path(Path::new("README.md")),
+            InputFormat::Markdown
+        );
+        assert_eq!(
+            InputFormat::from_path(Path::new("a.HTML")),
+            InputFormat::Html
+        );
+        assert_eq!(
+            InputFormat::from_path(Path::new("notes.txt")),
+            InputFormat::Plain
+        );
+        assert_eq!("md".parse::<InputFormat>(), Ok(InputFormat::Markdown));
+    }
+}
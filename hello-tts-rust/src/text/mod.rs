@@ -0,0 +1,14 @@
+//! Text processing applied to input before it reaches a backend
+
+pub mod chunk;
+pub mod markup;
+pub mod normalize;
+pub mod sanitize;
+pub mod sentences;
+pub mod ssml;
+
+pub use chunk::split_into_chunks;
+pub use markup::{to_plain_text, InputFormat};
+pub use normalize::{normalize, EmojiHandling, NormalizationConfig, UrlHandling};
+pub use sanitize::{sanitize, Sanitized, TextWarning};
+pub use sentences::split_sentences;
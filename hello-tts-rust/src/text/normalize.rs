@@ -0,0 +1,648 @@
+//! Text normalization applied before synthesis.
+//!
+//! The pipeline expands dates, currency amounts, abbreviations and numbers into
+//! words and strips or verbalizes URLs and emoji. Rules are selected by the
+//! language code of the voice (`en`, `de`, `fr`, `es`); languages without a
+//! rule table only get the language-neutral steps. The pipeline is off unless
+//! `enabled` is set, so text reaches the backend as written by default.
+
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// How URLs in the input text are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UrlHandling {
+    /// Leave URLs untouched
+    Keep,
+    /// Remove URLs entirely
+    Strip,
+    /// Replace URLs with their spoken domain name ("example dot com")
+    Domain,
+}
+
+/// How emoji in the input text are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmojiHandling {
+    /// Leave emoji untouched
+    Keep,
+    /// Remove emoji entirely
+    Strip,
+    /// Replace known emoji with their names, strip the rest
+    Verbalize,
+}
+
+/// Configuration of the text normalization pipeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NormalizationConfig {
+    /// Run the pipeline at all; the other settings only apply when set
+    pub enabled: bool,
+    pub numbers: bool,
+    pub dates: bool,
+    pub currency: bool,
+    pub abbreviations: bool,
+    pub urls: UrlHandling,
+    pub emoji: EmojiHandling,
+}
+
+impl Default for NormalizationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            numbers: true,
+            dates: true,
+            currency: true,
+            abbreviations: true,
+            urls: UrlHandling::Domain,
+            emoji: EmojiHandling::Strip,
+        }
+    }
+}
+
+impl NormalizationConfig {
+    /// Configuration that leaves text untouched
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            ..Self::default()
+        }
+    }
+}
+
+/// Per-language rule table
+struct LanguageRules {
+    abbreviations: &'static [(&'static str, &'static str)],
+    /// Matches any of `abbreviations`, compiled on first use
+    abbreviation_regex: OnceLock<Regex>,
+    /// (symbol, singular, plural)
+    currencies: &'static [(&'static str, &'static str, &'static str)],
+    months: [&'static str; 12],
+    date_format: fn(u32, &str, i32) -> String,
+    dot: &'static str,
+}
+
+static EN: LanguageRules = LanguageRules {
+    abbreviation_regex: OnceLock::new(),
+    abbreviations: &[
+        ("Dr.", "Doctor"),
+        ("Mr.", "Mister"),
+        ("Mrs.", "Missus"),
+        ("Prof.", "Professor"),
+        ("e.g.", "for example"),
+        ("i.e.", "that is"),
+        ("etc.", "et cetera"),
+        ("vs.", "versus"),
+        ("approx.", "approximately"),
+    ],
+    currencies: &[
+        ("$", "dollar", "dollars"),
+        ("€", "euro", "euros"),
+        ("£", "pound", "pounds"),
+        ("¥", "yen", "yen"),
+    ],
+    months: [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ],
+    date_format: |day, month, year| {
+        format!(
+            "{} {}, {}",
+            month,
+            ordinal_to_words_en(day as u64),
+            year_to_words_en(year)
+        )
+    },
+    dot: "dot",
+};
+
+static DE: LanguageRules = LanguageRules {
+    abbreviation_regex: OnceLock::new(),
+    abbreviations: &[
+        ("z.B.", "zum Beispiel"),
+        ("z. B.", "zum Beispiel"),
+        ("usw.", "und so weiter"),
+        ("bzw.", "beziehungsweise"),
+        ("d.h.", "das heißt"),
+        ("Dr.", "Doktor"),
+        ("Nr.", "Nummer"),
+        ("ca.", "circa"),
+    ],
+    currencies: &[
+        ("$", "Dollar", "Dollar"),
+        ("€", "Euro", "Euro"),
+        ("£", "Pfund", "Pfund"),
+        ("¥", "Yen", "Yen"),
+    ],
+    months: [
+        "Januar",
+        "Februar",
+        "März",
+        "April",
+        "Mai",
+        "Juni",
+        "Juli",
+        "August",
+        "September",
+        "Oktober",
+        "November",
+        "Dezember",
+    ],
+    date_format: |day, month, year| format!("{}. {} {}", day, month, year),
+    dot: "Punkt",
+};
+
+static FR: LanguageRules = LanguageRules {
+    abbreviation_regex: OnceLock::new(),
+    abbreviations: &[
+        ("M.", "Monsieur"),
+        ("Mme", "Madame"),
+        ("Mlle", "Mademoiselle"),
+        ("p.ex.", "par exemple"),
+        ("etc.", "et cetera"),
+    ],
+    currencies: &[
+        ("$", "dollar", "dollars"),
+        ("€", "euro", "euros"),
+        ("£", "livre", "livres"),
+        ("¥", "yen", "yens"),
+    ],
+    months: [
+        "janvier",
+        "février",
+        "mars",
+        "avril",
+        "mai",
+        "juin",
+        "juillet",
+        "août",
+        "septembre",
+        "octobre",
+        "novembre",
+        "décembre",
+    ],
+    date_format: |day, month, year| format!("{} {} {}", day, month, year),
+    dot: "point",
+};
+
+static ES: LanguageRules = LanguageRules {
+    abbreviation_regex: OnceLock::new(),
+    abbreviations: &[
+        ("Sr.", "Señor"),
+        ("Sra.", "Señora"),
+        ("Dr.", "Doctor"),
+        ("p.ej.", "por ejemplo"),
+        ("etc.", "etcétera"),
+    ],
+    currencies: &[
+        ("$", "dólar", "dólares"),
+        ("€", "euro", "euros"),
+        ("£", "libra", "libras"),
+        ("¥", "yen", "yenes"),
+    ],
+    months: [
+        "enero",
+        "febrero",
+        "marzo",
+        "abril",
+        "mayo",
+        "junio",
+        "julio",
+        "agosto",
+        "septiembre",
+        "octubre",
+        "noviembre",
+        "diciembre",
+    ],
+    date_format: |day, month, year| format!("{} de {} de {}", day, month, year),
+    dot: "punto",
+};
+
+fn rules_for(language: &str) -> Option<&'static LanguageRules> {
+    match language_code(language).as_str() {
+        "en" => Some(&EN),
+        "de" => Some(&DE),
+        "fr" => Some(&FR),
+        "es" => Some(&ES),
+        _ => None,
+    }
+}
+
+/// Extract the lowercase language code from a voice name or locale
+/// (`en-US-AriaNeural` → `en`, `zh_CN` → `zh`)
+pub fn language_code(voice_or_locale: &str) -> String {
+    voice_or_locale
+        .split(['-', '_'])
+        .next()
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+/// Run the normalization pipeline on `text` using the rules for `language`
+///
+/// `language` may be a bare language code, a locale or a full voice name.
+pub fn normalize(text: &str, language: &str, config: &NormalizationConfig) -> String {
+    if !config.enabled {
+        return text.to_string();
+    }
+
+    let rules = rules_for(language);
+    let is_english = language_code(language) == "en";
+    let mut out = text.to_string();
+
+    out = match config.urls {
+        UrlHandling::Keep => out,
+        UrlHandling::Strip => url_regex().replace_all(&out, "").into_owned(),
+        UrlHandling::Domain => {
+            let dot = rules.map(|r| r.dot).unwrap_or(".");
+            url_regex()
+                .replace_all(&out, |caps: &Captures| spoken_domain(&caps[0], dot))
+                .into_owned()
+        }
+    };
+
+    out = match config.emoji {
+        EmojiHandling::Keep => out,
+        EmojiHandling::Strip => emoji_regex().replace_all(&out, "").into_owned(),
+        EmojiHandling::Verbalize => emoji_regex()
+            .replace_all(&out, |caps: &Captures| {
+                let name = if is_english {
+                    emoji_name(&caps[0])
+                } else {
+                    None
+                };
+                name.map(|n| format!(" {} ", n)).unwrap_or_default()
+            })
+            .into_owned(),
+    };
+
+    if let Some(rules) = rules {
+        if config.dates {
+            out = expand_dates(&out, rules);
+        }
+        if config.currency {
+            out = expand_currency(&out, rules, is_english);
+        }
+        if config.abbreviations {
+            out = expand_abbreviations(&out, rules);
+        }
+    }
+
+    if config.numbers && is_english {
+        out = expand_numbers_en(&out);
+    }
+
+    collapse_spaces(&out)
+}
+
+fn url_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?:https?://|www\.)[^\s<>]+[^\s<>.,;:!?)]").unwrap())
+}
+
+fn emoji_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"(?:[\x{1F300}-\x{1FAFF}\x{2600}-\x{27BF}\x{2B50}\x{1F1E6}-\x{1F1FF}][\x{FE0F}\x{200D}\x{1F3FB}-\x{1F3FF}]*)+",
+        )
+        .unwrap()
+    })
+}
+
+fn spoken_domain(url: &str, dot: &str) -> String {
+    let without_scheme = url
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_start_matches("www.");
+    let host = without_scheme
+        .split(['/', '?', '#', ':'])
+        .next()
+        .unwrap_or(without_scheme);
+    host.split('.')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(&format!(" {} ", dot))
+}
+
+fn emoji_name(emoji: &str) -> Option<&'static str> {
+    let base: String = emoji
+        .chars()
+        .filter(|c| !matches!(*c, '\u{FE0F}' | '\u{200D}' | '\u{1F3FB}'..='\u{1F3FF}'))
+        .collect();
+    let name = match base.as_str() {
+        "😀" => "grinning face",
+        "😂" => "face with tears of joy",
+        "🙂" => "slightly smiling face",
+        "😊" => "smiling face",
+        "😢" => "crying face",
+        "😉" => "winking face",
+        "👍" => "thumbs up",
+        "👎" => "thumbs down",
+        "👋" => "waving hand",
+        "🙏" => "folded hands",
+        "❤" => "red heart",
+        "🎉" => "party popper",
+        "🔥" => "fire",
+        "🚀" => "rocket",
+        "✅" => "check mark",
+        "❌" => "cross mark",
+        "⭐" => "star",
+        _ => return None,
+    };
+    Some(name)
+}
+
+fn expand_dates(text: &str, rules: &LanguageRules) -> String {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"\b(\d{4})-(\d{2})-(\d{2})\b").unwrap());
+    re.replace_all(text, |caps: &Captures| {
+        let year: i32 = caps[1].parse().unwrap_or(0);
+        let month: usize = caps[2].parse().unwrap_or(0);
+        let day: u32 = caps[3].parse().unwrap_or(0);
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return caps[0].to_string();
+        }
+        (rules.date_format)(day, rules.months[month - 1], year)
+    })
+    .into_owned()
+}
+
+fn expand_currency(text: &str, rules: &LanguageRules, is_english: bool) -> String {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| {
+        Regex::new(r"([$€£¥])\s?(\d{1,3}(?:,\d{3})+|\d+)(?:\.(\d{2}))?\b").unwrap()
+    });
+    re.replace_all(text, |caps: &Captures| {
+        let Some(&(_, singular, plural)) = rules
+            .currencies
+            .iter()
+            .find(|(symbol, _, _)| *symbol == &caps[1])
+        else {
+            return caps[0].to_string();
+        };
+        let whole = caps[2].replace(',', "");
+        let unit = if whole == "1" { singular } else { plural };
+        match caps.get(3) {
+            Some(cents) if is_english && cents.as_str() != "00" => {
+                let cents = cents.as_str().trim_start_matches('0');
+                let cent_unit = if cents == "1" { "cent" } else { "cents" };
+                format!("{} {} and {} {}", whole, unit, cents, cent_unit)
+            }
+            Some(cents) if !is_english && cents.as_str() != "00" => {
+                format!("{},{} {}", whole, cents.as_str(), unit)
+            }
+            _ => format!("{} {}", whole, unit),
+        }
+    })
+    .into_owned()
+}
+
+fn expand_abbreviations(text: &str, rules: &LanguageRules) -> String {
+    let re = rules.abbreviation_regex.get_or_init(|| {
+        let alternatives: Vec<String> = rules
+            .abbreviations
+            .iter()
+            .map(|(abbr, _)| regex::escape(abbr))
+            .collect();
+        Regex::new(&format!(r"(^|[\s(])({})", alternatives.join("|"))).unwrap()
+    });
+    re.replace_all(text, |caps: &Captures| {
+        let expansion = rules
+            .abbreviations
+            .iter()
+            .find(|(abbr, _)| *abbr == &caps[2])
+            .map_or(&caps[2], |(_, expansion)| expansion);
+        format!("{}{}", &caps[1], expansion)
+    })
+    .into_owned()
+}
+
+fn expand_numbers_en(text: &str) -> String {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| {
+        Regex::new(r"\b(\d{1,3}(?:,\d{3})+|\d+)((?:\.\d+)+)?(st|nd|rd|th)?\b").unwrap()
+    });
+    re.replace_all(text, |caps: &Captures| {
+        let Ok(whole) = caps[1].replace(',', "").parse::<u64>() else {
+            return caps[0].to_string();
+        };
+        if caps.get(3).is_some() && caps.get(2).is_none() {
+            return ordinal_to_words_en(whole);
+        }
+        let mut words = number_to_words_en(whole);
+        let Some(fraction) = caps.get(2) else {
+            return words;
+        };
+        let parts: Vec<&str> = fraction.as_str()[1..].split('.').collect();
+        if let [decimals] = parts[..] {
+            words.push_str(" point");
+            for digit in decimals.chars() {
+                words.push(' ');
+                words.push_str(ONES[digit.to_digit(10).unwrap_or(0) as usize]);
+            }
+        } else {
+            // A version or address such as 1.2.3, read part by part
+            for part in parts {
+                words.push_str(" point ");
+                match part.parse() {
+                    Ok(n) => words.push_str(&number_to_words_en(n)),
+                    Err(_) => words.push_str(part),
+                }
+            }
+        }
+        words
+    })
+    .into_owned()
+}
+
+const ONES: [&str; 20] = [
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+];
+
+const TENS: [&str; 10] = [
+    "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+
+const SCALES: [(u64, &str); 6] = [
+    (1_000_000_000_000_000_000, "quintillion"),
+    (1_000_000_000_000_000, "quadrillion"),
+    (1_000_000_000_000, "trillion"),
+    (1_000_000_000, "billion"),
+    (1_000_000, "million"),
+    (1_000, "thousand"),
+];
+
+/// Spell out a cardinal number in English
+pub fn number_to_words_en(n: u64) -> String {
+    if n < 20 {
+        return ONES[n as usize].to_string();
+    }
+    if n < 100 {
+        let tens = TENS[(n / 10) as usize];
+        return match n % 10 {
+            0 => tens.to_string(),
+            ones => format!("{}-{}", tens, ONES[ones as usize]),
+        };
+    }
+    if n < 1000 {
+        let hundreds = format!("{} hundred", ONES[(n / 100) as usize]);
+        return match n % 100 {
+            0 => hundreds,
+            rest => format!("{} {}", hundreds, number_to_words_en(rest)),
+        };
+    }
+    for (scale, name) in SCALES {
+        if n >= scale {
+            let head = format!("{} {}", number_to_words_en(n / scale), name);
+            return match n % scale {
+                0 => head,
+                rest => format!("{} {}", head, number_to_words_en(rest)),
+            };
+        }
+    }
+    unreachable!()
+}
+
+/// Spell out an ordinal number in English (`21` → `twenty-first`)
+pub fn ordinal_to_words_en(n: u64) -> String {
+    let cardinal = number_to_words_en(n);
+    let (head, last) = match cardinal.rfind([' ', '-']) {
+        Some(idx) => cardinal.split_at(idx + 1),
+        None => ("", cardinal.as_str()),
+    };
+    let last = match last {
+        "one" => "first".to_string(),
+        "two" => "second".to_string(),
+        "three" => "third".to_string(),
+        "five" => "fifth".to_string(),
+        "eight" => "eighth".to_string(),
+        "nine" => "ninth".to_string(),
+        "twelve" => "twelfth".to_string(),
+        word if word.ends_with('y') => format!("{}ieth", &word[..word.len() - 1]),
+        word => format!("{}th", word),
+    };
+    format!("{}{}", head, last)
+}
+
+fn year_to_words_en(year: i32) -> String {
+    let year = year.unsigned_abs() as u64;
+    match year {
+        1100..=1999 | 2010..=2099 if !year.is_multiple_of(100) => format!(
+            "{} {}",
+            number_to_words_en(year / 100),
+            if year % 100 < 10 {
+                format!("oh {}", number_to_words_en(year % 100))
+            } else {
+                number_to_words_en(year % 100)
+            }
+        ),
+        _ => number_to_words_en(year),
+    }
+}
+
+fn collapse_spaces(text: &str) -> String {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"[ \t]{2,}").unwrap());
+    re.replace_all(text.trim(), " ").into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number_to_words_en() {
+        assert_eq!(number_to_words_en(0), "zero");
+        assert_eq!(number_to_words_en(42), "forty-two");
+        assert_eq!(
+            number_to_words_en(1234),
+            "one thousand two hundred thirty-four"
+        );
+        assert_eq!(ordinal_to_words_en(21), "twenty-first");
+        assert_eq!(ordinal_to_words_en(12), "twelfth");
+    }
+
+    fn enabled() -> NormalizationConfig {
+        NormalizationConfig {
+            enabled: true,
+            ..NormalizationConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_normalize_english() {
+        let config = enabled();
+        assert_eq!(
+            normalize("Dr. Smith paid $3.50 on 2024-03-15", "en-US-AriaNeural", &config),
+            "Doctor Smith paid three dollars and fifty cents on March fifteenth, twenty twenty-four"
+        );
+        assert_eq!(
+            normalize("See https://example.com/docs 🚀", "en", &config),
+            "See example dot com"
+        );
+        assert_eq!(
+            normalize("Version 1.2.3 costs 2.5", "en", &config),
+            "Version one point two point three costs two point five"
+        );
+    }
+
+    #[test]
+    fn test_normalize_german_and_steps_disabled() {
+        let config = enabled();
+        assert_eq!(
+            normalize("z.B. am 2024-05-01 für €10", "de-DE-KatjaNeural", &config),
+            "zum Beispiel am 1. Mai 2024 für 10 Euro"
+        );
+        assert_eq!(
+            normalize("Dr. Weber: €10.00, z. B. €2.50", "de", &config),
+            "Doktor Weber: 10 Euro, zum Beispiel 2,50 Euro"
+        );
+
+        let config = NormalizationConfig {
+            numbers: false,
+            emoji: EmojiHandling::Verbalize,
+            ..enabled()
+        };
+        assert_eq!(normalize("Room 42 👍", "en", &config), "Room 42 thumbs up");
+        assert_eq!(
+            normalize("Room 42 👍", "en", &NormalizationConfig::disabled()),
+            "Room 42 👍"
+        );
+        assert_eq!(
+            normalize("Room 42 👍", "en", &NormalizationConfig::default()),
+            "Room 42 👍"
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! Input cleanup before text reaches a backend.
+//!
+//! [`sanitize`] removes control characters and byte order marks, which some
+//! backends reject with opaque errors, composes the text to Unicode NFC so
+//! decomposed accents (common in text copied on macOS) are spoken as one
+//! letter, and collapses runs of spaces and blank lines. It also reports
+//! letters of a script the voice's language is not written in, such as
+//! Cyrillic for an English voice, which are usually skipped or misread.
+
+use super::normalize::language_code;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use unicode_normalization::UnicodeNormalization;
+
+/// Something about the input the listener may not hear as written
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TextWarning {
+    /// Control characters that were removed
+    ControlCharacters { count: usize },
+    /// SSML tags that were removed because raw SSML is not allowed
+    SsmlTags { count: usize },
+    /// Letters of a script the voice's language is not written in
+    UnsupportedScript {
+        script: String,
+        count: usize,
+        language: String,
+    },
+}
+
+impl fmt::Display for TextWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextWarning::ControlCharacters { count } => {
+                write!(f, "Removed {} control characters", count)
+            }
+            TextWarning::SsmlTags { count } => write!(f, "Removed {} SSML tags", count),
+            TextWarning::UnsupportedScript {
+                script,
+                count,
+                language,
+            } => write!(
+                f,
+                "{} {} letters may not be spoken by a voice for {}",
+                count, script, language
+            ),
+        }
+    }
+}
+
+/// Result of [`sanitize`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sanitized {
+    pub text: String,
+    pub warnings: Vec<TextWarning>,
+}
+
+/// Clean up `text` for a voice of `language` (a language code, locale or
+/// voice name) and report what it may not speak
+pub fn sanitize(text: &str, language: &str) -> Sanitized {
+    let mut warnings = Vec::new();
+    let mut removed = 0;
+    let kept: String = text
+        .chars()
+        .filter(|&c| {
+            // Form feeds are page breaks and collapse like other whitespace
+            let strip = (c.is_control() && !matches!(c, '\n' | '\t' | '\u{c}')) || c == '\u{FEFF}';
+            removed += strip as usize;
+            !strip
+        })
+        .collect();
+    if removed > 0 {
+        warnings.push(TextWarning::ControlCharacters { count: removed });
+    }
+
+    let text = collapse_whitespace(&kept.nfc().collect::<String>());
+    warnings.extend(unsupported_scripts(&text, language));
+    Sanitized { text, warnings }
+}
+
+/// Single spaces within lines and at most one blank line between paragraphs
+fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::new();
+    let mut blank_lines = 0;
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() {
+            blank_lines += 1;
+            continue;
+        }
+        if !out.is_empty() {
+            out.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+        }
+        out.push_str(&line);
+        blank_lines = 0;
+    }
+    out
+}
+
+/// Scripts a language is written in, besides Latin letters, which most voices
+/// spell out or read as English
+fn scripts_of(language: &str) -> Option<&'static [&'static str]> {
+    Some(match language {
+        "ru" | "uk" | "be" | "bg" | "mk" | "sr" | "kk" | "mn" => &["Cyrillic"],
+        "el" => &["Greek"],
+        "ar" | "fa" | "ur" | "ps" => &["Arabic"],
+        "he" | "yi" => &["Hebrew"],
+        "hi" | "mr" | "ne" => &["Devanagari"],
+        "th" => &["Thai"],
+        "zh" | "yue" => &["Han"],
+        "ja" => &["Han", "Kana"],
+        "ko" => &["Hangul", "Han"],
+        "en" | "de" | "fr" | "es" | "it" | "pt" | "nl" | "pl" | "cs" | "sv" | "da" | "nb"
+        | "fi" | "tr" | "vi" | "id" | "ro" | "hu" => &[],
+        _ => return None,
+    })
+}
+
+/// Script of a letter outside Latin, for the scripts [`scripts_of`] knows
+fn script_of(c: char) -> Option<&'static str> {
+    Some(match c {
+        '\u{0370}'..='\u{03FF}' | '\u{1F00}'..='\u{1FFF}' => "Greek",
+        '\u{0400}'..='\u{052F}' => "Cyrillic",
+        '\u{0590}'..='\u{05FF}' | '\u{FB1D}'..='\u{FB4F}' => "Hebrew",
+        '\u{0600}'..='\u{06FF}' | '\u{0750}'..='\u{077F}' => "Arabic",
+        '\u{FB50}'..='\u{FDFF}' | '\u{FE70}'..='\u{FEFC}' => "Arabic",
+        '\u{0900}'..='\u{097F}' => "Devanagari",
+        '\u{0E00}'..='\u{0E7F}' => "Thai",
+        '\u{1100}'..='\u{11FF}' | '\u{AC00}'..='\u{D7AF}' => "Hangul",
+        '\u{3040}'..='\u{30FF}' => "Kana",
+        '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' => "Han",
+        _ => return None,
+    })
+}
+
+fn unsupported_scripts(text: &str, language: &str) -> Vec<TextWarning> {
+    let code = language_code(language);
+    let Some(expected) = scripts_of(&code) else {
+        return Vec::new();
+    };
+    let mut counts: Vec<(&'static str, usize)> = Vec::new();
+    for script in text
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .filter_map(script_of)
+    {
+        if expected.contains(&script) {
+            continue;
+        }
+        match counts.iter_mut().find(|(name, _)| *name == script) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((script, 1)),
+        }
+    }
+    counts
+        .into_iter()
+        .map(|(script, count)| TextWarning::UnsupportedScript {
+            script: script.to_string(),
+            count,
+            language: code.clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_controls_whitespace_and_nfc() {
+        let sanitized = sanitize(
+            "\u{FEFF}Cafe\u{301}\u{0007}  au\tlait\n\n\n\nFin  ",
+            "fr-FR",
+        );
+        assert_eq!(sanitized.text, "Café au lait\n\nFin");
+        assert_eq!(
+            sanitized.warnings,
+            [TextWarning::ControlCharacters { count: 2 }]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_scripts() {
+        let warnings = sanitize("Hello Привет мир", "en-US-AriaNeural").warnings;
+        assert_eq!(
+            warnings,
+            [TextWarning::UnsupportedScript {
+                script: "Cyrillic".to_string(),
+                count: 9,
+                language: "en".to_string()
+            }]
+        );
+        assert!(sanitize("東京タワー is tall", "ja-JP").warnings.is_empty());
+        assert!(sanitize("Привет", "xx").warnings.is_empty());
+
+        // Bidi marks and Hebrew points survive; Hebrew is expected for he
+        let hebrew = "\u{200F}שָׁלוֹם (Hello)";
+        let sanitized = sanitize(hebrew, "he-IL-HilaNeural");
+        assert_eq!(sanitized.text.chars().next(), Some('\u{200F}'));
+        assert!(sanitized.warnings.is_empty());
+        assert_eq!(sanitize("مرحبا", "en").warnings.len(), 1);
+    }
+}
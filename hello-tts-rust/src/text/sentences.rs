@@ -0,0 +1,124 @@
+//! Language-aware sentence splitting.
+//!
+//! Sentences are found with the Unicode sentence boundary rules (UAX #29),
+//! which handle CJK full stops and decimal numbers. A boundary after a known
+//! abbreviation of the language (`Dr.`, `z.B.`, `Mme.`) or after an initial
+//! (`J. Smith`) is dropped. Thai, Lao, Khmer and Burmese end sentences with a
+//! space rather than punctuation, so for them every run of whitespace is a
+//! boundary.
+
+use super::normalize::language_code;
+use unicode_segmentation::UnicodeSegmentation;
+
+const EN: &[&str] = &[
+    "mr.", "mrs.", "ms.", "dr.", "prof.", "sr.", "jr.", "st.", "mt.", "vs.", "e.g.", "i.e.", "no.",
+    "inc.", "ltd.", "co.", "approx.", "dept.", "fig.", "gen.", "gov.", "lt.", "sgt.",
+];
+const DE: &[&str] = &[
+    "dr.", "prof.", "hr.", "fr.", "nr.", "str.", "z.b.", "u.a.", "d.h.", "bzw.", "ca.", "evtl.",
+    "ggf.", "inkl.", "vgl.", "bspw.", "sog.", "s.", "st.",
+];
+const FR: &[&str] = &[
+    "m.", "mm.", "mme.", "mlle.", "dr.", "pr.", "me.", "st.", "ste.", "p.ex.", "env.", "cf.",
+    "n°.", "av.", "bd.",
+];
+const ES: &[&str] = &[
+    "sr.", "sra.", "srta.", "dr.", "dra.", "ud.", "uds.", "lic.", "ing.", "p.ej.", "aprox.",
+    "núm.", "av.", "sto.", "sta.",
+];
+const IT: &[&str] = &[
+    "sig.", "sigg.", "dott.", "prof.", "ing.", "avv.", "p.es.", "ecc.", "n.", "s.",
+];
+const PT: &[&str] = &[
+    "sr.", "sra.", "dr.", "dra.", "prof.", "profa.", "p.ex.", "av.", "sto.", "sta.",
+];
+
+/// Abbreviations after which a period does not end a sentence in `language`
+///
+/// `language` may be a bare language code, a locale or a full voice name.
+pub fn abbreviations(language: &str) -> &'static [&'static str] {
+    match language_code(language).as_str() {
+        "en" => EN,
+        "de" => DE,
+        "fr" => FR,
+        "es" => ES,
+        "it" => IT,
+        "pt" => PT,
+        _ => &[],
+    }
+}
+
+/// Split `text` into trimmed sentences using the rules for `language`
+pub fn split_sentences<'a>(text: &'a str, language: &str) -> Vec<&'a str> {
+    if matches!(language_code(language).as_str(), "th" | "lo" | "km" | "my") {
+        return text.split_whitespace().collect();
+    }
+
+    let abbreviations = abbreviations(language);
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut end = 0;
+    for piece in text.split_sentence_bounds() {
+        end += piece.len();
+        if !continues(piece.trim_end(), abbreviations) {
+            sentences.push(text[start..end].trim());
+            start = end;
+        }
+    }
+    if start < text.len() {
+        sentences.push(text[start..].trim());
+    }
+    sentences.retain(|s| !s.is_empty());
+    sentences
+}
+
+/// Whether the sentence ending in `piece` continues past its final period
+fn continues(piece: &str, abbreviations: &[&str]) -> bool {
+    if !piece.ends_with('.') {
+        return false;
+    }
+    let last_word = piece
+        .rsplit(|c: char| c.is_whitespace() || c == '(' || c == '"')
+        .next()
+        .unwrap_or(piece);
+    let mut letters = last_word.trim_end_matches('.').chars();
+    let initial = matches!((letters.next(), letters.next()), (Some(c), None) if c.is_uppercase());
+    initial || abbreviations.contains(&last_word.to_lowercase().as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_abbreviations_and_initials_do_not_end_sentences() {
+        assert_eq!(
+            split_sentences(
+                "Dr. Smith met J. R. Jones. They talked.",
+                "en-US-AriaNeural"
+            ),
+            ["Dr. Smith met J. R. Jones.", "They talked."]
+        );
+        assert_eq!(
+            split_sentences("Obst, z.B. Äpfel. Und Birnen.", "de"),
+            ["Obst, z.B. Äpfel.", "Und Birnen."]
+        );
+        // Without a list for the language only the Unicode rules apply
+        assert_eq!(
+            split_sentences("Dr. Who. Pi is 3.14!", "xx"),
+            ["Dr.", "Who.", "Pi is 3.14!"]
+        );
+    }
+
+    #[test]
+    fn test_scripts_without_spaces_or_periods() {
+        assert_eq!(
+            split_sentences("今日は。いい天気ですね！", "ja-JP"),
+            ["今日は。", "いい天気ですね！"]
+        );
+        assert_eq!(
+            split_sentences("สวัสดีครับ วันนี้อากาศดี", "th-TH"),
+            ["สวัสดีครับ", "วันนี้อากาศดี"]
+        );
+    }
+}
@@ -0,0 +1,160 @@
+//! SSML payloads and protection against markup in user text.
+//!
+//! Text read from the clipboard, a file or a web form may contain SSML tags,
+//! whether pasted by accident or on purpose. Unless `TTSConfig.allow_raw_ssml`
+//! is set, [`strip_tags`] removes them before synthesis so a stray
+//! `<prosody rate="x-fast">` cannot change how the rest of the text is spoken.
+//! [`build`] wraps text in a `<speak>` document for backends that take SSML,
+//! escaping it so characters such as `&` and `<` cannot break the payload.
+
+use super::markup::decode_entities;
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Escape the characters with a meaning in XML: `& < > " '`
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn tag_pattern() -> &'static Regex {
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    TAG.get_or_init(|| {
+        Regex::new(
+            r"(?i)</?(speak|voice|prosody|break|emphasis|say-as|phoneme|sub|audio|mark|lang|p|s|w|bookmark|(mstts|amazon):[\w-]+)\b[^<>]*>",
+        )
+        .unwrap()
+    })
+}
+
+/// Remove SSML tags from `text`, keeping the text inside them
+///
+/// Returns the text and the number of tags removed. Only SSML element names
+/// are recognized, so `a < b` or `<3` are left alone. Entities are decoded
+/// only if tags were found, since plain text may contain a literal `&amp;`.
+pub fn strip_tags(text: &str) -> (String, usize) {
+    let tags = tag_pattern().find_iter(text).count();
+    if tags == 0 {
+        return (text.to_string(), 0);
+    }
+    let stripped = tag_pattern().replace_all(text, " ");
+    (decode_entities(&stripped), tags)
+}
+
+/// Whether a prosody value leaves the voice unchanged (`0%`, `+0Hz`, `100%` or empty)
+pub fn is_neutral(value: &str) -> bool {
+    let value = value.trim();
+    matches!(value, "" | "100%" | "default")
+        || ["%", "Hz", "st"]
+            .iter()
+            .filter_map(|unit| value.strip_suffix(unit))
+            .any(|amount| amount.parse::<f64>() == Ok(0.0))
+}
+
+/// A `<speak>` document reading `text` with `voice` and the given prosody
+///
+/// `text` is escaped; `rate`, `pitch` and `volume` take SSML values such as
+/// `+10%` or `loud`, and a neutral value (`0%`, `100%` or empty) is omitted.
+pub fn build(text: &str, voice: &str, rate: &str, pitch: &str, volume: &str) -> String {
+    build_styled(text, voice, None, rate, pitch, volume)
+}
+
+/// [`build`] speaking in `style`, e.g. `cheerful`, with the
+/// `mstts:express-as` element of Azure voices
+pub fn build_styled(
+    text: &str,
+    voice: &str,
+    style: Option<&str>,
+    rate: &str,
+    pitch: &str,
+    volume: &str,
+) -> String {
+    let language = voice.splitn(3, '-').take(2).collect::<Vec<_>>().join("-");
+    let mut attributes = String::new();
+    for (name, value) in [("rate", rate), ("pitch", pitch), ("volume", volume)] {
+        if !is_neutral(value) {
+            attributes.push_str(&format!(" {}=\"{}\"", name, escape(value.trim())));
+        }
+    }
+    let body = if attributes.is_empty() {
+        escape(text)
+    } else {
+        format!("<prosody{}>{}</prosody>", attributes, escape(text))
+    };
+    let (namespace, body) = match style {
+        Some(style) => (
+            " xmlns:mstts=\"https://www.w3.org/2001/mstts\"",
+            format!(
+                "<mstts:express-as style=\"{}\">{}</mstts:express-as>",
+                escape(style),
+                body
+            ),
+        ),
+        None => ("", body),
+    };
+    format!(
+        "<speak version=\"1.0\" xmlns=\"http://www.w3.org/2001/10/synthesis\"{} xml:lang=\"{}\"><voice name=\"{}\">{}</voice></speak>",
+        namespace,
+        escape(&language),
+        escape(voice),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation::check_ssml;
+
+    #[test]
+    fn test_escape_and_build() {
+        assert_eq!(
+            escape(r#"Fish & "chips" <'n'> more"#),
+            "Fish &amp; &quot;chips&quot; &lt;&apos;n&apos;&gt; more"
+        );
+        let ssml = build("a < b & c", "en-US-AriaNeural", "+10%", "0%", "100%");
+        assert_eq!(
+            ssml,
+            "<speak version=\"1.0\" xmlns=\"http://www.w3.org/2001/10/synthesis\" xml:lang=\"en-US\">\
+             <voice name=\"en-US-AriaNeural\"><prosody rate=\"+10%\">a &lt; b &amp; c</prosody></voice></speak>"
+        );
+        assert_eq!(check_ssml(&ssml), Ok(()));
+
+        // Right-to-left text passes through untouched
+        let hebrew = build("שלום \"עולם\"", "he-IL-HilaNeural", "0%", "0%", "100%");
+        assert!(hebrew.contains(">שלום &quot;עולם&quot;</voice>"));
+        assert_eq!(check_ssml(&hebrew), Ok(()));
+
+        let sad = build_styled("Oh", "en-US-AriaNeural", Some("sad"), "-10%", "0%", "100%");
+        assert!(sad.contains(
+            "<mstts:express-as style=\"sad\"><prosody rate=\"-10%\">Oh</prosody></mstts:express-as>"
+        ));
+        assert_eq!(check_ssml(&sad), Ok(()));
+    }
+
+    #[test]
+    fn test_strip_tags() {
+        let (text, tags) =
+            strip_tags("Read <prosody rate=\"x-fast\">this</prosody><break time='3s'/> &amp; that");
+        assert_eq!(text, "Read  this   & that");
+        assert_eq!(tags, 3);
+        assert_eq!(
+            strip_tags("1 < 2 &amp; <3 <b>x</b>"),
+            ("1 < 2 &amp; <3 <b>x</b>".to_string(), 0)
+        );
+        assert_eq!(
+            strip_tags("<mstts:express-as style=\"sad\">Oh</mstts:express-as>").1,
+            2
+        );
+    }
+}
@@ -1,96 +1,2120 @@
-use crate::audio_player::AudioPlayer;
+use crate::alignment;
+use crate::analysis;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::audio::{self, Segment};
+use crate::audio_player;
+use crate::audio_player::AudioSink;
 use crate::backends::edge::EdgeTTS;
 use crate::backends::google::GoogleTTS;
-use crate::backends::TTSBackend;
-use crate::config::TTSConfig;
-use crate::models::{TTSError, Voice};
-use log::{info};
-use std::path::Path;
+use crate::backends::mock::MockBackend;
+use crate::backends::{temp, TTSBackend, BUILTIN_BACKENDS};
+use crate::catalog::VoiceCatalog;
+use crate::clock::{self, Clock};
+use crate::config::{self, TTSConfig};
+use crate::convert::{self, Encoding, Pcm};
+use crate::dialogue;
+use crate::events::{EventHandler, TTSEvent};
+use crate::health::{BackendHealth, HealthTracker};
+use crate::hooks;
+use crate::metrics::{Metrics, MetricsSnapshot};
+use crate::models::{ErrorKind, SynthesisResult, SynthesizedAudio, TTSError, Voice};
+use crate::naming::FileNamer;
+use crate::notifications::DesktopNotifier;
+use crate::output::OutputManager;
+use crate::pdf;
+use crate::quota::Quota;
+use crate::rate_limit::RateLimiter;
+use crate::text::{self, InputFormat};
+use crate::usage::Usage;
+use crate::validation::{self, ValidationOptions, ValidationProblem, ValidationReport};
+use crate::voice_ref::VoiceRef;
+use crate::voice_remap::{self, VoiceResolution};
+use futures_util::stream::{self, Stream, StreamExt};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::fs;
+use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, field, info, instrument, warn, Span};
+
+/// Backend shared between the processor and in-flight requests
+type SharedBackend = Arc<dyn TTSBackend + Send + Sync>;
 
 /// TTS Processor that delegates to a configured backend
+///
+/// Clones are cheap and share the backends, voice cache, rate limiter and
+/// metrics, so a processor can be handed to as many tokio tasks as needed.
+#[derive(Clone)]
 pub struct TTSProcessor {
     config: TTSConfig,
-    voices_cache: Option<Vec<Voice>>,
-    backend: Box<dyn TTSBackend + Send + Sync>,
+    voices_cache: Arc<RwLock<Option<Vec<Voice>>>>,
+    backend: SharedBackend,
+    /// Other backends, created on first use by `synthesize_with_backend`
+    /// and the fallback chain
+    other_backends: Arc<Mutex<HashMap<&'static str, SharedBackend>>>,
+    /// Voice lists of the other backends, cached like `voices_cache`
+    other_voices: Arc<RwLock<HashMap<&'static str, Vec<Voice>>>>,
+    health: Arc<HealthTracker>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    quota: Arc<Quota>,
+    handlers: Vec<Arc<dyn EventHandler>>,
+    metrics: Arc<Metrics>,
+    clock: Arc<dyn Clock>,
+    cancel: Option<CancellationToken>,
+    /// Replaces the sink selected by `TTSConfig.audio_output` when set
+    audio_sink: Option<Arc<dyn AudioSink + Send + Sync>>,
+    /// The configured prosody or style differs from the one the backends
+    /// were created with
+    custom_prosody: bool,
 }
 
 impl TTSProcessor {
     /// Create a new TTSProcessor with optional configuration
+    ///
+    /// Without an explicit configuration, settings are loaded with
+    /// [`config::load_layered`], falling back to defaults if that fails.
+    ///
+    /// # Panics
+    ///
+    /// If the configured backend cannot be created, for example because its
+    /// proxy secret cannot be resolved; [`try_new`](Self::try_new) returns
+    /// that error instead.
     pub fn new(config: Option<TTSConfig>) -> Self {
-        let config = config.unwrap_or_default();
-        let backend: Box<dyn TTSBackend + Send + Sync> = match config.backend.as_str() {
-            "google" => Box::new(GoogleTTS::new()),
-            _ => Box::new(EdgeTTS::new()),
+        Self::try_new(config).unwrap_or_else(|e| panic!("Cannot create the TTS backend: {}", e))
+    }
+
+    /// Create a new TTSProcessor, failing if the configured backend cannot be created
+    pub fn try_new(config: Option<TTSConfig>) -> Result<Self, TTSError> {
+        let config = config.unwrap_or_else(|| {
+            config::load_layered().unwrap_or_else(|e| {
+                warn!("Falling back to default configuration: {}", e);
+                TTSConfig::default()
+            })
+        });
+        temp::sweep_once(Duration::from_secs(config.temp_max_age_hours * 3600));
+        let backend = match Self::create_backend(&config.backend, &config)? {
+            Some(backend) => backend,
+            None => Box::new(Self::edge_backend(&config)?),
         };
+        Ok(Self::with_backend(config, backend))
+    }
+
+    /// Create a processor around an already constructed backend
+    pub fn with_backend(config: TTSConfig, backend: Box<dyn TTSBackend + Send + Sync>) -> Self {
+        let rate_limiter = config
+            .requests_per_minute
+            .and_then(|rpm| RateLimiter::new(rpm, config.rate_limit_burst))
+            .map(Arc::new);
+
+        let quota = Arc::new(Quota::from_config(&config));
+        let health = HealthTracker::new(
+            config.circuit_failure_threshold,
+            Duration::from_secs(config.backend_cooldown_secs),
+        );
+
+        let mut handlers: Vec<Arc<dyn EventHandler>> = Vec::new();
+        if config.notify_on_completion {
+            handlers.push(Arc::new(DesktopNotifier));
+        }
 
         Self {
+            clock: clock::from_config(&config),
             config,
-            voices_cache: None,
-            backend,
+            voices_cache: Arc::new(RwLock::new(None)),
+            backend: backend.into(),
+            other_backends: Arc::new(Mutex::new(HashMap::new())),
+            other_voices: Arc::new(RwLock::new(HashMap::new())),
+            health: Arc::new(health),
+            rate_limiter,
+            quota,
+            handlers,
+            metrics: Arc::new(Metrics::new()),
+            cancel: None,
+            audio_sink: None,
+            custom_prosody: false,
+        }
+    }
+
+    /// Play audio through `sink` instead of the one selected by `TTSConfig.audio_output`
+    ///
+    /// The sink receives the audio in `TTSConfig.output_format`, so `pcm_s16le`
+    /// arrives without a header.
+    pub fn set_audio_sink(&mut self, sink: impl AudioSink + Send + Sync + 'static) {
+        self.audio_sink = Some(Arc::new(sink));
+    }
+
+    /// A clone whose backend calls are aborted once `token` is cancelled
+    ///
+    /// Cancelled synthesis fails with [`TTSError::Cancelled`]; a cancelled
+    /// batch stops early and reports the remaining items as pending.
+    /// Subprocesses of the backends are killed and their temp files removed.
+    pub fn with_cancellation(&self, token: CancellationToken) -> Self {
+        Self {
+            cancel: Some(token),
+            ..self.clone()
+        }
+    }
+
+    /// A clone whose [`file_namer`](Self::file_namer) tells the time with
+    /// `clock` instead of the one of [`clock::from_config`]
+    pub fn with_clock(&self, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            ..self.clone()
+        }
+    }
+
+    /// A clone that speaks at `rate` and `pitch` instead of the configured ones
+    ///
+    /// Backends without prosody settings ignore them, see
+    /// [`TTSBackend::with_prosody`].
+    pub fn with_prosody(&self, rate: &str, pitch: &str) -> Self {
+        let mut processor = self.clone();
+        processor.config.rate = rate.to_string();
+        processor.config.pitch = pitch.to_string();
+        processor.custom_prosody = true;
+        processor
+    }
+
+    /// A clone that speaks in `style`, e.g. `cheerful`, see [`TTSConfig::style`]
+    pub fn with_style(&self, style: &str) -> Self {
+        let mut processor = self.clone();
+        processor.config.style = Some(style.to_string());
+        processor.custom_prosody = true;
+        processor
+    }
+
+    /// Run `operation` unless or until the cancellation token fires
+    async fn cancellable<T>(
+        &self,
+        operation: impl Future<Output = Result<T, TTSError>>,
+    ) -> Result<T, TTSError> {
+        match &self.cancel {
+            Some(token) => tokio::select! {
+                biased;
+                _ = token.cancelled() => Err(TTSError::Cancelled),
+                result = operation => result,
+            },
+            None => operation.await,
+        }
+    }
+
+    /// Copy of the counters and latency histograms recorded so far
+    pub fn metrics_snapshot(&self) -> MetricsSnapshot {
+        self.metrics.snapshot()
+    }
+
+    /// Characters synthesized per backend since the processor was created,
+    /// with their estimated cost
+    pub fn usage(&self) -> Usage {
+        Usage::from_metrics(&self.metrics.snapshot(), &self.config)
+    }
+
+    /// Shared handle to the live metrics, e.g. for serving them over HTTP
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
+
+    /// Register a handler that receives every [`TTSEvent`] emitted by this processor
+    ///
+    /// Clones made before the call do not see the handler.
+    pub fn subscribe(&mut self, handler: impl EventHandler + 'static) {
+        self.handlers.push(Arc::new(handler));
+    }
+
+    /// Send `event` to every subscribed handler
+    pub(crate) fn emit(&self, event: TTSEvent) {
+        for handler in &self.handlers {
+            handler.on_event(&event);
         }
     }
 
+    /// Backend called `name`, or `None` if there is no such backend
+    fn create_backend(
+        name: &str,
+        config: &TTSConfig,
+    ) -> Result<Option<Box<dyn TTSBackend + Send + Sync>>, TTSError> {
+        Ok(match name {
+            "edge" => Some(Box::new(Self::edge_backend(config)?)),
+            "google" => Some(Box::new(GoogleTTS::with_options(
+                config.synthesis_options(),
+            ))),
+            "mock" => Some(Box::new(MockBackend::new())),
+            _ => None,
+        })
+    }
+
+    /// The backend called `name`, created and cached on first use
+    pub(crate) fn backend_named(&self, name: &str) -> Result<SharedBackend, TTSError> {
+        let mut backends = self.other_backends.lock().unwrap();
+        if let Some(backend) = backends.get(name) {
+            return Ok(backend.clone());
+        }
+        let backend: SharedBackend = Self::create_backend(name, &self.config)?
+            .ok_or_else(|| TTSError::Config(format!("Unknown backend: {}", name)))?
+            .into();
+        info!("Created {} backend", backend.name());
+        backends.insert(backend.name(), backend.clone());
+        Ok(backend)
+    }
+
+    /// Use `backend` whenever a backend of its name is requested, e.g. by
+    /// [`synthesize_with_backend`](Self::synthesize_with_backend) or the
+    /// fallback chain, instead of creating one from the configuration
+    pub fn register_backend(&self, backend: Box<dyn TTSBackend + Send + Sync>) {
+        let backend: SharedBackend = backend.into();
+        let mut backends = self.other_backends.lock().unwrap();
+        backends.insert(backend.name(), backend);
+    }
+
+    /// Names of the configured backend and `TTSConfig.fallback_backends`, in order
+    fn chain_names(&self) -> Vec<&str> {
+        let mut names = vec![self.backend_name()];
+        for name in &self.config.fallback_backends {
+            if !names.contains(&name.as_str()) {
+                names.push(name);
+            }
+        }
+        names
+    }
+
+    fn chain_member(&self, name: &str) -> Result<SharedBackend, TTSError> {
+        if name == self.backend_name() {
+            Ok(self.backend.clone())
+        } else {
+            self.backend_named(name)
+        }
+    }
+
+    /// Probe the configured and fallback backends with
+    /// [`TTSBackend::health_check`], updating the state the fallback chain uses
+    pub async fn health_check(&self) -> Vec<BackendHealth> {
+        let mut statuses = Vec::new();
+        for name in self.chain_names() {
+            let result = match self.chain_member(name) {
+                Ok(backend) => self.cancellable(backend.health_check()).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => self.record_success(name),
+                Err(e) => {
+                    if self.health.mark_down(name, &e.to_string()) {
+                        self.circuit_opened(name, &e);
+                    }
+                }
+            }
+            statuses.push(self.health.status(name));
+        }
+        statuses
+    }
+
+    /// Health of the configured and fallback backends as last seen, without
+    /// probing them
+    pub fn backend_health(&self) -> Vec<BackendHealth> {
+        let names = self.chain_names();
+        names
+            .into_iter()
+            .map(|name| self.health.status(name))
+            .collect()
+    }
+
+    fn edge_backend(config: &TTSConfig) -> Result<EdgeTTS, TTSError> {
+        let proxy = config.proxy_for("edge")?;
+        Ok(EdgeTTS::with_proxy(proxy.as_deref())?
+            .with_synthesis_options(config.synthesis_options()))
+    }
+
     /// Convert text to audio data using the configured backend
+    ///
+    /// Markup is stripped according to `TTSConfig.input_format` and the text is
+    /// run through the normalization pipeline configured in
+    /// `TTSConfig.text_normalization` before it is sent to the backend. Text
+    /// longer than `TTSConfig.max_chunk_chars` is split into chunks that are
+    /// synthesized up to `TTSConfig.max_concurrent` at a time and joined in order.
+    ///
+    /// `voice` may be a [`VoiceRef`] such as `google:fr`, which sends the text
+    /// to that backend, or a language code mapped in `TTSConfig.language_voices`.
     pub async fn synthesize_text(&self, text: &str, voice: &str) -> Result<Vec<u8>, TTSError> {
-        self.backend.synthesize_text(text, voice).await
+        self.synthesize_formatted(text, voice, self.config.input_format)
+            .await
+    }
+
+    /// Convert text in the given input format to audio data
+    pub async fn synthesize_formatted(
+        &self,
+        text: &str,
+        voice: &str,
+        format: InputFormat,
+    ) -> Result<Vec<u8>, TTSError> {
+        Ok(self
+            .synthesize_with_fallback(text, voice, format, false)
+            .await?
+            .0
+            .audio)
+    }
+
+    /// Convert text to audio data using the backend called `backend_name`
+    ///
+    /// Backends other than the configured one are created on first use and
+    /// kept for later calls, so one long-lived processor can serve requests
+    /// for any backend.
+    pub async fn synthesize_with_backend(
+        &self,
+        text: &str,
+        voice: &str,
+        backend_name: &str,
+    ) -> Result<Vec<u8>, TTSError> {
+        self.synthesize_formatted_with_backend(text, voice, self.config.input_format, backend_name)
+            .await
+    }
+
+    /// [`synthesize_with_backend`](Self::synthesize_with_backend) for text in
+    /// the given input format
+    pub async fn synthesize_formatted_with_backend(
+        &self,
+        text: &str,
+        voice: &str,
+        format: InputFormat,
+        backend_name: &str,
+    ) -> Result<Vec<u8>, TTSError> {
+        let voice = self.resolve_voice(voice).voice;
+        if backend_name == self.backend_name() {
+            return self.synthesize_formatted(text, &voice, format).await;
+        }
+        let backend = self.backend_named(backend_name)?;
+        Ok(self
+            .synthesize_timed(&backend, text, &voice, format, false)
+            .await?
+            .audio)
+    }
+
+    /// Convert text to audio along with the word boundary and viseme timings
+    /// reported by the backend
+    ///
+    /// Timings of later chunks are shifted by the end of the previous chunk's
+    /// last timing, which is an approximation when a chunk ends in silence.
+    pub async fn synthesize_detailed(
+        &self,
+        text: &str,
+        voice: &str,
+    ) -> Result<SynthesizedAudio, TTSError> {
+        let format = self.config.input_format;
+        Ok(self
+            .synthesize_with_fallback(text, voice, format, true)
+            .await?
+            .0)
+    }
+
+    /// Synthesize with the backend `voice` names or else the configured
+    /// backend, falling back to `TTSConfig.fallback_backends` in order while
+    /// its circuit is open, and return the name of the backend used
+    ///
+    /// See [`crate::health`] for when a circuit opens and closes.
+    async fn synthesize_with_fallback(
+        &self,
+        text: &str,
+        voice: &str,
+        format: InputFormat,
+        timings: bool,
+    ) -> Result<(SynthesizedAudio, &'static str), TTSError> {
+        let voice_ref = self.resolve_voice(voice);
+        let voice = voice_ref.voice.as_str();
+        if let Some(backend) = self.routed_backend(&voice_ref)? {
+            let synthesized = self
+                .synthesize_timed(&backend, text, voice, format, timings)
+                .await?;
+            return Ok((synthesized, backend.name()));
+        }
+
+        let chain = self.backend_chain()?;
+        let mut last_error = None;
+        for (index, backend) in chain.iter().enumerate() {
+            let name = backend.name();
+            if self.health.is_open(name) {
+                debug!("Skipping {} backend while its circuit is open", name);
+                continue;
+            }
+            let voice = if index == 0 {
+                voice.to_string()
+            } else {
+                self.fallback_voice(backend, voice).await
+            };
+            match self
+                .synthesize_timed(backend, text, &voice, format, timings)
+                .await
+            {
+                Ok(synthesized) => {
+                    self.record_success(name);
+                    return Ok((synthesized, name));
+                }
+                Err(e) if e.kind().falls_back() => {
+                    if self.health.record_failure(name, &e.to_string()) {
+                        self.circuit_opened(name, &e);
+                    }
+                    if index + 1 < chain.len() {
+                        warn!(error = %e, "{} backend failed, falling back", name);
+                    }
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            let health = self.health.status(self.backend_name());
+            TTSError::backend_error(
+                self.backend_name(),
+                ErrorKind::Unavailable,
+                format!(
+                    "Circuit open for every backend, retrying {} in {}s: {}",
+                    health.backend,
+                    health.retry_in_secs.unwrap_or(0),
+                    health.error.unwrap_or_default()
+                ),
+            )
+        }))
+    }
+
+    /// `voice`, named for the configured backend, as a voice of the fallback
+    /// `backend`: itself if listed there, else its [`voice_remap::nearest`]
+    /// voice of the same locale or language
+    async fn fallback_voice(&self, backend: &SharedBackend, voice: &str) -> String {
+        let voices = self.voices_of(backend).await.unwrap_or_default();
+        match voice_remap::nearest(voice, &voices) {
+            Some(mapped) => {
+                info!(
+                    "Speaking {} as {} with the {} backend",
+                    voice,
+                    mapped.name,
+                    backend.name()
+                );
+                mapped.name.clone()
+            }
+            None => voice.to_string(),
+        }
+    }
+
+    fn circuit_opened(&self, backend: &str, error: &TTSError) {
+        let cooldown = Duration::from_secs(self.config.backend_cooldown_secs);
+        warn!(error = %error, "Circuit opened for {} backend for {:?}", backend, cooldown);
+        self.emit(TTSEvent::CircuitOpened {
+            backend: backend.to_string(),
+            error: error.to_string(),
+            cooldown,
+        });
+    }
+
+    fn record_success(&self, backend: &str) {
+        if self.health.record_success(backend) {
+            info!("Circuit closed for {} backend", backend);
+            self.emit(TTSEvent::CircuitClosed {
+                backend: backend.to_string(),
+            });
+        }
+    }
+
+    /// `voice`, or the voice `TTSConfig.language_voices` maps it to, as a [`VoiceRef`]
+    fn resolve_voice(&self, voice: &str) -> VoiceRef {
+        VoiceRef::parse(self.config.voice_for_language(voice).unwrap_or(voice))
+    }
+
+    /// Backend named by `voice`, or `None` for the configured backend
+    fn routed_backend(&self, voice: &VoiceRef) -> Result<Option<SharedBackend>, TTSError> {
+        match voice.backend.as_deref() {
+            Some(name) if name != self.backend_name() => self.backend_named(name).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// The configured backend followed by `TTSConfig.fallback_backends`
+    fn backend_chain(&self) -> Result<Vec<SharedBackend>, TTSError> {
+        let names = self.chain_names();
+        names
+            .into_iter()
+            .map(|name| self.chain_member(name))
+            .collect()
+    }
+
+    /// [`synthesize_voice`](Self::synthesize_voice), rejecting or substituting
+    /// voices the backend does not know according to `TTSConfig.voice_resolution`,
+    /// see [`voice_remap`]
+    async fn synthesize_timed(
+        &self,
+        backend: &SharedBackend,
+        text: &str,
+        voice: &str,
+        format: InputFormat,
+        timings: bool,
+    ) -> Result<SynthesizedAudio, TTSError> {
+        if self.config.effective_voice_resolution() == VoiceResolution::Strict {
+            voice_remap::check_listed(voice, &self.voices_of(backend).await?)?;
+            return self
+                .synthesize_voice(backend, text, voice, format, timings)
+                .await;
+        }
+        let voice = match voice_remap::successor(voice).filter(|_| backend.name() == "edge") {
+            Some(successor) => {
+                self.voice_substituted(backend.name(), voice, successor);
+                successor
+            }
+            None => voice,
+        };
+        match self
+            .synthesize_voice(backend, text, voice, format, timings)
+            .await
+        {
+            Err(e) if e.kind() == ErrorKind::VoiceNotFound => {
+                let voices = self.voices_of(backend).await.unwrap_or_default();
+                let default_voice = &self.config.default_voice;
+                let substitute = voice_remap::nearest(voice, &voices).or_else(|| {
+                    voices.iter().find(|v| {
+                        v.name.eq_ignore_ascii_case(default_voice)
+                            && !v.name.eq_ignore_ascii_case(voice)
+                    })
+                });
+                let Some(substitute) = substitute else {
+                    return Err(e);
+                };
+                self.voice_substituted(backend.name(), voice, &substitute.name);
+                self.synthesize_voice(backend, text, &substitute.name, format, timings)
+                    .await
+            }
+            result => result,
+        }
+    }
+
+    /// Voices of `backend`, cached per backend when `TTSConfig.cache_voices` is set
+    async fn voices_of(&self, backend: &SharedBackend) -> Result<Vec<Voice>, TTSError> {
+        if Arc::ptr_eq(backend, &self.backend) {
+            return self.list_voices().await;
+        }
+        if self.config.cache_voices {
+            if let Some(voices) = self.other_voices.read().await.get(backend.name()) {
+                return Ok(voices.clone());
+            }
+        }
+        let voices = self.cancellable(backend.list_voices()).await?;
+        if self.config.cache_voices {
+            self.other_voices
+                .write()
+                .await
+                .insert(backend.name(), voices.clone());
+        }
+        Ok(voices)
+    }
+
+    fn voice_substituted(&self, backend: &'static str, requested: &str, substitute: &str) {
+        warn!(
+            "Voice {} is not available from the {} backend, using {} instead",
+            requested, backend, substitute
+        );
+        self.emit(TTSEvent::VoiceSubstituted {
+            backend,
+            requested: requested.to_string(),
+            substitute: substitute.to_string(),
+        });
+    }
+
+    #[instrument(
+        skip_all,
+        fields(
+            backend = backend.name(),
+            voice = %voice,
+            text_len = text.len(),
+            chunks = field::Empty,
+            latency_ms = field::Empty,
+        )
+    )]
+    async fn synthesize_voice(
+        &self,
+        backend: &SharedBackend,
+        text: &str,
+        voice: &str,
+        format: InputFormat,
+        timings: bool,
+    ) -> Result<SynthesizedAudio, TTSError> {
+        let started = Instant::now();
+        let chunks = self.prepare_text(text, voice, format);
+        if chunks.len() > 1 {
+            info!("Synthesizing long text in {} chunks", chunks.len());
+        }
+        Span::current().record("chunks", chunks.len());
+        self.emit(TTSEvent::SynthesisStarted {
+            backend: backend.name(),
+            voice: voice.to_string(),
+            text_chars: chunks.iter().map(|c| c.chars().count()).sum(),
+            chunks: chunks.len(),
+        });
+
+        let mut synthesized = SynthesizedAudio::default();
+        let mut segments = Vec::new();
+        let mut offset_ms = 0;
+        let mut parts = self.chunk_stream(backend.clone(), chunks, voice, timings);
+        while let Some(part) = parts.next().await {
+            let part = part?;
+            let mut chunk_end = 0;
+            for mut boundary in part.boundaries {
+                chunk_end = chunk_end.max(boundary.offset_ms + boundary.duration_ms);
+                boundary.offset_ms += offset_ms;
+                synthesized.boundaries.push(boundary);
+            }
+            for mut viseme in part.visemes {
+                chunk_end = chunk_end.max(viseme.offset_ms);
+                viseme.offset_ms += offset_ms;
+                synthesized.visemes.push(viseme);
+            }
+            offset_ms += chunk_end.saturating_sub(self.crossfade_ms());
+            segments.push(Segment::Audio(part.audio));
+        }
+        synthesized.audio = self.join_audio(segments)?;
+
+        let duration = started.elapsed();
+        Span::current().record("latency_ms", duration.as_millis() as u64);
+        self.metrics.record_synthesis(backend.name());
+        info!(bytes = synthesized.audio.len(), "Synthesis complete");
+        self.emit(TTSEvent::SynthesisCompleted {
+            duration,
+            bytes: synthesized.audio.len(),
+        });
+        Ok(synthesized)
+    }
+
+    /// Join the parts of one recording in the output format
+    ///
+    /// The `wav`, `pcm_s16le` and `opus` formats are joined gaplessly with
+    /// [`audio::concat`] and `TTSConfig.crossfade_ms`, then encoded with
+    /// `TTSConfig.sample_rate` and `channels`. The crate has no MP3 encoder,
+    /// so other formats are joined frame by frame with MP3 silence.
+    pub fn join_audio(&self, segments: Vec<Segment>) -> Result<Vec<u8>, TTSError> {
+        let Some(format) = Encoding::parse(&self.config.output_format) else {
+            return Ok(segments
+                .into_iter()
+                .flat_map(|segment| match segment {
+                    Segment::Audio(audio) => audio,
+                    Segment::Silence(duration) => dialogue::mp3_silence(duration),
+                })
+                .collect());
+        };
+        let crossfade = Duration::from_millis(self.config.crossfade_ms);
+        let pcm = audio::concat(&segments, crossfade)?;
+        Ok(convert::encode(
+            pcm,
+            &self.config.synthesis_options(),
+            format,
+        )?)
+    }
+
+    /// Overlap of joined parts; only encoded formats are crossfaded
+    fn crossfade_ms(&self) -> u64 {
+        match Encoding::parse(&self.config.output_format) {
+            Some(_) => self.config.crossfade_ms,
+            None => 0,
+        }
+    }
+
+    /// `audio` as the decoder and audio devices read it: headerless PCM is
+    /// wrapped in a WAV header and Opus is decoded to WAV
+    pub fn decodable<'a>(&self, audio: &'a [u8]) -> Cow<'a, [u8]> {
+        let format = Encoding::parse(&self.config.output_format);
+        match (format, self.config.sample_rate) {
+            (Some(Encoding::S16le), Some(sample_rate)) => {
+                let channels = self.config.channels.unwrap_or(1);
+                Cow::Owned(Pcm::from_le_bytes(audio, channels, sample_rate).to_wav())
+            }
+            #[cfg(all(feature = "opus", not(target_arch = "wasm32")))]
+            (Some(Encoding::Opus), _) => match crate::opus::decode(audio) {
+                Ok(pcm) => Cow::Owned(pcm.to_wav()),
+                Err(e) => {
+                    debug!("Opus decoding skipped: {}", e);
+                    Cow::Borrowed(audio)
+                }
+            },
+            _ => Cow::Borrowed(audio),
+        }
+    }
+
+    /// A clone that returns the audio as the backend sends it, for parts
+    /// that are joined with [`join_audio`](Self::join_audio) before the whole
+    /// is converted
+    pub fn unconverted(&self) -> Self {
+        let mut processor = self.clone();
+        processor.config.output_format = "mp3".to_string();
+        processor.config.sample_rate = None;
+        processor.config.channels = None;
+        processor
+    }
+
+    /// Synthesize `chunks` up to `TTSConfig.max_concurrent` at a time,
+    /// yielding them strictly in order as soon as each one and all before it
+    /// have arrived
+    fn chunk_stream<'a>(
+        &'a self,
+        backend: SharedBackend,
+        chunks: Vec<String>,
+        voice: &str,
+        timings: bool,
+    ) -> impl Stream<Item = Result<SynthesizedAudio, TTSError>> + Send + 'a {
+        let total = chunks.len();
+        let voice = voice.to_string();
+        stream::iter(chunks)
+            .map(move |chunk| {
+                let backend = backend.clone();
+                let voice = voice.clone();
+                async move {
+                    let mut part = self
+                        .synthesize_with_retry(backend.as_ref(), &chunk, &voice, timings)
+                        .await?;
+                    if timings && self.config.estimate_timings && part.boundaries.is_empty() {
+                        if let Some(stats) = analysis::measure(&part.audio) {
+                            part.boundaries =
+                                alignment::estimate_boundaries(&chunk, stats.duration_ms);
+                        }
+                    }
+                    Ok(part)
+                }
+            })
+            .buffered(self.config.max_concurrent.max(1))
+            .enumerate()
+            .map(move |(index, part)| {
+                if let Ok(part) = &part {
+                    self.emit(TTSEvent::ChunkReceived {
+                        index,
+                        total,
+                        bytes: part.audio.len(),
+                    });
+                }
+                part
+            })
+    }
+
+    /// Synthesize chunks from [`prepare_text`](Self::prepare_text), with word
+    /// boundaries, yielding each one in order as soon as it is available
+    ///
+    /// Chunks are synthesized in parallel like in
+    /// [`synthesize_text`](Self::synthesize_text), but playback or streaming
+    /// to a client can start once the first chunk has arrived. The stream
+    /// ends after the first error.
+    pub fn synthesize_chunks<'a>(
+        &'a self,
+        chunks: Vec<String>,
+        voice: &'a str,
+    ) -> impl Stream<Item = Result<SynthesizedAudio, TTSError>> + Send + 'a {
+        let mut failed = false;
+        let voice_ref = self.resolve_voice(voice);
+        let routed = match self.routed_backend(&voice_ref) {
+            Ok(backend) => backend,
+            Err(e) => return stream::once(std::future::ready(Err(e))).boxed(),
+        };
+        let backend = routed.unwrap_or_else(|| self.backend.clone());
+        self.chunk_stream(backend, chunks, &voice_ref.voice, true)
+            .take_while(move |part| {
+                let keep = !failed;
+                failed |= part.is_err();
+                std::future::ready(keep)
+            })
+            .boxed()
+    }
+
+    /// Speak a text, Markdown or HTML file to a sibling audio file, e.g.
+    /// `draft.md` to `draft.mp3`, and return the audio path
+    pub async fn synthesize_file(&self, path: &Path, voice: &str) -> Result<PathBuf, TTSError> {
+        let text = fs::read_to_string(path).await?;
+        let audio = self
+            .synthesize_formatted(&text, voice, InputFormat::from_path(path))
+            .await?;
+        let output = path.with_extension(self.config.file_extension());
+        fs::write(&output, &audio).await?;
+        Ok(output)
+    }
+
+    /// Strip markup, normalize and split text into the chunks sent to the backend
+    ///
+    /// Text that fits in `TTSConfig.max_chunk_chars` is returned as a single chunk.
+    /// A page break ([`pdf::PAGE_BREAK`]) always ends a chunk.
+    pub fn prepare_text(&self, text: &str, voice: &str, format: InputFormat) -> Vec<String> {
+        if text.contains(pdf::PAGE_BREAK) {
+            return text
+                .split(pdf::PAGE_BREAK)
+                .flat_map(|page| self.prepare_text(page, voice, format))
+                .filter(|chunk| !chunk.trim().is_empty())
+                .collect();
+        }
+        let voice = &self.resolve_voice(voice).voice;
+        let text = self.sanitized_text(text, voice, format).text;
+        let text = text::normalize(&text, voice, &self.config.text_normalization);
+        let chunks = text::split_into_chunks(&text, self.config.max_chunk_chars, voice);
+        if chunks.len() <= 1 {
+            vec![text]
+        } else {
+            chunks
+        }
+    }
+
+    /// Plain text with markup stripped, cleaned up by [`text::sanitize`]
+    ///
+    /// SSML tags are stripped too unless `TTSConfig.allow_raw_ssml` is set.
+    fn sanitized_text(&self, text: &str, voice: &str, format: InputFormat) -> text::Sanitized {
+        let voice = &self.resolve_voice(voice).voice;
+        let (text, tags) = if self.config.allow_raw_ssml {
+            (text.to_string(), 0)
+        } else {
+            text::ssml::strip_tags(text)
+        };
+        let mut sanitized = text::sanitize(
+            &text::to_plain_text(&text, format, self.config.markup_prosody),
+            voice,
+        );
+        if tags > 0 {
+            sanitized
+                .warnings
+                .insert(0, text::TextWarning::SsmlTags { count: tags });
+        }
+        sanitized
+    }
+
+    /// A `<speak>` document for backends that take SSML, using the configured
+    /// rate, pitch, volume and style and the voice's `TTSConfig.voice_overrides`
+    ///
+    /// `text` is escaped; with `TTSConfig.allow_raw_ssml` set, text that is
+    /// already a `<speak>` document is returned as is.
+    pub fn ssml_payload(&self, text: &str, voice: &str) -> String {
+        if self.config.allow_raw_ssml && validation::looks_like_ssml(text) {
+            return text.to_string();
+        }
+        let (text, _) = text::ssml::strip_tags(text);
+        let options = self
+            .config
+            .voice_options(voice)
+            .unwrap_or_else(|| self.config.synthesis_options());
+        text::ssml::build_styled(
+            &text,
+            voice,
+            options.style.as_deref(),
+            &options.rate,
+            &options.pitch,
+            &options.volume,
+        )
+    }
+
+    /// Synthesize one chunk from [`prepare_text`](Self::prepare_text), with word boundaries
+    ///
+    /// Rate limiting and retries apply as for [`synthesize_text`](Self::synthesize_text),
+    /// and so does routing by a `backend:voice` reference, but not the fallback chain.
+    pub async fn synthesize_chunk(
+        &self,
+        chunk: &str,
+        voice: &str,
+    ) -> Result<SynthesizedAudio, TTSError> {
+        let voice_ref = self.resolve_voice(voice);
+        let backend = self.routed_backend(&voice_ref)?;
+        let backend = backend.unwrap_or_else(|| self.backend.clone());
+        self.synthesize_with_retry(backend.as_ref(), chunk, &voice_ref.voice, true)
+            .await
+    }
+
+    /// Call the backend, retrying retryable failures up to `TTSConfig.max_retries` times
+    ///
+    /// The text counts against the character quota once, however many
+    /// attempts it takes, and not at all if they fail or are cancelled.
+    async fn synthesize_with_retry(
+        &self,
+        backend: &(dyn TTSBackend + Send + Sync),
+        text: &str,
+        voice: &str,
+        boundaries: bool,
+    ) -> Result<SynthesizedAudio, TTSError> {
+        let reservation = self.quota.reserve(text.chars().count())?;
+        let result = self
+            .cancellable(self.retry_loop(backend, text, voice, boundaries))
+            .await;
+        if result.is_ok() {
+            reservation.commit();
+        }
+        result
+    }
+
+    async fn retry_loop(
+        &self,
+        backend: &(dyn TTSBackend + Send + Sync),
+        text: &str,
+        voice: &str,
+        boundaries: bool,
+    ) -> Result<SynthesizedAudio, TTSError> {
+        let mut attempt = 0;
+        loop {
+            self.throttle().await;
+            match self
+                .backend_request(backend, text, voice, boundaries, attempt)
+                .await
+            {
+                Err(e) if e.is_retryable() && attempt < self.config.max_retries => {
+                    let delay = Duration::from_millis(500 << attempt.min(6));
+                    attempt += 1;
+                    self.metrics.record_retry(backend.name());
+                    warn!(
+                        error = %e,
+                        "Retrying synthesis in {:?} (attempt {}/{})",
+                        delay, attempt, self.config.max_retries
+                    );
+                    self.emit(TTSEvent::Retry {
+                        attempt,
+                        delay,
+                        error: e.to_string(),
+                    });
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Send a single synthesis request to the backend
+    #[instrument(
+        skip_all,
+        fields(
+            backend = backend.name(),
+            voice = %voice,
+            text_len = text.len(),
+            attempt = attempt,
+            latency_ms = field::Empty,
+        )
+    )]
+    async fn backend_request(
+        &self,
+        backend: &(dyn TTSBackend + Send + Sync),
+        text: &str,
+        voice: &str,
+        boundaries: bool,
+        attempt: u32,
+    ) -> Result<SynthesizedAudio, TTSError> {
+        let prosody = self
+            .config
+            .voice_options(voice)
+            .or_else(|| self.custom_prosody.then(|| self.config.synthesis_options()))
+            .and_then(|options| backend.with_prosody(&options));
+        let backend = prosody.as_deref().unwrap_or(backend);
+        let started = Instant::now();
+        let result = if boundaries {
+            backend.synthesize_with_boundaries(text, voice).await
+        } else {
+            backend
+                .synthesize_text(text, voice)
+                .await
+                .map(|audio| SynthesizedAudio {
+                    audio,
+                    ..SynthesizedAudio::default()
+                })
+        };
+        let latency = started.elapsed();
+        Span::current().record("latency_ms", latency.as_millis() as u64);
+        self.metrics.record_request(
+            backend.name(),
+            latency,
+            result.as_ref().ok().map(|r| r.audio.len()),
+        );
+        if result.is_ok() {
+            self.metrics
+                .record_characters(backend.name(), text.chars().count());
+        }
+        #[cfg(feature = "otel")]
+        crate::telemetry::record_synthesis(backend.name(), latency, &result);
+        result
+    }
+
+    /// Wait for the rate limiter configured by `TTSConfig.requests_per_minute`
+    async fn throttle(&self) {
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire().await;
+        }
     }
 
     /// Get all available voices from the configured backend
-    pub async fn list_voices(&mut self) -> Result<Vec<Voice>, TTSError> {
+    #[instrument(skip_all, fields(backend = self.backend_name()))]
+    pub async fn list_voices(&self) -> Result<Vec<Voice>, TTSError> {
         if self.config.cache_voices {
-            if let Some(ref voices) = self.voices_cache {
+            if let Some(voices) = self.voices_cache.read().await.as_ref() {
                 info!("Using cached voices");
+                self.metrics.record_voice_cache(true);
+                self.emit(TTSEvent::CacheHit);
                 return Ok(voices.clone());
             }
         }
 
+        if self.config.cache_voices {
+            self.metrics.record_voice_cache(false);
+        }
         info!("Fetching voices from backend");
-        let voices = self.backend.list_voices().await?;
+        let voices = self
+            .cancellable(async {
+                self.throttle().await;
+                self.backend.list_voices().await
+            })
+            .await?;
 
         if self.config.cache_voices {
-            self.voices_cache = Some(voices.clone());
+            *self.voices_cache.write().await = Some(voices.clone());
         }
 
         Ok(voices)
     }
 
+    /// Get ready for a faster first utterance
+    ///
+    /// Fetches and caches the voice list and lets the backend check its
+    /// tools, see [`TTSBackend::warm_up`]. No synthesis connection is opened
+    /// ahead of time: the Edge backend runs edge-tts, which connects anew for
+    /// every request, so each utterance still pays the WebSocket handshake.
+    /// Meant for interactive frontends such as the speak daemon and watch modes.
+    #[instrument(skip_all, fields(backend = self.backend_name()))]
+    pub async fn warm_up(&self) -> Result<(), TTSError> {
+        let started = Instant::now();
+        self.list_voices().await?;
+        self.cancellable(self.backend.warm_up()).await?;
+        info!(
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "Backend warmed up"
+        );
+        Ok(())
+    }
+
+    /// Check a request without synthesizing anything
+    ///
+    /// Verifies that the text is not empty, that every chunk fits the backend's
+    /// request limit, that SSML input is well formed, that `voice` is offered by
+    /// the backend and that `options.output_path` could be written. All
+    /// problems are reported together.
+    pub async fn validate(
+        &self,
+        text: &str,
+        voice: &str,
+        options: &ValidationOptions,
+    ) -> ValidationReport {
+        let mut problems = Vec::new();
+        let format = options.input_format.unwrap_or(self.config.input_format);
+
+        if text.trim().is_empty() {
+            problems.push(ValidationProblem::EmptyText);
+        } else {
+            if validation::looks_like_ssml(text) {
+                if let Err(reason) = validation::check_ssml(text) {
+                    problems.push(ValidationProblem::MalformedSsml { reason });
+                }
+            }
+            if let Some(limit) = self.backend.max_text_chars() {
+                for (chunk, content) in self.prepare_text(text, voice, format).iter().enumerate() {
+                    let chars = content.chars().count();
+                    if chars > limit {
+                        problems.push(ValidationProblem::TextTooLong {
+                            chunk,
+                            chars,
+                            limit,
+                        });
+                    }
+                }
+            }
+        }
+
+        let voice_ref = self.resolve_voice(voice);
+        let voices = match self.routed_backend(&voice_ref) {
+            Ok(Some(backend)) => self.cancellable(backend.list_voices()).await,
+            Ok(None) => self.list_voices().await,
+            Err(e) => Err(e),
+        };
+        match voices {
+            // Backends without a voice list accept any voice name
+            Ok(voices) if voices.is_empty() => {}
+            Ok(voices) => {
+                if !voices
+                    .iter()
+                    .any(|v| v.name.eq_ignore_ascii_case(&voice_ref.voice))
+                {
+                    problems.push(ValidationProblem::VoiceNotFound {
+                        voice: voice.to_string(),
+                    });
+                }
+            }
+            Err(e) => problems.push(ValidationProblem::VoicesUnavailable {
+                error: e.to_string(),
+            }),
+        }
+
+        if let Some(path) = &options.output_path {
+            if let Err(reason) = validation::check_writable(path) {
+                problems.push(ValidationProblem::OutputNotWritable {
+                    path: path.clone(),
+                    reason,
+                });
+            }
+        }
+
+        ValidationReport { problems }
+    }
+
     /// Synthesize text and play it, optionally saving to a file
+    ///
+    /// A bare file name is saved in `TTSConfig.output_directory`.
+    #[instrument(skip_all, fields(backend = self.backend_name(), voice = %voice, play = play))]
     pub async fn synthesize_and_play(
         &self,
         text: &str,
         voice: &str,
         output_path: Option<&Path>,
         play: bool,
-    ) -> Result<(), TTSError> {
-        let audio_data = self.synthesize_text(text, voice).await?;
+    ) -> Result<SynthesisResult, TTSError> {
+        let started = Instant::now();
+        let warnings = self
+            .sanitized_text(text, voice, self.config.input_format)
+            .warnings;
+        for warning in &warnings {
+            warn!("{}", warning);
+        }
+        if self.config.visemes && !self.supports_visemes(voice)? {
+            warn!("The backend for {} does not report visemes", voice);
+        }
+        let (synthesized, backend) = self
+            .synthesize_with_fallback(
+                text,
+                voice,
+                self.config.input_format,
+                self.config.visemes || self.config.word_timings,
+            )
+            .await?;
+        let audio_data = synthesized.audio;
+        let output_path = output_path.map(|path| self.output_manager().resolve(path));
+        let result = SynthesisResult {
+            backend: backend.to_string(),
+            voice: voice.to_string(),
+            output_path: output_path.clone(),
+            bytes: audio_data.len(),
+            elapsed_ms: started.elapsed().as_millis() as u64,
+            visemes: synthesized.visemes,
+            boundaries: synthesized.boundaries,
+            warnings,
+            stats: analysis::measure(&self.decodable(&audio_data)),
+        };
 
-        if let Some(path) = output_path {
+        if let Some(path) = &output_path {
             if let Some(parent) = path.parent() {
                 fs::create_dir_all(parent).await?;
             }
             fs::write(path, &audio_data).await?;
         }
+        #[cfg(feature = "history")]
+        self.record_history(&crate::history::NewEntry {
+            text,
+            voice,
+            backend,
+            duration_ms: result.stats.as_ref().map(|stats| stats.duration_ms),
+            elapsed_ms: result.elapsed_ms,
+            output_path: output_path.as_deref(),
+        });
+        self.synthesis_hooks(
+            output_path.as_deref(),
+            voice,
+            backend,
+            result.stats.as_ref().map(|stats| stats.duration_ms),
+            result.bytes,
+        )
+        .await;
 
         if play {
-            let player = AudioPlayer::new().map_err(|e| TTSError::Synthesis(e.to_string()))?;
-            player
-                .play_audio_data(audio_data, Some("mp3"))
-                .map_err(|e| TTSError::Synthesis(e.to_string()))?;
+            if let Some(sink) = &self.audio_sink {
+                sink.play(audio_data)?;
+            } else {
+                #[cfg(not(target_arch = "wasm32"))]
+                audio_player::open_sink(&self.config.audio_output)?
+                    .play(self.decodable(&audio_data).into_owned())?;
+                #[cfg(target_arch = "wasm32")]
+                return Err(crate::AudioError::Device(
+                    "Use web::WebAudioPlayer for playback in the browser".to_string(),
+                )
+                .into());
+            }
         }
 
-        Ok(())
+        Ok(result)
+    }
+
+    /// Run the `on_synthesis_complete` hooks of a finished synthesis
+    pub(crate) async fn synthesis_hooks(
+        &self,
+        output_path: Option<&Path>,
+        voice: &str,
+        backend: &str,
+        duration_ms: Option<u64>,
+        bytes: usize,
+    ) {
+        let hooks_to_run = &self.config.hooks.on_synthesis_complete;
+        if hooks_to_run.is_empty() {
+            return;
+        }
+        let vars = vec![
+            (
+                "output_path",
+                output_path
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_default(),
+            ),
+            ("voice", voice.to_string()),
+            ("backend", backend.to_string()),
+            ("duration", seconds(duration_ms)),
+            (
+                "duration_ms",
+                duration_ms.map(|ms| ms.to_string()).unwrap_or_default(),
+            ),
+            ("bytes", bytes.to_string()),
+        ];
+        hooks::run("on_synthesis_complete", hooks_to_run, &vars).await;
+    }
+
+    /// Add a synthesis to the history database; failures are only logged
+    #[cfg(feature = "history")]
+    pub(crate) fn record_history(&self, entry: &crate::history::NewEntry) {
+        let recorded = crate::history::History::from_config(&self.config)
+            .and_then(|history| history.record(entry));
+        if let Err(e) = recorded {
+            warn!(error = %e, "Could not record the synthesis in the history");
+        }
+    }
+
+    /// Configuration this processor was created with
+    pub fn config(&self) -> &TTSConfig {
+        &self.config
+    }
+
+    /// Namer for generated output filenames, using this processor's clock
+    pub fn file_namer(&self) -> Result<FileNamer, TTSError> {
+        Ok(FileNamer::from_config(&self.config)?.with_clock(self.clock.clone()))
+    }
+
+    /// Output directory handling for `TTSConfig.output_directory`
+    pub fn output_manager(&self) -> OutputManager {
+        OutputManager::from_config(&self.config)
     }
 
-    /// Clear the cached voice list
-    pub fn clear_voice_cache(&mut self) {
-        self.voices_cache = None;
+    /// Name of the backend this processor delegates to
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.name()
+    }
+
+    /// Whether the backend `voice` is sent to reports viseme timings, see
+    /// [`TTSBackend::supports_visemes`]
+    pub fn supports_visemes(&self, voice: &str) -> Result<bool, TTSError> {
+        let voice = self.resolve_voice(voice);
+        Ok(match self.routed_backend(&voice)? {
+            Some(backend) => backend.supports_visemes(),
+            None => self.backend.supports_visemes(),
+        })
+    }
+
+    /// Voices of every built-in backend, normalized and tagged with their backend
+    ///
+    /// Backends whose voice list cannot be fetched are skipped with a
+    /// warning; the call fails only if none could be listed.
+    pub async fn voice_catalog(&self) -> Result<VoiceCatalog, TTSError> {
+        let mut catalog = VoiceCatalog::default();
+        let mut listed = 0;
+        let mut last_error = None;
+        for &name in BUILTIN_BACKENDS {
+            let voices = if name == self.backend_name() {
+                self.list_voices().await
+            } else {
+                match self.backend_named(name) {
+                    Ok(backend) => self.cancellable(backend.list_voices()).await,
+                    Err(e) => Err(e),
+                }
+            };
+            match voices {
+                Ok(voices) => {
+                    catalog.add(name, voices);
+                    listed += 1;
+                }
+                Err(e) => {
+                    warn!(error = %e, "Leaving {} voices out of the catalog", name);
+                    last_error = Some(e);
+                }
+            }
+        }
+        match last_error {
+            Some(e) if listed == 0 => Err(e),
+            _ => Ok(catalog),
+        }
+    }
+
+    /// Clear the cached voice lists
+    pub async fn clear_voice_cache(&self) {
+        *self.voices_cache.write().await = None;
+        self.other_voices.write().await.clear();
+    }
+
+    /// Save audio through the backend; a bare file name lands in `TTSConfig.output_directory`
+    pub async fn save_audio(&self, audio_data: &[u8], filename: &str) -> Result<(), TTSError> {
+        let path = self.output_manager().resolve(Path::new(filename));
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).await?;
+        }
+        self.backend
+            .save_audio(audio_data, &path.to_string_lossy())
+            .await
     }
 }
 
+/// `duration_ms` in seconds for hook variables, empty if unknown
+fn seconds(duration_ms: Option<u64>) -> String {
+    duration_ms
+        .map(|ms| format!("{:.3}", ms as f64 / 1000.0))
+        .unwrap_or_default()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::backends::mock::MockBackend;
+    use crate::config::VoiceOverride;
+    use crate::models::{ErrorKind, Viseme};
+    use std::sync::{Arc, Mutex};
+
+    fn mock_processor(config: TTSConfig, backend: MockBackend) -> TTSProcessor {
+        TTSProcessor::with_backend(config, Box::new(backend))
+    }
+
+    #[test]
+    fn test_unresolved_proxy_secret_is_an_error() {
+        let config = TTSConfig {
+            proxy: Some("env:HELLO_TTS_TEST_UNSET_PROXY".to_string()),
+            ..TTSConfig::default()
+        };
+        let err = TTSProcessor::try_new(Some(config.clone())).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::Config);
+
+        let processor = mock_processor(config, MockBackend::new());
+        assert!(processor.backend_named("edge").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_events_are_emitted() {
+        let mut processor = mock_processor(
+            TTSConfig {
+                max_chunk_chars: 13,
+                text_normalization: crate::text::NormalizationConfig::disabled(),
+                ..TTSConfig::default()
+            },
+            MockBackend::new(),
+        );
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        processor.subscribe(move |e: &TTSEvent| sink.lock().unwrap().push(e.clone()));
+
+        let audio = processor
+            .synthesize_text("First one. Second one.", "en-US-AriaNeural")
+            .await
+            .unwrap();
+        processor.list_voices().await.unwrap();
+        processor.list_voices().await.unwrap();
+
+        let events = events.lock().unwrap();
+        assert!(matches!(
+            events[0],
+            TTSEvent::SynthesisStarted {
+                chunks: 2,
+                backend: "mock",
+                ..
+            }
+        ));
+        assert_eq!(
+            events[1],
+            TTSEvent::ChunkReceived {
+                index: 0,
+                total: 2,
+                bytes: 10
+            }
+        );
+        assert!(matches!(
+            events[3],
+            TTSEvent::SynthesisCompleted { bytes, .. } if bytes == audio.len()
+        ));
+        assert_eq!(events[4], TTSEvent::CacheHit);
+
+        let metrics = processor.metrics_snapshot();
+        assert_eq!(metrics.backends["mock"].requests, 2);
+        assert_eq!(metrics.backends["mock"].syntheses, 1);
+        assert_eq!(metrics.cache_hit_rate(), Some(0.5));
+    }
+
+    #[tokio::test]
+    async fn test_retryable_failure_is_retried() {
+        let backend = MockBackend::new()
+            .then_error(ErrorKind::RateLimited, "429 Too Many Requests")
+            .then_audio(b"audio".to_vec());
+        let processor = mock_processor(TTSConfig::default(), backend.clone());
+
+        let audio = processor
+            .synthesize_text("Hello", "en-US-AriaNeural")
+            .await
+            .unwrap();
+        assert_eq!(audio, b"audio");
+        assert_eq!(backend.calls().len(), 2);
+        assert_eq!(processor.metrics_snapshot().backends["mock"].retries, 1);
+    }
+
+    #[tokio::test]
+    async fn test_permanent_failure_is_not_retried() {
+        let backend = MockBackend::new().then_error(ErrorKind::VoiceNotFound, "no such voice");
+        let processor = mock_processor(TTSConfig::default(), backend.clone());
+
+        let err = processor
+            .synthesize_text("Hello", "xx-XX-Nobody")
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::VoiceNotFound);
+        assert_eq!(backend.calls().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retired_and_missing_voices_are_substituted() {
+        let voice = |name: &str| {
+            Voice::new(
+                name.to_string(),
+                name.to_string(),
+                "en-US".to_string(),
+                "Male".to_string(),
+            )
+        };
+        let backend = MockBackend::new()
+            .with_name("edge")
+            .with_voices(vec![voice("en-US-GuyNeural"), voice("en-US-AriaNeural")])
+            .then_audio(b"1".to_vec())
+            .then_error(ErrorKind::VoiceNotFound, "no such voice")
+            .then_audio(b"2".to_vec())
+            .then_error(ErrorKind::VoiceNotFound, "no such voice");
+        let processor = mock_processor(TTSConfig::default(), backend.clone());
+        processor
+            .synthesize_text("Hi", "en-US-JessaNeural")
+            .await
+            .unwrap();
+        processor
+            .synthesize_text("Hi", "en-US-GoneNeural")
+            .await
+            .unwrap();
+        let voices: Vec<String> = backend.calls().into_iter().map(|call| call.voice).collect();
+        assert_eq!(
+            voices,
+            ["en-US-AriaNeural", "en-US-GoneNeural", "en-US-AriaNeural"]
+        );
+
+        let strict = mock_processor(
+            TTSConfig {
+                voice_resolution: VoiceResolution::Strict,
+                ..TTSConfig::default()
+            },
+            backend.clone(),
+        );
+        let err = strict
+            .synthesize_text("Hi", "en-US-GoneNeural")
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::VoiceNotFound);
+        assert_eq!(
+            backend.calls().len(),
+            3,
+            "strict resolution fails before synthesizing"
+        );
+
+        let other = MockBackend::new()
+            .with_name("google")
+            .with_voices(vec![voice("en-US-GuyNeural")]);
+        strict.register_backend(Box::new(other.clone()));
+        for _ in 0..2 {
+            strict
+                .synthesize_with_backend("Hi", "en-US-GuyNeural", "google")
+                .await
+                .unwrap();
+        }
+        assert_eq!(other.voice_list_calls(), 1, "cached per backend");
+    }
+
+    #[tokio::test]
+    async fn test_lenient_resolution_falls_back_to_default_voice() {
+        let voice = |name: &str, locale: &str| {
+            Voice::new(
+                name.to_string(),
+                name.to_string(),
+                locale.to_string(),
+                "Female".to_string(),
+            )
+        };
+        let backend = MockBackend::new()
+            .with_voices(vec![voice("en-US-AriaNeural", "en-US")])
+            .then_error(ErrorKind::VoiceNotFound, "no such voice")
+            .then_audio(b"1".to_vec());
+        let processor = mock_processor(TTSConfig::default(), backend.clone());
+        processor
+            .synthesize_text("Hallo", "de-DE-GoneNeural")
+            .await
+            .unwrap();
+        assert_eq!(backend.calls()[1].voice, "en-US-AriaNeural");
+    }
+
+    #[tokio::test]
+    async fn test_validate_reports_all_problems() {
+        let backend = MockBackend::new()
+            .with_voices(vec![Voice::new(
+                "en-US-AriaNeural".to_string(),
+                "Aria".to_string(),
+                "en-US".to_string(),
+                "Female".to_string(),
+            )])
+            .with_max_text_chars(10);
+        let processor = mock_processor(TTSConfig::default(), backend.clone());
+
+        let options = ValidationOptions {
+            output_path: Some(std::env::temp_dir()),
+            ..ValidationOptions::default()
+        };
+        let report = processor
+            .validate(
+                "<speak>Far too long for this backend",
+                "en-US-Nobody",
+                &options,
+            )
+            .await;
+        assert_eq!(report.problems.len(), 4);
+        assert!(matches!(
+            report.problems[0],
+            ValidationProblem::MalformedSsml { .. }
+        ));
+        assert!(matches!(
+            report.problems[1],
+            ValidationProblem::TextTooLong { limit: 10, .. }
+        ));
+        assert!(matches!(
+            report.problems[2],
+            ValidationProblem::VoiceNotFound { .. }
+        ));
+        assert!(matches!(
+            report.problems[3],
+            ValidationProblem::OutputNotWritable { .. }
+        ));
+
+        let report = processor
+            .validate("Short", "en-us-arianeural", &ValidationOptions::default())
+            .await;
+        assert!(report.is_ok());
+        assert!(backend.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_bare_file_names_use_output_directory() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-out-{}", uuid::Uuid::new_v4()));
+        let processor = mock_processor(
+            TTSConfig {
+                output_directory: dir.to_string_lossy().into_owned(),
+                ..TTSConfig::default()
+            },
+            MockBackend::new(),
+        );
+        let result = processor
+            .synthesize_and_play("Hi", "en-US-AriaNeural", Some(Path::new("hi.mp3")), false)
+            .await
+            .unwrap();
+        assert_eq!(result.output_path, Some(dir.join("hi.mp3")));
+        assert_eq!(std::fs::read(dir.join("hi.mp3")).unwrap(), b"Hi");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_visemes_are_offset_across_chunks() {
+        let backend = MockBackend::new().with_visemes(vec![
+            Viseme {
+                offset_ms: 0,
+                id: 0,
+            },
+            Viseme {
+                offset_ms: 400,
+                id: 12,
+            },
+        ]);
+        let processor = mock_processor(
+            TTSConfig {
+                max_chunk_chars: 13,
+                visemes: true,
+                text_normalization: crate::text::NormalizationConfig::disabled(),
+                ..TTSConfig::default()
+            },
+            backend,
+        );
+        let result = processor
+            .synthesize_and_play("First one. Second one.", "en-US-AriaNeural", None, false)
+            .await
+            .unwrap();
+        let offsets: Vec<u64> = result.visemes.iter().map(|v| v.offset_ms).collect();
+        assert_eq!(offsets, [0, 400, 400, 800]);
+        assert!(!processor.supports_visemes("google:fr").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_input_is_sanitized_with_warnings() {
+        let backend = MockBackend::new();
+        let processor = mock_processor(TTSConfig::default(), backend.clone());
+        let result = processor
+            .synthesize_and_play("Cafe\u{301}\u{0}  Привет", "en-US-AriaNeural", None, false)
+            .await
+            .unwrap();
+        assert_eq!(backend.calls()[0].text, "Café Привет");
+        let warnings: Vec<String> = result.warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            warnings,
+            [
+                "Removed 1 control characters",
+                "6 Cyrillic letters may not be spoken by a voice for en"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ssml_tags_are_stripped_unless_allowed() {
+        let pasted = "Hi <prosody rate=\"x-fast\">there</prosody> &amp; bye";
+        let backend = MockBackend::new();
+        let processor = mock_processor(TTSConfig::default(), backend.clone());
+        let result = processor
+            .synthesize_and_play(pasted, "en-US-AriaNeural", None, false)
+            .await
+            .unwrap();
+        assert_eq!(backend.calls()[0].text, "Hi there & bye");
+        assert_eq!(result.warnings[0].to_string(), "Removed 2 SSML tags");
+        assert!(!processor
+            .ssml_payload(pasted, "en-US-AriaNeural")
+            .contains("x-fast"));
+
+        let config = TTSConfig {
+            allow_raw_ssml: true,
+            ..TTSConfig::default()
+        };
+        let processor = mock_processor(config, backend.clone());
+        processor
+            .synthesize_and_play(pasted, "en-US-AriaNeural", None, false)
+            .await
+            .unwrap();
+        assert!(backend.calls()[1]
+            .text
+            .contains("<prosody rate=\"x-fast\">"));
+        let raw = "<speak><break time=\"1s\"/></speak>";
+        assert_eq!(processor.ssml_payload(raw, "en-US-AriaNeural"), raw);
+    }
+
+    #[tokio::test]
+    async fn test_voice_overrides_apply_per_voice() {
+        let slower = VoiceOverride {
+            rate: Some("-15%".to_string()),
+            ..VoiceOverride::default()
+        };
+        let config = TTSConfig {
+            voice_overrides: HashMap::from([("en-GB-RyanNeural".to_string(), slower)]),
+            ..TTSConfig::default()
+        };
+        let backend = MockBackend::new();
+        let processor = mock_processor(config, backend.clone());
+        processor
+            .synthesize_text("Hi", "en-GB-RyanNeural")
+            .await
+            .unwrap();
+        processor
+            .synthesize_text("Hi", "en-US-AriaNeural")
+            .await
+            .unwrap();
+        let calls = backend.calls();
+        assert_eq!(calls[0].options.as_ref().unwrap().rate, "-15%");
+        assert_eq!(calls[1].options, None);
+        assert!(processor
+            .ssml_payload("Hi", "en-GB-RyanNeural")
+            .contains("rate=\"-15%\""));
+    }
+
+    #[tokio::test]
+    async fn test_word_timings_are_estimated_per_chunk() {
+        let second = crate::dialogue::mp3_silence(Duration::from_secs(1));
+        let backend = MockBackend::new()
+            .then_audio(second.clone())
+            .then_audio(second);
+        let processor = mock_processor(
+            TTSConfig {
+                max_chunk_chars: 13,
+                max_concurrent: 1,
+                word_timings: true,
+                estimate_timings: true,
+                text_normalization: crate::text::NormalizationConfig::disabled(),
+                ..TTSConfig::default()
+            },
+            backend,
+        );
+        let result = processor
+            .synthesize_and_play("First one. Second one.", "en-US-AriaNeural", None, false)
+            .await
+            .unwrap();
+        let words: Vec<&str> = result.boundaries.iter().map(|b| b.text.as_str()).collect();
+        assert_eq!(words, ["First", "one.", "Second", "one."]);
+        // The second chunk starts where the first chunk's last word ends
+        let first_end = result.boundaries[1].offset_ms + result.boundaries[1].duration_ms;
+        assert!(first_end > 500);
+        assert_eq!(result.boundaries[2].offset_ms, first_end);
+    }
+
+    #[tokio::test]
+    async fn test_chunks_are_synthesized_in_parallel_and_kept_in_order() {
+        let backend = MockBackend::new().with_latency(Duration::from_millis(100));
+        let config = TTSConfig {
+            max_chunk_chars: 13,
+            max_concurrent: 3,
+            ..TTSConfig::default()
+        };
+        let processor = mock_processor(config, backend.clone());
+        let text = "One thing. Two things. Three things.";
+        let started = Instant::now();
+        let audio = processor.synthesize_text(text, "v").await.unwrap();
+        assert!(started.elapsed() < Duration::from_millis(250));
+        assert_eq!(audio, b"One thing.Two things.Three things.");
+
+        let chunks = processor.prepare_text(text, "v", InputFormat::Plain);
+        let parts: Vec<_> = processor.synthesize_chunks(chunks, "v").collect().await;
+        let parts: Vec<Vec<u8>> = parts.into_iter().map(|p| p.unwrap().audio).collect();
+        assert_eq!(
+            parts,
+            [&b"One thing."[..], b"Two things.", b"Three things."]
+        );
+
+        let pages = processor.prepare_text("One.\n\u{c}\nTwo.\u{c}", "v", InputFormat::Plain);
+        assert_eq!(pages, ["One.", "Two."]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_synthesis_gives_quota_back() {
+        let config = TTSConfig {
+            max_characters_per_run: Some(5),
+            ..TTSConfig::default()
+        };
+        let backend = MockBackend::new().then_error(ErrorKind::Synthesis, "boom");
+        let processor = mock_processor(config, backend);
+        assert!(processor.synthesize_text("Hello", "v").await.is_err());
+        processor.synthesize_text("Hello", "v").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_deterministic_daily_quota_uses_the_real_day() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-quota-{}", uuid::Uuid::new_v4()));
+        let quota_file = dir.join("quota.json");
+        let config = TTSConfig {
+            deterministic: true,
+            max_characters_per_day: Some(5),
+            quota_file: Some(quota_file.display().to_string()),
+            ..TTSConfig::default()
+        };
+        let processor = mock_processor(config, MockBackend::new());
+        // The budget of the pinned day is spent, which must not block today
+        let pinned = crate::quota::DailyUsage {
+            date: processor.clock.now().format("%Y-%m-%d").to_string(),
+            characters: 5,
+        };
+        pinned.save(&quota_file).unwrap();
+        processor.synthesize_text("Hello", "v").await.unwrap();
+        let err = processor.synthesize_text("Hi", "v").await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::QuotaExceeded);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_backend_override_is_created_once() {
+        let config = TTSConfig {
+            backend: "google".to_string(),
+            text_normalization: crate::text::NormalizationConfig::disabled(),
+            ..TTSConfig::default()
+        };
+        let processor = TTSProcessor::new(Some(config));
+        for _ in 0..2 {
+            let audio = processor
+                .synthesize_with_backend("Hello", "en-US-AriaNeural", "mock")
+                .await
+                .unwrap();
+            assert_eq!(audio, b"Hello");
+        }
+        assert_eq!(processor.other_backends.lock().unwrap().len(), 1);
+        assert_eq!(processor.metrics_snapshot().backends["mock"].requests, 2);
+
+        let err = processor
+            .synthesize_with_backend("Hello", "en-US-AriaNeural", "nope")
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Config);
+    }
+
+    #[tokio::test]
+    async fn test_voice_refs_route_to_their_backend() {
+        let edge = MockBackend::new().with_name("edge");
+        let google = MockBackend::new().with_name("google");
+        let config = TTSConfig {
+            language_voices: HashMap::from([("fr".to_string(), "google:fr".to_string())]),
+            text_normalization: crate::text::NormalizationConfig::disabled(),
+            ..TTSConfig::default()
+        };
+        let processor = mock_processor(config, edge.clone());
+        processor.register_backend(Box::new(google.clone()));
+
+        processor
+            .synthesize_text("Hi", "en-US-AriaNeural")
+            .await
+            .unwrap();
+        processor
+            .synthesize_text("Hi", "edge:en-US-GuyNeural")
+            .await
+            .unwrap();
+        let result = processor
+            .synthesize_and_play("Bonjour", "fr", None, false)
+            .await
+            .unwrap();
+        assert_eq!(result.backend, "google");
+        let voices = |backend: &MockBackend| -> Vec<String> {
+            backend.calls().into_iter().map(|c| c.voice).collect()
+        };
+        assert_eq!(voices(&edge), ["en-US-AriaNeural", "en-US-GuyNeural"]);
+        assert_eq!(voices(&google), ["fr"]);
+
+        let err = processor
+            .synthesize_text("Hi", "openai:alloy")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid configuration: Unknown backend: openai"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fallback_skips_down_backend_during_cooldown() {
+        let edge = MockBackend::new()
+            .with_name("edge")
+            .then_error(ErrorKind::Network, "connection refused");
+        let google = MockBackend::new().with_name("google");
+        let config = TTSConfig {
+            max_retries: 0,
+            fallback_backends: vec!["google".to_string()],
+            circuit_failure_threshold: 1,
+            text_normalization: crate::text::NormalizationConfig::disabled(),
+            ..TTSConfig::default()
+        };
+        let processor = mock_processor(config, edge.clone());
+        processor.register_backend(Box::new(google.clone()));
+
+        for _ in 0..2 {
+            let audio = processor
+                .synthesize_text("Hi", "en-US-AriaNeural")
+                .await
+                .unwrap();
+            assert_eq!(audio, b"Hi");
+        }
+        // The second request skipped edge while its circuit was open
+        assert_eq!((edge.calls().len(), google.calls().len()), (1, 2));
+        let health = processor.backend_health();
+        assert!(!health[0].healthy && health[1].healthy);
+        assert!(health[0]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("connection refused"));
+
+        google.set_down(Some("gtts-cli missing"));
+        let health = processor.health_check().await;
+        assert_eq!(
+            health.iter().map(|h| h.healthy).collect::<Vec<_>>(),
+            [true, false]
+        );
+        processor
+            .synthesize_text("Hi", "en-US-AriaNeural")
+            .await
+            .unwrap();
+        assert_eq!(edge.calls().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_maps_voice_to_the_fallback_backend() {
+        let edge = MockBackend::new()
+            .with_name("edge")
+            .then_error(ErrorKind::Network, "connection refused");
+        let google = MockBackend::new().with_name("google").with_voices(vec![
+            Voice::new(
+                "en".to_string(),
+                "English".to_string(),
+                "en".to_string(),
+                "Unknown".to_string(),
+            ),
+            Voice::new(
+                "en-US".to_string(),
+                "English (United States)".to_string(),
+                "en-US".to_string(),
+                "Unknown".to_string(),
+            ),
+        ]);
+        let config = TTSConfig {
+            max_retries: 0,
+            fallback_backends: vec!["google".to_string()],
+            ..TTSConfig::default()
+        };
+        let processor = mock_processor(config, edge.clone());
+        processor.register_backend(Box::new(google.clone()));
+        processor
+            .synthesize_text("Hi", "en-US-AriaNeural")
+            .await
+            .unwrap();
+        assert_eq!(edge.calls()[0].voice, "en-US-AriaNeural");
+        assert_eq!(google.calls()[0].voice, "en-US");
+    }
+
+    #[tokio::test]
+    async fn test_missing_tool_falls_back_without_retries() {
+        let edge = MockBackend::new()
+            .with_name("edge")
+            .then_error(ErrorKind::NotInstalled, "No module named edge_tts");
+        let google = MockBackend::new().with_name("google");
+        let config = TTSConfig {
+            max_retries: 3,
+            fallback_backends: vec!["google".to_string()],
+            text_normalization: crate::text::NormalizationConfig::disabled(),
+            ..TTSConfig::default()
+        };
+        let processor = mock_processor(config, edge.clone());
+        processor.register_backend(Box::new(google.clone()));
+        processor.synthesize_text("Hi", "v").await.unwrap();
+        assert_eq!((edge.calls().len(), google.calls().len()), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_circuit_opens_after_consecutive_failures() {
+        let backend = MockBackend::new()
+            .then_error(ErrorKind::Unavailable, "down")
+            .then_error(ErrorKind::Unavailable, "down")
+            .then_error(ErrorKind::Unavailable, "down");
+        let config = TTSConfig {
+            max_retries: 0,
+            circuit_failure_threshold: 2,
+            text_normalization: crate::text::NormalizationConfig::disabled(),
+            ..TTSConfig::default()
+        };
+        let mut processor = mock_processor(config, backend.clone());
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let log = events.clone();
+        processor.subscribe(move |e: &TTSEvent| {
+            if matches!(
+                e,
+                TTSEvent::CircuitOpened { .. } | TTSEvent::CircuitClosed { .. }
+            ) {
+                log.lock().unwrap().push(e.clone());
+            }
+        });
+
+        for _ in 0..2 {
+            assert!(processor.synthesize_text("Hi", "v").await.is_err());
+        }
+        assert!(matches!(
+            &events.lock().unwrap()[..],
+            [TTSEvent::CircuitOpened { backend, .. }] if backend == "mock"
+        ));
+        // Without a fallback, requests fail at once while the circuit is open
+        let err = processor.synthesize_text("Hi", "v").await.unwrap_err();
+        assert!(err.to_string().contains("Circuit open"));
+        assert_eq!(backend.calls().len(), 2);
+
+        processor.health_check().await;
+        assert!(matches!(
+            events.lock().unwrap().last(),
+            Some(TTSEvent::CircuitClosed { .. })
+        ));
+        assert!(processor.synthesize_text("Hi", "v").await.is_err());
+        assert_eq!(processor.backend_health()[0].consecutive_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_voice_list_is_cached() {
+        let backend = MockBackend::new();
+        let processor = mock_processor(TTSConfig::default(), backend.clone());
+        processor.list_voices().await.unwrap();
+        processor.list_voices().await.unwrap();
+        assert_eq!(backend.voice_list_calls(), 1);
+
+        // Clones share the cache, also from other tasks
+        let clone = processor.clone();
+        tokio::spawn(async move { clone.list_voices().await.unwrap() })
+            .await
+            .unwrap();
+        assert_eq!(backend.voice_list_calls(), 1);
+        processor.clear_voice_cache().await;
+        processor.list_voices().await.unwrap();
+        assert_eq!(backend.voice_list_calls(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_voice_catalog_merges_backends() {
+        let voice = |name: &str, locale: &str| {
+            Voice::new(
+                name.to_string(),
+                name.to_string(),
+                locale.to_string(),
+                "f".to_string(),
+            )
+        };
+        let backend = MockBackend::new().with_voices(vec![voice("mock-b", "de-DE")]);
+        let processor = mock_processor(TTSConfig::default(), backend);
+        processor.register_backend(Box::new(
+            MockBackend::new()
+                .with_name("edge")
+                .with_voices(vec![voice("en-US-AriaNeural", "en-US")]),
+        ));
+        processor.register_backend(Box::new(
+            MockBackend::new()
+                .with_name("google")
+                .with_voices(vec![voice("fr", "fr-fr")]),
+        ));
+
+        let catalog = processor.voice_catalog().await.unwrap();
+        let voices: Vec<(&str, &str, &str)> = catalog
+            .voices
+            .iter()
+            .map(|v| {
+                (
+                    v.backend.as_str(),
+                    v.voice.name.as_str(),
+                    v.voice.locale.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            voices,
+            [
+                ("edge", "en-US-AriaNeural", "en-US"),
+                ("google", "fr", "fr-FR"),
+                ("mock", "mock-b", "de-DE")
+            ]
+        );
+        assert_eq!(catalog.voices[0].voice.gender, "Female");
+    }
+
+    #[tokio::test]
+    async fn test_usage_counts_characters_sent() {
+        let processor = mock_processor(TTSConfig::default(), MockBackend::new());
+        processor.synthesize_text("Hello there", "v").await.unwrap();
+        let usage = processor.usage();
+        assert_eq!(usage.backends["mock"].characters, 11);
+        assert_eq!((usage.characters, usage.estimated_cost), (11, None));
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_caches_voices() {
+        let backend = MockBackend::new();
+        let processor = mock_processor(TTSConfig::default(), backend.clone());
+        processor.warm_up().await.unwrap();
+        processor.list_voices().await.unwrap();
+        assert_eq!(backend.voice_list_calls(), 1);
+    }
 
     #[test]
     fn test_voice_creation() {
@@ -136,9 +2160,3 @@ mod tests {
         assert_eq!(client.config.default_voice, "en-US-AriaNeural");
     }
 }
-
-impl TTSProcessor {
-    pub async fn save_audio(&self, audio_data: &[u8], filename: &str) -> Result<(), TTSError> {
-        self.backend.save_audio(audio_data, filename).await
-    }
-}
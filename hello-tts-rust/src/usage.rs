@@ -0,0 +1,104 @@
+//! Characters synthesized per backend and what they cost.
+//!
+//! Paid services bill by the character. [`TTSProcessor::usage`](crate::TTSProcessor::usage)
+//! reports the characters sent to each backend by a processor and its clones,
+//! priced with [`price_per_million_chars`]: the backend's
+//! `price_per_million_chars` setting, or else [`DEFAULT_PRICES`]. Backends
+//! without a price (Edge, Google, mock) report no cost.
+
+use crate::config::TTSConfig;
+use crate::metrics::MetricsSnapshot;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// List prices in USD per million characters of neural voices, as a
+/// fallback for backends without a configured price
+pub const DEFAULT_PRICES: [(&str, f64); 3] = [("azure", 16.0), ("polly", 16.0), ("openai", 15.0)];
+
+/// Price per million characters for `backend`, if it is a paid backend
+pub fn price_per_million_chars(config: &TTSConfig, backend: &str) -> Option<f64> {
+    config
+        .backend_config(backend)
+        .and_then(|b| b.price_per_million_chars)
+        .or_else(|| {
+            DEFAULT_PRICES
+                .iter()
+                .find(|(name, _)| *name == backend)
+                .map(|(_, price)| *price)
+        })
+}
+
+/// Cost of sending `characters` to `backend`, if it is a paid backend
+pub fn estimate_cost(config: &TTSConfig, backend: &str, characters: u64) -> Option<f64> {
+    price_per_million_chars(config, backend).map(|price| characters as f64 * price / 1_000_000.0)
+}
+
+/// Usage of one backend
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BackendUsage {
+    pub syntheses: u64,
+    pub requests: u64,
+    /// Characters of successful requests, after markup stripping and normalization
+    pub characters: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_cost: Option<f64>,
+}
+
+/// Result of [`TTSProcessor::usage`](crate::TTSProcessor::usage)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Usage {
+    /// Per-backend usage keyed by backend name
+    pub backends: BTreeMap<String, BackendUsage>,
+    pub characters: u64,
+    /// Sum of the backend costs; `None` when only free backends were used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_cost: Option<f64>,
+}
+
+impl Usage {
+    /// Usage recorded in `metrics`, priced for `config`
+    pub fn from_metrics(metrics: &MetricsSnapshot, config: &TTSConfig) -> Self {
+        let backends: BTreeMap<String, BackendUsage> = metrics
+            .backends
+            .iter()
+            .map(|(name, m)| {
+                let usage = BackendUsage {
+                    syntheses: m.syntheses,
+                    requests: m.requests,
+                    characters: m.characters,
+                    estimated_cost: estimate_cost(config, name, m.characters),
+                };
+                (name.clone(), usage)
+            })
+            .collect();
+        let costs: Vec<f64> = backends.values().filter_map(|b| b.estimated_cost).collect();
+        Self {
+            characters: backends.values().map(|b| b.characters).sum(),
+            estimated_cost: (!costs.is_empty()).then(|| costs.iter().sum()),
+            backends,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
+
+    #[test]
+    fn test_usage_is_priced_per_backend() {
+        let mut config = TTSConfig::default();
+        config.backend_config_mut("openai").price_per_million_chars = Some(30.0);
+        let metrics = Metrics::new();
+        metrics.record_characters("azure", 500_000);
+        metrics.record_characters("openai", 100_000);
+        metrics.record_characters("edge", 1_000);
+
+        let usage = Usage::from_metrics(&metrics.snapshot(), &config);
+        assert_eq!(usage.characters, 601_000);
+        assert_eq!(usage.backends["azure"].estimated_cost, Some(8.0));
+        assert_eq!(usage.backends["openai"].estimated_cost, Some(3.0));
+        assert_eq!(usage.backends["edge"].estimated_cost, None);
+        assert_eq!(usage.estimated_cost, Some(11.0));
+    }
+}
@@ -0,0 +1,256 @@
+//! Checks run by [`TTSProcessor::validate`](crate::TTSProcessor::validate)
+//! before any audio is produced.
+
+use crate::text::InputFormat;
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// What to check besides the text and voice
+#[derive(Debug, Clone, Default)]
+pub struct ValidationOptions {
+    /// Input format of the text; defaults to `TTSConfig.input_format`
+    pub input_format: Option<InputFormat>,
+    /// File the audio would be written to
+    pub output_path: Option<PathBuf>,
+}
+
+/// A problem that would make synthesis fail or misbehave
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "problem", rename_all = "snake_case")]
+pub enum ValidationProblem {
+    EmptyText,
+    VoiceNotFound {
+        voice: String,
+    },
+    /// The voice list could not be fetched, so the voice was not checked
+    VoicesUnavailable {
+        error: String,
+    },
+    TextTooLong {
+        chunk: usize,
+        chars: usize,
+        limit: usize,
+    },
+    MalformedSsml {
+        reason: String,
+    },
+    OutputNotWritable {
+        path: PathBuf,
+        reason: String,
+    },
+}
+
+impl fmt::Display for ValidationProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationProblem::EmptyText => write!(f, "Text is empty"),
+            ValidationProblem::VoiceNotFound { voice } => {
+                write!(f, "Voice not found: {}", voice)
+            }
+            ValidationProblem::VoicesUnavailable { error } => {
+                write!(f, "Could not check the voice: {}", error)
+            }
+            ValidationProblem::TextTooLong {
+                chunk,
+                chars,
+                limit,
+            } => write!(
+                f,
+                "Chunk {} has {} characters, more than the backend limit of {}",
+                chunk + 1,
+                chars,
+                limit
+            ),
+            ValidationProblem::MalformedSsml { reason } => write!(f, "Malformed SSML: {}", reason),
+            ValidationProblem::OutputNotWritable { path, reason } => {
+                write!(f, "Cannot write {}: {}", path.display(), reason)
+            }
+        }
+    }
+}
+
+/// Every problem found by a validation run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ValidationReport {
+    pub problems: Vec<ValidationProblem>,
+}
+
+impl ValidationReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Whether `text` is meant as SSML, i.e. starts with a `<speak>` element
+pub fn looks_like_ssml(text: &str) -> bool {
+    let text = text.trim_start();
+    let text = match text.strip_prefix("<?xml") {
+        Some(rest) => rest
+            .split_once("?>")
+            .map_or("", |(_, rest)| rest)
+            .trim_start(),
+        None => text,
+    };
+    text.starts_with("<speak")
+}
+
+/// Check that SSML tags are balanced and entities are well formed
+///
+/// This is a structural check only; it does not validate element names or
+/// attributes against the SSML schema.
+pub fn check_ssml(text: &str) -> Result<(), String> {
+    let mut open: Vec<&str> = Vec::new();
+    let mut rest = text;
+    let mut roots = 0;
+    while let Some(index) = rest.find(['<', '&']) {
+        let (before, tail) = rest.split_at(index);
+        if open.is_empty() && !before.trim().is_empty() {
+            return Err("text outside of the <speak> element".to_string());
+        }
+        if tail.starts_with('&') {
+            let end = tail
+                .find(';')
+                .filter(|end| is_entity(&tail[1..*end]))
+                .ok_or("unescaped '&' (use &amp;)")?;
+            rest = &tail[end + 1..];
+            continue;
+        }
+        let (skip, terminator) = if tail.starts_with("<!--") {
+            (4, "-->")
+        } else if tail.starts_with("<?") {
+            (2, "?>")
+        } else {
+            (1, ">")
+        };
+        let end = tail[skip..]
+            .find(terminator)
+            .map(|end| skip + end)
+            .ok_or_else(|| format!("unterminated tag starting at {:?}", truncate(tail)))?;
+        rest = &tail[end + terminator.len()..];
+        if skip > 1 {
+            continue;
+        }
+
+        let tag = &tail[1..end];
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim();
+            match open.pop() {
+                Some(expected) if expected == name => {}
+                Some(expected) => {
+                    return Err(format!("</{}> closes <{}>", name, expected));
+                }
+                None => return Err(format!("</{}> has no opening tag", name)),
+            }
+        } else {
+            let name = tag
+                .split_whitespace()
+                .next()
+                .unwrap_or("")
+                .trim_end_matches('/');
+            if name.is_empty() {
+                return Err("empty tag name".to_string());
+            }
+            if open.is_empty() {
+                roots += 1;
+                if roots > 1 {
+                    return Err("more than one root element".to_string());
+                }
+            }
+            if !tag.ends_with('/') {
+                open.push(name);
+            }
+        }
+    }
+    if let Some(name) = open.pop() {
+        return Err(format!("<{}> is never closed", name));
+    }
+    if !rest.trim().is_empty() {
+        return Err("text outside of the <speak> element".to_string());
+    }
+    Ok(())
+}
+
+fn is_entity(name: &str) -> bool {
+    matches!(name, "amp" | "lt" | "gt" | "quot" | "apos")
+        || name
+            .strip_prefix("#x")
+            .is_some_and(|hex| !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()))
+        || name
+            .strip_prefix('#')
+            .is_some_and(|dec| !dec.is_empty() && dec.chars().all(|c| c.is_ascii_digit()))
+}
+
+fn truncate(text: &str) -> String {
+    text.chars().take(20).collect()
+}
+
+/// Check that a file could be written at `path` without writing anything
+///
+/// Missing parent directories are fine as long as the closest existing
+/// ancestor is a writable directory, since output directories are created on
+/// demand.
+pub fn check_writable(path: &Path) -> Result<(), String> {
+    if path.is_dir() {
+        return Err("is a directory".to_string());
+    }
+    if let Ok(metadata) = path.metadata() {
+        if metadata.permissions().readonly() {
+            return Err("file is read-only".to_string());
+        }
+        return Ok(());
+    }
+    let ancestor = path
+        .ancestors()
+        .skip(1)
+        .map(|p| {
+            if p.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                p
+            }
+        })
+        .find(|p| p.exists())
+        .ok_or("no existing parent directory")?;
+    let metadata = ancestor.metadata().map_err(|e| e.to_string())?;
+    if !metadata.is_dir() {
+        Err(format!("{} is not a directory", ancestor.display()))
+    } else if metadata.permissions().readonly() {
+        Err(format!("{} is read-only", ancestor.display()))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_ssml() {
+        assert!(looks_like_ssml(
+            "<?xml version=\"1.0\"?>\n<speak version=\"1.0\">"
+        ));
+        assert!(!looks_like_ssml("Hello <b>world</b>"));
+
+        let valid = "<speak version=\"1.0\"><voice name=\"en-US-AriaNeural\">Fish &amp; chips<break time=\"500ms\"/></voice><!-- note --></speak>";
+        assert_eq!(check_ssml(valid), Ok(()));
+        assert!(check_ssml("<speak><prosody rate=\"slow\">Hi</speak>")
+            .unwrap_err()
+            .contains("closes <prosody>"));
+        assert!(check_ssml("<speak>Fish & chips</speak>")
+            .unwrap_err()
+            .contains("unescaped"));
+        assert!(check_ssml("<speak>Hi")
+            .unwrap_err()
+            .contains("never closed"));
+        assert!(check_ssml("<speak>Hi</speak> trailing").is_err());
+    }
+
+    #[test]
+    fn test_check_writable() {
+        let dir = std::env::temp_dir();
+        assert_eq!(check_writable(&dir.join("missing/sub/out.mp3")), Ok(()));
+        assert!(check_writable(&dir).is_err());
+    }
+}
@@ -0,0 +1,109 @@
+//! Voices qualified with the backend that offers them.
+//!
+//! A [`VoiceRef`] is written `backend:voice`, e.g. `edge:en-US-AriaNeural`,
+//! `google:fr` or `openai:alloy`, or as a bare voice name for the configured
+//! backend. [`TTSProcessor`](crate::TTSProcessor) accepts a reference wherever
+//! it takes a voice and sends the request to the named backend, so one
+//! configuration can use Edge for one language and gTTS for another through
+//! `TTSConfig.language_voices`.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A voice name, optionally qualified with its backend
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct VoiceRef {
+    /// `None` for the configured backend
+    pub backend: Option<String>,
+    pub voice: String,
+}
+
+impl VoiceRef {
+    /// Parse `backend:voice` or a bare voice name
+    ///
+    /// Only lowercase letters, digits and `_` count as a backend name, so
+    /// other text before a colon is kept as part of the voice.
+    pub fn parse(s: &str) -> Self {
+        match s.split_once(':') {
+            Some((backend, voice)) if is_backend_name(backend) && !voice.is_empty() => Self {
+                backend: Some(backend.to_string()),
+                voice: voice.to_string(),
+            },
+            _ => Self {
+                backend: None,
+                voice: s.to_string(),
+            },
+        }
+    }
+}
+
+fn is_backend_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+impl std::str::FromStr for VoiceRef {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let voice = Self::parse(s.trim());
+        if voice.voice.is_empty() {
+            return Err(format!("expected VOICE or BACKEND:VOICE, got {:?}", s));
+        }
+        Ok(voice)
+    }
+}
+
+impl TryFrom<String> for VoiceRef {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<VoiceRef> for String {
+    fn from(voice: VoiceRef) -> Self {
+        voice.to_string()
+    }
+}
+
+impl fmt::Display for VoiceRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.backend {
+            Some(backend) => write!(f, "{}:{}", backend, self.voice),
+            None => f.write_str(&self.voice),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        let edge = VoiceRef::parse("edge:en-US-AriaNeural");
+        assert_eq!(edge.backend.as_deref(), Some("edge"));
+        assert_eq!(edge.voice, "en-US-AriaNeural");
+        assert_eq!(edge.to_string(), "edge:en-US-AriaNeural");
+
+        let bare = VoiceRef::parse("en-US-AriaNeural");
+        assert_eq!(
+            (bare.backend, bare.voice.as_str()),
+            (None, "en-US-AriaNeural")
+        );
+        // Not a backend name, so the colon belongs to the voice
+        assert_eq!(VoiceRef::parse("Voice: Anna").backend, None);
+        assert_eq!(VoiceRef::parse("google:").voice, "google:");
+        assert!("".parse::<VoiceRef>().is_err());
+
+        let json = serde_json::to_string(&VoiceRef::parse("google:fr")).unwrap();
+        assert_eq!(json, "\"google:fr\"");
+        let parsed: VoiceRef = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.backend.as_deref(), Some("google"));
+    }
+}
@@ -0,0 +1,166 @@
+//! Substitutes for retired and missing voices.
+//!
+//! Edge retires voices from time to time, which used to fail every run still
+//! configured with one. `TTSConfig.voice_resolution` chooses what happens to
+//! a voice the backend does not know:
+//!
+//! - [`VoiceResolution::Lenient`] (the default) replaces a retired Edge voice
+//!   listed in [`RETIRED_EDGE_VOICES`] by its successor before synthesizing,
+//!   and when a backend answers that a voice does not exist, tries once more
+//!   with the [`nearest`] voice of its list or else `TTSConfig.default_voice`,
+//!   logging a warning and emitting
+//!   [`TTSEvent::VoiceSubstituted`](crate::TTSEvent::VoiceSubstituted)
+//! - [`VoiceResolution::Strict`] checks the voice against the backend's voice
+//!   list first and fails with [`TTSError::VoiceNotFound`] without sending
+//!   any text if it is not listed
+//!
+//! `strict_voices = true`, the earlier switch, still selects strict
+//! resolution and wins over `voice_resolution`.
+
+use crate::models::{TTSError, Voice};
+use crate::naming;
+use serde::{Deserialize, Serialize};
+
+/// How `TTSConfig.voice_resolution` treats voices a backend does not list
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VoiceResolution {
+    /// Fail before synthesizing
+    Strict,
+    /// Substitute a similar voice with a warning
+    #[default]
+    Lenient,
+}
+
+impl std::str::FromStr for VoiceResolution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "strict" => Ok(Self::Strict),
+            "lenient" => Ok(Self::Lenient),
+            other => Err(format!("Unknown voice resolution: {}", other)),
+        }
+    }
+}
+
+/// Fail with [`TTSError::VoiceNotFound`] unless `voice` is in `voices`; an
+/// empty list means the backend accepts any voice name
+pub fn check_listed(voice: &str, voices: &[Voice]) -> Result<(), TTSError> {
+    if voices.is_empty() || voices.iter().any(|v| v.name.eq_ignore_ascii_case(voice)) {
+        Ok(())
+    } else {
+        Err(TTSError::VoiceNotFound(voice.to_string()))
+    }
+}
+
+/// Retired Edge voice names and the voices that replaced them
+pub const RETIRED_EDGE_VOICES: &[(&str, &str)] = &[
+    ("de-DE-HeddaRUS", "de-DE-KatjaNeural"),
+    ("de-DE-Stefan-Apollo", "de-DE-ConradNeural"),
+    ("en-AU-HayleyRUS", "en-AU-NatashaNeural"),
+    ("en-GB-George-Apollo", "en-GB-RyanNeural"),
+    ("en-GB-HazelRUS", "en-GB-SoniaNeural"),
+    ("en-GB-MiaNeural", "en-GB-SoniaNeural"),
+    ("en-IN-Heera-Apollo", "en-IN-NeerjaNeural"),
+    ("en-US-AriaRUS", "en-US-AriaNeural"),
+    ("en-US-BenjaminRUS", "en-US-GuyNeural"),
+    ("en-US-GuyRUS", "en-US-GuyNeural"),
+    ("en-US-JessaNeural", "en-US-AriaNeural"),
+    ("en-US-JessaRUS", "en-US-AriaNeural"),
+    ("en-US-ZiraRUS", "en-US-JennyNeural"),
+    ("es-ES-HelenaRUS", "es-ES-ElviraNeural"),
+    ("es-ES-Laura-Apollo", "es-ES-ElviraNeural"),
+    ("fr-FR-HortenseRUS", "fr-FR-DeniseNeural"),
+    ("fr-FR-Julie-Apollo", "fr-FR-DeniseNeural"),
+    ("it-IT-Cosimo-Apollo", "it-IT-DiegoNeural"),
+    ("it-IT-LuciaRUS", "it-IT-ElsaNeural"),
+    ("ja-JP-Ayumi-Apollo", "ja-JP-NanamiNeural"),
+    ("ja-JP-HarukaRUS", "ja-JP-NanamiNeural"),
+    ("ko-KR-HeamiRUS", "ko-KR-SunHiNeural"),
+    ("pt-BR-HeloisaRUS", "pt-BR-FranciscaNeural"),
+    ("ru-RU-Irina-Apollo", "ru-RU-SvetlanaNeural"),
+    ("zh-CN-HuihuiRUS", "zh-CN-XiaoxiaoNeural"),
+    ("zh-CN-Yaoyao-Apollo", "zh-CN-XiaoxiaoNeural"),
+];
+
+/// Successor of a retired Edge voice
+pub fn successor(voice: &str) -> Option<&'static str> {
+    RETIRED_EDGE_VOICES
+        .iter()
+        .find(|(retired, _)| retired.eq_ignore_ascii_case(voice))
+        .map(|(_, successor)| *successor)
+}
+
+/// Substitute for `voice` among `voices`: its successor if listed, else the
+/// first voice by name of the same locale, else of the same language; `None`
+/// when `voice` itself is listed or nothing matches
+pub fn nearest<'a>(voice: &str, voices: &'a [Voice]) -> Option<&'a Voice> {
+    let named = |name: &str| voices.iter().find(|v| v.name.eq_ignore_ascii_case(name));
+    if named(voice).is_some() {
+        return None;
+    }
+    if let Some(voice) = successor(voice).and_then(named) {
+        return Some(voice);
+    }
+    let locale = naming::locale(voice);
+    let language = locale.split('-').next().unwrap_or_default();
+    let first = |matches: &dyn Fn(&Voice) -> bool| {
+        voices
+            .iter()
+            .filter(|v| matches(v))
+            .min_by(|a, b| a.name.cmp(&b.name))
+    };
+    first(&|v| v.locale.eq_ignore_ascii_case(&locale)).or_else(|| {
+        first(&|v| {
+            v.locale
+                .split('-')
+                .next()
+                .is_some_and(|l| l.eq_ignore_ascii_case(language))
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voice(name: &str, locale: &str) -> Voice {
+        Voice::new(
+            name.to_string(),
+            name.to_string(),
+            locale.to_string(),
+            "Female".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_nearest_voice() {
+        let voices = [
+            voice("en-US-JennyNeural", "en-US"),
+            voice("en-US-AriaNeural", "en-US"),
+            voice("en-GB-SoniaNeural", "en-GB"),
+        ];
+        assert_eq!(successor("en-us-jessaneural"), Some("en-US-AriaNeural"));
+        assert!(nearest("en-US-JennyNeural", &voices).is_none());
+        assert_eq!(
+            nearest("en-US-ZiraRUS", &voices).unwrap().name,
+            "en-US-JennyNeural"
+        );
+        // Same locale first, then same language, by name for a stable choice
+        assert_eq!(
+            nearest("en-US-NobodyNeural", &voices).unwrap().name,
+            "en-US-AriaNeural"
+        );
+        assert_eq!(
+            nearest("en-IE-EmilyNeural", &voices).unwrap().name,
+            "en-GB-SoniaNeural"
+        );
+        assert!(nearest("de-DE-KatjaNeural", &voices).is_none());
+
+        assert!(check_listed("en-gb-sonianeural", &voices).is_ok());
+        assert!(check_listed("de-DE-KatjaNeural", &voices).is_err());
+        assert!(check_listed("de-DE-KatjaNeural", &[]).is_ok());
+        assert_eq!("Strict".parse(), Ok(VoiceResolution::Strict));
+    }
+}
@@ -0,0 +1,147 @@
+//! Watch text and Markdown files and re-synthesize them when they change.
+//!
+//! Each changed file is spoken to a sibling audio file with the same stem,
+//! e.g. `chapter1.md` to `chapter1.mp3`. Editors often write a file several
+//! times per save, so events are coalesced for a short debounce interval and
+//! files whose content did not change are skipped.
+
+use crate::models::TTSError;
+use crate::naming::fnv1a;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+/// Extensions of the files that are watched
+const TEXT_EXTENSIONS: [&str; 6] = ["txt", "text", "md", "markdown", "html", "htm"];
+
+/// Whether `path` looks like a text, Markdown or HTML file
+pub fn is_text_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| TEXT_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+/// Reports text files below a directory, or a single file, as they change
+pub struct FileWatcher {
+    _watcher: notify::RecommendedWatcher,
+    events: UnboundedReceiver<PathBuf>,
+    debounce: Duration,
+    hashes: HashMap<PathBuf, u64>,
+}
+
+impl FileWatcher {
+    /// Watch `path`, which may be a directory (watched recursively) or a file
+    pub fn new(path: &Path, debounce: Duration) -> Result<Self, TTSError> {
+        let path = path.canonicalize()?;
+
+        // Editors often save by replacing the file, so a single file is
+        // watched through its directory
+        let (root, only) = if path.is_dir() {
+            (path.clone(), None)
+        } else {
+            let parent = path.parent().unwrap_or(Path::new("/")).to_path_buf();
+            (parent, Some(path))
+        };
+        let mode = if only.is_some() {
+            RecursiveMode::NonRecursive
+        } else {
+            RecursiveMode::Recursive
+        };
+
+        let (tx, events) = unbounded_channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let Ok(event) = event else { return };
+                if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    return;
+                }
+                for changed in event.paths {
+                    let wanted = match &only {
+                        Some(file) => changed == *file,
+                        None => is_text_file(&changed),
+                    };
+                    if wanted {
+                        let _ = tx.send(changed);
+                    }
+                }
+            })
+            .map_err(watch_error)?;
+        watcher.watch(&root, mode).map_err(watch_error)?;
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            debounce,
+            hashes: HashMap::new(),
+        })
+    }
+
+    /// Wait for the next batch of changed files
+    ///
+    /// Returns `None` when the watcher stopped. Files that were removed or
+    /// whose content is the same as at their last change are left out, so the
+    /// result may be empty.
+    pub async fn changed(&mut self) -> Option<Vec<PathBuf>> {
+        let mut changed = BTreeSet::from([self.events.recv().await?]);
+        while let Ok(Some(path)) = tokio::time::timeout(self.debounce, self.events.recv()).await {
+            changed.insert(path);
+        }
+        Some(
+            changed
+                .into_iter()
+                .filter(|path| self.content_changed(path))
+                .collect(),
+        )
+    }
+
+    fn content_changed(&mut self, path: &Path) -> bool {
+        let Ok(content) = std::fs::read(path) else {
+            return false;
+        };
+        let hash = fnv1a(&content);
+        self.hashes.insert(path.to_path_buf(), hash) != Some(hash)
+    }
+}
+
+fn watch_error(e: notify::Error) -> TTSError {
+    TTSError::Config(format!("Cannot watch files: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_files() {
+        assert!(is_text_file(Path::new("a/draft.MD")));
+        assert!(!is_text_file(Path::new("a/draft.mp3")));
+        assert!(!is_text_file(Path::new("a/README")));
+    }
+
+    #[tokio::test]
+    async fn test_changes_are_reported_once() {
+        let dir = std::env::temp_dir().join(format!("hello-tts-watch-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut watcher = FileWatcher::new(&dir, Duration::from_millis(100)).unwrap();
+
+        let draft = dir.canonicalize().unwrap().join("draft.md");
+        std::fs::write(&draft, "# Draft").unwrap();
+        std::fs::write(dir.join("ignored.mp3"), b"").unwrap();
+        let changed = tokio::time::timeout(Duration::from_secs(5), watcher.changed())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(changed, std::slice::from_ref(&draft));
+
+        // Rewriting the same content is not a change
+        std::fs::write(&draft, "# Draft").unwrap();
+        let changed = tokio::time::timeout(Duration::from_secs(5), watcher.changed())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(changed.is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,212 @@
+//! Browser support for `wasm32-unknown-unknown` builds (`wasm` feature).
+//!
+//! Browsers cannot run the edge-tts tool and cannot send the headers the Edge
+//! synthesis endpoint expects, so synthesis goes through a hello-tts HTTP
+//! gateway (see the `server` feature) with [`WebTTSClient`]. The public Edge
+//! voice list is fetched directly. Audio is played with the Web Audio API by
+//! [`WebAudioPlayer`].
+//!
+//! ```text
+//! cargo rustc --release --lib --crate-type cdylib --target wasm32-unknown-unknown --features wasm
+//! wasm-bindgen --target web --out-dir pkg target/wasm32-unknown-unknown/release/hello_tts_rust.wasm
+//! ```
+
+use crate::audio_player::AudioError;
+use crate::backends::edge_voices::{EdgeVoiceData, VOICES_URL};
+use crate::models::{ErrorKind, TTSError, Voice};
+use std::cell::RefCell;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    AudioBuffer, AudioBufferSourceNode, AudioContext, AudioScheduledSourceNode, RequestInit,
+    Response,
+};
+
+fn js_error(context: &str, value: JsValue) -> String {
+    format!(
+        "{}: {}",
+        context,
+        value.as_string().unwrap_or_else(|| format!("{:?}", value))
+    )
+}
+
+fn fetch_error(context: &str, value: JsValue) -> TTSError {
+    TTSError::backend_error("web", ErrorKind::Network, js_error(context, value))
+}
+
+/// Fetch `request_url` with `init` and return the successful response
+async fn fetch(request_url: &str, init: &RequestInit) -> Result<Response, TTSError> {
+    let window = web_sys::window()
+        .ok_or_else(|| TTSError::Config("No window object available".to_string()))?;
+    let response: Response = JsFuture::from(window.fetch_with_str_and_init(request_url, init))
+        .await
+        .map_err(|e| fetch_error("fetch failed", e))?
+        .dyn_into()
+        .map_err(|e| fetch_error("unexpected fetch result", e))?;
+    if !response.ok() {
+        return Err(TTSError::backend_error(
+            "web",
+            ErrorKind::from_status(response.status()),
+            format!("{} returned HTTP {}", request_url, response.status()),
+        ));
+    }
+    Ok(response)
+}
+
+async fn response_text(response: &Response) -> Result<String, TTSError> {
+    let promise = response
+        .text()
+        .map_err(|e| fetch_error("reading body", e))?;
+    JsFuture::from(promise)
+        .await
+        .map_err(|e| fetch_error("reading body", e))?
+        .as_string()
+        .ok_or_else(|| TTSError::backend_error("web", ErrorKind::Parse, "Body is not text"))
+}
+
+/// Client for a hello-tts HTTP gateway, usable from the browser
+pub struct WebTTSClient {
+    base_url: String,
+}
+
+impl WebTTSClient {
+    /// Create a client for the gateway at `base_url`, e.g. `http://localhost:8080`
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Synthesize `text` with `voice` through the gateway's `POST /synthesize`
+    pub async fn synthesize(&self, text: &str, voice: &str) -> Result<Vec<u8>, TTSError> {
+        let body = serde_json::json!({ "text": text, "voice": voice }).to_string();
+        let init = RequestInit::new();
+        init.set_method("POST");
+        init.set_body(&JsValue::from_str(&body));
+        let headers = web_sys::Headers::new().map_err(|e| fetch_error("headers", e))?;
+        headers
+            .set("content-type", "application/json")
+            .map_err(|e| fetch_error("headers", e))?;
+        init.set_headers(&headers);
+
+        let response = fetch(&format!("{}/synthesize", self.base_url), &init).await?;
+        let promise = response
+            .array_buffer()
+            .map_err(|e| fetch_error("reading audio", e))?;
+        let buffer = JsFuture::from(promise)
+            .await
+            .map_err(|e| fetch_error("reading audio", e))?;
+        Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+    }
+
+    /// List the gateway backend's voices through `GET /voices`
+    pub async fn list_voices(&self) -> Result<Vec<Voice>, TTSError> {
+        let response = fetch(&format!("{}/voices", self.base_url), &RequestInit::new()).await?;
+        Ok(serde_json::from_str(&response_text(&response).await?)?)
+    }
+}
+
+/// Fetch the public Edge voice list directly, without a gateway
+pub async fn fetch_edge_voices() -> Result<Vec<Voice>, TTSError> {
+    let response = fetch(VOICES_URL, &RequestInit::new()).await?;
+    let voices: Vec<EdgeVoiceData> = serde_json::from_str(&response_text(&response).await?)?;
+    Ok(voices.into_iter().map(Voice::from).collect())
+}
+
+/// Audio player backed by the Web Audio API
+pub struct WebAudioPlayer {
+    context: AudioContext,
+    source: RefCell<Option<AudioBufferSourceNode>>,
+}
+
+impl WebAudioPlayer {
+    /// Create a player; browsers only allow this after a user gesture
+    pub fn new() -> Result<Self, AudioError> {
+        let context =
+            AudioContext::new().map_err(|e| AudioError::Device(js_error("AudioContext", e)))?;
+        Ok(Self {
+            context,
+            source: RefCell::new(None),
+        })
+    }
+
+    /// Decode and start playing encoded audio (MP3, WAV, OGG, ...)
+    ///
+    /// Returns once playback has started; any previous playback is stopped.
+    pub async fn play_audio_data(&self, audio_data: &[u8]) -> Result<(), AudioError> {
+        let bytes = js_sys::Uint8Array::from(audio_data);
+        let promise = self
+            .context
+            .decode_audio_data(&bytes.buffer())
+            .map_err(|e| AudioError::Decode(js_error("decodeAudioData", e)))?;
+        let buffer: AudioBuffer = JsFuture::from(promise)
+            .await
+            .map_err(|e| AudioError::Decode(js_error("decodeAudioData", e)))?
+            .dyn_into()
+            .map_err(|e| AudioError::Decode(js_error("decodeAudioData", e)))?;
+
+        let source = self
+            .context
+            .create_buffer_source()
+            .map_err(|e| AudioError::Playback(js_error("createBufferSource", e)))?;
+        source.set_buffer(Some(&buffer));
+        source
+            .connect_with_audio_node(&self.context.destination())
+            .map_err(|e| AudioError::Playback(js_error("connect", e)))?;
+        self.stop();
+        source
+            .start()
+            .map_err(|e| AudioError::Playback(js_error("start", e)))?;
+        *self.source.borrow_mut() = Some(source);
+        Ok(())
+    }
+
+    /// Stop current playback
+    pub fn stop(&self) {
+        if let Some(source) = self.source.borrow_mut().take() {
+            let node: &AudioScheduledSourceNode = &source;
+            let _ = node.stop();
+        }
+    }
+
+    /// Pause current playback
+    pub async fn pause(&self) -> Result<(), AudioError> {
+        let promise = self
+            .context
+            .suspend()
+            .map_err(|e| AudioError::Playback(js_error("suspend", e)))?;
+        JsFuture::from(promise)
+            .await
+            .map_err(|e| AudioError::Playback(js_error("suspend", e)))?;
+        Ok(())
+    }
+
+    /// Resume paused playback
+    pub async fn resume(&self) -> Result<(), AudioError> {
+        let promise = self
+            .context
+            .resume()
+            .map_err(|e| AudioError::Playback(js_error("resume", e)))?;
+        JsFuture::from(promise)
+            .await
+            .map_err(|e| AudioError::Playback(js_error("resume", e)))?;
+        Ok(())
+    }
+}
+
+/// Synthesize `text` through the gateway at `base_url` and play it
+///
+/// Entry point for demo pages: `await speak("http://localhost:8080", "Hello", "en-US-AriaNeural")`.
+#[wasm_bindgen]
+pub async fn speak(base_url: String, text: String, voice: String) -> Result<(), JsValue> {
+    let audio = WebTTSClient::new(&base_url)
+        .synthesize(&text, &voice)
+        .await
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    WebAudioPlayer::new()
+        .map_err(|e| JsValue::from_str(&e.to_string()))?
+        .play_audio_data(&audio)
+        .await
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}